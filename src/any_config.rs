@@ -0,0 +1,455 @@
+use axum_core::extract::{FromRequest, Request};
+use http::{header, HeaderMap};
+use serde::de::DeserializeOwned;
+#[cfg(any(feature = "json", feature = "toml"))]
+use serde::Deserialize;
+#[cfg(any(feature = "json", feature = "toml"))]
+use serde_yaml::Value;
+
+#[cfg(any(feature = "json", feature = "toml"))]
+use crate::classify::Classified;
+use crate::rejection::*;
+use crate::YamlConfig;
+
+/// Configuration extractor accepting YAML, JSON or TOML bodies.
+///
+/// The parser is selected from the `Content-Type` header and the body is
+/// deserialized into the same `T` whatever format it was written in:
+///
+/// - the YAML media types accepted by the [`YamlConfig`] in the request
+///   extensions, `application/yaml` or any `+yaml` suffix by default,
+/// - `application/json` (or any `+json` suffix) with the `json` feature,
+/// - `application/toml` with the `toml` feature.
+///
+/// Other content types are rejected with `415 Unsupported Media Type`, and
+/// parse failures with a rejection specific to the format. Whatever the
+/// format, the body is read like [`Yaml`](crate::Yaml) reads it, transcoded
+/// according to its `charset` and checked against its digests as configured.
+///
+/// The [`policy`](YamlConfig::policy), [`key_case`](YamlConfig::key_case),
+/// [`empty_as_none`](YamlConfig::empty_as_none) and `fill_defaults` settings
+/// apply to JSON and TOML documents as well, so switching the `Content-Type`
+/// does not get a denied document through. The parse limits, error
+/// collection and snippets only apply to YAML bodies.
+///
+/// # Example
+///
+/// ```no_run
+/// use axum::{routing::post, Router};
+/// use axum_yaml::AnyConfig;
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Settings {
+///     name: String,
+///     replicas: u32,
+/// }
+///
+/// async fn ingest(AnyConfig(settings): AnyConfig<Settings>) {
+///     // `settings` was posted as YAML, JSON or TOML
+/// }
+///
+/// let app = Router::new().route("/config", post(ingest));
+/// # let _: Router = app;
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AnyConfig<T>(pub T);
+
+/// Format of an [`AnyConfig`] body.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ConfigFormat {
+    /// YAML.
+    Yaml,
+    /// JSON, requires the `json` feature.
+    #[cfg(feature = "json")]
+    Json,
+    /// TOML, requires the `toml` feature.
+    #[cfg(feature = "toml")]
+    Toml,
+}
+
+impl ConfigFormat {
+    /// Select the format from the `Content-Type` header, if it is supported.
+    ///
+    /// YAML media types are recognized as under the default [`YamlConfig`].
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        Self::detect(&YamlConfig::default(), headers)
+    }
+
+    /// Select the format from the `Content-Type` header, recognizing YAML
+    /// media types according to `config`.
+    fn detect(config: &YamlConfig, headers: &HeaderMap) -> Option<Self> {
+        if config.yaml_content_type(headers) {
+            return Some(Self::Yaml);
+        }
+
+        let mime = headers
+            .get(header::CONTENT_TYPE)?
+            .to_str()
+            .ok()?
+            .parse::<mime::Mime>()
+            .ok()?;
+        if mime.type_() != "application" {
+            return None;
+        }
+
+        #[cfg(feature = "json")]
+        if mime.subtype() == "json" || mime.suffix().is_some_and(|name| name == "json") {
+            return Some(Self::Json);
+        }
+
+        #[cfg(feature = "toml")]
+        if mime.subtype() == "toml" {
+            return Some(Self::Toml);
+        }
+
+        None
+    }
+}
+
+impl<T, S> FromRequest<S> for AnyConfig<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = AnyConfigRejection;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let config = YamlConfig::from_extensions(req.extensions());
+        config
+            .report(async {
+                let Some(format) = ConfigFormat::detect(&config, req.headers()) else {
+                    config.drain(req).await;
+                    return Err(UnsupportedConfigContentType::default().into());
+                };
+
+                let bytes = config.read_body(req, state).await?;
+                if format == ConfigFormat::Yaml {
+                    return Ok(AnyConfig(config.deserialize_body(bytes).await?));
+                }
+                Self::parse(&config, format, &bytes)
+            })
+            .await
+    }
+}
+
+impl<T> AnyConfig<T>
+where
+    T: DeserializeOwned,
+{
+    /// Construct an `AnyConfig<T>` from a byte slice in the given `format`.
+    pub fn from_bytes(format: ConfigFormat, bytes: &[u8]) -> Result<Self, AnyConfigRejection> {
+        Self::parse(&YamlConfig::default(), format, bytes)
+    }
+
+    /// Deserialize `bytes` in `format`, YAML documents according to `config`.
+    fn parse(
+        config: &YamlConfig,
+        format: ConfigFormat,
+        bytes: &[u8],
+    ) -> Result<Self, AnyConfigRejection> {
+        match format {
+            ConfigFormat::Yaml => Ok(AnyConfig(config.deserialize(bytes)?)),
+            #[cfg(feature = "json")]
+            ConfigFormat::Json => {
+                let mut deserializer = serde_json::Deserializer::from_slice(bytes);
+                if config.needs_document() {
+                    let document =
+                        Value::deserialize(&mut deserializer).map_err(JsonError::from_err)?;
+                    deserializer.end().map_err(JsonError::from_err)?;
+                    return Self::from_document(config, document, |err| {
+                        JsonError::from_err(err.error).into()
+                    });
+                }
+
+                let value = serde_path_to_error::deserialize(&mut deserializer)
+                    .map_err(JsonError::from_err)?;
+                deserializer.end().map_err(JsonError::from_err)?;
+                Ok(AnyConfig(value))
+            }
+            #[cfg(feature = "toml")]
+            ConfigFormat::Toml => {
+                let text = std::str::from_utf8(bytes).map_err(TomlError::from_err)?;
+                let deserializer = toml::Deserializer::parse(text).map_err(TomlError::from_err)?;
+                if config.needs_document() {
+                    let document = Value::deserialize(deserializer).map_err(TomlError::from_err)?;
+                    return Self::from_document(config, document, |err| {
+                        TomlError::from_err(err.error).into()
+                    });
+                }
+
+                serde_path_to_error::deserialize(deserializer)
+                    .map(AnyConfig)
+                    .map_err(|err| TomlError::from_err(err).into())
+            }
+        }
+    }
+
+    /// Deserialize a JSON or TOML `document` after running it through the
+    /// policy, key case and defaults of `config`, like YAML documents.
+    #[cfg(any(feature = "json", feature = "toml"))]
+    fn from_document(
+        config: &YamlConfig,
+        document: Value,
+        error: fn(Classified) -> AnyConfigRejection,
+    ) -> Result<Self, AnyConfigRejection> {
+        let document = config.prepare_value(document)?;
+        config
+            .deserialize_value(document)
+            .map(AnyConfig)
+            .map_err(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use axum::routing::post;
+    use axum::Router;
+    use http::StatusCode;
+    use serde::Deserialize;
+
+    use crate::test_client::TestClient;
+
+    #[derive(Deserialize)]
+    struct Input {
+        foo: String,
+    }
+
+    async fn post_config(content_type: &str, body: &'static str) -> (StatusCode, String) {
+        let app = Router::new().route(
+            "/",
+            post(|AnyConfig(input): AnyConfig<Input>| async { input.foo }),
+        );
+
+        let res = TestClient::new(app)
+            .post("/")
+            .body(body)
+            .header("content-type", content_type)
+            .await;
+        (res.status(), res.text().await)
+    }
+
+    #[tokio::test]
+    async fn yaml() {
+        let (status, body) = post_config("application/yaml", "foo: bar").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, "bar");
+    }
+
+    #[cfg(feature = "json")]
+    #[tokio::test]
+    async fn json() {
+        let (status, body) = post_config("application/json", r#"{"foo": "bar"}"#).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, "bar");
+
+        let (status, body) = post_config("application/json", r#"{"foo": 1}"#).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body.starts_with("Failed to deserialize the JSON body"));
+    }
+
+    #[cfg(feature = "toml")]
+    #[tokio::test]
+    async fn toml() {
+        let (status, body) = post_config("application/toml", "foo = \"bar\"").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, "bar");
+
+        let (status, body) = post_config("application/toml", "foo = ").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body.starts_with("Failed to deserialize the TOML body"));
+    }
+
+    #[tokio::test]
+    async fn yaml_config() {
+        use axum::Extension;
+
+        let app = Router::new()
+            .route(
+                "/",
+                post(|AnyConfig(input): AnyConfig<Input>| async { input.foo }),
+            )
+            .layer(Extension(
+                YamlConfig::new()
+                    .accept_media_types(["text/yaml"])
+                    .vendor_media_types(["application/vnd.acme+yaml"])
+                    .transcode_charsets(true),
+            ));
+        let client = TestClient::new(app);
+
+        let res = client
+            .post("/")
+            .body(b"foo: caf\xE9".as_slice())
+            .header("content-type", "text/yaml; charset=iso-8859-1")
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.text().await, "café");
+
+        let res = client
+            .post("/")
+            .body("foo: bar")
+            .header("content-type", "application/vnd.other+yaml")
+            .await;
+        assert_eq!(res.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+
+    #[tokio::test]
+    async fn alias_limits() {
+        use axum::Extension;
+        use serde_yaml::Value;
+
+        let bomb = "a: &a [x, x, x, x]\nb: &b [*a, *a, *a, *a]\nc: [*b, *b, *b, *b]\n";
+        let app = |config: YamlConfig| {
+            let app = Router::new()
+                .route("/", post(|_: AnyConfig<Value>| async {}))
+                .layer(Extension(config));
+            TestClient::new(app)
+        };
+
+        let res = app(YamlConfig::new().max_aliases(7))
+            .post("/")
+            .body(bomb)
+            .header("content-type", "application/yaml")
+            .await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            res.text().await,
+            "YAML document exceeds a limit: body has 8 aliases, more than the limit of 7"
+        );
+
+        let res = app(YamlConfig::new().max_expanded_nodes(50))
+            .post("/")
+            .body(bomb)
+            .header("content-type", "application/yaml")
+            .await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            res.text().await,
+            "YAML document exceeds a limit: body has more than 50 nodes once aliases are expanded"
+        );
+    }
+
+    #[tokio::test]
+    async fn depth_limit() {
+        use axum::Extension;
+        use serde_yaml::Value;
+
+        let app = Router::new()
+            .route("/", post(|_: AnyConfig<Value>| async {}))
+            .layer(Extension(YamlConfig::new().max_depth(2)));
+        let client = TestClient::new(app);
+
+        let res = client
+            .post("/")
+            .body("a: [1, 2]")
+            .header("content-type", "application/yaml")
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let res = client
+            .post("/")
+            .body("a: [1, [2]]")
+            .header("content-type", "application/yaml")
+            .await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            res.text().await,
+            "YAML document exceeds a limit: document is nested more than 2 levels deep"
+        );
+    }
+
+    #[tokio::test]
+    async fn collection_limits() {
+        use axum::Extension;
+        use serde_yaml::Value;
+
+        let app = |config: YamlConfig| {
+            let app = Router::new()
+                .route("/", post(|_: AnyConfig<Value>| async {}))
+                .layer(Extension(config));
+            TestClient::new(app)
+        };
+        let body = "ports: [80, 443, 8080]\nuser: {name: alice, id: 7}\n";
+
+        let res = app(YamlConfig::new().max_sequence_len(2))
+            .post("/")
+            .body(body)
+            .header("content-type", "application/yaml")
+            .await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            res.text().await,
+            "YAML document exceeds a limit: sequence at `ports` has 3 items, more than the limit of 2"
+        );
+
+        let res = app(YamlConfig::new().max_mapping_keys(1))
+            .post("/")
+            .body(body)
+            .header("content-type", "application/yaml")
+            .await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            res.text().await,
+            "YAML document exceeds a limit: mapping at `.` has 2 keys, more than the limit of 1"
+        );
+    }
+
+    #[cfg(all(feature = "json", feature = "toml"))]
+    #[tokio::test]
+    async fn document_settings() {
+        use axum::Extension;
+        use serde_yaml::Value;
+
+        use crate::case::KeyCase;
+        use crate::policy::Policy;
+
+        let app = Router::new()
+            .route(
+                "/",
+                post(|AnyConfig(value): AnyConfig<Value>| async move {
+                    serde_yaml::to_string(&value).unwrap()
+                }),
+            )
+            .layer(Extension(
+                YamlConfig::new()
+                    .policy(Policy::new().deny_value("spec.host_network", true))
+                    .key_case(KeyCase::Snake),
+            ));
+        let client = TestClient::new(app);
+
+        let denied = [
+            ("application/yaml", "spec: {hostNetwork: true}"),
+            ("application/json", r#"{"spec": {"hostNetwork": true}}"#),
+            ("application/toml", "[spec]\nhostNetwork = true"),
+        ];
+        for (content_type, body) in denied {
+            let res = client
+                .post("/")
+                .body(body)
+                .header("content-type", content_type)
+                .await;
+            assert_eq!(res.status(), StatusCode::FORBIDDEN, "{content_type}");
+            assert_eq!(
+                res.text().await,
+                "Document violates policy: `spec.host_network: true` is not allowed"
+            );
+        }
+
+        let res = client
+            .post("/")
+            .body(r#"{"spec": {"hostNetwork": false}}"#)
+            .header("content-type", "application/json")
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.text().await, "spec:\n  host_network: false\n");
+    }
+
+    #[tokio::test]
+    async fn unsupported_content_type() {
+        let (status, _) = post_config("text/plain", "foo: bar").await;
+        assert_eq!(status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+}
@@ -0,0 +1,329 @@
+//! Extractor trying several target types in turn.
+//!
+//! Deserializing into a top-level `#[serde(untagged)]` enum reports only
+//! "data did not match any variant", which leaves clients guessing.
+//! [`YamlAnyOf`] tries each candidate type in order instead, returns the
+//! first one that matched, and otherwise rejects the request with the reason
+//! every candidate failed.
+//!
+//! ```no_run
+//! use axum::{routing::post, Router};
+//! use axum_yaml::any_of::{AnyOf2, YamlAnyOf};
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize)]
+//! struct UserV2 {
+//!     name: String,
+//!     email: String,
+//! }
+//!
+//! #[derive(Deserialize)]
+//! struct UserV1 {
+//!     username: String,
+//! }
+//!
+//! async fn create(YamlAnyOf(user): YamlAnyOf<(UserV2, UserV1)>) {
+//!     match user {
+//!         AnyOf2::A(v2) => { /* ... */ }
+//!         AnyOf2::B(v1) => { /* migrate ... */ }
+//!     }
+//! }
+//!
+//! let app = Router::new().route("/users", post(create));
+//! # let _: Router = app;
+//! ```
+
+use std::fmt;
+use std::time::Instant;
+
+use axum_core::extract::{FromRequest, Request};
+use serde::de::DeserializeOwned;
+use serde_yaml::Value;
+
+use crate::rejection::*;
+use crate::timing::ParseTiming;
+use crate::YamlConfig;
+
+/// Extractor deserializing the request body into the first of several
+/// candidate types that accepts it.
+///
+/// `T` is a tuple of two to six candidate types, tried from left to right.
+/// The extracted value is an [`AnyOf2`] to [`AnyOf6`] whose variant tells
+/// which candidate matched. If none does, the request is rejected with a
+/// [`YamlError`] listing the error of every candidate.
+///
+/// See the [module documentation](self) for an example.
+pub struct YamlAnyOf<T: Candidates>(pub T::Output);
+
+impl<T, S> FromRequest<S> for YamlAnyOf<T>
+where
+    T: Candidates,
+    S: Send + Sync,
+{
+    type Rejection = YamlRejection;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let config = YamlConfig::from_extensions(req.extensions());
+        config
+            .report(async {
+                if config.yaml_content_type(req.headers()) {
+                    let timing = req.extensions().get::<ParseTiming>().cloned();
+                    let bytes = config.read_body(req, state).await?;
+
+                    let start = Instant::now();
+                    let result = config
+                        .document_body(bytes)
+                        .await
+                        .and_then(|document| Self::from_document(&config, document));
+                    if let Some(timing) = timing {
+                        timing.record(start.elapsed());
+                    }
+                    result
+                } else {
+                    config.drain(req).await;
+                    Err(config.missing_content_type().into())
+                }
+            })
+            .await
+    }
+}
+
+impl<T> YamlAnyOf<T>
+where
+    T: Candidates,
+{
+    /// Construct a `YamlAnyOf<T>` from a byte slice, trying every candidate
+    /// in turn.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, YamlRejection> {
+        let config = YamlConfig::new();
+        Self::from_document(&config, config.document(bytes)?)
+    }
+
+    fn from_document(config: &YamlConfig, document: Value) -> Result<Self, YamlRejection> {
+        T::deserialize(config, document)
+            .map(YamlAnyOf)
+            .map_err(|errors| {
+                config.error_status(
+                    YamlError::new(
+                        YamlErrorKind::InvalidValue,
+                        format!("no candidate matched: {}", errors.join("; ")),
+                    )
+                    .into(),
+                )
+            })
+    }
+}
+
+impl<T> fmt::Debug for YamlAnyOf<T>
+where
+    T: Candidates,
+    T::Output: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("YamlAnyOf").field(&self.0).finish()
+    }
+}
+
+impl<T> Clone for YamlAnyOf<T>
+where
+    T: Candidates,
+    T::Output: Clone,
+{
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+/// Extractor deserializing the request body as `A`, or as `B` if that fails.
+///
+/// Shorthand for a [`YamlAnyOf`] of two candidates that can be destructured
+/// in handler arguments, e.g. for a route accepting two versions of a
+/// payload.
+///
+/// ```no_run
+/// use axum::{routing::post, Router};
+/// use axum_yaml::any_of::{AnyOf2, EitherYaml};
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct UserV2 {
+///     name: String,
+/// }
+///
+/// #[derive(Deserialize)]
+/// struct UserV1 {
+///     username: String,
+/// }
+///
+/// async fn create(EitherYaml(user): EitherYaml<UserV2, UserV1>) -> String {
+///     match user {
+///         AnyOf2::A(v2) => v2.name,
+///         AnyOf2::B(v1) => v1.username,
+///     }
+/// }
+///
+/// let app = Router::new().route("/users", post(create));
+/// # let _: Router = app;
+/// ```
+#[derive(Debug, Clone)]
+pub struct EitherYaml<A, B>(pub AnyOf2<A, B>);
+
+impl<A, B, S> FromRequest<S> for EitherYaml<A, B>
+where
+    A: DeserializeOwned,
+    B: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = YamlRejection;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let YamlAnyOf(value) = YamlAnyOf::<(A, B)>::from_request(req, state).await?;
+        Ok(EitherYaml(value))
+    }
+}
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// Tuple of candidate types for [`YamlAnyOf`].
+///
+/// Implemented for tuples of two to six types implementing
+/// [`serde::de::DeserializeOwned`].
+pub trait Candidates: sealed::Sealed {
+    /// Enum holding the candidate that matched.
+    type Output;
+
+    #[doc(hidden)]
+    fn deserialize(config: &YamlConfig, document: Value) -> Result<Self::Output, Vec<String>>;
+}
+
+macro_rules! any_of {
+    ($name:ident { $($ty:ident),+ }) => {
+        /// Candidate of a [`YamlAnyOf`] that matched, named after its
+        /// position in the candidate tuple.
+        #[derive(Debug, Clone, PartialEq)]
+        pub enum $name<$($ty),+> {
+            $(
+                #[allow(missing_docs)]
+                $ty($ty),
+            )+
+        }
+
+        impl<$($ty),+> sealed::Sealed for ($($ty,)+) {}
+
+        impl<$($ty),+> Candidates for ($($ty,)+)
+        where
+            $($ty: DeserializeOwned,)+
+        {
+            type Output = $name<$($ty),+>;
+
+            fn deserialize(
+                config: &YamlConfig,
+                document: Value,
+            ) -> Result<Self::Output, Vec<String>> {
+                let mut errors = Vec::new();
+                $(
+                    match config.deserialize_value::<$ty>(document.clone()) {
+                        Ok(value) => return Ok($name::$ty(value)),
+                        Err(err) => {
+                            errors.push(format!("`{}`: {}", std::any::type_name::<$ty>(), err.error))
+                        }
+                    }
+                )+
+                Err(errors)
+            }
+        }
+    };
+}
+
+any_of!(AnyOf2 { A, B });
+any_of!(AnyOf3 { A, B, C });
+any_of!(AnyOf4 { A, B, C, D });
+any_of!(AnyOf5 { A, B, C, D, E });
+any_of!(AnyOf6 { A, B, C, D, E, F });
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use axum::routing::post;
+    use axum::Router;
+    use http::StatusCode;
+    use serde::Deserialize;
+
+    use crate::test_client::TestClient;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct V2 {
+        name: String,
+        email: String,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct V1 {
+        username: String,
+    }
+
+    #[test]
+    fn first_match() {
+        let YamlAnyOf(user) =
+            YamlAnyOf::<(V2, V1)>::from_bytes(b"name: alice\nemail: a@example.com\n").unwrap();
+        assert_eq!(
+            user,
+            AnyOf2::A(V2 {
+                name: "alice".to_owned(),
+                email: "a@example.com".to_owned(),
+            })
+        );
+
+        let YamlAnyOf(user) = YamlAnyOf::<(V2, V1)>::from_bytes(b"username: alice\n").unwrap();
+        assert_eq!(
+            user,
+            AnyOf2::B(V1 {
+                username: "alice".to_owned(),
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn no_match() {
+        let app = Router::new().route("/", post(|_: YamlAnyOf<(V2, V1, u32)>| async {}));
+
+        let res = TestClient::new(app)
+            .post("/")
+            .body("name: alice")
+            .header("content-type", "application/yaml")
+            .await;
+
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let body = res.text().await;
+        assert!(body.contains("no candidate matched"));
+        assert!(body.contains("V2`: missing field `email`"));
+        assert!(body.contains("V1`: missing field `username`"));
+        assert!(body.contains("`u32`: invalid type: map"));
+    }
+
+    #[tokio::test]
+    async fn either() {
+        let app = Router::new().route(
+            "/",
+            post(|EitherYaml(user): EitherYaml<V2, V1>| async move {
+                match user {
+                    AnyOf2::A(v2) => v2.name,
+                    AnyOf2::B(v1) => v1.username,
+                }
+            }),
+        );
+
+        let res = TestClient::new(app)
+            .post("/")
+            .body("username: bob")
+            .header("content-type", "application/yaml")
+            .await;
+
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.text().await, "bob");
+    }
+}
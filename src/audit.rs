@@ -0,0 +1,306 @@
+//! Auditing of rejected request bodies.
+//!
+//! [`AuditLayer`] keeps a copy of the request body as the extractors of this
+//! crate read it, and hands it to an [`AuditSink`] along with the rejection
+//! when the request is rejected, so teams can debug client integrations
+//! after the fact. Accepted requests are not reported.
+//!
+//! Sinks are called on the request path: those writing to files, object
+//! stores or message queues should hand the record over to a background task.
+//! Store a digest of [`AuditRecord::body`] instead of the body itself when
+//! payloads may carry secrets.
+//!
+//! ```no_run
+//! use axum::{routing::post, Router};
+//! use axum_yaml::{audit::{AuditLayer, AuditRecord}, Yaml};
+//! use serde_yaml::Value;
+//!
+//! async fn create(Yaml(manifest): Yaml<Value>) {}
+//!
+//! let app: Router = Router::new()
+//!     .route("/manifests", post(create))
+//!     .layer(AuditLayer::new(|record: AuditRecord| {
+//!         eprintln!(
+//!             "rejected {} {}: {}\n{}",
+//!             record.method(),
+//!             record.uri(),
+//!             record.rejection().message(),
+//!             String::from_utf8_lossy(record.body()),
+//!         );
+//!     }));
+//! ```
+
+use std::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+
+use axum_core::{body::Body, response::Response};
+use bytes::{Bytes, BytesMut};
+use http::{Method, Request, Uri};
+use http_body_util::BodyExt;
+use pin_project_lite::pin_project;
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::rejection::RejectionBody;
+
+/// Bytes of a request body kept by default, see [`AuditLayer::max_body`].
+const DEFAULT_MAX_BODY: usize = 64 * 1024;
+
+/// Destination of the [`AuditRecord`]s of rejected requests.
+///
+/// Implemented for closures taking an [`AuditRecord`].
+pub trait AuditSink: Send + Sync + 'static {
+    /// Record a rejected request.
+    fn record(&self, record: AuditRecord);
+}
+
+impl<F> AuditSink for F
+where
+    F: Fn(AuditRecord) + Send + Sync + 'static,
+{
+    fn record(&self, record: AuditRecord) {
+        self(record)
+    }
+}
+
+/// Rejected request reported to an [`AuditSink`].
+#[derive(Debug, Clone)]
+pub struct AuditRecord {
+    method: Method,
+    uri: Uri,
+    rejection: RejectionBody,
+    body: Bytes,
+    truncated: bool,
+}
+
+impl AuditRecord {
+    /// Get the method of the rejected request.
+    pub fn method(&self) -> &Method {
+        &self.method
+    }
+
+    /// Get the URI of the rejected request.
+    pub fn uri(&self) -> &Uri {
+        &self.uri
+    }
+
+    /// Get the rejection of the request.
+    pub fn rejection(&self) -> &RejectionBody {
+        &self.rejection
+    }
+
+    /// Get the bytes of the request body read before the rejection, as
+    /// received.
+    pub fn body(&self) -> &[u8] {
+        &self.body
+    }
+
+    /// Get whether the body was longer than the [`AuditLayer::max_body`] kept.
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+}
+
+/// [`Layer`] reporting the bodies of rejected requests to an [`AuditSink`].
+#[derive(Clone)]
+pub struct AuditLayer {
+    sink: Arc<dyn AuditSink>,
+    max_body: usize,
+}
+
+impl AuditLayer {
+    /// Create a layer reporting rejected requests to `sink`.
+    pub fn new<K>(sink: K) -> Self
+    where
+        K: AuditSink,
+    {
+        Self {
+            sink: Arc::new(sink),
+            max_body: DEFAULT_MAX_BODY,
+        }
+    }
+
+    /// Keep at most `max` bytes of each request body, 64 KiB by default.
+    pub fn max_body(mut self, max: usize) -> Self {
+        self.max_body = max;
+        self
+    }
+}
+
+impl fmt::Debug for AuditLayer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AuditLayer")
+            .field("max_body", &self.max_body)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S> Layer<S> for AuditLayer {
+    type Service = Audit<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Audit {
+            inner,
+            sink: self.sink.clone(),
+            max_body: self.max_body,
+        }
+    }
+}
+
+/// Middleware created by [`AuditLayer`].
+#[derive(Clone)]
+pub struct Audit<S> {
+    inner: S,
+    sink: Arc<dyn AuditSink>,
+    max_body: usize,
+}
+
+impl<S> fmt::Debug for Audit<S>
+where
+    S: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Audit")
+            .field("inner", &self.inner)
+            .field("max_body", &self.max_body)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Body bytes read by the extractors, shared with the response future.
+#[derive(Debug, Default)]
+struct Captured {
+    body: BytesMut,
+    truncated: bool,
+}
+
+impl<S> Service<Request<Body>> for Audit<S>
+where
+    S: Service<Request<Body>, Response = Response>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        let captured = Arc::new(Mutex::new(Captured::default()));
+        let method = req.method().clone();
+        let uri = req.uri().clone();
+
+        let max_body = self.max_body;
+        let req = req.map(|body| {
+            let captured = captured.clone();
+            Body::new(body.map_frame(move |frame| {
+                if let Some(data) = frame.data_ref() {
+                    let mut captured = captured.lock().unwrap_or_else(|err| err.into_inner());
+                    let kept = data.len().min(max_body - captured.body.len());
+                    captured.body.extend_from_slice(&data[..kept]);
+                    captured.truncated |= kept < data.len();
+                }
+                frame
+            }))
+        });
+
+        ResponseFuture {
+            inner: self.inner.call(req),
+            sink: self.sink.clone(),
+            captured,
+            method,
+            uri,
+        }
+    }
+}
+
+pin_project! {
+    /// Response future for [`Audit`].
+    pub struct ResponseFuture<F> {
+        #[pin]
+        inner: F,
+        sink: Arc<dyn AuditSink>,
+        captured: Arc<Mutex<Captured>>,
+        method: Method,
+        uri: Uri,
+    }
+}
+
+impl<F, E> Future for ResponseFuture<F>
+where
+    F: Future<Output = Result<Response, E>>,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let res = std::task::ready!(this.inner.poll(cx))?;
+        if let Some(rejection) = res.extensions().get::<RejectionBody>() {
+            let mut captured = this.captured.lock().unwrap_or_else(|err| err.into_inner());
+            this.sink.record(AuditRecord {
+                method: this.method.clone(),
+                uri: this.uri.clone(),
+                rejection: rejection.clone(),
+                body: std::mem::take(&mut captured.body).freeze(),
+                truncated: captured.truncated,
+            });
+        }
+        Poll::Ready(Ok(res))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use axum::routing::post;
+    use axum::Router;
+    use http::StatusCode;
+    use serde_yaml::Value;
+
+    use crate::test_client::TestClient;
+    use crate::Yaml;
+
+    #[tokio::test]
+    async fn record_rejections() {
+        let records = Arc::new(Mutex::new(Vec::new()));
+        let sink = {
+            let records = records.clone();
+            move |record: AuditRecord| records.lock().unwrap().push(record)
+        };
+        let app = Router::new()
+            .route("/", post(|Yaml(value): Yaml<Value>| async { Yaml(value) }))
+            .layer(AuditLayer::new(sink).max_body(6));
+        let client = TestClient::new(app);
+
+        let res = client
+            .post("/?dry_run")
+            .body("a: [1, 2")
+            .header("content-type", "application/yaml")
+            .await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+        let res = client
+            .post("/")
+            .body("a: 1")
+            .header("content-type", "application/yaml")
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let records = records.lock().unwrap();
+        let [record] = &records[..] else {
+            panic!("one request was rejected, got {records:?}");
+        };
+        assert_eq!(record.method(), Method::POST);
+        assert_eq!(record.uri(), "/?dry_run");
+        assert_eq!(record.rejection().code(), "syntax");
+        assert_eq!(record.body(), b"a: [1,");
+        assert!(record.is_truncated());
+    }
+}
@@ -0,0 +1,270 @@
+//! Validation of YAML fixtures from a build script.
+//!
+//! Requires the `build` feature. [`Fixtures`] checks every `.yaml` and `.yml`
+//! file under a directory, failing the build with `file:line:column`
+//! diagnostics instead of leaving broken bundles to be discovered at runtime.
+//!
+//! ```no_run
+//! // build.rs
+//! use axum_yaml::build::Fixtures;
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize)]
+//! struct Fixture {
+//!     name: String,
+//! }
+//!
+//! fn main() {
+//!     Fixtures::new("fixtures").deserialize_as::<Fixture>().run();
+//! }
+//! ```
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+type Location = Option<(usize, usize)>;
+type Check = fn(serde_yaml::Deserializer<'_>) -> Result<(), (Location, String)>;
+
+/// Set of YAML files to validate.
+#[derive(Debug, Clone)]
+pub struct Fixtures {
+    dir: PathBuf,
+    check: Option<(&'static str, Check)>,
+    #[cfg(feature = "schemars")]
+    schema: Option<schemars::Schema>,
+}
+
+impl Fixtures {
+    /// Validate the YAML files under `dir`, recursively.
+    ///
+    /// By default only the syntax of the files is checked.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            check: None,
+            #[cfg(feature = "schemars")]
+            schema: None,
+        }
+    }
+
+    /// Also check that every document deserializes into `T`.
+    pub fn deserialize_as<T>(mut self) -> Self
+    where
+        T: DeserializeOwned,
+    {
+        fn check<T: DeserializeOwned>(
+            deserializer: serde_yaml::Deserializer<'_>,
+        ) -> Result<(), (Location, String)> {
+            serde_path_to_error::deserialize::<_, T>(deserializer)
+                .map(drop)
+                .map_err(|err| (location(err.inner()), err.to_string()))
+        }
+
+        self.check = Some((std::any::type_name::<T>(), check::<T>));
+        self
+    }
+
+    /// Also check that every document conforms to `schema`.
+    ///
+    /// Requires the `schemars` feature.
+    #[cfg(feature = "schemars")]
+    pub fn schema(mut self, schema: schemars::Schema) -> Self {
+        self.schema = Some(schema);
+        self
+    }
+
+    /// Validate the files, returning every problem found.
+    pub fn check(&self) -> Result<(), FixtureErrors> {
+        let mut files = Vec::new();
+        collect_files(&self.dir, &mut files).map_err(|err| {
+            FixtureErrors(vec![FixtureError {
+                file: self.dir.clone(),
+                location: None,
+                message: err.to_string(),
+            }])
+        })?;
+        files.sort();
+
+        let mut errors = Vec::new();
+        for file in files {
+            self.check_file(&file, &mut errors);
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(FixtureErrors(errors))
+        }
+    }
+
+    /// Validate the files from a build script, panicking with every problem
+    /// found.
+    ///
+    /// Cargo is told to rerun the build script when the directory changes.
+    pub fn run(&self) {
+        println!("cargo:rerun-if-changed={}", self.dir.display());
+        if let Err(errors) = self.check() {
+            panic!("invalid YAML fixtures:\n{errors}");
+        }
+    }
+
+    fn check_file(&self, file: &Path, errors: &mut Vec<FixtureError>) {
+        let mut error = |location: Location, message: String| {
+            errors.push(FixtureError {
+                file: file.to_owned(),
+                location,
+                message,
+            })
+        };
+
+        let source = match fs::read_to_string(file) {
+            Ok(source) => source,
+            Err(err) => return error(None, err.to_string()),
+        };
+
+        for (index, document) in serde_yaml::Deserializer::from_str(&source).enumerate() {
+            let result = match self.check {
+                Some((_, check)) => check(document),
+                None => serde::de::IgnoredAny::deserialize(document)
+                    .map(drop)
+                    .map_err(|err| (location(&err), err.to_string())),
+            };
+            if let Err((location, err)) = result {
+                let message = match self.check {
+                    Some((ty, _)) => format!("document #{index} does not match `{ty}`: {err}"),
+                    None => format!("document #{index}: {err}"),
+                };
+                error(location, message);
+                // The remaining documents cannot be located after an error.
+                return;
+            }
+        }
+
+        #[cfg(feature = "schemars")]
+        if let Some(schema) = &self.schema {
+            for (index, document) in serde_yaml::Deserializer::from_str(&source).enumerate() {
+                let document = match serde_yaml::Value::deserialize(document) {
+                    Ok(document) => document,
+                    Err(err) => return error(None, err.to_string()),
+                };
+                if let Err(violations) = crate::contract::validate_schema(schema, &document) {
+                    for violation in violations.0 {
+                        error(None, format!("document #{index}: {violation}"));
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn location(err: &serde_yaml::Error) -> Location {
+    err.location().map(|at| (at.line(), at.column()))
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else if path
+            .extension()
+            .is_some_and(|extension| extension == "yaml" || extension == "yml")
+        {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Problem found in a YAML file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FixtureError {
+    /// The file holding the problem.
+    pub file: PathBuf,
+    /// One-based line and column of the problem, when known.
+    pub location: Option<(usize, usize)>,
+    /// Description of the problem.
+    pub message: String,
+}
+
+impl fmt::Display for FixtureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.file.display())?;
+        if let Some((line, column)) = self.location {
+            write!(f, ":{line}:{column}")?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+/// All problems found while validating fixtures.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FixtureErrors(pub Vec<FixtureError>);
+
+impl fmt::Display for FixtureErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, error) in self.0.iter().enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{error}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for FixtureErrors {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Deserialize)]
+    #[allow(dead_code)]
+    struct Fixture {
+        name: String,
+    }
+
+    fn fixtures(files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("axum-yaml-fixtures-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        for (name, source) in files {
+            let path = dir.join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, source).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn diagnostics() {
+        let dir = fixtures(&[
+            ("a.yaml", "name: a\n"),
+            ("nested/b.yml", "name: b\n---\nname: [b]\n"),
+            ("c.yaml", "name: c\n  bad: indent\n"),
+            ("notes.txt", "not yaml: ["),
+        ]);
+
+        let errors = Fixtures::new(&dir).check().unwrap_err();
+        assert_eq!(errors.0.len(), 1);
+        assert_eq!(errors.0[0].file, dir.join("c.yaml"));
+        assert_eq!(errors.0[0].location, Some((2, 6)));
+
+        let errors = Fixtures::new(&dir)
+            .deserialize_as::<Fixture>()
+            .check()
+            .unwrap_err();
+        let files: Vec<_> = errors.0.iter().map(|error| &error.file).collect();
+        assert_eq!(files, [&dir.join("c.yaml"), &dir.join("nested/b.yml")]);
+        assert_eq!(errors.0[1].location, Some((3, 7)));
+        assert!(errors.0[1]
+            .message
+            .starts_with("document #1 does not match `axum_yaml::build::tests::Fixture`: name:"));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,459 @@
+use std::time::Instant;
+
+use axum_core::{
+    extract::{FromRequest, Request},
+    response::{IntoResponse, Response},
+};
+use bytes::{BufMut, BytesMut};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_yaml::Value;
+
+use crate::rejection::*;
+use crate::ser::{self, SerializeConfig};
+use crate::timing::{ParseTiming, Step};
+use crate::YamlConfig;
+
+/// Multi-document YAML bundle of named resources.
+///
+/// When used as a response, every resource is written as its own document,
+/// introduced by a `--- # <name>` header comment identifying it. When used as
+/// an extractor, a bundle produced this way is split back into its documents
+/// and each one is deserialized into `T`, so a whole set of resources can be
+/// backed up and restored with a single request.
+///
+/// # Example
+///
+/// ```no_run
+/// use axum::{routing::get, Router};
+/// use axum_yaml::YamlBundle;
+/// use serde_yaml::Value;
+///
+/// async fn export() -> YamlBundle<Value> {
+///     YamlBundle(vec![
+///         ("users".to_owned(), Value::Null),
+///         ("groups".to_owned(), Value::Null),
+///     ])
+/// }
+///
+/// async fn import(YamlBundle(documents): YamlBundle<Value>) {
+///     for (name, document) in documents {
+///         // dispatch `document` by `name`
+///     }
+/// }
+///
+/// let app = Router::new().route("/bundle", get(export).post(import));
+/// # let _: Router = app;
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct YamlBundle<T>(pub Vec<(String, T)>);
+
+impl<T, S> FromRequest<S> for YamlBundle<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = YamlRejection;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let config = YamlConfig::from_extensions(req.extensions());
+        config
+            .report(async {
+                if config.yaml_content_type(req.headers()) {
+                    let timing = req.extensions().get::<ParseTiming>().cloned();
+                    let bytes = config.read_body(req, state).await?;
+
+                    let start = Instant::now();
+                    let result = match config.within_timeout(&bytes, prepare).await {
+                        Some(documents) => {
+                            documents.and_then(|documents| Self::from_prepared(&config, documents))
+                        }
+                        None => Self::parse(&config, &bytes),
+                    };
+                    if let Some(timing) = timing {
+                        timing.record(start.elapsed());
+                    }
+                    result
+                } else {
+                    config.drain(req).await;
+                    Err(config.missing_content_type().into())
+                }
+            })
+            .await
+    }
+}
+
+impl<T> YamlBundle<T>
+where
+    T: DeserializeOwned,
+{
+    /// Construct a `YamlBundle<T>` from a byte slice containing `---` separated
+    /// documents, each introduced by a `--- # <name>` header comment.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, YamlRejection> {
+        Self::parse(&YamlConfig::default(), bytes)
+    }
+
+    /// Split `bytes` into named documents deserialized according to `config`.
+    fn parse(config: &YamlConfig, bytes: &[u8]) -> Result<Self, YamlRejection> {
+        let mut documents = Vec::new();
+        for (name, source) in named_documents(config, bytes)? {
+            let deserializer = serde_yaml::Deserializer::from_str(source);
+            let value = config
+                .deserialize_document(deserializer)
+                .map_err(|rejection| in_document(rejection, name))?;
+            documents.push((name.to_owned(), value));
+        }
+        Ok(YamlBundle(documents))
+    }
+
+    /// Deserialize documents returned by [`prepare`] according to `config`.
+    fn from_prepared(
+        config: &YamlConfig,
+        documents: Vec<(String, Value)>,
+    ) -> Result<Self, YamlRejection> {
+        documents
+            .into_iter()
+            .map(
+                |(name, document)| match config.deserialize_prepared(document) {
+                    Ok(value) => Ok((name, value)),
+                    Err(rejection) => Err(in_document(rejection, &name)),
+                },
+            )
+            .collect::<Result<_, _>>()
+            .map(YamlBundle)
+    }
+}
+
+/// Split `bytes` into named document sources, once the whole bundle is
+/// checked against the limits of `config`.
+fn named_documents<'a>(
+    config: &YamlConfig,
+    bytes: &'a [u8],
+) -> Result<Vec<(&'a str, &'a str)>, YamlRejection> {
+    let text =
+        std::str::from_utf8(bytes).map_err(|err| YamlError::new(YamlErrorKind::Syntax, err))?;
+    config.check_source(bytes)?;
+
+    let mut documents = Vec::new();
+    for (index, (name, source)) in split_documents(text).into_iter().enumerate() {
+        config.check_document_count(index + 1)?;
+        let Some(name) = name else {
+            return Err(InvalidYamlBundle::from_err(format!(
+                "document #{index} has no `--- # <name>` header"
+            ))
+            .into());
+        };
+        documents.push((name, source));
+    }
+    Ok(documents)
+}
+
+/// Parse every named document of `bytes` like [`YamlConfig::document`].
+fn prepare(config: &YamlConfig, bytes: &[u8]) -> Result<Vec<(String, Value)>, YamlRejection> {
+    named_documents(config, bytes)?
+        .into_iter()
+        .map(|(name, source)| {
+            config
+                .prepare(serde_yaml::Deserializer::from_str(source))
+                .map(|document| (name.to_owned(), document))
+                .map_err(|rejection| in_document(rejection, name))
+        })
+        .collect()
+}
+
+/// Name the document `name` in the error of `rejection`.
+fn in_document(rejection: YamlRejection, name: &str) -> YamlRejection {
+    match rejection {
+        YamlRejection::YamlError(err) => err.in_document(name).into(),
+        rejection => rejection,
+    }
+}
+
+/// Split `text` into documents at `---` markers, returning the name found in
+/// each marker's header comment along with the document source.
+///
+/// Anything before the first marker that is not a comment or a directive is
+/// returned as an unnamed document.
+fn split_documents(text: &str) -> Vec<(Option<&str>, &str)> {
+    let mut documents = Vec::new();
+    let mut current: Option<(Option<&str>, usize)> = None;
+    let mut preamble_has_content = false;
+
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        let trimmed = line.trim_end();
+        if let Some(rest) = document_marker(trimmed) {
+            match current {
+                Some((name, start)) => documents.push((name, &text[start..offset])),
+                None if preamble_has_content => documents.push((None, &text[..offset])),
+                None => {}
+            }
+
+            let name = rest
+                .trim_start()
+                .strip_prefix('#')
+                .map(str::trim)
+                .filter(|name| !name.is_empty());
+            current = Some((name, offset));
+        } else if current.is_none() {
+            let content = trimmed.trim_start();
+            if !content.is_empty() && !content.starts_with('#') && !content.starts_with('%') {
+                preamble_has_content = true;
+            }
+        }
+        offset += line.len();
+    }
+
+    match current {
+        Some((name, start)) => documents.push((name, &text[start..])),
+        None if preamble_has_content => documents.push((None, text)),
+        None => {}
+    }
+
+    documents
+}
+
+fn document_marker(line: &str) -> Option<&str> {
+    let rest = line.strip_prefix("---")?;
+    (rest.is_empty() || rest.starts_with([' ', '\t'])).then_some(rest)
+}
+
+impl<T> IntoResponse for YamlBundle<T>
+where
+    T: Serialize,
+{
+    fn into_response(self) -> Response {
+        self.response(&SerializeConfig::current())
+    }
+}
+
+impl<T> YamlBundle<T>
+where
+    T: Serialize,
+{
+    /// Serialize the documents into a response according to `config`.
+    fn response(&self, config: &SerializeConfig) -> Response {
+        let step = Step::serialize(self.0.len());
+        let mut buf = BytesMut::with_capacity(128).writer();
+        for (name, document) in &self.0 {
+            let name = name.replace(['\r', '\n'], " ");
+            buf.get_mut()
+                .put_slice(format!("--- # {name}\n").as_bytes());
+
+            if let Err(err) = config.to_writer(&mut buf, document) {
+                return ser::error_response(err);
+            }
+        }
+
+        config.finish(buf.into_inner(), step)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use axum::routing::post;
+    use axum::Router;
+    use http::StatusCode;
+    use serde_yaml::Value;
+
+    use crate::test_client::TestClient;
+
+    #[tokio::test]
+    async fn round_trip() {
+        let app = Router::new().route(
+            "/",
+            post(|YamlBundle(documents): YamlBundle<Value>| async move { YamlBundle(documents) }),
+        );
+
+        let body = "--- # users\n- alice\n- bob\n--- # groups\nadmins:\n- alice\n";
+        let res = TestClient::new(app)
+            .post("/")
+            .body(body)
+            .header("content-type", "application/yaml")
+            .await;
+
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.text().await, body);
+    }
+
+    #[tokio::test]
+    async fn serialize_config() {
+        use crate::ser::FloatFormat;
+
+        let metrics: Value = serde_yaml::from_str("b: 1.5\na: 2.0\n").unwrap();
+        let config = SerializeConfig::new()
+            .sort_keys(true)
+            .float_format(FloatFormat::new().precision(2));
+        let res = YamlBundle(vec![("metrics".to_owned(), metrics)]).response(&config);
+
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(body, "--- # metrics\na: 2.00\nb: 1.50\n");
+    }
+
+    #[tokio::test]
+    async fn alias_limits() {
+        use axum::Extension;
+
+        let bomb = "--- # bomb\na: &a [x, x, x, x]\nb: &b [*a, *a, *a, *a]\nc: [*b, *b, *b, *b]\n";
+        let app = |config: YamlConfig| {
+            let app = Router::new()
+                .route("/", post(|_: YamlBundle<Value>| async {}))
+                .layer(Extension(config));
+            TestClient::new(app)
+        };
+
+        let res = app(YamlConfig::new().max_aliases(7))
+            .post("/")
+            .body(bomb)
+            .header("content-type", "application/yaml")
+            .await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            res.text().await,
+            "YAML document exceeds a limit: body has 8 aliases, more than the limit of 7"
+        );
+
+        let res = app(YamlConfig::new().max_expanded_nodes(50))
+            .post("/")
+            .body(bomb)
+            .header("content-type", "application/yaml")
+            .await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            res.text().await,
+            "YAML document exceeds a limit: body has more than 50 nodes once aliases are expanded"
+        );
+    }
+
+    #[tokio::test]
+    async fn depth_limit() {
+        use axum::Extension;
+
+        let app = Router::new()
+            .route("/", post(|_: YamlBundle<Value>| async {}))
+            .layer(Extension(YamlConfig::new().max_depth(2)));
+        let client = TestClient::new(app);
+
+        let res = client
+            .post("/")
+            .body("--- # a\na: [1, 2]\n--- # b\nb: 2\n")
+            .header("content-type", "application/yaml")
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let res = client
+            .post("/")
+            .body("--- # a\na: 1\n--- # b\nb: [1, [2]]\n")
+            .header("content-type", "application/yaml")
+            .await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            res.text().await,
+            "YAML document exceeds a limit: document is nested more than 2 levels deep"
+        );
+    }
+
+    #[tokio::test]
+    async fn collection_limits() {
+        use axum::Extension;
+
+        let app = |config: YamlConfig| {
+            let app = Router::new()
+                .route("/", post(|_: YamlBundle<Value>| async {}))
+                .layer(Extension(config));
+            TestClient::new(app)
+        };
+        let body = "--- # a\nports: [80, 443]\n--- # b\n{a: 1, b: 2, c: 3}\n";
+
+        let res = app(YamlConfig::new().max_sequence_len(2).max_mapping_keys(3))
+            .post("/")
+            .body(body)
+            .header("content-type", "application/yaml")
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let res = app(YamlConfig::new().max_sequence_len(1))
+            .post("/")
+            .body(body)
+            .header("content-type", "application/yaml")
+            .await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            res.text().await,
+            "YAML document exceeds a limit: sequence at `ports` has 2 items, more than the limit of 1"
+        );
+
+        let res = app(YamlConfig::new().max_mapping_keys(2))
+            .post("/")
+            .body(body)
+            .header("content-type", "application/yaml")
+            .await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            res.text().await,
+            "YAML document exceeds a limit: mapping at `.` has 3 keys, more than the limit of 2"
+        );
+    }
+
+    #[tokio::test]
+    async fn max_documents() {
+        use axum::Extension;
+
+        let app = Router::new()
+            .route("/", post(|_: YamlBundle<Value>| async {}))
+            .layer(Extension(YamlConfig::new().max_documents(2)));
+        let client = TestClient::new(app);
+
+        let res = client
+            .post("/")
+            .body("--- # a\n1\n--- # b\n2\n")
+            .header("content-type", "application/yaml")
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let res = client
+            .post("/")
+            .body("--- # a\n1\n--- # b\n2\n--- # c\n3\n")
+            .header("content-type", "application/yaml")
+            .await;
+        assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(
+            res.text().await,
+            "Too many YAML documents: body has more than 2 documents"
+        );
+    }
+
+    #[test]
+    fn invalid_document() {
+        let err = YamlBundle::<u32>::from_bytes(b"--- # a\n1\n--- # b\nx\n").unwrap_err();
+        assert_eq!(err.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(err.kind(), Some(YamlErrorKind::InvalidType));
+        assert_eq!(
+            err.body_text(),
+            "Failed to deserialize the YAML body into the target type: document `b`: \
+             invalid type: string \"x\", expected u32 at line 2 column 1"
+        );
+    }
+
+    #[test]
+    fn unnamed_document() {
+        let err = YamlBundle::<Value>::from_bytes(b"a: 1\n--- # b\nb: 2\n").unwrap_err();
+        assert_eq!(err.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            err.body_text(),
+            "Invalid YAML bundle: document #0 has no `--- # <name>` header"
+        );
+    }
+
+    #[test]
+    fn split_skips_preamble_comments() {
+        let documents = split_documents("# backup\n%YAML 1.2\n--- # a\nx: 1\n---   #  b \n");
+        assert_eq!(
+            documents,
+            vec![(Some("a"), "--- # a\nx: 1\n"), (Some("b"), "---   #  b \n")]
+        );
+    }
+}
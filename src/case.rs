@@ -0,0 +1,130 @@
+//! Case transformation of mapping keys.
+//!
+//! Lets one set of Rust types serve clients expecting different key styles:
+//! [`YamlConfig::key_case`](crate::YamlConfig::key_case) renames the keys of
+//! extracted documents before deserialization, and
+//! [`SerializeConfig::key_case`](crate::ser::SerializeConfig::key_case) the
+//! keys of responses after serialization.
+
+use serde_yaml::Value;
+
+/// Case style of mapping keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyCase {
+    /// `snake_case` keys.
+    Snake,
+    /// `camelCase` keys.
+    Camel,
+}
+
+impl KeyCase {
+    /// Convert a single key to this case.
+    pub fn convert(self, key: &str) -> String {
+        match self {
+            Self::Snake => to_snake_case(key),
+            Self::Camel => to_camel_case(key),
+        }
+    }
+
+    /// Rename every string key of `value`, recursively, to this case.
+    pub fn apply(self, value: &mut Value) {
+        match value {
+            Value::Mapping(mapping) => {
+                let entries = std::mem::take(mapping);
+                for (key, mut child) in entries {
+                    self.apply(&mut child);
+                    let key = match key {
+                        Value::String(key) => Value::String(self.convert(&key)),
+                        key => key,
+                    };
+                    mapping.insert(key, child);
+                }
+            }
+            Value::Sequence(sequence) => sequence.iter_mut().for_each(|item| self.apply(item)),
+            Value::Tagged(tagged) => self.apply(&mut tagged.value),
+            _ => {}
+        }
+    }
+}
+
+fn to_snake_case(key: &str) -> String {
+    let chars: Vec<char> = key.chars().collect();
+    let mut converted = String::with_capacity(key.len() + 4);
+    for (index, &c) in chars.iter().enumerate() {
+        if c == '-' {
+            converted.push('_');
+        } else if c.is_uppercase() {
+            let previous = index.checked_sub(1).map(|index| chars[index]);
+            let next = chars.get(index + 1);
+            // `userId` -> `user_id`, `HTTPServer` -> `http_server`
+            let boundary = match previous {
+                Some(previous) if previous.is_lowercase() || previous.is_ascii_digit() => true,
+                Some(previous) if previous.is_uppercase() => next.is_some_and(|c| c.is_lowercase()),
+                _ => false,
+            };
+            if boundary {
+                converted.push('_');
+            }
+            converted.extend(c.to_lowercase());
+        } else {
+            converted.push(c);
+        }
+    }
+    converted
+}
+
+fn to_camel_case(key: &str) -> String {
+    // Keep leading separators, as in `_private`.
+    let body = key.trim_start_matches(['_', '-']);
+    let mut converted = key[..key.len() - body.len()].to_owned();
+    let mut upper = false;
+    for c in body.chars() {
+        match c {
+            '_' | '-' => upper = true,
+            c if upper => {
+                converted.extend(c.to_uppercase());
+                upper = false;
+            }
+            c => converted.push(c),
+        }
+    }
+    converted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn convert() {
+        for (camel, snake) in [
+            ("userId", "user_id"),
+            ("httpServer", "http_server"),
+            ("name", "name"),
+            ("_privateKey", "_private_key"),
+            ("ipv4Address", "ipv4_address"),
+        ] {
+            assert_eq!(KeyCase::Snake.convert(camel), snake);
+            assert_eq!(KeyCase::Camel.convert(snake), camel);
+        }
+        assert_eq!(KeyCase::Snake.convert("HTTPServer"), "http_server");
+        assert_eq!(KeyCase::Snake.convert("max-retries"), "max_retries");
+        assert_eq!(KeyCase::Camel.convert("max-retries"), "maxRetries");
+    }
+
+    #[test]
+    fn apply() {
+        let mut document: Value =
+            serde_yaml::from_str("userId: 1\nitems:\n- itemName: a\n  tags: {fooBar: x}\n")
+                .unwrap();
+        KeyCase::Snake.apply(&mut document);
+
+        assert_eq!(
+            document,
+            serde_yaml::from_str::<Value>(
+                "user_id: 1\nitems:\n- item_name: a\n  tags: {foo_bar: x}\n"
+            )
+            .unwrap()
+        );
+    }
+}
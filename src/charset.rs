@@ -0,0 +1,139 @@
+//! `charset` parameter of request bodies.
+
+use std::borrow::Cow;
+
+use http::{header, HeaderMap};
+
+use crate::rejection::{UnsupportedCharset, YamlError, YamlErrorKind};
+
+/// Encoding of a request body, as declared by its `Content-Type`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Charset {
+    /// UTF-8, also assumed without a `charset` parameter.
+    Utf8,
+    /// UTF-16, big endian unless a byte order mark says otherwise.
+    Utf16,
+    Utf16Le,
+    Utf16Be,
+    Latin1,
+}
+
+impl Charset {
+    /// Read the `charset` of the `Content-Type` of `headers`.
+    ///
+    /// Charsets other than UTF-8 and US-ASCII are only supported when
+    /// `transcode` is set.
+    pub(crate) fn from_headers(
+        headers: &HeaderMap,
+        transcode: bool,
+    ) -> Result<Self, UnsupportedCharset> {
+        let Some(charset) = headers
+            .get(header::CONTENT_TYPE)
+            .and_then(|content_type| content_type.to_str().ok())
+            .and_then(|content_type| content_type.parse::<mime::Mime>().ok())
+            .and_then(|mime| Some(mime.get_param(mime::CHARSET)?.as_str().to_ascii_lowercase()))
+        else {
+            return Ok(Self::Utf8);
+        };
+
+        let decoded = match charset.as_str() {
+            "utf-8" | "utf8" | "us-ascii" => return Ok(Self::Utf8),
+            "utf-16" => Self::Utf16,
+            "utf-16le" => Self::Utf16Le,
+            "utf-16be" => Self::Utf16Be,
+            "iso-8859-1" | "latin1" => Self::Latin1,
+            _ => {
+                return Err(UnsupportedCharset::from_err(format!(
+                    "`{charset}` is not supported"
+                )))
+            }
+        };
+        if !transcode {
+            return Err(UnsupportedCharset::from_err(format!(
+                "`{charset}` bodies are not accepted, send UTF-8"
+            )));
+        }
+        Ok(decoded)
+    }
+
+    /// Transcode `bytes` from this charset to UTF-8.
+    pub(crate) fn decode(self, bytes: &[u8]) -> Result<Cow<'_, [u8]>, YamlError> {
+        let little_endian = match self {
+            Self::Utf8 => return Ok(Cow::Borrowed(bytes)),
+            Self::Latin1 => {
+                let text: String = bytes.iter().map(|&b| char::from(b)).collect();
+                return Ok(Cow::Owned(text.into_bytes()));
+            }
+            Self::Utf16 => bytes.starts_with(&[0xFF, 0xFE]),
+            Self::Utf16Le => true,
+            Self::Utf16Be => false,
+        };
+
+        if !bytes.len().is_multiple_of(2) {
+            return Err(YamlError::new(
+                YamlErrorKind::Syntax,
+                "UTF-16 body has an odd length",
+            ));
+        }
+        let units = bytes.chunks_exact(2).map(|pair| {
+            let pair = [pair[0], pair[1]];
+            if little_endian {
+                u16::from_le_bytes(pair)
+            } else {
+                u16::from_be_bytes(pair)
+            }
+        });
+        let text = char::decode_utf16(units)
+            .collect::<Result<String, _>>()
+            .map_err(|err| YamlError::new(YamlErrorKind::Syntax, err))?;
+        // A byte order mark is not part of the document.
+        let text = text.strip_prefix('\u{FEFF}').unwrap_or(&text);
+        Ok(Cow::Owned(text.as_bytes().to_vec()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn charset(content_type: &'static str, transcode: bool) -> Result<Charset, String> {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::CONTENT_TYPE, content_type.parse().unwrap());
+        Charset::from_headers(&headers, transcode).map_err(|err| err.body_text())
+    }
+
+    #[test]
+    fn parameter() {
+        assert_eq!(charset("application/yaml", false), Ok(Charset::Utf8));
+        assert_eq!(
+            charset("application/yaml; charset=UTF-8", false),
+            Ok(Charset::Utf8)
+        );
+        assert_eq!(
+            charset("application/yaml; charset=utf-16le", false).unwrap_err(),
+            "Unsupported charset: `utf-16le` bodies are not accepted, send UTF-8"
+        );
+        assert_eq!(
+            charset("application/yaml; charset=utf-16le", true),
+            Ok(Charset::Utf16Le)
+        );
+        assert_eq!(
+            charset("application/yaml; charset=koi8-r", true).unwrap_err(),
+            "Unsupported charset: `koi8-r` is not supported"
+        );
+    }
+
+    #[test]
+    fn decode() {
+        let utf16: Vec<u8> = "\u{FEFF}a: é"
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .collect();
+        assert_eq!(Charset::Utf16.decode(&utf16).unwrap(), "a: é".as_bytes());
+        assert_eq!(
+            Charset::Latin1.decode(b"a: \xE9").unwrap(),
+            "a: é".as_bytes()
+        );
+        assert!(Charset::Utf16Be.decode(b"\xD8\x00").is_err());
+    }
+}
@@ -0,0 +1,783 @@
+//! Classification of deserialization errors where they are raised, see
+//! [`YamlErrorKind`].
+//!
+//! A `serde_yaml::Error` does not tell a parser failure from a value the
+//! target type refused. [`deserialize`] wraps the deserializer, its visitors
+//! and accessors, so that every error is seen where it is raised:
+//!
+//! - the target type raises its errors through the constructors of
+//!   [`de::Error`], such as `missing_field` or `invalid_type`, which record
+//!   their kind in a [`DataError`] before it is handed back to `serde_yaml`
+//!   as a custom error, keeping its location;
+//! - an error raised by the wrapped deserializer itself is an `invalid_type`
+//!   if it asked the visitor what it expected, an `invalid_length` if the
+//!   visitor left elements of its sequence or mapping unread, a
+//!   [`MultipleDocuments`](YamlErrorKind::MultipleDocuments) if the
+//!   top-level value of a stream was visited in full, and a syntax error
+//!   otherwise.
+//!
+//! Errors of the wrapped deserializer are stashed by the wrapper receiving
+//! them and given back unchanged once the target type returns them.
+
+use std::cell::Cell;
+use std::fmt;
+use std::marker::PhantomData;
+
+use serde::de::{
+    self, DeserializeSeed, Deserializer, EnumAccess, Expected, MapAccess, SeqAccess, Unexpected,
+    VariantAccess, Visitor,
+};
+
+use crate::rejection::YamlErrorKind;
+
+/// Error deserializing a document into the target type, along with its kind.
+#[derive(Debug)]
+pub(crate) struct Classified {
+    pub(crate) kind: YamlErrorKind,
+    pub(crate) error: serde_path_to_error::Error<serde_yaml::Error>,
+}
+
+/// Deserialize `T` from a parsed `document`, classifying its error.
+pub(crate) fn deserialize<'de, T, D>(document: D) -> Result<T, Classified>
+where
+    T: de::Deserialize<'de>,
+    D: Deserializer<'de, Error = serde_yaml::Error>,
+{
+    classify(PhantomData, document, false)
+}
+
+/// Deserialize the value of `seed` from a parsed `document`, classifying its
+/// error.
+pub(crate) fn deserialize_seed<'de, S, D>(seed: S, document: D) -> Result<S::Value, Classified>
+where
+    S: DeserializeSeed<'de>,
+    D: Deserializer<'de, Error = serde_yaml::Error>,
+{
+    classify(seed, document, false)
+}
+
+/// Deserialize `T` from a YAML stream, classifying its error, which may be
+/// that the stream holds several documents.
+pub(crate) fn deserialize_stream<'de, T>(
+    deserializer: serde_yaml::Deserializer<'de>,
+) -> Result<T, Classified>
+where
+    T: de::Deserialize<'de>,
+{
+    classify(PhantomData, deserializer, true)
+}
+
+fn classify<'de, S, D>(seed: S, deserializer: D, stream: bool) -> Result<S::Value, Classified>
+where
+    S: DeserializeSeed<'de>,
+    D: Deserializer<'de, Error = serde_yaml::Error>,
+{
+    let mut track = serde_path_to_error::Track::new();
+    let context = Context::default();
+    let mut stash = None;
+    let result = seed.deserialize(Classify {
+        inner: serde_path_to_error::Deserializer::new(deserializer, &mut track),
+        stash: &mut stash,
+        context: &context,
+        top: true,
+    });
+    result.map_err(|err| {
+        let err = restore(err, &mut stash, &context);
+        // Past the top-level value, a stream fails on the unread elements of
+        // a tuple, at their location, or on a second document, at none.
+        let kind = match context.kind.get() {
+            _ if context.trailing.get() && stream && err.location().is_none() => {
+                YamlErrorKind::MultipleDocuments
+            }
+            kind => kind.unwrap_or(YamlErrorKind::Syntax),
+        };
+        Classified {
+            kind,
+            error: serde_path_to_error::Error::new(track.path(), err),
+        }
+    })
+}
+
+/// Error raised by the target type, with the kind of the constructor it was
+/// raised through.
+#[derive(Debug)]
+pub(crate) struct DataError {
+    kind: YamlErrorKind,
+    message: String,
+    missing_field: Option<&'static str>,
+}
+
+impl DataError {
+    fn new(kind: YamlErrorKind, message: impl fmt::Display) -> Self {
+        Self {
+            kind,
+            message: message.to_string(),
+            missing_field: None,
+        }
+    }
+
+    pub(crate) fn kind(&self) -> YamlErrorKind {
+        self.kind
+    }
+
+    /// Field named by a `missing_field` error.
+    pub(crate) fn missing_field(&self) -> Option<&'static str> {
+        self.missing_field
+    }
+}
+
+impl fmt::Display for DataError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for DataError {}
+
+// The messages are those of the default implementations, which `serde_yaml`
+// uses as well.
+type Message = de::value::Error;
+
+impl de::Error for DataError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self::new(YamlErrorKind::InvalidValue, msg)
+    }
+
+    fn invalid_type(unexp: Unexpected<'_>, exp: &dyn Expected) -> Self {
+        Self::new(
+            YamlErrorKind::InvalidType,
+            Message::invalid_type(unexp, exp),
+        )
+    }
+
+    fn invalid_value(unexp: Unexpected<'_>, exp: &dyn Expected) -> Self {
+        Self::new(
+            YamlErrorKind::InvalidValue,
+            Message::invalid_value(unexp, exp),
+        )
+    }
+
+    fn invalid_length(len: usize, exp: &dyn Expected) -> Self {
+        Self::new(
+            YamlErrorKind::InvalidValue,
+            Message::invalid_length(len, exp),
+        )
+    }
+
+    fn unknown_variant(variant: &str, expected: &'static [&'static str]) -> Self {
+        Self::new(
+            YamlErrorKind::InvalidValue,
+            Message::unknown_variant(variant, expected),
+        )
+    }
+
+    fn unknown_field(field: &str, expected: &'static [&'static str]) -> Self {
+        Self::new(
+            YamlErrorKind::UnknownField,
+            Message::unknown_field(field, expected),
+        )
+    }
+
+    fn missing_field(field: &'static str) -> Self {
+        Self {
+            missing_field: Some(field),
+            ..Self::new(YamlErrorKind::MissingField, Message::missing_field(field))
+        }
+    }
+
+    fn duplicate_field(field: &'static str) -> Self {
+        Self::new(YamlErrorKind::InvalidValue, Message::duplicate_field(field))
+    }
+}
+
+/// Error handed to the target type.
+#[derive(Debug)]
+enum Error {
+    /// Error of the wrapped deserializer, stashed by the wrapper that
+    /// received it.
+    Inner(String),
+    /// Error raised by the target type.
+    Data(DataError),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Inner(message) => f.write_str(message),
+            Self::Data(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+macro_rules! data_error {
+    ($($method:ident($($arg:ident: $ty:ty),*),)*) => {
+        $(
+            fn $method($($arg: $ty),*) -> Self {
+                Self::Data(<DataError as de::Error>::$method($($arg),*))
+            }
+        )*
+    };
+}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self::Data(<DataError as de::Error>::custom(msg))
+    }
+
+    data_error! {
+        invalid_type(unexp: Unexpected<'_>, exp: &dyn Expected),
+        invalid_value(unexp: Unexpected<'_>, exp: &dyn Expected),
+        invalid_length(len: usize, exp: &dyn Expected),
+        unknown_variant(variant: &str, expected: &'static [&'static str]),
+        unknown_field(field: &str, expected: &'static [&'static str]),
+        missing_field(field: &'static str),
+        duplicate_field(field: &'static str),
+    }
+}
+
+/// Classification shared by the wrappers of one deserialization.
+#[derive(Default)]
+struct Context {
+    /// Kind of the error being returned, `None` for a syntax error.
+    kind: Cell<Option<YamlErrorKind>>,
+    /// Whether the error was raised by the deserializer after the visitor of
+    /// the top-level value returned.
+    trailing: Cell<bool>,
+}
+
+/// Hand `err`, returned by the target type, back to the wrapped
+/// deserializer.
+fn restore<E>(err: Error, stash: &mut Option<E>, context: &Context) -> E
+where
+    E: de::Error,
+{
+    match err {
+        Error::Inner(message) => stash.take().unwrap_or_else(|| E::custom(message)),
+        Error::Data(err) => {
+            context.kind.set(Some(err.kind));
+            E::custom(err.message)
+        }
+    }
+}
+
+/// Stash `err`, raised by the wrapped deserializer, for the target type.
+fn stash<E>(err: E, stash: &mut Option<E>) -> Error
+where
+    E: de::Error,
+{
+    let message = err.to_string();
+    *stash = Some(err);
+    Error::Inner(message)
+}
+
+/// What the wrapped deserializer did with one visitor.
+#[derive(Default)]
+struct Visit {
+    /// Whether the deserializer asked the visitor what it expected, to
+    /// describe an `invalid_type`.
+    expected: Cell<bool>,
+    /// Whether the visitor returned, and whether it returned an error.
+    visited: Cell<bool>,
+    failed: Cell<bool>,
+    /// Whether the visitor was given a sequence or mapping, and read it to
+    /// its end.
+    opened: Cell<bool>,
+    ended: Cell<bool>,
+}
+
+impl Visit {
+    /// Stash `result`, the outcome of the wrapped deserializer, recording
+    /// the kind of its error unless the visitor raised it.
+    fn finish<T, E>(
+        &self,
+        result: Result<T, E>,
+        stash: &mut Option<E>,
+        context: &Context,
+        top: bool,
+    ) -> Result<T, Error>
+    where
+        E: de::Error,
+    {
+        result.map_err(|err| {
+            if !self.failed.get() {
+                context.kind.set(self.kind());
+                context.trailing.set(top && self.visited.get());
+            }
+            self::stash(err, stash)
+        })
+    }
+
+    /// Hand `result`, returned by the visitor, back to the wrapped
+    /// deserializer.
+    fn returned<T, E>(
+        &self,
+        result: Result<T, Error>,
+        stash: &mut Option<E>,
+        context: &Context,
+    ) -> Result<T, E>
+    where
+        E: de::Error,
+    {
+        self.visited.set(true);
+        result.map_err(|err| {
+            self.failed.set(true);
+            restore(err, stash, context)
+        })
+    }
+
+    /// Kind of an error raised by the wrapped deserializer.
+    fn kind(&self) -> Option<YamlErrorKind> {
+        if self.expected.get() {
+            Some(YamlErrorKind::InvalidType)
+        } else if self.visited.get() && self.opened.get() && !self.ended.get() {
+            Some(YamlErrorKind::InvalidValue)
+        } else {
+            None
+        }
+    }
+}
+
+/// Deserializer handing classified errors to the target type.
+struct Classify<'a, D, E> {
+    inner: D,
+    stash: &'a mut Option<E>,
+    context: &'a Context,
+    /// Whether this is the deserializer of the whole document.
+    top: bool,
+}
+
+macro_rules! deserialize {
+    ($($method:ident($($arg:ident: $ty:ty),*),)*) => {
+        $(
+            fn $method<V>(self, $($arg: $ty,)* visitor: V) -> Result<V::Value, Error>
+            where
+                V: Visitor<'de>,
+            {
+                let visit = Visit::default();
+                let result = self
+                    .inner
+                    .$method($($arg,)* Wrap::new(visitor, &visit, self.context));
+                visit.finish(result, self.stash, self.context, self.top)
+            }
+        )*
+    };
+}
+
+impl<'de, D> Deserializer<'de> for Classify<'_, D, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    type Error = Error;
+
+    deserialize! {
+        deserialize_any(),
+        deserialize_bool(),
+        deserialize_i8(),
+        deserialize_i16(),
+        deserialize_i32(),
+        deserialize_i64(),
+        deserialize_i128(),
+        deserialize_u8(),
+        deserialize_u16(),
+        deserialize_u32(),
+        deserialize_u64(),
+        deserialize_u128(),
+        deserialize_f32(),
+        deserialize_f64(),
+        deserialize_char(),
+        deserialize_str(),
+        deserialize_string(),
+        deserialize_bytes(),
+        deserialize_byte_buf(),
+        deserialize_option(),
+        deserialize_unit(),
+        deserialize_unit_struct(name: &'static str),
+        deserialize_newtype_struct(name: &'static str),
+        deserialize_seq(),
+        deserialize_tuple(len: usize),
+        deserialize_tuple_struct(name: &'static str, len: usize),
+        deserialize_map(),
+        deserialize_struct(name: &'static str, fields: &'static [&'static str]),
+        deserialize_enum(name: &'static str, variants: &'static [&'static str]),
+        deserialize_identifier(),
+        deserialize_ignored_any(),
+    }
+
+    fn is_human_readable(&self) -> bool {
+        self.inner.is_human_readable()
+    }
+}
+
+/// Visitor of the target type, handing its errors back to the wrapped
+/// deserializer.
+struct Wrap<'a, V> {
+    visitor: V,
+    visit: &'a Visit,
+    context: &'a Context,
+}
+
+impl<'a, V> Wrap<'a, V> {
+    fn new(visitor: V, visit: &'a Visit, context: &'a Context) -> Self {
+        Self {
+            visitor,
+            visit,
+            context,
+        }
+    }
+}
+
+macro_rules! visit {
+    ($($method:ident($ty:ty),)*) => {
+        $(
+            fn $method<E>(self, v: $ty) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                let result = self.visitor.$method(v);
+                self.visit.returned(result, &mut None, self.context)
+            }
+        )*
+    };
+}
+
+impl<'de, V> Visitor<'de> for Wrap<'_, V>
+where
+    V: Visitor<'de>,
+{
+    type Value = V::Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.visit.expected.set(true);
+        self.visitor.expecting(f)
+    }
+
+    visit! {
+        visit_bool(bool),
+        visit_i8(i8),
+        visit_i16(i16),
+        visit_i32(i32),
+        visit_i64(i64),
+        visit_i128(i128),
+        visit_u8(u8),
+        visit_u16(u16),
+        visit_u32(u32),
+        visit_u64(u64),
+        visit_u128(u128),
+        visit_f32(f32),
+        visit_f64(f64),
+        visit_char(char),
+        visit_str(&str),
+        visit_borrowed_str(&'de str),
+        visit_string(String),
+        visit_bytes(&[u8]),
+        visit_borrowed_bytes(&'de [u8]),
+        visit_byte_buf(Vec<u8>),
+    }
+
+    fn visit_none<E>(self) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        let result = self.visitor.visit_none();
+        self.visit.returned(result, &mut None, self.context)
+    }
+
+    fn visit_unit<E>(self) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        let result = self.visitor.visit_unit();
+        self.visit.returned(result, &mut None, self.context)
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let mut stash = None;
+        let result = self.visitor.visit_some(Classify {
+            inner: deserializer,
+            stash: &mut stash,
+            context: self.context,
+            top: false,
+        });
+        self.visit.returned(result, &mut stash, self.context)
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let mut stash = None;
+        let result = self.visitor.visit_newtype_struct(Classify {
+            inner: deserializer,
+            stash: &mut stash,
+            context: self.context,
+            top: false,
+        });
+        self.visit.returned(result, &mut stash, self.context)
+    }
+
+    fn visit_seq<A>(self, seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        self.visit.opened.set(true);
+        let mut access = Access::new(seq, self.visit, self.context);
+        let result = self.visitor.visit_seq(&mut access);
+        self.visit.returned(result, &mut access.stash, self.context)
+    }
+
+    fn visit_map<A>(self, map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        self.visit.opened.set(true);
+        let mut access = Access::new(map, self.visit, self.context);
+        let result = self.visitor.visit_map(&mut access);
+        self.visit.returned(result, &mut access.stash, self.context)
+    }
+
+    fn visit_enum<A>(self, data: A) -> Result<Self::Value, A::Error>
+    where
+        A: EnumAccess<'de>,
+    {
+        let mut stash = None;
+        let result = self.visitor.visit_enum(Enum {
+            inner: data,
+            stash: &mut stash,
+            context: self.context,
+        });
+        self.visit.returned(result, &mut stash, self.context)
+    }
+}
+
+/// Seed of the target type, classifying the errors of the value it
+/// deserializes.
+struct Seed<'a, S> {
+    seed: S,
+    context: &'a Context,
+    /// Set if the value failed, as opposed to the accessor handing it over.
+    failed: &'a Cell<bool>,
+}
+
+impl<'a, S> Seed<'a, S> {
+    fn new(seed: S, context: &'a Context, failed: &'a Cell<bool>) -> Self {
+        Self {
+            seed,
+            context,
+            failed,
+        }
+    }
+}
+
+impl<'de, S> DeserializeSeed<'de> for Seed<'_, S>
+where
+    S: DeserializeSeed<'de>,
+{
+    type Value = S::Value;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let mut stash = None;
+        let result = self.seed.deserialize(Classify {
+            inner: deserializer,
+            stash: &mut stash,
+            context: self.context,
+            top: false,
+        });
+        result.map_err(|err| {
+            self.failed.set(true);
+            restore(err, &mut stash, self.context)
+        })
+    }
+}
+
+/// Record the kind of `err`, raised by an accessor, unless the value handed
+/// to `failed` raised it, and stash it.
+fn accessor_error<E>(err: E, failed: &Cell<bool>, stash: &mut Option<E>, context: &Context) -> Error
+where
+    E: de::Error,
+{
+    if !failed.get() {
+        context.kind.set(None);
+    }
+    self::stash(err, stash)
+}
+
+/// Sequence or mapping visited by the target type.
+struct Access<'a, A, E> {
+    inner: A,
+    stash: Option<E>,
+    visit: &'a Visit,
+    context: &'a Context,
+}
+
+impl<'a, A, E> Access<'a, A, E> {
+    fn new(inner: A, visit: &'a Visit, context: &'a Context) -> Self {
+        Self {
+            inner,
+            stash: None,
+            visit,
+            context,
+        }
+    }
+
+    /// Record the end of the sequence or mapping when `next` is `None`.
+    fn next<T>(&self, next: Option<T>) -> Option<T> {
+        if next.is_none() {
+            self.visit.ended.set(true);
+        }
+        next
+    }
+}
+
+impl<'de, A> SeqAccess<'de> for Access<'_, A, A::Error>
+where
+    A: SeqAccess<'de>,
+{
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        let failed = Cell::new(false);
+        match self
+            .inner
+            .next_element_seed(Seed::new(seed, self.context, &failed))
+        {
+            Ok(next) => Ok(self.next(next)),
+            Err(err) => Err(accessor_error(err, &failed, &mut self.stash, self.context)),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.inner.size_hint()
+    }
+}
+
+impl<'de, A> MapAccess<'de> for Access<'_, A, A::Error>
+where
+    A: MapAccess<'de>,
+{
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Error>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        let failed = Cell::new(false);
+        match self
+            .inner
+            .next_key_seed(Seed::new(seed, self.context, &failed))
+        {
+            Ok(next) => Ok(self.next(next)),
+            Err(err) => Err(accessor_error(err, &failed, &mut self.stash, self.context)),
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let failed = Cell::new(false);
+        self.inner
+            .next_value_seed(Seed::new(seed, self.context, &failed))
+            .map_err(|err| accessor_error(err, &failed, &mut self.stash, self.context))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.inner.size_hint()
+    }
+}
+
+/// Enum visited by the target type.
+struct Enum<'a, A, E> {
+    inner: A,
+    stash: &'a mut Option<E>,
+    context: &'a Context,
+}
+
+impl<'de, 'a, A> EnumAccess<'de> for Enum<'a, A, A::Error>
+where
+    A: EnumAccess<'de>,
+{
+    type Error = Error;
+    type Variant = Enum<'a, A::Variant, A::Error>;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant), Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let failed = Cell::new(false);
+        match self
+            .inner
+            .variant_seed(Seed::new(seed, self.context, &failed))
+        {
+            Ok((value, variant)) => Ok((
+                value,
+                Enum {
+                    inner: variant,
+                    stash: self.stash,
+                    context: self.context,
+                },
+            )),
+            Err(err) => Err(accessor_error(err, &failed, self.stash, self.context)),
+        }
+    }
+}
+
+impl<'de, A> VariantAccess<'de> for Enum<'_, A, A::Error>
+where
+    A: VariantAccess<'de>,
+{
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        let failed = Cell::new(false);
+        self.inner
+            .unit_variant()
+            .map_err(|err| accessor_error(err, &failed, self.stash, self.context))
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        let failed = Cell::new(false);
+        self.inner
+            .newtype_variant_seed(Seed::new(seed, self.context, &failed))
+            .map_err(|err| accessor_error(err, &failed, self.stash, self.context))
+    }
+
+    fn tuple_variant<V>(self, len: usize, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        let visit = Visit::default();
+        let result = self
+            .inner
+            .tuple_variant(len, Wrap::new(visitor, &visit, self.context));
+        visit.finish(result, self.stash, self.context, false)
+    }
+
+    fn struct_variant<V>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        let visit = Visit::default();
+        let result = self
+            .inner
+            .struct_variant(fields, Wrap::new(visitor, &visit, self.context));
+        visit.finish(result, self.stash, self.context, false)
+    }
+}
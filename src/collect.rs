@@ -0,0 +1,1057 @@
+//! Deserialization reporting every field-level error of a document, see
+//! [`YamlConfig::collect_errors`](crate::YamlConfig::collect_errors).
+//!
+//! `serde` stops at the first error, since a `Visitor` is consumed by the
+//! value it fails on. [`Node`] deserializes a parsed document and checks the
+//! type of each value against the one requested before visiting it, so a
+//! mismatch is recorded and answered with a placeholder value of the
+//! requested type, like `0` or `""`, letting deserialization carry on.
+//!
+//! Errors raised by the target type itself, such as missing or unknown
+//! fields, cannot be predicted. The innermost value they pass through is
+//! remembered, and deserialization is run again with a [`Fix`] at that path:
+//! unknown fields are skipped and the other values replaced by placeholders,
+//! reporting their error in document order.
+
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+
+use serde::de::{
+    self, value::StrDeserializer, DeserializeOwned, DeserializeSeed, Deserializer as _, EnumAccess,
+    Error as _, MapAccess, SeqAccess, Unexpected, VariantAccess, Visitor,
+};
+use serde_yaml::Value;
+
+use crate::classify::DataError as Error;
+use crate::rejection::YamlFieldError;
+
+/// Deserialize `document` into `T`, reporting up to `max` errors.
+pub(crate) fn deserialize<T>(
+    document: &Value,
+    max: usize,
+    empty_as_none: bool,
+) -> Result<T, Vec<YamlFieldError>>
+where
+    T: DeserializeOwned,
+{
+    let mut fixes = HashMap::new();
+    loop {
+        let collector = Collector {
+            errors: RefCell::default(),
+            failed: RefCell::default(),
+            stopped: Cell::new(false),
+            fixes: &fixes,
+            max,
+            empty_as_none,
+        };
+        let result = T::deserialize(Node::new(document, String::new(), &collector));
+
+        let mut errors = collector.errors.into_inner();
+        let err = match result {
+            Ok(value) if errors.is_empty() => return Ok(value),
+            Ok(_) => return Err(errors),
+            Err(err) => err,
+        };
+        if collector.stopped.get() {
+            return Err(errors);
+        }
+
+        let failed = collector.failed.into_inner();
+        let Some((path, fix)) = failed.filter(|(path, _)| !fixes.contains_key(path)) else {
+            // Fixing the value did not help, report the error as is.
+            errors.push(field_error(String::new(), &err));
+            return Err(errors);
+        };
+        if errors.len() + 1 >= max {
+            errors.push(fix.error);
+            return Err(errors);
+        }
+        fixes.insert(path, fix);
+    }
+}
+
+fn field_error(path: String, err: &Error) -> YamlFieldError {
+    YamlFieldError::new(err.kind(), path, err.to_string())
+}
+
+/// How a value failing in a previous run is handled, along with its error.
+#[derive(Debug)]
+struct Fix {
+    kind: FixKind,
+    error: YamlFieldError,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FixKind {
+    /// Replace the value with a placeholder.
+    Placeholder,
+    /// Add the missing field of a struct with a placeholder value.
+    Missing,
+    /// Skip the unknown field of a struct.
+    Skip,
+}
+
+/// State of one deserialization run.
+struct Collector<'a> {
+    errors: RefCell<Vec<YamlFieldError>>,
+    /// Innermost value an unpredicted error passed through, with its fix.
+    failed: RefCell<Option<(String, Fix)>>,
+    /// Whether `max` errors were reported, unwinding deserialization.
+    stopped: Cell<bool>,
+    fixes: &'a HashMap<String, Fix>,
+    max: usize,
+    empty_as_none: bool,
+}
+
+impl Collector<'_> {
+    /// Report `error`, failing once `max` errors were reported.
+    fn report(&self, error: YamlFieldError) -> Result<(), Error> {
+        let mut errors = self.errors.borrow_mut();
+        errors.push(error);
+        if errors.len() >= self.max {
+            self.stopped.set(true);
+            return Err(Error::custom("too many errors"));
+        }
+        Ok(())
+    }
+
+    /// Remember `path` as the innermost value `err` passed through, unless a
+    /// value inside it already was.
+    fn fail(&self, path: &str, kind: FixKind, err: Error) -> Error {
+        let mut failed = self.failed.borrow_mut();
+        if failed.is_none() && !self.stopped.get() {
+            let (path, error) = match kind {
+                FixKind::Missing => {
+                    let field = err.missing_field().unwrap_or_default();
+                    (child_key(path, field), field_error(path.to_owned(), &err))
+                }
+                _ => (path.to_owned(), field_error(path.to_owned(), &err)),
+            };
+            *failed = Some((path, Fix { kind, error }));
+        }
+        err
+    }
+
+    /// Remember the key at `path` as the innermost value `err` passed
+    /// through, to skip its entry.
+    fn fail_key(&self, path: &str, err: Error) -> Error {
+        let err = self.fail(path, FixKind::Skip, err);
+        if let Some((failed, fix)) = &mut *self.failed.borrow_mut() {
+            if failed == path {
+                fix.kind = FixKind::Skip;
+            }
+        }
+        err
+    }
+
+    fn fix(&self, path: &str, kind: FixKind) -> Option<&Fix> {
+        self.fixes.get(path).filter(|fix| fix.kind == kind)
+    }
+}
+
+fn child_key(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_owned()
+    } else {
+        format!("{path}.{key}")
+    }
+}
+
+fn child_index(path: &str, index: usize) -> String {
+    format!("{path}[{index}]")
+}
+
+fn unexpected(value: &Value) -> Unexpected<'_> {
+    match value {
+        Value::Null => Unexpected::Unit,
+        Value::Bool(b) => Unexpected::Bool(*b),
+        Value::Number(n) => match (n.as_u64(), n.as_i64(), n.as_f64()) {
+            (Some(n), _, _) => Unexpected::Unsigned(n),
+            (_, Some(n), _) => Unexpected::Signed(n),
+            (_, _, n) => Unexpected::Float(n.unwrap_or_default()),
+        },
+        Value::String(s) => Unexpected::Str(s),
+        Value::Sequence(_) => Unexpected::Seq,
+        Value::Mapping(_) => Unexpected::Map,
+        Value::Tagged(_) => Unexpected::Enum,
+    }
+}
+
+/// Value of the document being deserialized.
+struct Node<'a> {
+    value: &'a Value,
+    path: String,
+    collector: &'a Collector<'a>,
+}
+
+impl<'a> Node<'a> {
+    fn new(value: &'a Value, path: String, collector: &'a Collector<'a>) -> Self {
+        Self {
+            value,
+            path,
+            collector,
+        }
+    }
+
+    /// Report `err` and continue with a placeholder.
+    fn recover(&self, err: Error) -> Result<Placeholder, Error> {
+        self.collector
+            .report(field_error(self.path.clone(), &err))
+            .map(|()| Placeholder)
+    }
+
+    fn invalid_type<'de, V>(&self, visitor: &V) -> Result<Placeholder, Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.recover(Error::invalid_type(unexpected(self.value), visitor))
+    }
+
+    /// Remember the path of an error raised by a visitor.
+    fn visited<T>(&self, result: Result<T, Error>) -> Result<T, Error> {
+        result.map_err(|err| {
+            let kind = match err.missing_field() {
+                Some(_) => FixKind::Missing,
+                None => FixKind::Placeholder,
+            };
+            self.collector.fail(&self.path, kind, err)
+        })
+    }
+
+    fn integer<'de, V>(&self, visitor: V, min: i128, max: i128) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        let Value::Number(n) = self.value else {
+            return self.invalid_type(&visitor)?.deserialize_u64(visitor);
+        };
+        let integer = match (n.as_u64(), n.as_i64()) {
+            (Some(u), _) => i128::from(u),
+            (_, Some(i)) => i128::from(i),
+            (None, None) => return self.invalid_type(&visitor)?.deserialize_u64(visitor),
+        };
+        if !(min..=max).contains(&integer) {
+            return self
+                .recover(Error::invalid_value(unexpected(self.value), &visitor))?
+                .deserialize_u64(visitor);
+        }
+        match u64::try_from(integer) {
+            Ok(u) => self.visited(visitor.visit_u64(u)),
+            Err(_) => self.visited(visitor.visit_i64(integer as i64)),
+        }
+    }
+}
+
+macro_rules! deserialize_integer {
+    ($($method:ident: $ty:ty,)*) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value, Error>
+            where
+                V: Visitor<'de>,
+            {
+                self.integer(visitor, <$ty>::MIN as i128, <$ty>::MAX as i128)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for Node<'_> {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        let result = match self.value {
+            Value::Null => visitor.visit_unit(),
+            Value::Bool(b) => visitor.visit_bool(*b),
+            Value::Number(n) => match (n.as_u64(), n.as_i64(), n.as_f64()) {
+                (Some(n), _, _) => visitor.visit_u64(n),
+                (_, Some(n), _) => visitor.visit_i64(n),
+                (_, _, n) => visitor.visit_f64(n.unwrap_or_default()),
+            },
+            Value::String(s) => visitor.visit_str(s),
+            Value::Sequence(_) => return self.deserialize_seq(visitor),
+            Value::Mapping(_) => return self.deserialize_map(visitor),
+            Value::Tagged(_) => return self.deserialize_enum("", &[], visitor),
+        };
+        self.visited(result)
+    }
+
+    fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self.value {
+            Value::Bool(b) => self.visited(visitor.visit_bool(*b)),
+            _ => self.invalid_type(&visitor)?.deserialize_bool(visitor),
+        }
+    }
+
+    deserialize_integer! {
+        deserialize_i8: i8,
+        deserialize_i16: i16,
+        deserialize_i32: i32,
+        deserialize_i64: i64,
+        deserialize_i128: i64,
+        deserialize_u8: u8,
+        deserialize_u16: u16,
+        deserialize_u32: u32,
+        deserialize_u64: u64,
+        deserialize_u128: u64,
+    }
+
+    fn deserialize_f32<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_f64(visitor)
+    }
+
+    fn deserialize_f64<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self.value {
+            Value::Number(n) => self.visited(visitor.visit_f64(n.as_f64().unwrap_or_default())),
+            _ => self.invalid_type(&visitor)?.deserialize_f64(visitor),
+        }
+    }
+
+    fn deserialize_char<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        let Value::String(s) = self.value else {
+            return self.invalid_type(&visitor)?.deserialize_char(visitor);
+        };
+        let mut chars = s.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => self.visited(visitor.visit_char(c)),
+            _ => self
+                .recover(Error::invalid_value(Unexpected::Str(s), &visitor))?
+                .deserialize_char(visitor),
+        }
+    }
+
+    fn deserialize_str<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self.value {
+            Value::String(s) => self.visited(visitor.visit_str(s)),
+            _ => self.invalid_type(&visitor)?.deserialize_str(visitor),
+        }
+    }
+
+    fn deserialize_string<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self.value {
+            Value::String(s) => self.visited(visitor.visit_str(s)),
+            Value::Sequence(_) => self.deserialize_seq(visitor),
+            _ => self.invalid_type(&visitor)?.deserialize_bytes(visitor),
+        }
+    }
+
+    fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self.value {
+            Value::Null => self.visited(visitor.visit_none()),
+            Value::String(s) if s.is_empty() && self.collector.empty_as_none => {
+                self.visited(visitor.visit_none())
+            }
+            _ => {
+                let path = self.path.clone();
+                let collector = self.collector;
+                visitor
+                    .visit_some(self)
+                    .map_err(|err| collector.fail(&path, FixKind::Placeholder, err))
+            }
+        }
+    }
+
+    fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self.value {
+            Value::Null => self.visited(visitor.visit_unit()),
+            _ => self.invalid_type(&visitor)?.deserialize_unit(visitor),
+        }
+    }
+
+    fn deserialize_unit_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        let path = self.path.clone();
+        let collector = self.collector;
+        visitor
+            .visit_newtype_struct(self)
+            .map_err(|err| collector.fail(&path, FixKind::Placeholder, err))
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self.value {
+            Value::Sequence(items) => self.visited(visitor.visit_seq(Seq {
+                items: items.iter().enumerate(),
+                path: &self.path,
+                collector: self.collector,
+            })),
+            _ => self.invalid_type(&visitor)?.deserialize_seq(visitor),
+        }
+    }
+
+    fn deserialize_tuple<V>(self, _len: usize, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_struct("", &[], visitor)
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        let Value::Mapping(mapping) = self.value else {
+            return self.invalid_type(&visitor)?.deserialize_map(visitor);
+        };
+        let missing = fields
+            .iter()
+            .copied()
+            .filter(|field| !mapping.contains_key(*field))
+            .filter(|field| {
+                self.collector
+                    .fix(&child_key(&self.path, field), FixKind::Missing)
+                    .is_some()
+            })
+            .collect::<Vec<_>>()
+            .into_iter();
+        self.visited(visitor.visit_map(Map {
+            entries: mapping.iter(),
+            missing,
+            value: None,
+            path: &self.path,
+            collector: self.collector,
+        }))
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        let (variant, value) = match self.value {
+            Value::String(variant) => (variant.clone(), None),
+            Value::Tagged(tagged) => (
+                tagged.tag.to_string().trim_start_matches('!').to_owned(),
+                Some(&tagged.value),
+            ),
+            Value::Mapping(mapping) if mapping.len() == 1 => match mapping.iter().next() {
+                Some((Value::String(variant), value)) => (variant.clone(), Some(value)),
+                _ => {
+                    return self
+                        .invalid_type(&visitor)?
+                        .deserialize_enum("", &[], visitor)
+                }
+            },
+            _ => {
+                return self
+                    .invalid_type(&visitor)?
+                    .deserialize_enum("", &[], visitor)
+            }
+        };
+        self.visited(visitor.visit_enum(Enum {
+            variant,
+            value,
+            path: &self.path,
+            collector: self.collector,
+        }))
+    }
+
+    fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self.value {
+            Value::String(s) => self.visited(visitor.visit_str(s)),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
+}
+
+/// Deserializer of a value that failed in a previous run, reporting its error
+/// and answering with a placeholder.
+struct Fixed<'a> {
+    fix: &'a Fix,
+    collector: &'a Collector<'a>,
+}
+
+impl Fixed<'_> {
+    fn deserialize<'de, T>(self, seed: T) -> Result<T::Value, Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        self.collector.report(self.fix.error.clone())?;
+        seed.deserialize(Placeholder)
+    }
+}
+
+struct Seq<'a, I> {
+    items: I,
+    path: &'a str,
+    collector: &'a Collector<'a>,
+}
+
+impl<'de, 'a, I> SeqAccess<'de> for Seq<'a, I>
+where
+    I: Iterator<Item = (usize, &'a Value)>,
+{
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        let Some((index, item)) = self.items.next() else {
+            return Ok(None);
+        };
+        let path = child_index(self.path, index);
+        if let Some(fix) = self.collector.fix(&path, FixKind::Placeholder) {
+            let collector = self.collector;
+            return Fixed { fix, collector }.deserialize(seed).map(Some);
+        }
+        seed.deserialize(Node::new(item, path.clone(), self.collector))
+            .map(Some)
+            .map_err(|err| self.collector.fail(&path, FixKind::Placeholder, err))
+    }
+}
+
+struct Map<'a, I> {
+    entries: I,
+    /// Missing fields fixed in a previous run.
+    missing: std::vec::IntoIter<&'static str>,
+    /// Path of the value of the current entry, and the value unless missing.
+    value: Option<(String, Option<&'a Value>)>,
+    path: &'a str,
+    collector: &'a Collector<'a>,
+}
+
+impl<'de, 'a, I> MapAccess<'de> for Map<'a, I>
+where
+    I: Iterator<Item = (&'a Value, &'a Value)>,
+{
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Error>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        for (key, value) in self.entries.by_ref() {
+            let path = match key {
+                Value::String(key) => child_key(self.path, key),
+                Value::Number(n) => child_key(self.path, &n.to_string()),
+                Value::Bool(b) => child_key(self.path, &b.to_string()),
+                _ => child_key(self.path, "?"),
+            };
+            if let Some(fix) = self.collector.fix(&path, FixKind::Skip) {
+                self.collector.report(fix.error.clone())?;
+                continue;
+            }
+
+            let key = seed
+                .deserialize(Node::new(key, path.clone(), self.collector))
+                .map_err(|err| self.collector.fail_key(&path, err))?;
+            self.value = Some((path, Some(value)));
+            return Ok(Some(key));
+        }
+
+        let Some(field) = self.missing.next() else {
+            return Ok(None);
+        };
+        self.value = Some((child_key(self.path, field), None));
+        seed.deserialize(StrDeserializer::<Error>::new(field))
+            .map(Some)
+    }
+
+    fn next_value_seed<T>(&mut self, seed: T) -> Result<T::Value, Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        let Some((path, value)) = self.value.take() else {
+            return Err(Error::custom("value requested before its key"));
+        };
+        let fix = match value {
+            Some(_) => self.collector.fix(&path, FixKind::Placeholder),
+            None => self.collector.fix(&path, FixKind::Missing),
+        };
+        if let Some(fix) = fix {
+            let collector = self.collector;
+            return Fixed { fix, collector }.deserialize(seed);
+        }
+
+        let value = value.unwrap_or(&Value::Null);
+        seed.deserialize(Node::new(value, path.clone(), self.collector))
+            .map_err(|err| self.collector.fail(&path, FixKind::Placeholder, err))
+    }
+}
+
+struct Enum<'a> {
+    variant: String,
+    value: Option<&'a Value>,
+    path: &'a str,
+    collector: &'a Collector<'a>,
+}
+
+impl<'de, 'a> EnumAccess<'de> for Enum<'a> {
+    type Error = Error;
+    type Variant = Variant<'a>;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant), Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let path = child_key(self.path, &self.variant);
+        let variant = seed.deserialize(StrDeserializer::<Error>::new(&self.variant))?;
+        Ok((
+            variant,
+            Variant {
+                value: self.value,
+                path,
+                collector: self.collector,
+            },
+        ))
+    }
+}
+
+struct Variant<'a> {
+    value: Option<&'a Value>,
+    path: String,
+    collector: &'a Collector<'a>,
+}
+
+impl<'de> VariantAccess<'de> for Variant<'_> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        match self.value {
+            None | Some(Value::Null) => Ok(()),
+            Some(value) => Node::new(value, self.path, self.collector)
+                .recover(Error::invalid_type(unexpected(value), &"unit variant"))
+                .map(drop),
+        }
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        let value = self.value.unwrap_or(&Value::Null);
+        seed.deserialize(Node::new(value, self.path, self.collector))
+    }
+
+    fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        let value = self.value.unwrap_or(&Value::Null);
+        Node::new(value, self.path, self.collector).deserialize_seq(visitor)
+    }
+
+    fn struct_variant<V>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        let value = self.value.unwrap_or(&Value::Null);
+        Node::new(value, self.path, self.collector).deserialize_struct("", fields, visitor)
+    }
+}
+
+/// Deserializer answering with the zero value of the requested type, to
+/// carry on after an error.
+struct Placeholder;
+
+macro_rules! placeholder {
+    ($($($method:ident)* => $visit:ident($zero:expr),)*) => {
+        $($(
+            fn $method<V>(self, visitor: V) -> Result<V::Value, Error>
+            where
+                V: Visitor<'de>,
+            {
+                visitor.$visit($zero)
+            }
+        )*)*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for Placeholder {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_bool(false)
+    }
+
+    placeholder! {
+        deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64 deserialize_i128
+        deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64 deserialize_u128 => visit_u64(0),
+        deserialize_f32 deserialize_f64 => visit_f64(0.0),
+        deserialize_str deserialize_string deserialize_identifier => visit_str(""),
+        deserialize_bytes deserialize_byte_buf => visit_bytes(&[]),
+    }
+
+    fn deserialize_char<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_char('\0')
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_none()
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_seq(Placeholders(0))
+    }
+
+    fn deserialize_tuple<V>(self, len: usize, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_seq(Placeholders(len))
+    }
+
+    fn deserialize_tuple_struct<V>(
+        self,
+        _name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_seq(Placeholders(len))
+    }
+
+    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_map(PlaceholderFields([].iter()))
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_map(PlaceholderFields(fields.iter()))
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_enum(PlaceholderVariant(
+            variants.first().copied().unwrap_or_default(),
+        ))
+    }
+
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
+
+    serde::forward_to_deserialize_any! {
+        unit unit_struct
+    }
+}
+
+/// Placeholder sequence of `len` elements.
+struct Placeholders(usize);
+
+impl<'de> SeqAccess<'de> for Placeholders {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        if self.0 == 0 {
+            return Ok(None);
+        }
+        self.0 -= 1;
+        seed.deserialize(Placeholder).map(Some)
+    }
+}
+
+/// Placeholder struct with every field.
+struct PlaceholderFields(std::slice::Iter<'static, &'static str>);
+
+impl<'de> MapAccess<'de> for PlaceholderFields {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Error>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        self.0
+            .next()
+            .map(|field| seed.deserialize(StrDeserializer::<Error>::new(field)))
+            .transpose()
+    }
+
+    fn next_value_seed<T>(&mut self, seed: T) -> Result<T::Value, Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        seed.deserialize(Placeholder)
+    }
+}
+
+/// Placeholder enum, holding its first variant.
+struct PlaceholderVariant(&'static str);
+
+impl<'de> EnumAccess<'de> for PlaceholderVariant {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self), Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        Ok((
+            seed.deserialize(StrDeserializer::<Error>::new(self.0))?,
+            self,
+        ))
+    }
+}
+
+impl<'de> VariantAccess<'de> for PlaceholderVariant {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        seed.deserialize(Placeholder)
+    }
+
+    fn tuple_variant<V>(self, len: usize, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_seq(Placeholders(len))
+    }
+
+    fn struct_variant<V>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_map(PlaceholderFields(fields.iter()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::BTreeMap;
+    use std::num::NonZeroU8;
+
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    #[serde(deny_unknown_fields)]
+    struct Deployment {
+        name: String,
+        replicas: u8,
+        #[serde(default)]
+        paused: bool,
+        priority: Option<NonZeroU8>,
+        containers: Vec<Container>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct Container {
+        image: String,
+        ports: BTreeMap<String, u16>,
+        pull: Pull,
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    enum Pull {
+        Always,
+        Never,
+    }
+
+    fn errors(yaml: &str, max: usize) -> Vec<String> {
+        let document: Value = serde_yaml::from_str(yaml).unwrap();
+        deserialize::<Deployment>(&document, max, false)
+            .unwrap_err()
+            .iter()
+            .map(|err| format!("{}: {}", err.path(), err.message()))
+            .collect()
+    }
+
+    #[test]
+    fn every_error() {
+        let yaml = "\
+name: [web]
+replicas: 300
+priority: 0
+replcas: 3
+containers:
+- image: nginx
+  ports: {http: eighty, https: 443}
+  pull: Sometimes
+- ports: {}
+  pull: Never
+";
+        assert_eq!(
+            errors(yaml, usize::MAX),
+            [
+                "name: invalid type: sequence, expected a string",
+                "replicas: invalid value: integer `300`, expected u8",
+                "priority: invalid value: integer `0`, expected a nonzero u8",
+                "replcas: unknown field `replcas`, expected one of \
+                 `name`, `replicas`, `paused`, `priority`, `containers`",
+                "containers[0].ports.http: invalid type: string \"eighty\", expected u16",
+                "containers[0].pull: unknown variant `Sometimes`, expected `Always` or `Never`",
+                "containers[1]: missing field `image`",
+            ]
+        );
+        assert_eq!(errors(yaml, 2).len(), 2);
+    }
+
+    #[test]
+    fn valid() {
+        let document: Value = serde_yaml::from_str(
+            "name: web\nreplicas: 3\ncontainers:\n- {image: nginx, ports: {http: 80}, pull: Always}",
+        )
+        .unwrap();
+        let deployment = deserialize::<Deployment>(&document, 10, false).unwrap();
+        assert_eq!(deployment.containers[0].ports["http"], 80);
+        assert!(!deployment.paused);
+    }
+}
@@ -0,0 +1,1638 @@
+use std::borrow::Cow;
+use std::future::Future;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use axum_core::{
+    body::Body,
+    extract::{FromRequest, Request},
+};
+use bytes::Bytes;
+use http::{header, Extensions, HeaderMap, Method, StatusCode};
+use http_body_util::BodyExt;
+use serde::de::DeserializeOwned;
+use serde_yaml::Value;
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::case::KeyCase;
+use crate::charset::Charset;
+use crate::classify::Classified;
+use crate::de::EmptyAsNone;
+use crate::lenient::Leniency;
+use crate::limits::Limits;
+use crate::multi_doc::is_marker;
+use crate::policy::Policy;
+use crate::rejection::*;
+use crate::ser::{self, SerializeConfig};
+use crate::timing::Step;
+
+/// Configuration of the YAML extractors.
+///
+/// The extractors look the configuration up in the request extensions, so it
+/// is usually installed for a whole router with axum's `Extension` layer, or
+/// by using the configuration itself as a [`Layer`].
+/// Requests without a `YamlConfig` use the default configuration.
+/// [`StateYaml`](crate::yaml::StateYaml) takes it from the application state
+/// instead.
+///
+/// Besides parsing, it sets how the rejections of the extractors are
+/// reported: their [`rejection_format`](Self::rejection_format), message
+/// length, redaction and log level, and how responses are
+/// [`serialized`](Self::serialize) when the configuration is used as a
+/// [`Layer`].
+///
+/// # Example
+///
+/// ```no_run
+/// use axum::{routing::post, Extension, Router};
+/// use axum_yaml::{policy::Policy, Yaml, YamlConfig};
+/// use serde_yaml::Value;
+///
+/// async fn create(Yaml(manifest): Yaml<Value>) {
+///     // `manifest` does not use the host network
+/// }
+///
+/// let config = YamlConfig::new().policy(Policy::new().deny_value("spec.hostNetwork", true));
+///
+/// let app: Router = Router::new()
+///     .route("/manifests", post(create))
+///     .layer(Extension(config));
+/// ```
+///
+/// Settings for both directions, installed as a layer:
+///
+/// ```no_run
+/// use axum::{routing::post, Router};
+/// use axum_yaml::{case::KeyCase, ser::SerializeConfig, Yaml, YamlConfig};
+/// use serde_yaml::Value;
+///
+/// async fn echo(Yaml(document): Yaml<Value>) -> Yaml<Value> {
+///     Yaml(document)
+/// }
+///
+/// let config = YamlConfig::new()
+///     .key_case(KeyCase::Snake)
+///     .serialize(SerializeConfig::new().key_case(KeyCase::Camel));
+///
+/// let app: Router = Router::new().route("/echo", post(echo)).layer(config);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct YamlConfig {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Inner {
+    policy: Option<Policy>,
+    key_case: Option<KeyCase>,
+    drain_limit: usize,
+    limits: Limits,
+    empty_as_none: bool,
+    yaml_versions: Option<Vec<String>>,
+    media_types: Vec<String>,
+    vendor_types: Option<Vec<String>>,
+    transcode: bool,
+    reject_unexpected_bodies: bool,
+    data_error_status: Option<StatusCode>,
+    error_snippets: bool,
+    error_path_headers: bool,
+    max_errors: usize,
+    max_documents: Option<usize>,
+    #[cfg(feature = "timeout")]
+    parse_timeout: Option<std::time::Duration>,
+    #[cfg(feature = "digest")]
+    verify_digests: bool,
+    progress: Option<Progress>,
+    #[cfg(feature = "schemars")]
+    defaults: Option<schemars::Schema>,
+    rejections: RejectionSettings,
+    serializer: Option<Arc<SerializeConfig>>,
+}
+
+type ProgressFn = dyn Fn(u64, Option<u64>) + Send + Sync;
+
+#[derive(Clone)]
+struct Progress(Arc<ProgressFn>);
+
+impl std::fmt::Debug for Progress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Progress")
+    }
+}
+
+impl Inner {
+    /// Whether documents are parsed into a [`Value`] before being
+    /// deserialized into the target type.
+    fn needs_document(&self) -> bool {
+        self.policy.is_some()
+            || self.key_case.is_some()
+            || self.empty_as_none
+            || self.max_errors > 1
+            || self.has_defaults()
+    }
+
+    #[cfg(feature = "schemars")]
+    fn has_defaults(&self) -> bool {
+        self.defaults.is_some()
+    }
+
+    #[cfg(not(feature = "schemars"))]
+    fn has_defaults(&self) -> bool {
+        false
+    }
+}
+
+impl YamlConfig {
+    /// Create the default configuration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reject documents violating `policy` before they are deserialized.
+    pub fn policy(mut self, policy: Policy) -> Self {
+        Arc::make_mut(&mut self.inner).policy = Some(policy);
+        self
+    }
+
+    /// Rename the mapping keys of documents to `case` before they are
+    /// deserialized, e.g. to accept `camelCase` documents into `snake_case`
+    /// fields.
+    pub fn key_case(mut self, case: KeyCase) -> Self {
+        Arc::make_mut(&mut self.inner).key_case = Some(case);
+        self
+    }
+
+    /// Deserialize empty strings into `None` for every `Option` field, as
+    /// [`de::empty_as_none`](crate::de::empty_as_none) does for one field.
+    pub fn empty_as_none(mut self, enabled: bool) -> Self {
+        Arc::make_mut(&mut self.inner).empty_as_none = enabled;
+        self
+    }
+
+    /// Fill properties missing from documents with their `default` value in
+    /// `schema` before they are deserialized.
+    ///
+    /// Requires the `schemars` feature. See [`defaults`](crate::defaults).
+    #[cfg(feature = "schemars")]
+    pub fn fill_defaults(mut self, schema: schemars::Schema) -> Self {
+        Arc::make_mut(&mut self.inner).defaults = Some(schema);
+        self
+    }
+
+    /// Reject documents containing a mapping with more than `max` keys.
+    ///
+    /// Guards against "wide" documents that stay within size limits but
+    /// degrade the deserialization of hash maps. The keys are counted by a
+    /// first pass over the body, before the mapping is built.
+    pub fn max_mapping_keys(mut self, max: usize) -> Self {
+        Arc::make_mut(&mut self.inner).limits.max_mapping_keys = Some(max);
+        self
+    }
+
+    /// Reject documents containing a sequence with more than `max` items,
+    /// with a `400 Bad Request`.
+    ///
+    /// Like [`max_mapping_keys`](Self::max_mapping_keys), the items are
+    /// counted by a first pass over the body, so a moderately sized body of
+    /// short items such as `[0,0,0,...]` cannot allocate millions of nodes.
+    pub fn max_sequence_len(mut self, max: usize) -> Self {
+        Arc::make_mut(&mut self.inner).limits.max_sequence_len = Some(max);
+        self
+    }
+
+    /// Reject documents repeating a key within the same mapping, instead of
+    /// letting the last value win.
+    ///
+    /// The rejection names the duplicated key with its path and location,
+    /// e.g. ``b: duplicate key `c` at line 5 column 3``.
+    pub fn reject_duplicate_keys(mut self, enabled: bool) -> Self {
+        Arc::make_mut(&mut self.inner).limits.unique_keys = enabled;
+        self
+    }
+
+    /// Reject documents with tags outside the YAML core schema, such as
+    /// `!!binary`, `!!timestamp` or application tags like `!Circle`, with a
+    /// `400 Bad Request`.
+    ///
+    /// `!!str`, `!!int` and the other core tags are still accepted.
+    pub fn core_tags_only(mut self, enabled: bool) -> Self {
+        Arc::make_mut(&mut self.inner).limits.core_tags_only = enabled;
+        self
+    }
+
+    /// Reject request bodies with more than `max` aliases, such as
+    /// `*defaults`, with a `400 Bad Request`.
+    ///
+    /// The aliases are counted by scanning the source before it is parsed,
+    /// since the parser expands them without reporting them. Use
+    /// [`max_expanded_nodes`](Self::max_expanded_nodes), which counts the
+    /// nodes the parser produces, as the guard against "billion laughs"
+    /// payloads expanding a few aliases into millions of nodes.
+    pub fn max_aliases(mut self, max: usize) -> Self {
+        Arc::make_mut(&mut self.inner).limits.max_aliases = Some(max);
+        self
+    }
+
+    /// Reject request bodies with more than `max` scalars, sequences and
+    /// mappings once their aliases are expanded, with a `400 Bad Request`.
+    ///
+    /// The nodes are counted by a first pass over the body that stops at the
+    /// limit without building them, so oversized bodies are parsed twice at
+    /// most, even when no alias is involved.
+    pub fn max_expanded_nodes(mut self, max: usize) -> Self {
+        Arc::make_mut(&mut self.inner).limits.max_nodes = Some(max);
+        self
+    }
+
+    /// Reject documents with sequences and mappings nested more than `max`
+    /// levels deep, with a `400 Bad Request`. A top-level mapping is one
+    /// level deep.
+    ///
+    /// The depth is checked by a first pass over the body that stops at the
+    /// limit, before the document is built. Documents nested deeper than 128
+    /// levels are rejected as syntax errors by the parser regardless.
+    pub fn max_depth(mut self, max: usize) -> Self {
+        Arc::make_mut(&mut self.inner).limits.max_depth = Some(max);
+        self
+    }
+
+    /// Reject request bodies holding more than `max` documents with a
+    /// `413 Payload Too Large`.
+    ///
+    /// Applies to [`YamlMultiDoc`](crate::YamlMultiDoc), which stops parsing
+    /// at the first document over the limit, and to
+    /// [`YamlDocumentStream`](crate::YamlDocumentStream), which yields the
+    /// rejection in place of that document and ends, and to
+    /// [`YamlBundle`](crate::bundle::YamlBundle).
+    pub fn max_documents(mut self, max: usize) -> Self {
+        Arc::make_mut(&mut self.inner).max_documents = Some(max);
+        self
+    }
+
+    /// Reject documents declaring a `%YAML` version other than `versions`,
+    /// e.g. `["1.2"]`, instead of parsing them with different semantics than
+    /// their author intended.
+    ///
+    /// Documents without a `%YAML` directive are always accepted.
+    pub fn yaml_versions<I>(mut self, versions: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        Arc::make_mut(&mut self.inner).yaml_versions =
+            Some(versions.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Also accept request bodies whose `Content-Type` is one of
+    /// `media_types`, e.g. `text/yaml` or `application/x-yaml`, which older
+    /// clients still send.
+    ///
+    /// `application/yaml` and the `+yaml` media types are always accepted.
+    /// Parameters such as `charset` are ignored when matching. Bodies read
+    /// with one of `media_types` are reported as a
+    /// [`Leniency::LegacyContentType`], see the [`lenient`](crate::lenient)
+    /// module.
+    pub fn accept_media_types<I>(mut self, media_types: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        Arc::make_mut(&mut self.inner).media_types.extend(
+            media_types
+                .into_iter()
+                .map(|media_type| media_type.as_ref().trim().to_ascii_lowercase()),
+        );
+        self
+    }
+
+    /// Only accept the `+yaml` media types matching `patterns`, instead of
+    /// every `application/*+yaml` type.
+    ///
+    /// A pattern is either a media type, such as `application/openapi+yaml`,
+    /// or a vendor tree ending with `*`, such as `application/vnd.mycorp.*`.
+    /// `application/yaml` is always accepted. Requests with another `+yaml`
+    /// type are rejected with a
+    /// [`MissingYamlContentType`](crate::rejection::MissingYamlContentType)
+    /// listing the accepted types.
+    pub fn vendor_media_types<I>(mut self, patterns: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        Arc::make_mut(&mut self.inner).vendor_types = Some(
+            patterns
+                .into_iter()
+                .map(|pattern| pattern.as_ref().trim().to_ascii_lowercase())
+                .collect(),
+        );
+        self
+    }
+
+    /// Transcode request bodies declaring a UTF-16 or ISO-8859-1 `charset`
+    /// to UTF-8 before parsing them.
+    ///
+    /// Without it, such bodies are rejected with
+    /// [`UnsupportedCharset`](crate::rejection::UnsupportedCharset), like
+    /// those declaring any charset other than UTF-8 or US-ASCII. Bodies
+    /// without a `charset` are parsed as UTF-8.
+    pub fn transcode_charsets(mut self, transcode: bool) -> Self {
+        Arc::make_mut(&mut self.inner).transcode = transcode;
+        self
+    }
+
+    /// Reject `GET`, `HEAD` and `DELETE` requests with a body, which HTTP
+    /// gives no meaning, with
+    /// [`UnexpectedRequestBody`](crate::rejection::UnexpectedRequestBody)
+    /// instead of extracting it.
+    ///
+    /// Requests of these methods without a body are still extracted, e.g.
+    /// into `None` by [`OptionalYaml`](crate::OptionalYaml) or `Yaml<Option<T>>`.
+    /// Off by default, so tools sending such bodies keep working.
+    pub fn reject_unexpected_bodies(mut self, reject: bool) -> Self {
+        Arc::make_mut(&mut self.inner).reject_unexpected_bodies = reject;
+        self
+    }
+
+    /// Reply with `status` instead of `400 Bad Request` to request bodies
+    /// that are valid YAML but do not deserialize into the target type, e.g.
+    /// `422 Unprocessable Entity` as axum's `Json` does.
+    ///
+    /// Syntax errors keep `400 Bad Request`, see
+    /// [`YamlErrorKind::is_syntax`](crate::rejection::YamlErrorKind::is_syntax).
+    pub fn data_error_status(mut self, status: StatusCode) -> Self {
+        Arc::make_mut(&mut self.inner).data_error_status = Some(status);
+        self
+    }
+
+    /// Quote the lines of the request body leading to a syntax or
+    /// deserialization error in the rejection message, with a caret under
+    /// the failing column, for humans posting hand-written YAML:
+    ///
+    /// ```text
+    ///   |
+    /// 1 | a: 1
+    /// 2 | b: [{x: 1, y: z}]
+    ///   |               ^
+    /// ```
+    ///
+    /// Snippets echo the request body, so they are left out while
+    /// [`redact_values`](Self::redact_values) is on.
+    pub fn error_snippets(mut self, enabled: bool) -> Self {
+        Arc::make_mut(&mut self.inner).error_snippets = enabled;
+        self
+    }
+
+    /// Report the serde path and location of deserialization errors in the
+    /// `X-Error-Path` and `X-Error-Location` headers of the rejection, e.g.
+    /// `X-Error-Path: b[0].y` and `X-Error-Location: 2:15` for line 2,
+    /// column 15, so thin clients can point at the failing field without
+    /// parsing the body.
+    pub fn error_path_headers(mut self, enabled: bool) -> Self {
+        Arc::make_mut(&mut self.inner).error_path_headers = enabled;
+        self
+    }
+
+    /// Report up to `max` values of a document that fail to deserialize in
+    /// a single [`YamlErrors`](crate::rejection::YamlErrors) rejection,
+    /// rather than stopping at the first one, so clients can fix a
+    /// hand-written document in one round trip.
+    ///
+    /// Values of the wrong type or out of range are replaced by a placeholder
+    /// to carry on, unknown fields are skipped and missing ones filled in.
+    /// Deserialization runs again for each missing or unknown field and each
+    /// error raised by a `Deserialize` implementation, e.g. a failed
+    /// validation, so keep `max` low for documents with many of them. Syntax
+    /// errors still stop at the first one. Defaults to `1`.
+    pub fn collect_errors(mut self, max: usize) -> Self {
+        Arc::make_mut(&mut self.inner).max_errors = max;
+        self
+    }
+
+    /// Reject request bodies taking longer than `timeout` to parse with a
+    /// `408 Request Timeout`, guarding against inputs that are cheap to send
+    /// but slow to parse.
+    ///
+    /// Requires the `timeout` feature and a Tokio runtime. The document is
+    /// parsed, checked against the limits and policy, on Tokio's blocking
+    /// pool, and only deserialized into the target type once it completes in
+    /// time. A parse that times out is abandoned but keeps its blocking
+    /// thread until it ends, so bound its cost with limits such as
+    /// [`max_expanded_nodes`](Self::max_expanded_nodes) as well.
+    ///
+    /// Applies to every extractor parsing the body as it is extracted.
+    /// [`LazyYaml::parse`](crate::LazyYaml::parse) and
+    /// [`YamlDocumentStream`](crate::YamlDocumentStream), which parse later
+    /// and one call or document at a time, are not bounded by it.
+    #[cfg(feature = "timeout")]
+    pub fn parse_timeout(mut self, timeout: std::time::Duration) -> Self {
+        Arc::make_mut(&mut self.inner).parse_timeout = Some(timeout);
+        self
+    }
+
+    /// Reject request bodies not matching their `Content-Digest` or
+    /// `Repr-Digest` header ([RFC 9530]) with a `400 Bad Request`, before
+    /// they are parsed.
+    ///
+    /// Requires the `digest` feature. `sha-256` and `sha-512` digests are
+    /// checked against the body as the extractor receives it, so it must not
+    /// be decompressed by an earlier layer. Requests without either header
+    /// are accepted, and [`YamlDocumentStream`](crate::YamlDocumentStream)
+    /// bodies are not verified since their documents are handled as they
+    /// arrive.
+    ///
+    /// [RFC 9530]: https://www.rfc-editor.org/rfc/rfc9530
+    #[cfg(feature = "digest")]
+    pub fn verify_digests(mut self, enabled: bool) -> Self {
+        Arc::make_mut(&mut self.inner).verify_digests = enabled;
+        self
+    }
+
+    /// Render the body of rejections in `format` instead of plain text.
+    ///
+    /// Rejections of `axum_core`, such as
+    /// [`BytesRejection`](axum_core::extract::rejection::BytesRejection), keep
+    /// their plain text body.
+    pub fn rejection_format(mut self, format: BodyFormat) -> Self {
+        Arc::make_mut(&mut self.inner).rejections.format = format;
+        self
+    }
+
+    /// Truncate the messages of rejections to `max` characters, ending them
+    /// with `…`.
+    ///
+    /// Messages can grow with the request body, e.g. when every candidate of
+    /// a [`YamlAnyOf`](crate::any_of::YamlAnyOf) reports its error. The
+    /// limit applies to response bodies and logged messages alike.
+    pub fn max_message_len(mut self, max: usize) -> Self {
+        Arc::make_mut(&mut self.inner).rejections.max_message_len = max;
+        self
+    }
+
+    /// Replace the values of the request body echoed by [`YamlError`]
+    /// messages, such as `invalid type: string "hunter2"`, with `***`.
+    ///
+    /// Keeps passwords and tokens sent in the wrong field out of responses
+    /// and logs, while field names and expected types are still reported.
+    pub fn redact_values(mut self, redact: bool) -> Self {
+        Arc::make_mut(&mut self.inner).rejections.redact_values = redact;
+        self
+    }
+
+    /// Emit the events of rejected requests at `level` instead of
+    /// [`LogLevel::Trace`].
+    ///
+    /// Raising it, e.g. to [`LogLevel::Warn`], makes parse failures visible in
+    /// production without enabling trace-level logging globally.
+    pub fn rejection_log_level(mut self, level: LogLevel) -> Self {
+        Arc::make_mut(&mut self.inner).rejections.log_level = level;
+        self
+    }
+
+    /// Read and discard up to `limit` bytes of the body of requests rejected
+    /// before their body is read, e.g. for a wrong `Content-Type`.
+    ///
+    /// Unread bodies can force the connection to be closed while a keep-alive
+    /// client is still uploading. Draining lets the connection be reused, at
+    /// the cost of receiving the rejected upload. Bodies larger than `limit`
+    /// are left unread. Defaults to `0`, draining nothing.
+    pub fn drain_rejected_body(mut self, limit: usize) -> Self {
+        Arc::make_mut(&mut self.inner).drain_limit = limit;
+        self
+    }
+
+    /// Call `progress` with the number of bytes received so far, and the
+    /// `Content-Length` of the request if known, every time a chunk of the
+    /// body is read.
+    ///
+    /// Lets the progress of large uploads be reported, e.g. to a UI, while
+    /// keeping the extractors.
+    pub fn progress<F>(mut self, progress: F) -> Self
+    where
+        F: Fn(u64, Option<u64>) + Send + Sync + 'static,
+    {
+        Arc::make_mut(&mut self.inner).progress = Some(Progress(Arc::new(progress)));
+        self
+    }
+
+    /// Serialize the YAML responses of the routes this configuration is
+    /// [layered](Layer) on with `config`.
+    ///
+    /// It replaces the configuration of an enclosing
+    /// [`SerializeConfigLayer`](crate::ser::SerializeConfigLayer). Installed
+    /// as an `Extension` or taken from state, the configuration does not
+    /// reach responses; pass [`serializer`](Self::serializer) to
+    /// [`Yaml::with_config`](crate::Yaml::with_config) there instead.
+    pub fn serialize(mut self, config: SerializeConfig) -> Self {
+        Arc::make_mut(&mut self.inner).serializer = Some(Arc::new(config));
+        self
+    }
+
+    /// The configuration set with [`serialize`](Self::serialize), if any.
+    pub fn serializer(&self) -> Option<&SerializeConfig> {
+        self.inner.serializer.as_deref()
+    }
+
+    pub(crate) fn from_extensions(extensions: &Extensions) -> Self {
+        extensions.get::<Self>().cloned().unwrap_or_default()
+    }
+
+    /// Whether the `Content-Type` of `headers` is accepted as YAML.
+    pub(crate) fn yaml_content_type(&self, headers: &HeaderMap) -> bool {
+        let Some(mime) = content_type(headers) else {
+            return false;
+        };
+
+        let essence = mime.essence_str();
+        if crate::yaml::is_yaml_media_type(essence) {
+            return match &self.inner.vendor_types {
+                Some(vendor_types) if essence != "application/yaml" => {
+                    vendor_types
+                        .iter()
+                        .any(|pattern| match pattern.strip_suffix('*') {
+                            Some(tree) => essence.starts_with(tree),
+                            None => essence == pattern,
+                        })
+                }
+                _ => true,
+            };
+        }
+        self.legacy_media_type(essence)
+    }
+
+    /// Whether `essence` is only accepted as one of the
+    /// [`accept_media_types`](Self::accept_media_types).
+    fn legacy_media_type(&self, essence: &str) -> bool {
+        self.inner
+            .media_types
+            .iter()
+            .any(|media_type| media_type == essence)
+    }
+
+    /// Run `extract`, reporting its rejection according to this
+    /// configuration.
+    pub(crate) async fn report<T, R>(
+        &self,
+        extract: impl Future<Output = Result<T, R>>,
+    ) -> Result<T, R>
+    where
+        R: WithSettings,
+    {
+        extract.await.map_err(|rejection| self.reject(rejection))
+    }
+
+    /// Report `rejection` according to this configuration.
+    pub(crate) fn reject<R>(&self, rejection: R) -> R
+    where
+        R: WithSettings,
+    {
+        rejection.with_settings(self.inner.rejections)
+    }
+
+    /// Rejection for a request whose `Content-Type` is not accepted.
+    pub(crate) fn missing_content_type(&self) -> MissingYamlContentType {
+        self.reject(MissingYamlContentType::accepting(
+            self.inner.vendor_types.as_deref(),
+            &self.inner.media_types,
+        ))
+    }
+
+    /// Wrap the body of `req` to report the progress of reading it, if a
+    /// progress callback is configured.
+    pub(crate) fn track_progress(&self, req: Request) -> Request {
+        let Some(Progress(progress)) = self.inner.progress.clone() else {
+            return req;
+        };
+
+        let total = req
+            .headers()
+            .get(header::CONTENT_LENGTH)
+            .and_then(|length| length.to_str().ok()?.parse().ok());
+        let mut received = 0;
+        req.map(|body| {
+            Body::new(body.map_frame(move |frame| {
+                if let Some(data) = frame.data_ref() {
+                    received += data.len() as u64;
+                    progress(received, total);
+                }
+                frame
+            }))
+        })
+    }
+
+    /// Read the body of `req`, transcoded to UTF-8 according to its
+    /// `charset`.
+    pub(crate) async fn read_body<S>(&self, req: Request, state: &S) -> Result<Bytes, YamlRejection>
+    where
+        S: Send + Sync,
+    {
+        let (bytes, charset) = self.read_raw_body(req, state).await?;
+        Ok(match charset.decode(&bytes)? {
+            Cow::Borrowed(_) => bytes,
+            Cow::Owned(decoded) => Bytes::from(decoded),
+        })
+    }
+
+    /// Read the body of `req` as received, along with its charset.
+    pub(crate) async fn read_raw_body<S>(
+        &self,
+        req: Request,
+        state: &S,
+    ) -> Result<(Bytes, Charset), YamlRejection>
+    where
+        S: Send + Sync,
+    {
+        let legacy = content_type(req.headers()).is_some_and(|mime| {
+            let essence = mime.essence_str();
+            !crate::yaml::is_yaml_media_type(essence) && self.legacy_media_type(essence)
+        });
+        if legacy {
+            Leniency::LegacyContentType.report(req.headers(), req.extensions());
+        }
+
+        let method = req.method().clone();
+        let bodiless = self.inner.reject_unexpected_bodies
+            && matches!(method, Method::GET | Method::HEAD | Method::DELETE);
+        let unexpected_body =
+            || UnexpectedRequestBody::from_err(format!("`{method}` requests must not have a body"));
+        if bodiless && announces_body(req.headers()) {
+            self.drain(req).await;
+            return Err(unexpected_body().into());
+        }
+
+        let charset = match Charset::from_headers(req.headers(), self.inner.transcode) {
+            Ok(charset) => charset,
+            Err(rejection) => {
+                self.drain(req).await;
+                return Err(rejection.into());
+            }
+        };
+        #[cfg(feature = "digest")]
+        let digests = if self.inner.verify_digests {
+            match crate::content_digest::Digests::from_headers(req.headers()) {
+                Ok(digests) => digests,
+                Err(rejection) => {
+                    self.drain(req).await;
+                    return Err(rejection.into());
+                }
+            }
+        } else {
+            Default::default()
+        };
+        let bytes = Bytes::from_request(self.track_progress(req), state).await?;
+        // Bodies may also come without a length, e.g. in HTTP/2 requests.
+        if bodiless && !bytes.is_empty() {
+            return Err(unexpected_body().into());
+        }
+        #[cfg(feature = "digest")]
+        digests.verify(&bytes)?;
+        Ok((bytes, charset))
+    }
+
+    /// Drain the body of a request about to be rejected, up to the configured
+    /// limit.
+    pub(crate) async fn drain(&self, req: Request) {
+        let mut remaining = self.inner.drain_limit;
+        if remaining == 0 {
+            return;
+        }
+
+        let mut body = req.into_body();
+        while let Some(Ok(frame)) = body.frame().await {
+            let Some(data) = frame.data_ref() else {
+                continue;
+            };
+            match remaining.checked_sub(data.len()) {
+                Some(left) if left > 0 => remaining = left,
+                _ => return,
+            }
+        }
+    }
+
+    /// Deserialize `bytes` into `T` according to this configuration.
+    pub(crate) fn deserialize<T>(&self, bytes: &[u8]) -> Result<T, YamlRejection>
+    where
+        T: DeserializeOwned,
+    {
+        let step = Step::parse(bytes.len());
+        self.check_source(bytes)?;
+        let value = self
+            .deserialize_document(serde_yaml::Deserializer::from_slice(bytes))
+            .map_err(|rejection| self.annotate(rejection, bytes))?;
+        step.documents(1);
+        step.end();
+        Ok(value)
+    }
+
+    /// Deserialize every `---` separated document of `bytes` into `T`.
+    pub(crate) fn deserialize_documents<T>(&self, bytes: &[u8]) -> Result<Vec<T>, YamlRejection>
+    where
+        T: DeserializeOwned,
+    {
+        // An empty body holds no documents rather than a single null one.
+        if bytes.trim_ascii().is_empty() {
+            return Ok(Vec::new());
+        }
+        let step = Step::parse(bytes.len());
+        self.check_source(bytes)?;
+
+        let values: Vec<T> = serde_yaml::Deserializer::from_slice(bytes)
+            .enumerate()
+            .map(|(index, document)| {
+                self.check_document_count(index + 1)?;
+                self.deserialize_document(document)
+            })
+            .collect::<Result<_, _>>()
+            .map_err(|rejection| self.annotate(rejection, bytes))?;
+        step.documents(values.len());
+        step.end();
+        Ok(values)
+    }
+
+    /// Check that a body holding at least `count` documents is within
+    /// [`max_documents`](Self::max_documents).
+    pub(crate) fn check_document_count(&self, count: usize) -> Result<(), TooManyYamlDocuments> {
+        match self.inner.max_documents {
+            Some(max) if count > max => Err(TooManyYamlDocuments::from_err(format!(
+                "body has more than {max} documents"
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Check `bytes` against the accepted versions and the limits applying
+    /// before they are parsed.
+    pub(crate) fn check_source(&self, bytes: &[u8]) -> Result<(), YamlRejection> {
+        self.check_versions(bytes)?;
+        self.inner
+            .limits
+            .check_source(bytes)
+            .map_err(|rejection| self.annotate(rejection, bytes))
+    }
+
+    /// Check the `%YAML` directives of `bytes` against the accepted versions.
+    ///
+    /// Directives are only read from the prefix of each document, up to its
+    /// `---` marker or first line of content, so a `%YAML` line within a
+    /// multi-line scalar is left alone.
+    fn check_versions(&self, bytes: &[u8]) -> Result<(), UnsupportedYamlVersion> {
+        let Some(versions) = &self.inner.yaml_versions else {
+            return Ok(());
+        };
+
+        let mut prefix = true;
+        for line in bytes.split(|&b| b == b'\n') {
+            let line = line.trim_ascii_end();
+            if is_marker(line, b"...") {
+                prefix = true;
+                continue;
+            }
+            if !prefix {
+                continue;
+            }
+            let text = line.trim_ascii_start();
+            if !line.starts_with(b"%") && !text.is_empty() && !text.starts_with(b"#") {
+                prefix = false;
+                continue;
+            }
+            let Some(directive) = line
+                .strip_prefix(b"%YAML")
+                .filter(|rest| rest.starts_with(b" ") || rest.starts_with(b"\t"))
+            else {
+                continue;
+            };
+            let directive = String::from_utf8_lossy(directive);
+            let version = directive.split('#').next().unwrap_or_default().trim();
+            if !versions.iter().any(|accepted| accepted == version) {
+                return Err(UnsupportedYamlVersion::from_err(format!(
+                    "`%YAML {version}` is not accepted, expected one of {}",
+                    versions.join(", ")
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn deserialize_document<T>(
+        &self,
+        deserializer: serde_yaml::Deserializer<'_>,
+    ) -> Result<T, YamlRejection>
+    where
+        T: DeserializeOwned,
+    {
+        let inner = &*self.inner;
+        if !inner.needs_document() {
+            return crate::classify::deserialize_stream(deserializer)
+                .map_err(|err| self.error_status(YamlError::data(err).into()));
+        }
+
+        let document = self.prepare(deserializer)?;
+        self.deserialize_prepared(document)
+    }
+
+    /// Run `parse` over `bytes` on Tokio's blocking pool within the
+    /// configured [`YamlConfig::parse_timeout`], or return `None` if there is
+    /// none, for the caller to parse `bytes` in place.
+    #[cfg_attr(not(feature = "timeout"), allow(unused_variables))]
+    pub(crate) async fn within_timeout<R>(
+        &self,
+        bytes: &Bytes,
+        parse: fn(&Self, &[u8]) -> Result<R, YamlRejection>,
+    ) -> Option<Result<R, YamlRejection>>
+    where
+        R: Send + 'static,
+    {
+        #[cfg(feature = "timeout")]
+        if let Some(timeout) = self.inner.parse_timeout {
+            let config = self.clone();
+            let source = bytes.clone();
+            let task = tokio::task::spawn_blocking(move || parse(&config, &source));
+            return Some(match tokio::time::timeout(timeout, task).await {
+                Ok(Ok(result)) => result,
+                Ok(Err(err)) => std::panic::resume_unwind(err.into_panic()),
+                Err(_) => Err(YamlParseTimeout::from_err(format!(
+                    "parsing took longer than {timeout:?}"
+                ))
+                .into()),
+            });
+        }
+        None
+    }
+
+    /// Deserialize `bytes` into `T` according to this configuration, within
+    /// the configured [`YamlConfig::parse_timeout`].
+    pub(crate) async fn deserialize_body<T>(&self, bytes: Bytes) -> Result<T, YamlRejection>
+    where
+        T: DeserializeOwned,
+    {
+        match self.within_timeout(&bytes, Self::document).await {
+            Some(document) => self
+                .deserialize_prepared(document?)
+                .map_err(|rejection| self.annotate(rejection, &bytes)),
+            None => self.deserialize(&bytes),
+        }
+    }
+
+    /// Deserialize every document of `bytes` into `T` according to this
+    /// configuration, within the configured [`YamlConfig::parse_timeout`].
+    pub(crate) async fn deserialize_documents_body<T>(
+        &self,
+        bytes: Bytes,
+    ) -> Result<Vec<T>, YamlRejection>
+    where
+        T: DeserializeOwned,
+    {
+        match self.within_timeout(&bytes, Self::documents).await {
+            Some(documents) => documents?
+                .into_iter()
+                .map(|document| self.deserialize_prepared(document))
+                .collect::<Result<_, _>>()
+                .map_err(|rejection| self.annotate(rejection, &bytes)),
+            None => self.deserialize_documents(&bytes),
+        }
+    }
+
+    /// Parse one document like [`YamlConfig::document`], within the
+    /// configured [`YamlConfig::parse_timeout`].
+    pub(crate) async fn document_body(&self, bytes: Bytes) -> Result<Value, YamlRejection> {
+        match self.within_timeout(&bytes, Self::document).await {
+            Some(document) => document,
+            None => self.document(&bytes),
+        }
+    }
+
+    /// Deserialize a document returned by [`YamlConfig::prepare`].
+    pub(crate) fn deserialize_prepared<T>(&self, document: Value) -> Result<T, YamlRejection>
+    where
+        T: DeserializeOwned,
+    {
+        let inner = &*self.inner;
+        if inner.max_errors > 1 {
+            return crate::collect::deserialize(&document, inner.max_errors, inner.empty_as_none)
+                .map_err(|errors| self.error_status(YamlErrors::new(errors).into()));
+        }
+        self.deserialize_value(document)
+            .map_err(|err| self.error_status(YamlError::data(err).into()))
+    }
+
+    /// Apply the configured [`YamlConfig::data_error_status`] to `rejection`.
+    pub(crate) fn error_status(&self, rejection: YamlRejection) -> YamlRejection {
+        match (rejection, self.inner.data_error_status) {
+            (YamlRejection::YamlError(err), Some(status)) if !err.kind().is_syntax() => {
+                err.with_status(status).into()
+            }
+            (YamlRejection::YamlErrors(errors), Some(status)) => errors.with_status(status).into(),
+            (rejection, _) => rejection,
+        }
+    }
+
+    /// Parse one document and run it through the limits, key case, defaults
+    /// and policy configured.
+    pub(crate) fn document(&self, bytes: &[u8]) -> Result<Value, YamlRejection> {
+        let step = Step::parse(bytes.len());
+        self.check_source(bytes)?;
+        let document = self
+            .prepare(serde_yaml::Deserializer::from_slice(bytes))
+            .map_err(|rejection| self.annotate(rejection, bytes))?;
+        step.documents(1);
+        step.end();
+        Ok(document)
+    }
+
+    /// Parse every `---` separated document of `bytes` like
+    /// [`YamlConfig::document`].
+    fn documents(&self, bytes: &[u8]) -> Result<Vec<Value>, YamlRejection> {
+        if bytes.trim_ascii().is_empty() {
+            return Ok(Vec::new());
+        }
+        let step = Step::parse(bytes.len());
+        self.check_source(bytes)?;
+
+        let documents: Vec<Value> = serde_yaml::Deserializer::from_slice(bytes)
+            .enumerate()
+            .map(|(index, document)| {
+                self.check_document_count(index + 1)?;
+                self.prepare(document)
+            })
+            .collect::<Result<_, _>>()
+            .map_err(|rejection| self.annotate(rejection, bytes))?;
+        step.documents(documents.len());
+        step.end();
+        Ok(documents)
+    }
+
+    /// Quote `bytes` in the [`YamlError`] of `rejection` if
+    /// [`YamlConfig::error_snippets`] is enabled, and report its path in
+    /// headers if [`YamlConfig::error_path_headers`] is.
+    pub(crate) fn annotate(&self, rejection: YamlRejection, bytes: &[u8]) -> YamlRejection {
+        let YamlRejection::YamlError(mut err) = rejection else {
+            return rejection;
+        };
+        if self.inner.error_snippets {
+            err = err.with_snippet(bytes);
+        }
+        if self.inner.error_path_headers {
+            err = err.with_path_headers();
+        }
+        err.into()
+    }
+
+    pub(crate) fn prepare(
+        &self,
+        deserializer: serde_yaml::Deserializer<'_>,
+    ) -> Result<Value, YamlRejection> {
+        let document =
+            crate::classify::deserialize_stream(deserializer).map_err(YamlError::parse)?;
+        self.prepare_value(document)
+    }
+
+    /// Whether documents go through [`prepare_value`](Self::prepare_value)
+    /// before being deserialized.
+    #[cfg(any(feature = "json", feature = "toml"))]
+    pub(crate) fn needs_document(&self) -> bool {
+        self.inner.needs_document()
+    }
+
+    /// Run a parsed document, of any format, through the key case, defaults
+    /// and policy configured.
+    pub(crate) fn prepare_value(&self, mut document: Value) -> Result<Value, YamlRejection> {
+        let inner = &*self.inner;
+        if let Some(case) = inner.key_case {
+            case.apply(&mut document);
+        }
+        #[cfg(feature = "schemars")]
+        if let Some(schema) = &inner.defaults {
+            crate::defaults::fill_defaults(schema, &mut document);
+        }
+        if let Some(policy) = &inner.policy {
+            policy.check(&document)?;
+        }
+        Ok(document)
+    }
+
+    /// Deserialize a document returned by [`YamlConfig::document`], along
+    /// with the entries `T` ignored.
+    pub(crate) fn deserialize_extra<T>(&self, document: Value) -> Result<(T, Value), Classified>
+    where
+        T: DeserializeOwned,
+    {
+        crate::extra::deserialize_extra(document, self.inner.empty_as_none)
+    }
+
+    /// Deserialize a document returned by [`YamlConfig::document`].
+    pub(crate) fn deserialize_value<T>(&self, document: Value) -> Result<T, Classified>
+    where
+        T: DeserializeOwned,
+    {
+        if self.inner.empty_as_none {
+            crate::classify::deserialize(EmptyAsNone(document))
+        } else {
+            crate::classify::deserialize(document)
+        }
+    }
+}
+
+/// The `Content-Type` of `headers`, if valid.
+fn content_type(headers: &HeaderMap) -> Option<mime::Mime> {
+    headers
+        .get(header::CONTENT_TYPE)?
+        .to_str()
+        .ok()?
+        .parse()
+        .ok()
+}
+
+/// Whether the headers of a request announce a non-empty body.
+fn announces_body(headers: &HeaderMap) -> bool {
+    headers.contains_key(header::TRANSFER_ENCODING)
+        || headers
+            .get(header::CONTENT_LENGTH)
+            .is_some_and(|length| length != "0")
+}
+/// Installs the configuration in the extensions of requests, and serializes
+/// responses with its [`serializer`](YamlConfig::serializer).
+impl<S> Layer<S> for YamlConfig {
+    type Service = YamlConfigService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        YamlConfigService {
+            inner,
+            config: self.clone(),
+        }
+    }
+}
+
+/// Middleware created by using a [`YamlConfig`] as a [`Layer`].
+#[derive(Debug, Clone)]
+pub struct YamlConfigService<S> {
+    inner: S,
+    config: YamlConfig,
+}
+
+impl<S, ReqBody> Service<http::Request<ReqBody>> for YamlConfigService<S>
+where
+    S: Service<http::Request<ReqBody>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = ser::ResponseFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: http::Request<ReqBody>) -> Self::Future {
+        req.extensions_mut().insert(self.config.clone());
+        let serializer = match &self.config.inner.serializer {
+            Some(serializer) => serializer.clone(),
+            None => SerializeConfig::current(),
+        };
+        ser::ResponseFuture::scoped(serializer, || self.inner.call(req))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::BTreeMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use std::sync::Mutex;
+
+    use axum_core::extract::FromRequest;
+    use bytes::Bytes;
+    use serde::Deserialize;
+
+    use crate::Yaml;
+
+    async fn chunks_read(config: YamlConfig) -> usize {
+        let read = Arc::new(AtomicUsize::new(0));
+        let chunks = (0..4).map({
+            let read = read.clone();
+            move |_| {
+                read.fetch_add(1, Ordering::SeqCst);
+                Ok::<_, std::io::Error>(Bytes::from_static(&[0; 10]))
+            }
+        });
+        let req = Request::new(Body::from_stream(futures_util::stream::iter(chunks)));
+
+        config.drain(req).await;
+        read.load(Ordering::SeqCst)
+    }
+
+    #[tokio::test]
+    async fn drain() {
+        assert_eq!(chunks_read(YamlConfig::new()).await, 0);
+        assert_eq!(
+            chunks_read(YamlConfig::new().drain_rejected_body(25)).await,
+            3
+        );
+        assert_eq!(
+            chunks_read(YamlConfig::new().drain_rejected_body(1000)).await,
+            4
+        );
+    }
+
+    #[tokio::test]
+    async fn progress() {
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let config = YamlConfig::new().progress({
+            let reports = reports.clone();
+            move |received, total| reports.lock().unwrap().push((received, total))
+        });
+
+        let chunks = ["a: 1\n", "b: 2\n"].map(Ok::<_, std::io::Error>);
+        let mut req = Request::new(Body::from_stream(futures_util::stream::iter(chunks)));
+        req.headers_mut()
+            .insert(header::CONTENT_TYPE, "application/yaml".parse().unwrap());
+        req.headers_mut()
+            .insert(header::CONTENT_LENGTH, "10".parse().unwrap());
+        req.extensions_mut().insert(config);
+
+        let Yaml(value) = Yaml::<Value>::from_request(req, &()).await.unwrap();
+        assert_eq!(value["b"], 2);
+        assert_eq!(*reports.lock().unwrap(), [(5, Some(10)), (10, Some(10))]);
+    }
+
+    #[test]
+    fn yaml_versions() {
+        let config = YamlConfig::new().yaml_versions(["1.2"]);
+        let deserialize = |source: &str| {
+            config
+                .deserialize::<Value>(source.as_bytes())
+                .map_err(|err| err.body_text())
+        };
+
+        assert!(deserialize("a: 1\n").is_ok());
+        assert!(deserialize("%YAML 1.2\n---\na: 1\n").is_ok());
+        assert_eq!(
+            deserialize("%YAML 1.1 # legacy\n---\na: yes\n").unwrap_err(),
+            "Unsupported YAML version: `%YAML 1.1` is not accepted, expected one of 1.2"
+        );
+        assert_eq!(
+            deserialize("%YAML 1.2\n---\na: 1\n...\n%YAML 1.1\n---\na: 2\n").unwrap_err(),
+            "Unsupported YAML version: `%YAML 1.1` is not accepted, expected one of 1.2"
+        );
+
+        // Only the directives before the `---` marker of a document count.
+        assert!(deserialize("--- \"a\n%YAML 1.1\"\n").is_ok());
+        assert!(deserialize("%YAML 1.2\n--- \"a\n%YAML 1.1\"\n").is_ok());
+        assert!(deserialize("%YAMLX 1.1\n---\na: 1\n")
+            .unwrap_err()
+            .contains("unknown directive"));
+    }
+
+    #[test]
+    fn accept_media_types() {
+        let headers = |content_type: &'static str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(header::CONTENT_TYPE, content_type.parse().unwrap());
+            headers
+        };
+
+        let config = YamlConfig::new();
+        assert!(config.yaml_content_type(&headers("application/yaml")));
+        assert!(!config.yaml_content_type(&headers("text/yaml")));
+
+        let config = config.accept_media_types(["text/yaml", "Application/X-YAML"]);
+        assert!(config.yaml_content_type(&headers("application/yaml")));
+        assert!(config.yaml_content_type(&headers("text/yaml; charset=utf-8")));
+        assert!(config.yaml_content_type(&headers("application/x-yaml")));
+        assert!(!config.yaml_content_type(&headers("text/x-yaml")));
+    }
+
+    #[test]
+    fn vendor_media_types() {
+        let headers = |content_type: &'static str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(header::CONTENT_TYPE, content_type.parse().unwrap());
+            headers
+        };
+
+        let config = YamlConfig::new()
+            .vendor_media_types(["application/openapi+yaml", "application/vnd.mycorp.*"]);
+        assert!(config.yaml_content_type(&headers("application/yaml")));
+        assert!(config.yaml_content_type(&headers("application/openapi+yaml")));
+        assert!(config.yaml_content_type(&headers("application/vnd.mycorp.deploy+yaml")));
+        assert!(!config.yaml_content_type(&headers("application/vnd.other+yaml")));
+        assert_eq!(
+            config.missing_content_type().body_text(),
+            "Expected request with `Content-Type: application/yaml`, \
+             or one of application/openapi+yaml, application/vnd.mycorp.*"
+        );
+    }
+
+    #[test]
+    fn data_error_status() {
+        #[derive(Debug, Deserialize)]
+        #[allow(dead_code)]
+        struct Item {
+            id: u32,
+        }
+
+        let status = |config: &YamlConfig, body: &str| {
+            config
+                .deserialize::<Item>(body.as_bytes())
+                .unwrap_err()
+                .status()
+        };
+
+        let config = YamlConfig::new();
+        assert_eq!(status(&config, "id: x"), StatusCode::BAD_REQUEST);
+
+        for config in [
+            config.data_error_status(StatusCode::UNPROCESSABLE_ENTITY),
+            YamlConfig::new()
+                .key_case(KeyCase::Snake)
+                .data_error_status(StatusCode::UNPROCESSABLE_ENTITY),
+        ] {
+            assert_eq!(status(&config, "id: x"), StatusCode::UNPROCESSABLE_ENTITY);
+            assert_eq!(status(&config, "{}"), StatusCode::UNPROCESSABLE_ENTITY);
+            assert_eq!(status(&config, "id: '1"), StatusCode::BAD_REQUEST);
+        }
+    }
+
+    #[test]
+    fn error_snippets() {
+        let body = b"items:\n  - 1\n  - two\n";
+        let config = YamlConfig::new();
+        let err = config
+            .deserialize::<BTreeMap<String, Vec<u8>>>(body)
+            .unwrap_err();
+        assert!(!err.body_text().contains('^'));
+
+        let config = config.error_snippets(true);
+        let YamlRejection::YamlError(err) = config
+            .deserialize::<BTreeMap<String, Vec<u8>>>(body)
+            .unwrap_err()
+        else {
+            panic!("`two` is not a number");
+        };
+        assert_eq!(
+            err.snippet().unwrap(),
+            "  |\n2 |   - 1\n3 |   - two\n  |     ^"
+        );
+        assert!(err.body_text().ends_with(err.snippet().unwrap()));
+    }
+
+    async fn reject<T>(config: YamlConfig, body: &'static str) -> YamlRejection
+    where
+        T: DeserializeOwned,
+    {
+        let mut req = Request::new(Body::from(body));
+        req.headers_mut()
+            .insert(header::CONTENT_TYPE, "application/yaml".parse().unwrap());
+        req.extensions_mut().insert(config);
+        Yaml::<T>::from_request(req, &()).await.err().unwrap()
+    }
+
+    #[tokio::test]
+    async fn max_message_len() {
+        let body = "port: été\n";
+        let message = reject::<BTreeMap<String, u8>>(YamlConfig::new(), body)
+            .await
+            .body_text();
+        assert_eq!(
+            message,
+            "Failed to deserialize the YAML body into the target type: port: port: \
+             invalid type: string \"été\", expected u8 at line 1 column 7"
+        );
+
+        // Keep the first, two-byte, `é` of `été` before the ellipsis.
+        let max = message[..message.find("été").unwrap()].chars().count() + 2;
+        let config = YamlConfig::new().max_message_len(max);
+        let truncated = reject::<BTreeMap<String, u8>>(config, body)
+            .await
+            .body_text();
+        assert_eq!(
+            truncated,
+            "Failed to deserialize the YAML body into the target type: port: port: \
+             invalid type: string \"é…"
+        );
+        assert_eq!(truncated.chars().count(), max);
+    }
+
+    #[tokio::test]
+    async fn redact_values() {
+        use crate::rejection::ToRejectionBody;
+
+        let body = "user: admin\npin: \"1234\"\n";
+        let config = YamlConfig::new().error_snippets(true);
+        let err = reject::<BTreeMap<String, u16>>(config.clone(), body).await;
+        assert!(err.body_text().contains("\"admin\""));
+        assert!(err.rejection_body().snippet().is_some());
+
+        let config = config.redact_values(true);
+        let err = reject::<BTreeMap<String, u16>>(config.clone(), body).await;
+        assert_eq!(
+            err.body_text(),
+            "Failed to deserialize the YAML body into the target type: user: user: \
+             invalid type: string \"***\", expected u16 at line 1 column 7"
+        );
+        assert_eq!(err.rejection_body().snippet(), None);
+
+        let rejection = reject::<BTreeMap<String, u16>>(config.collect_errors(2), body).await;
+        assert_eq!(
+            rejection.body_text(),
+            "Failed to deserialize the YAML body into the target type: 2 errors\n\
+             - user: invalid type: string \"***\", expected u16\n\
+             - pin: invalid type: string \"***\", expected u16"
+        );
+        let messages: Vec<_> = rejection
+            .rejection_body()
+            .errors()
+            .iter()
+            .map(|err| err.message().to_owned())
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                "invalid type: string \"***\", expected u16",
+                "invalid type: string \"***\", expected u16"
+            ]
+        );
+    }
+
+    #[cfg(feature = "log")]
+    #[tokio::test]
+    async fn rejection_log_level_log() {
+        use axum_core::response::IntoResponse;
+
+        crate::test_log::install();
+
+        let body = "log_level_log: none\n";
+        let _ = reject::<BTreeMap<String, u16>>(YamlConfig::new(), body)
+            .await
+            .into_response();
+        let config = YamlConfig::new().rejection_log_level(LogLevel::Warn);
+        let _ = reject::<BTreeMap<String, u16>>(config, body)
+            .await
+            .into_response();
+
+        // `tracing` events are forwarded to `log` too when its `log` feature
+        // is enabled, as it is by `tower`, so keep our own records only.
+        let levels: Vec<_> = crate::test_log::records("log_level_log")
+            .into_iter()
+            .filter(|(_, _, message)| message.starts_with("rejecting request: "))
+            .map(|(target, level, _)| (target, level))
+            .collect();
+        assert_eq!(
+            levels,
+            [
+                ("axum_yaml::rejection".to_owned(), log::Level::Trace),
+                ("axum_yaml::rejection".to_owned(), log::Level::Warn),
+            ]
+        );
+    }
+
+    #[cfg(feature = "tracing")]
+    #[tokio::test]
+    async fn rejection_log_level_tracing() {
+        use axum_core::response::IntoResponse;
+        use tracing::span::{Attributes, Id, Record};
+        use tracing::{Event, Level, Metadata, Subscriber};
+
+        /// Target and level of every event.
+        #[derive(Default)]
+        struct Events(Mutex<Vec<(&'static str, Level)>>);
+
+        impl Subscriber for &'static Events {
+            fn enabled(&self, _: &Metadata<'_>) -> bool {
+                true
+            }
+
+            fn new_span(&self, _: &Attributes<'_>) -> Id {
+                Id::from_u64(1)
+            }
+
+            fn event(&self, event: &Event<'_>) {
+                let metadata = event.metadata();
+                self.0
+                    .lock()
+                    .unwrap()
+                    .push((metadata.target(), *metadata.level()));
+            }
+
+            fn record(&self, _: &Id, _: &Record<'_>) {}
+            fn record_follows_from(&self, _: &Id, _: &Id) {}
+            fn enter(&self, _: &Id) {}
+            fn exit(&self, _: &Id) {}
+        }
+
+        let events: &'static Events = Box::leak(Box::default());
+        let _guard = tracing::subscriber::set_default(events);
+
+        let body = "port: none\n";
+        let _ = reject::<BTreeMap<String, u16>>(YamlConfig::new(), body)
+            .await
+            .into_response();
+        let config = YamlConfig::new().rejection_log_level(LogLevel::Error);
+        let _ = reject::<BTreeMap<String, u16>>(config, body)
+            .await
+            .into_response();
+
+        let events: Vec<_> = events
+            .0
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(target, _)| *target == "axum_yaml::rejection")
+            .copied()
+            .collect();
+        assert_eq!(
+            events,
+            [
+                ("axum_yaml::rejection", Level::TRACE),
+                ("axum_yaml::rejection", Level::ERROR),
+            ]
+        );
+    }
+
+    #[test]
+    fn error_path_headers() {
+        use axum_core::response::IntoResponse;
+
+        let body = b"items:\n  - 1\n  - two\n";
+        let res = YamlConfig::new()
+            .deserialize::<BTreeMap<String, Vec<u8>>>(body)
+            .unwrap_err()
+            .into_response();
+        assert!(res.headers().get("x-error-path").is_none());
+
+        let res = YamlConfig::new()
+            .error_path_headers(true)
+            .deserialize::<BTreeMap<String, Vec<u8>>>(body)
+            .unwrap_err()
+            .into_response();
+        assert_eq!(res.headers()["x-error-path"], "items[1]");
+        assert_eq!(res.headers()["x-error-location"], "3:5");
+    }
+
+    #[test]
+    fn collect_errors() {
+        #[derive(Debug, Deserialize)]
+        #[allow(dead_code)]
+        struct Service {
+            name: String,
+            port: u16,
+        }
+
+        let body = b"- name: web\n  port: http\n- port: 70000\n";
+        let Err(YamlRejection::YamlError(err)) =
+            YamlConfig::new().deserialize::<Vec<Service>>(body)
+        else {
+            panic!("`http` is not a port");
+        };
+        assert_eq!(err.path().unwrap().to_string(), "[0].port");
+
+        let config = YamlConfig::new()
+            .collect_errors(10)
+            .data_error_status(StatusCode::UNPROCESSABLE_ENTITY);
+        let Err(YamlRejection::YamlErrors(errors)) = config.deserialize::<Vec<Service>>(body)
+        else {
+            panic!("`http` is not a port");
+        };
+        assert_eq!(errors.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(
+            errors.body_text(),
+            "Failed to deserialize the YAML body into the target type: 3 errors\n\
+             - [0].port: invalid type: string \"http\", expected u16\n\
+             - [1].port: invalid value: integer `70000`, expected u16\n\
+             - [1]: missing field `name`"
+        );
+        assert_eq!(errors.errors()[2].kind(), YamlErrorKind::MissingField);
+
+        let value = config.deserialize::<Vec<Service>>(b"- {name: web, port: 80}\n");
+        assert_eq!(value.unwrap()[0].port, 80);
+    }
+
+    #[cfg(feature = "timeout")]
+    #[tokio::test]
+    async fn parse_timeout() {
+        use std::time::Duration;
+
+        let config = YamlConfig::new().parse_timeout(Duration::from_secs(10));
+        let value: Vec<u8> = config
+            .deserialize_body(Bytes::from("[1, 2]"))
+            .await
+            .unwrap();
+        assert_eq!(value, [1, 2]);
+        let rejection = config
+            .deserialize_body::<Vec<u8>>(Bytes::from("[1, x]"))
+            .await
+            .unwrap_err();
+        assert_eq!(rejection.path().unwrap().to_string(), "[1]");
+
+        let body = format!("[{}0]", "0, ".repeat(500_000));
+        let rejection = YamlConfig::new()
+            .parse_timeout(Duration::from_millis(1))
+            .deserialize_body::<Vec<u8>>(Bytes::from(body))
+            .await
+            .unwrap_err();
+        assert!(matches!(rejection, YamlRejection::YamlParseTimeout(_)));
+        assert_eq!(rejection.status(), StatusCode::REQUEST_TIMEOUT);
+    }
+
+    #[cfg(feature = "timeout")]
+    #[tokio::test]
+    async fn parse_timeout_extractors() {
+        use std::time::Duration;
+
+        use crate::{YamlBundle, YamlMultiDoc};
+
+        let request = |body: String, timeout: Duration| {
+            let mut req = Request::new(Body::from(body));
+            req.headers_mut()
+                .insert(header::CONTENT_TYPE, "application/yaml".parse().unwrap());
+            req.extensions_mut()
+                .insert(YamlConfig::new().parse_timeout(timeout));
+            req
+        };
+        let long = format!("[{}0]", "0, ".repeat(500_000));
+
+        let req = request("[1]\n---\n[2, 3]\n".to_owned(), Duration::from_secs(10));
+        let YamlMultiDoc(values) = YamlMultiDoc::<Vec<u8>>::from_request(req, &())
+            .await
+            .unwrap();
+        assert_eq!(values, [vec![1], vec![2, 3]]);
+        let req = request(format!("[1]\n---\n{long}"), Duration::from_millis(1));
+        let rejection = YamlMultiDoc::<Vec<u8>>::from_request(req, &())
+            .await
+            .unwrap_err();
+        assert!(matches!(rejection, YamlRejection::YamlParseTimeout(_)));
+
+        let req = request("--- # a\n[1]\n".to_owned(), Duration::from_secs(10));
+        let YamlBundle(documents) = YamlBundle::<Vec<u8>>::from_request(req, &()).await.unwrap();
+        assert_eq!(documents, [("a".to_owned(), vec![1])]);
+        let req = request(format!("--- # a\n{long}"), Duration::from_millis(1));
+        let rejection = YamlBundle::<Vec<u8>>::from_request(req, &())
+            .await
+            .unwrap_err();
+        assert!(matches!(rejection, YamlRejection::YamlParseTimeout(_)));
+    }
+
+    #[cfg(feature = "digest")]
+    #[tokio::test]
+    async fn verify_digests() {
+        let extract = |digest: &'static str| {
+            let mut req = Request::new(Body::from("{\"hello\": \"world\"}\n"));
+            req.headers_mut()
+                .insert(header::CONTENT_TYPE, "application/yaml".parse().unwrap());
+            req.headers_mut()
+                .insert("content-digest", digest.parse().unwrap());
+            req.extensions_mut()
+                .insert(YamlConfig::new().verify_digests(true));
+            Yaml::<Value>::from_request(req, &())
+        };
+
+        let sha256 = "sha-256=:RK/0qy18MlBSVnWgjwz6lZEWjP/lF5HF9bvEF8FabDg=:";
+        let Yaml(value) = extract(sha256).await.unwrap();
+        assert_eq!(value["hello"], "world");
+
+        let mismatch = "sha-256=:X48E9qOokqqrvdts8nOJRJN3OWDUoyWxBf7kbu9DBPE=:";
+        let rejection = extract(mismatch).await.unwrap_err();
+        assert!(matches!(rejection, YamlRejection::InvalidBodyDigest(_)));
+        assert_eq!(rejection.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn serialize() {
+        use axum::routing::post;
+        use axum::Router;
+
+        use crate::ser::SerializeConfigLayer;
+        use crate::test_client::TestClient;
+
+        let echo = post(|Yaml(value): Yaml<BTreeMap<String, u32>>| async { Yaml(value) });
+        let app = Router::new()
+            .route(
+                "/",
+                echo.clone().layer(
+                    YamlConfig::new()
+                        .key_case(KeyCase::Snake)
+                        .serialize(SerializeConfig::new().key_case(KeyCase::Snake)),
+                ),
+            )
+            .route("/inherited", echo.layer(YamlConfig::new()))
+            .layer(SerializeConfigLayer::new(
+                SerializeConfig::new().key_case(KeyCase::Camel),
+            ));
+
+        let client = TestClient::new(app);
+        let res = client
+            .post("/")
+            .body("userId: 1")
+            .header("content-type", "application/yaml")
+            .await;
+        assert_eq!(res.text().await, "user_id: 1\n");
+
+        let res = client
+            .post("/inherited")
+            .body("user_id: 1")
+            .header("content-type", "application/yaml")
+            .await;
+        assert_eq!(res.text().await, "userId: 1\n");
+    }
+}
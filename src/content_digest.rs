@@ -0,0 +1,157 @@
+//! `Content-Digest` and `Repr-Digest` of request bodies, see
+//! [RFC 9530](https://www.rfc-editor.org/rfc/rfc9530).
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use http::{HeaderMap, HeaderName};
+use sha2::{Digest, Sha256, Sha512};
+
+use crate::rejection::InvalidBodyDigest;
+
+const HEADERS: [&str; 2] = ["content-digest", "repr-digest"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Algorithm {
+    Sha256,
+    Sha512,
+}
+
+impl Algorithm {
+    fn from_key(key: &str) -> Option<Self> {
+        match key {
+            "sha-256" => Some(Self::Sha256),
+            "sha-512" => Some(Self::Sha512),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Sha256 => "sha-256",
+            Self::Sha512 => "sha-512",
+        }
+    }
+
+    fn digest(self, body: &[u8]) -> Vec<u8> {
+        match self {
+            Self::Sha256 => Sha256::digest(body).to_vec(),
+            Self::Sha512 => Sha512::digest(body).to_vec(),
+        }
+    }
+}
+
+/// Digests a request declares for its body.
+#[derive(Debug, Default)]
+pub(crate) struct Digests(Vec<(&'static str, Algorithm, Vec<u8>)>);
+
+impl Digests {
+    /// Read the `Content-Digest` and `Repr-Digest` headers of `headers`.
+    ///
+    /// Algorithms other than `sha-256` and `sha-512` are ignored, but a
+    /// header with none of them is rejected rather than trusted.
+    pub(crate) fn from_headers(headers: &HeaderMap) -> Result<Self, InvalidBodyDigest> {
+        let mut digests = Vec::new();
+        for name in HEADERS {
+            let mut values = headers
+                .get_all(HeaderName::from_static(name))
+                .iter()
+                .peekable();
+            if values.peek().is_none() {
+                continue;
+            }
+
+            let malformed = || InvalidBodyDigest::from_err(format!("`{name}` is malformed"));
+            let supported = digests.len();
+            for value in values {
+                let value = value.to_str().map_err(|_| malformed())?;
+                for member in value.split(',') {
+                    let (key, value) = member.split_once('=').ok_or_else(malformed)?;
+                    let Some(algorithm) = Algorithm::from_key(key.trim()) else {
+                        continue;
+                    };
+                    // Parameters of the member, after a `;`, carry no meaning.
+                    let value = value.split(';').next().unwrap_or_default().trim();
+                    let digest = value
+                        .strip_prefix(':')
+                        .and_then(|value| value.strip_suffix(':'))
+                        .and_then(|value| STANDARD.decode(value).ok())
+                        .ok_or_else(malformed)?;
+                    digests.push((name, algorithm, digest));
+                }
+            }
+            if digests.len() == supported {
+                return Err(InvalidBodyDigest::from_err(format!(
+                    "`{name}` has no supported algorithm, expected `sha-256` or `sha-512`"
+                )));
+            }
+        }
+        Ok(Self(digests))
+    }
+
+    /// Check `body` against every digest.
+    pub(crate) fn verify(&self, body: &[u8]) -> Result<(), InvalidBodyDigest> {
+        for (name, algorithm, digest) in &self.0 {
+            if algorithm.digest(body) != *digest {
+                return Err(InvalidBodyDigest::from_err(format!(
+                    "`{name}` {} does not match the body",
+                    algorithm.name()
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use http::HeaderValue;
+
+    fn check(headers: &[(&'static str, &'static str)], body: &[u8]) -> Result<(), String> {
+        let mut map = HeaderMap::new();
+        for &(name, value) in headers {
+            map.append(name, HeaderValue::from_static(value));
+        }
+        Digests::from_headers(&map)
+            .and_then(|digests| digests.verify(body))
+            .map_err(|err| err.body_text())
+    }
+
+    #[test]
+    fn verify() {
+        let sha256 = "sha-256=:RK/0qy18MlBSVnWgjwz6lZEWjP/lF5HF9bvEF8FabDg=:";
+        let sha512 = "sha-512=:YMAam51Jz/jOATT6/zvHrLVgOYTGFy1d6GJiOHTohq4yP+pgk4vf2aCs\
+                      yRZOtw8MjkM7iw7yZ/WkppmM44T3qg==:";
+        let body = b"{\"hello\": \"world\"}\n";
+
+        assert!(check(&[], body).is_ok());
+        assert!(check(&[("content-digest", sha256)], body).is_ok());
+        assert!(check(&[("repr-digest", sha512)], body).is_ok());
+        assert!(check(
+            &[(
+                "content-digest",
+                "md5=:abc=:, sha-256=:RK/0qy18MlBSVnWgjwz6lZEWjP/lF5HF9bvEF8FabDg=:;x=1"
+            )],
+            body
+        )
+        .is_ok());
+
+        assert_eq!(
+            check(
+                &[("content-digest", sha256), ("repr-digest", sha512)],
+                b"{}\n"
+            )
+            .unwrap_err(),
+            "Invalid request body digest: `content-digest` sha-256 does not match the body"
+        );
+        assert_eq!(
+            check(&[("repr-digest", "md5=:abc=:")], body).unwrap_err(),
+            "Invalid request body digest: `repr-digest` has no supported algorithm, \
+             expected `sha-256` or `sha-512`"
+        );
+        assert_eq!(
+            check(&[("content-digest", "sha-256=RK/0qy18")], body).unwrap_err(),
+            "Invalid request body digest: `content-digest` is malformed"
+        );
+    }
+}
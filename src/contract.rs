@@ -0,0 +1,349 @@
+//! Contract testing of YAML responses against JSON Schemas.
+//!
+//! Requires the `schemars` feature. These helpers call a handler and check
+//! that what it actually serves conforms to the [`JsonSchema`] of its
+//! declared response type, reporting every violation with the path of the
+//! offending node.
+//!
+//! Documents are validated with the [`jsonschema`] crate, following the
+//! draft declared by the schema, 2020-12 for `schemars`. Formats are
+//! asserted, except those `jsonschema` does not know, such as the `uint16`
+//! or `int32` annotations of `schemars`, whose ranges are still checked
+//! through `minimum` and `maximum`.
+//!
+//! ```
+//! use axum::{body::Body, http::Request, routing::get, Router};
+//! use axum_yaml::{contract, Yaml};
+//! use schemars::JsonSchema;
+//! use serde::Serialize;
+//!
+//! #[derive(Serialize, JsonSchema)]
+//! struct User {
+//!     name: String,
+//! }
+//!
+//! async fn get_user() -> Yaml<User> {
+//!     Yaml(User { name: "alice".to_owned() })
+//! }
+//!
+//! let app = Router::new().route("/user", get(get_user));
+//! # async {
+//! let request = Request::get("/user").body(Body::empty()).unwrap();
+//! contract::assert_service_conforms::<User, _>(app, request).await;
+//! # };
+//! ```
+
+use std::fmt;
+
+use axum_core::{
+    extract::{FromRequest, Request},
+    response::Response,
+};
+use bytes::Bytes;
+use schemars::{JsonSchema, Schema};
+use serde_yaml::Value;
+use tower_service::Service;
+
+use crate::yaml::is_yaml_content_type;
+
+/// A single place where a document does not conform to its schema.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    /// JSON Pointer to the offending node, empty for the document root.
+    pub path: String,
+    /// Description of the violated constraint.
+    pub message: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = if self.path.is_empty() {
+            "/"
+        } else {
+            &self.path
+        };
+        write!(f, "{path}: {}", self.message)
+    }
+}
+
+/// All violations found while validating a document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violations(pub Vec<Violation>);
+
+impl fmt::Display for Violations {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, violation) in self.0.iter().enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{violation}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Violations {}
+
+/// Validate `document` against the schema of `T`.
+pub fn validate<T>(document: &Value) -> Result<(), Violations>
+where
+    T: JsonSchema,
+{
+    validate_schema(&schemars::schema_for!(T), document)
+}
+
+/// Validate `document` against `schema`.
+pub fn validate_schema(schema: &Schema, document: &Value) -> Result<(), Violations> {
+    let invalid = |message: String| {
+        Violations(vec![Violation {
+            path: String::new(),
+            message,
+        }])
+    };
+    let document = serde_json::to_value(document).map_err(|err| invalid(err.to_string()))?;
+    let validator = jsonschema::options()
+        .should_validate_formats(true)
+        .build(schema.as_value())
+        .map_err(|err| invalid(format!("invalid schema: {err}")))?;
+
+    let mut violations: Vec<_> = validator
+        .iter_errors(&document)
+        .map(|err| Violation {
+            path: err.instance_path().to_string(),
+            message: err.to_string(),
+        })
+        .collect();
+    violations.sort_by(|a, b| a.path.cmp(&b.path));
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(Violations(violations))
+    }
+}
+
+/// Assert that `response` is a YAML response whose body conforms to the
+/// schema of `T`, panicking with every violation otherwise.
+pub async fn assert_response_conforms<T>(response: Response)
+where
+    T: JsonSchema,
+{
+    assert!(
+        is_yaml_content_type(response.headers()),
+        "expected a YAML response, got Content-Type {:?}",
+        response.headers().get(http::header::CONTENT_TYPE),
+    );
+
+    let body = Bytes::from_request(Request::new(response.into_body()), &())
+        .await
+        .expect("failed to read the response body");
+    let document: Value = match serde_yaml::from_slice(&body) {
+        Ok(document) => document,
+        Err(err) => panic!("response body is not valid YAML: {err}"),
+    };
+
+    if let Err(violations) = validate::<T>(&document) {
+        panic!(
+            "response does not conform to the schema of `{}`:\n{violations}",
+            T::schema_name()
+        );
+    }
+}
+
+/// Call `service` with `request` and assert that its response conforms to
+/// the schema of `T`, like [`assert_response_conforms`].
+///
+/// An axum `Router` is such a service, so routes are checked in-process,
+/// with their layers and extractors, without binding a socket.
+pub async fn assert_service_conforms<T, S>(mut service: S, request: Request)
+where
+    T: JsonSchema,
+    S: Service<Request, Response = Response>,
+    S::Error: fmt::Debug,
+{
+    std::future::poll_fn(|cx| service.poll_ready(cx))
+        .await
+        .expect("service is not ready");
+    let response = service
+        .call(request)
+        .await
+        .expect("service failed to respond");
+    assert_response_conforms::<T>(response).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde::Serialize;
+    use serde_json::{json, Value as JsonValue};
+
+    #[allow(dead_code)]
+    #[derive(Serialize, JsonSchema)]
+    struct Container {
+        image: String,
+        #[schemars(range(min = 1, max = 65535))]
+        port: u32,
+    }
+
+    #[allow(dead_code)]
+    #[derive(Serialize, JsonSchema)]
+    #[serde(deny_unknown_fields)]
+    struct Deployment {
+        name: String,
+        containers: Vec<Container>,
+    }
+
+    #[test]
+    fn conforming_document() {
+        let document = serde_yaml::from_str("name: web\ncontainers:\n- image: nginx\n  port: 80\n");
+        validate::<Deployment>(&document.unwrap()).unwrap();
+    }
+
+    #[test]
+    fn violations() {
+        let document =
+            serde_yaml::from_str("containers:\n- image: 1\n  port: 0\nreplicas: 2\n").unwrap();
+        let violations = validate::<Deployment>(&document).unwrap_err();
+
+        assert_eq!(
+            violations.to_string(),
+            "/: \"name\" is a required property\n\
+             /: Additional properties are not allowed ('replicas' was unexpected)\n\
+             /containers/0/image: 1 is not of type \"string\"\n\
+             /containers/0/port: 0 is less than the minimum of 1"
+        );
+    }
+
+    fn violations_of(schema: JsonValue, document: &str) -> Vec<String> {
+        let schema = Schema::try_from(schema).unwrap();
+        let document = serde_yaml::from_str(document).unwrap();
+        match validate_schema(&schema, &document) {
+            Ok(()) => Vec::new(),
+            Err(violations) => violations.0.iter().map(ToString::to_string).collect(),
+        }
+    }
+
+    #[test]
+    fn keywords() {
+        let tuple = json!({
+            "type": "array",
+            "prefixItems": [{"type": "string"}, {"type": "integer"}],
+            "items": false,
+        });
+        assert_eq!(violations_of(tuple.clone(), "[a, 1]"), Vec::<String>::new());
+        assert_eq!(
+            violations_of(tuple, "[1, a, x]"),
+            [
+                "/0: 1 is not of type \"string\"",
+                "/1: \"a\" is not of type \"integer\"",
+                "/2: False schema does not allow \"x\""
+            ]
+        );
+
+        let labels = json!({
+            "type": "object",
+            "patternProperties": {"^x-": {"type": "string", "pattern": "^[a-z]+$"}},
+            "additionalProperties": false,
+        });
+        assert_eq!(
+            violations_of(
+                labels,
+                "x-team: Web
+x-tier: db
+owner: me
+"
+            ),
+            [
+                "/: Additional properties are not allowed ('owner' was unexpected)",
+                "/x-team: \"Web\" does not match \"^[a-z]+$\""
+            ]
+        );
+
+        let formats = json!({
+            "type": "object",
+            "properties": {
+                "id": {"type": "string", "format": "uuid"},
+                "at": {"type": "string", "format": "date-time"},
+                "ip": {"type": "string", "format": "ipv4"},
+                "port": {"type": "integer", "format": "uint16", "minimum": 0, "maximum": 65535},
+            },
+        });
+        assert_eq!(
+            violations_of(
+                formats,
+                "id: 1234
+at: 9999-99-99T99:99:99Z
+ip: 300.0.0.1
+port: 70000
+"
+            ),
+            [
+                "/at: \"9999-99-99T99:99:99Z\" is not a \"date-time\"",
+                "/id: 1234 is not of type \"string\"",
+                "/ip: \"300.0.0.1\" is not a \"ipv4\"",
+                "/port: 70000 is greater than the maximum of 65535"
+            ]
+        );
+
+        let reference = json!({
+            "$defs": {"Name": {"type": "string"}},
+            "$ref": "#/$defs/Name",
+            "maxLength": 3,
+        });
+        assert_eq!(
+            violations_of(reference, "alice"),
+            ["/: \"alice\" is longer than 3 characters"]
+        );
+
+        let unique = json!({"type": "array", "uniqueItems": true});
+        assert_eq!(
+            violations_of(unique, "[1, 1]"),
+            ["/: [1,1] has non-unique elements"]
+        );
+    }
+
+    fn router() -> axum::Router {
+        use crate::Yaml;
+        use axum::routing::get;
+
+        axum::Router::new()
+            .route(
+                "/valid",
+                get(|| async {
+                    Yaml(Deployment {
+                        name: "web".to_owned(),
+                        containers: Vec::new(),
+                    })
+                }),
+            )
+            .route(
+                "/invalid",
+                get(|| async { Yaml(serde_yaml::from_str::<Value>("name: 1").unwrap()) }),
+            )
+    }
+
+    fn get(uri: &str) -> Request {
+        http::Request::get(uri)
+            .body(axum_core::body::Body::empty())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn service_conforms() {
+        assert_service_conforms::<Deployment, _>(router(), get("/valid")).await;
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "response does not conform to the schema of `Deployment`")]
+    async fn service_violates() {
+        assert_service_conforms::<Deployment, _>(router(), get("/invalid")).await;
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "expected a YAML response")]
+    async fn non_yaml_response() {
+        use axum_core::response::IntoResponse;
+
+        assert_response_conforms::<Deployment>("name: web".into_response()).await;
+    }
+}
@@ -0,0 +1,720 @@
+//! YAML CRUD endpoints for a resource type.
+//!
+//! A [`CrudService`] serves a collection of resources of type `T` kept in a
+//! [`Storage`], replacing the handlers rewritten for every YAML-managed
+//! resource. Mounted with `Router::nest_service`, it answers:
+//!
+//! | Request           | Response                                          |
+//! |-------------------|---------------------------------------------------|
+//! | `GET /`           | mapping of every id to its resource               |
+//! | `POST /`          | `201 Created` with the stored resource            |
+//! | `GET /{id}`       | the resource, or `304 Not Modified`               |
+//! | `PUT /{id}`       | `200 OK`, or `201 Created` for a new id           |
+//! | `PATCH /{id}`     | the resource with a YAML merge patch applied      |
+//! | `DELETE /{id}`    | `204 No Content`                                  |
+//!
+//! Resources are served with a strong `ETag`, hashing the body exactly as it
+//! is served, so tags change with the [`SerializeConfig`] of the enclosing
+//! layer. They are only stable across restarts and replicas when the
+//! serialized form is, e.g. not for `HashMap` fields unless keys are sorted
+//! with [`SerializeConfig::sort_keys`]. `GET` honors `If-None-Match`, and
+//! `PUT`, `PATCH` and `DELETE` honor `If-Match`, answering
+//! `412 Precondition Failed` when the resource changed in between. As in
+//! RFC 9110, weak tags such as `W/"..."` only match in `If-None-Match`. The check
+//! is not atomic with the write: concurrent writers are only detected if they
+//! completed before the check.
+//!
+//! ```no_run
+//! use axum::Router;
+//! use axum_yaml::crud::{CrudService, MemoryStorage};
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Clone, Serialize, Deserialize)]
+//! struct User {
+//!     name: String,
+//! }
+//!
+//! let users = CrudService::new(MemoryStorage::<User>::new())
+//!     .location_prefix("/users/")
+//!     .validate(|user: &User| match user.name.is_empty() {
+//!         true => Err("`name` must not be empty"),
+//!         false => Ok(()),
+//!     });
+//!
+//! let app: Router = Router::new().nest_service("/users", users);
+//! ```
+//!
+//! The service is a builder rather than a `crud_router!` macro: a type and a
+//! [`Storage`] are all it needs, and as a plain `tower` service it mounts in
+//! any router without this crate depending on axum's `Router`.
+
+use std::convert::Infallible;
+use std::fmt;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use axum_core::{
+    extract::{FromRequest, Request},
+    response::{IntoResponse, Response},
+    BoxError,
+};
+use http::{header, HeaderMap, HeaderValue, Method, StatusCode};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_yaml::Value;
+use tower_service::Service;
+
+use crate::macros::{
+    __composite_rejection as composite_rejection, __define_rejection as define_rejection,
+};
+use crate::rejection::{YamlError, YamlRejection};
+use crate::ser::{self, SerializeConfig};
+use crate::{Yaml, YamlConfig};
+
+/// Storage of the resources served by a [`CrudService`].
+pub trait Storage<T>: Clone + Send + Sync + 'static {
+    /// List every resource along with its id.
+    fn list(&self) -> impl Future<Output = Result<Vec<(String, T)>, BoxError>> + Send;
+
+    /// Get the resource identified by `id`.
+    fn get(&self, id: &str) -> impl Future<Output = Result<Option<T>, BoxError>> + Send;
+
+    /// Store a new resource, returning its id.
+    fn insert(&self, resource: T) -> impl Future<Output = Result<String, BoxError>> + Send;
+
+    /// Store `resource` under `id`, replacing any previous resource.
+    fn replace(&self, id: &str, resource: T) -> impl Future<Output = Result<(), BoxError>> + Send;
+
+    /// Remove the resource identified by `id`, returning whether it existed.
+    fn remove(&self, id: &str) -> impl Future<Output = Result<bool, BoxError>> + Send;
+}
+
+/// In-memory [`Storage`] assigning sequential ids, for tests and prototypes.
+#[derive(Debug)]
+pub struct MemoryStorage<T> {
+    inner: Arc<Mutex<MemoryInner<T>>>,
+}
+
+#[derive(Debug)]
+struct MemoryInner<T> {
+    next_id: u64,
+    resources: Vec<(String, T)>,
+}
+
+impl<T> MemoryStorage<T> {
+    /// Create an empty storage.
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(MemoryInner {
+                next_id: 1,
+                resources: Vec::new(),
+            })),
+        }
+    }
+
+    fn with<R>(&self, f: impl FnOnce(&mut MemoryInner<T>) -> R) -> R {
+        f(&mut self.inner.lock().unwrap_or_else(|err| err.into_inner()))
+    }
+}
+
+impl<T> Default for MemoryStorage<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Clone for MemoryStorage<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T> Storage<T> for MemoryStorage<T>
+where
+    T: Clone + Send + Sync + 'static,
+{
+    async fn list(&self) -> Result<Vec<(String, T)>, BoxError> {
+        Ok(self.with(|inner| inner.resources.clone()))
+    }
+
+    async fn get(&self, id: &str) -> Result<Option<T>, BoxError> {
+        Ok(self.with(|inner| {
+            inner
+                .resources
+                .iter()
+                .find(|(key, _)| key == id)
+                .map(|(_, resource)| resource.clone())
+        }))
+    }
+
+    async fn insert(&self, resource: T) -> Result<String, BoxError> {
+        Ok(self.with(|inner| {
+            let id = inner.next_id.to_string();
+            inner.next_id += 1;
+            inner.resources.push((id.clone(), resource));
+            id
+        }))
+    }
+
+    async fn replace(&self, id: &str, resource: T) -> Result<(), BoxError> {
+        self.with(
+            |inner| match inner.resources.iter_mut().find(|(key, _)| key == id) {
+                Some((_, stored)) => *stored = resource,
+                None => inner.resources.push((id.to_owned(), resource)),
+            },
+        );
+        Ok(())
+    }
+
+    async fn remove(&self, id: &str) -> Result<bool, BoxError> {
+        Ok(self.with(|inner| {
+            let len = inner.resources.len();
+            inner.resources.retain(|(key, _)| key != id);
+            inner.resources.len() != len
+        }))
+    }
+}
+
+type Validator<T> = Arc<dyn Fn(&T) -> Result<(), BoxError> + Send + Sync>;
+
+/// Service serving YAML CRUD endpoints for the resources of a [`Storage`].
+///
+/// See the [module documentation](self).
+pub struct CrudService<T, St> {
+    storage: St,
+    validate: Option<Validator<T>>,
+    location_prefix: Option<String>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T, St> CrudService<T, St> {
+    /// Serve the resources of `storage`.
+    pub fn new(storage: St) -> Self {
+        Self {
+            storage,
+            validate: None,
+            location_prefix: None,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Check created and updated resources with `validate`, rejecting them
+    /// with `422 Unprocessable Entity` when it fails.
+    pub fn validate<F, E>(mut self, validate: F) -> Self
+    where
+        F: Fn(&T) -> Result<(), E> + Send + Sync + 'static,
+        E: Into<BoxError>,
+    {
+        self.validate = Some(Arc::new(move |resource| {
+            validate(resource).map_err(Into::into)
+        }));
+        self
+    }
+
+    /// Answer `POST` requests with a `Location` of `prefix` followed by the
+    /// id of the created resource, e.g. `/users/`.
+    ///
+    /// The path the service is mounted at is not known to the service, so
+    /// no `Location` is sent without a prefix.
+    pub fn location_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.location_prefix = Some(prefix.into());
+        self
+    }
+}
+
+impl<T, St> Clone for CrudService<T, St>
+where
+    St: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            storage: self.storage.clone(),
+            validate: self.validate.clone(),
+            location_prefix: self.location_prefix.clone(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T, St> fmt::Debug for CrudService<T, St>
+where
+    St: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CrudService")
+            .field("storage", &self.storage)
+            .field("location_prefix", &self.location_prefix)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T, St> Service<Request> for CrudService<T, St>
+where
+    T: Serialize + DeserializeOwned + Send + Sync + 'static,
+    St: Storage<T>,
+{
+    type Response = Response;
+    type Error = Infallible;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, Infallible>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        let this = self.clone();
+        Box::pin(async move {
+            let config = YamlConfig::from_extensions(req.extensions());
+            Ok(config
+                .report(this.handle(req))
+                .await
+                .unwrap_or_else(IntoResponse::into_response))
+        })
+    }
+}
+
+impl<T, St> CrudService<T, St>
+where
+    T: Serialize + DeserializeOwned + Send + Sync + 'static,
+    St: Storage<T>,
+{
+    async fn handle(self, req: Request) -> Result<Response, CrudRejection> {
+        let id = req.uri().path().trim_matches('/').to_owned();
+        if id.contains('/') {
+            return Err(ResourceNotFound::default().into());
+        }
+
+        let method = req.method().clone();
+        match (method, id.is_empty()) {
+            (Method::GET | Method::HEAD, true) => self.list().await,
+            (Method::POST, true) => self.create(req).await,
+            (Method::GET | Method::HEAD, false) => self.get(&id, req.headers()).await,
+            (Method::PUT, false) => self.put(&id, req).await,
+            (Method::PATCH, false) => self.patch(&id, req).await,
+            (Method::DELETE, false) => self.delete(&id, req.headers()).await,
+            (_, collection) => {
+                let allow = if collection {
+                    "GET, HEAD, POST"
+                } else {
+                    "GET, HEAD, PUT, PATCH, DELETE"
+                };
+                Ok((
+                    StatusCode::METHOD_NOT_ALLOWED,
+                    [(header::ALLOW, HeaderValue::from_static(allow))],
+                )
+                    .into_response())
+            }
+        }
+    }
+
+    async fn list(&self) -> Result<Response, CrudRejection> {
+        let resources = self.storage.list().await.map_err(StorageFailed::from_err)?;
+
+        let mut mapping = serde_yaml::Mapping::new();
+        for (id, resource) in resources {
+            let resource = serde_yaml::to_value(resource).map_err(StorageFailed::from_err)?;
+            mapping.insert(Value::String(id), resource);
+        }
+        Ok(Yaml(mapping).into_response())
+    }
+
+    async fn create(&self, req: Request) -> Result<Response, CrudRejection> {
+        let Yaml(resource) = Yaml::<T>::from_request(req, &()).await?;
+        self.check(&resource)?;
+
+        let id = self
+            .storage
+            .insert(resource)
+            .await
+            .map_err(StorageFailed::from_err)?;
+        let (etag, body) = represent(&self.load(&id).await?)?;
+
+        let mut response = (StatusCode::CREATED, [(header::ETAG, etag)], body).into_response();
+        if let Some(prefix) = &self.location_prefix {
+            if let Ok(location) = HeaderValue::from_str(&format!("{prefix}{id}")) {
+                response.headers_mut().insert(header::LOCATION, location);
+            }
+        }
+        Ok(response)
+    }
+
+    async fn get(&self, id: &str, headers: &HeaderMap) -> Result<Response, CrudRejection> {
+        let (etag, body) = represent(&self.load(id).await?)?;
+
+        if matches_etag(headers.get(header::IF_NONE_MATCH), &etag, true) {
+            return Ok((StatusCode::NOT_MODIFIED, [(header::ETAG, etag)]).into_response());
+        }
+        Ok(([(header::ETAG, etag)], body).into_response())
+    }
+
+    async fn put(&self, id: &str, req: Request) -> Result<Response, CrudRejection> {
+        let current = self
+            .storage
+            .get(id)
+            .await
+            .map_err(StorageFailed::from_err)?;
+        check_if_match(req.headers(), current.as_ref())?;
+
+        let Yaml(resource) = Yaml::<T>::from_request(req, &()).await?;
+        self.check(&resource)?;
+        let status = match current {
+            Some(_) => StatusCode::OK,
+            None => StatusCode::CREATED,
+        };
+        self.store(id, resource, status).await
+    }
+
+    async fn patch(&self, id: &str, req: Request) -> Result<Response, CrudRejection> {
+        let current = self.load(id).await?;
+        check_if_match(req.headers(), Some(&current))?;
+
+        let config = YamlConfig::from_extensions(req.extensions());
+        let Yaml(patch) = Yaml::<Value>::from_request(req, &()).await?;
+        let mut document = serde_yaml::to_value(current).map_err(StorageFailed::from_err)?;
+        merge_patch(&mut document, patch);
+        let resource: T = crate::classify::deserialize(document)
+            .map_err(|err| config.error_status(YamlError::data(err).into()))?;
+
+        self.check(&resource)?;
+        self.store(id, resource, StatusCode::OK).await
+    }
+
+    async fn delete(&self, id: &str, headers: &HeaderMap) -> Result<Response, CrudRejection> {
+        let current = self.load(id).await?;
+        check_if_match(headers, Some(&current))?;
+
+        match self
+            .storage
+            .remove(id)
+            .await
+            .map_err(StorageFailed::from_err)?
+        {
+            true => Ok(StatusCode::NO_CONTENT.into_response()),
+            false => Err(ResourceNotFound::default().into()),
+        }
+    }
+
+    async fn load(&self, id: &str) -> Result<T, CrudRejection> {
+        self.storage
+            .get(id)
+            .await
+            .map_err(StorageFailed::from_err)?
+            .ok_or_else(|| ResourceNotFound::default().into())
+    }
+
+    async fn store(
+        &self,
+        id: &str,
+        resource: T,
+        status: StatusCode,
+    ) -> Result<Response, CrudRejection> {
+        self.storage
+            .replace(id, resource)
+            .await
+            .map_err(StorageFailed::from_err)?;
+        let (etag, body) = represent(&self.load(id).await?)?;
+        Ok((status, [(header::ETAG, etag)], body).into_response())
+    }
+
+    fn check(&self, resource: &T) -> Result<(), InvalidResource> {
+        match &self.validate {
+            Some(validate) => validate(resource).map_err(InvalidResource::from_err),
+            None => Ok(()),
+        }
+    }
+}
+
+/// YAML response serving `resource`, along with the strong entity tag of
+/// its body.
+///
+/// The tag is the 64-bit FNV-1a hash of the body as it is sent, after the
+/// current [`SerializeConfig`] and its hooks ran.
+fn represent<T>(resource: &T) -> Result<(HeaderValue, Response), StorageFailed>
+where
+    T: Serialize,
+{
+    // Serialization errors surface here, so serializing the value succeeds.
+    let value = serde_yaml::to_value(resource).map_err(StorageFailed::from_err)?;
+    let mut hash = None;
+    let response = ser::response_with(&value, &SerializeConfig::current(), |body| {
+        hash = Some(body.iter().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+            (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+        }));
+    });
+    let hash = hash.ok_or_else(|| StorageFailed::from_err("failed to serialize the resource"))?;
+    let etag =
+        HeaderValue::from_str(&format!("\"{hash:016x}\"")).map_err(StorageFailed::from_err)?;
+    Ok((etag, response))
+}
+
+/// Whether `condition` lists `etag`, or is `*`.
+///
+/// `If-None-Match` uses the weak comparison of RFC 9110, ignoring the `W/`
+/// prefix, while `If-Match` uses the strong one, never matching weak tags.
+fn matches_etag(condition: Option<&HeaderValue>, etag: &HeaderValue, weak: bool) -> bool {
+    let Some(condition) = condition.and_then(|condition| condition.to_str().ok()) else {
+        return false;
+    };
+    condition.split(',').map(str::trim).any(|tag| {
+        tag == "*"
+            || match tag.strip_prefix("W/") {
+                Some(tag) => weak && tag == etag,
+                None => tag == etag,
+            }
+    })
+}
+
+fn check_if_match<T>(headers: &HeaderMap, current: Option<&T>) -> Result<(), CrudRejection>
+where
+    T: Serialize,
+{
+    let Some(condition) = headers.get(header::IF_MATCH) else {
+        return Ok(());
+    };
+    let matched = match current {
+        Some(current) => matches_etag(Some(condition), &represent(current)?.0, false),
+        None => false,
+    };
+    match matched {
+        true => Ok(()),
+        false => Err(PreconditionFailed::default().into()),
+    }
+}
+
+/// Apply a merge patch in the manner of RFC 7386: mappings are merged
+/// recursively, nulls remove keys and any other value replaces the target.
+fn merge_patch(target: &mut Value, patch: Value) {
+    let Value::Mapping(patch) = patch else {
+        *target = patch;
+        return;
+    };
+    if !target.is_mapping() {
+        *target = Value::Mapping(Default::default());
+    }
+    let Value::Mapping(target) = target else {
+        unreachable!();
+    };
+    for (key, value) in patch {
+        if value.is_null() {
+            target.remove(&key);
+        } else {
+            merge_patch(target.entry(key).or_insert(Value::Null), value);
+        }
+    }
+}
+
+define_rejection! {
+    #[status = NOT_FOUND]
+    #[body = "Resource not found"]
+    /// Rejection type for [`CrudService`] used if no resource has the
+    /// requested id.
+    pub struct ResourceNotFound;
+}
+define_rejection! {
+    #[status = PRECONDITION_FAILED]
+    #[body = "Resource does not match `If-Match`"]
+    /// Rejection type for [`CrudService`] used if the resource changed since
+    /// the client read it.
+    pub struct PreconditionFailed;
+}
+define_rejection! {
+    #[status = UNPROCESSABLE_ENTITY]
+    #[body = "Invalid resource"]
+    /// Rejection type for [`CrudService`] used if a resource fails
+    /// validation.
+    pub struct InvalidResource(Error);
+}
+define_rejection! {
+    #[status = INTERNAL_SERVER_ERROR]
+    #[body = "Failed to access the resource storage"]
+    /// Rejection type for [`CrudService`] used if the storage fails.
+    pub struct StorageFailed(Error);
+}
+
+composite_rejection! {
+    /// Rejection used for [`CrudService`].
+    pub enum CrudRejection {
+        YamlRejection,
+        ResourceNotFound,
+        PreconditionFailed,
+        InvalidResource,
+        StorageFailed,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use axum::Router;
+    use serde::Deserialize;
+
+    use crate::test_client::TestClient;
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    struct User {
+        name: String,
+        #[serde(default)]
+        admin: bool,
+    }
+
+    fn client() -> TestClient {
+        let users = CrudService::new(MemoryStorage::<User>::new())
+            .location_prefix("/users/")
+            .validate(|user: &User| match user.name.is_empty() {
+                true => Err("`name` must not be empty"),
+                false => Ok(()),
+            });
+        TestClient::new(Router::new().nest_service("/users", users))
+    }
+
+    #[tokio::test]
+    async fn crud() {
+        let client = client();
+
+        let res = client
+            .post("/users")
+            .body("name: alice")
+            .header("content-type", "application/yaml")
+            .await;
+        assert_eq!(res.status(), StatusCode::CREATED);
+        assert_eq!(res.headers()["location"], "/users/1");
+        let etag = res.headers()["etag"].to_str().unwrap().to_owned();
+        assert_eq!(res.text().await, "name: alice\nadmin: false\n");
+
+        let res = client
+            .get("/users/1")
+            .header("if-none-match", etag.as_str())
+            .await;
+        assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+
+        let res = client
+            .patch("/users/1")
+            .body("admin: true")
+            .header("content-type", "application/yaml")
+            .header("if-match", etag.as_str())
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.text().await, "name: alice\nadmin: true\n");
+
+        let res = client
+            .put("/users/1")
+            .body("name: bob")
+            .header("content-type", "application/yaml")
+            .header("if-match", etag.as_str())
+            .await;
+        assert_eq!(res.status(), StatusCode::PRECONDITION_FAILED);
+
+        let res = client
+            .put("/users/2")
+            .body("name: bob")
+            .header("content-type", "application/yaml")
+            .await;
+        assert_eq!(res.status(), StatusCode::CREATED);
+
+        let res = client.get("/users").await;
+        assert_eq!(
+            res.text().await,
+            "'1':\n  name: alice\n  admin: true\n'2':\n  name: bob\n  admin: false\n"
+        );
+
+        let res = client.delete("/users/1").await;
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+        let res = client.get("/users/1").await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn etag() {
+        let alice = User {
+            name: "alice".to_owned(),
+            admin: false,
+        };
+        let etag = || represent(&alice).unwrap().0;
+        assert_eq!(etag(), "\"02b3d46c1ac96f18\"");
+
+        let config = Arc::new(SerializeConfig::new().hook(|body, _| body.extend_from_slice(b"#")));
+        let served = SerializeConfig::scope(&config, etag);
+        assert_ne!(served, etag());
+        assert_eq!(SerializeConfig::scope(&config, etag), served);
+    }
+
+    #[test]
+    fn etag_comparison() {
+        let etag = HeaderValue::from_static("\"abc\"");
+        let condition = |value| Some(HeaderValue::from_static(value));
+
+        for weak in [true, false] {
+            assert!(matches_etag(condition("\"abc\"").as_ref(), &etag, weak));
+            assert!(matches_etag(
+                condition("\"x\", \"abc\"").as_ref(),
+                &etag,
+                weak
+            ));
+            assert!(matches_etag(condition("*").as_ref(), &etag, weak));
+            assert!(!matches_etag(condition("\"abcd\"").as_ref(), &etag, weak));
+            assert!(!matches_etag(None, &etag, weak));
+        }
+        assert!(matches_etag(condition("W/\"abc\"").as_ref(), &etag, true));
+        assert!(!matches_etag(condition("W/\"abc\"").as_ref(), &etag, false));
+    }
+
+    #[tokio::test]
+    async fn weak_if_match() {
+        let client = client();
+        let res = client
+            .post("/users")
+            .body("name: alice")
+            .header("content-type", "application/yaml")
+            .await;
+        let etag = res.headers()["etag"].to_str().unwrap().to_owned();
+        let weak = format!("W/{etag}");
+
+        let res = client
+            .get("/users/1")
+            .header("if-none-match", weak.as_str())
+            .await;
+        assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+
+        let res = client
+            .delete("/users/1")
+            .header("if-match", weak.as_str())
+            .await;
+        assert_eq!(res.status(), StatusCode::PRECONDITION_FAILED);
+        let res = client
+            .delete("/users/1")
+            .header("if-match", etag.as_str())
+            .await;
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    }
+
+    #[tokio::test]
+    async fn invalid_resource() {
+        let res = client()
+            .post("/users")
+            .body("name: ''")
+            .header("content-type", "application/yaml")
+            .await;
+        assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(
+            res.text().await,
+            "Invalid resource: `name` must not be empty"
+        );
+    }
+
+    #[test]
+    fn merge_patch() {
+        let mut target: Value = serde_yaml::from_str("a: 1\nb: {c: 2, d: 3}\n").unwrap();
+        let patch: Value = serde_yaml::from_str("a: ~\nb: {c: 4}\ne: 5\n").unwrap();
+        super::merge_patch(&mut target, patch);
+
+        assert_eq!(
+            target,
+            serde_yaml::from_str::<Value>("b: {c: 4, d: 3}\ne: 5\n").unwrap()
+        );
+    }
+}
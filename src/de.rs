@@ -0,0 +1,329 @@
+//! Serde helpers for idioms common in YAML documents.
+//!
+//! Use them with `#[serde(deserialize_with = "...")]`:
+//!
+//! ```
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize)]
+//! struct Job {
+//!     #[serde(default, deserialize_with = "axum_yaml::de::one_or_many")]
+//!     tags: Vec<String>,
+//! }
+//!
+//! let one: Job = serde_yaml::from_str("tags: nightly").unwrap();
+//! let many: Job = serde_yaml::from_str("tags: [nightly, slow]").unwrap();
+//! assert_eq!(one.tags, ["nightly"]);
+//! assert_eq!(many.tags, ["nightly", "slow"]);
+//! ```
+
+use std::fmt;
+use std::marker::PhantomData;
+
+use serde::de::value::{
+    BoolDeserializer, EnumAccessDeserializer, F64Deserializer, I64Deserializer,
+    MapAccessDeserializer, MapDeserializer, SeqAccessDeserializer, SeqDeserializer,
+    StrDeserializer, StringDeserializer, U64Deserializer,
+};
+use serde::de::{
+    Deserialize, DeserializeOwned, Deserializer, EnumAccess, Error, IntoDeserializer, MapAccess,
+    SeqAccess, Visitor,
+};
+use serde_yaml::Value;
+
+/// Deserialize either a single `T` or a sequence of `T` into a `Vec<T>`.
+///
+/// A null value, as in `key:`, yields an empty `Vec`.
+pub fn one_or_many<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    deserializer.deserialize_any(OneOrMany(PhantomData))
+}
+
+struct OneOrMany<T>(PhantomData<T>);
+
+impl<T> OneOrMany<T> {
+    fn one<'de, D>(deserializer: D) -> Result<Vec<T>, D::Error>
+    where
+        D: Deserializer<'de>,
+        T: Deserialize<'de>,
+    {
+        T::deserialize(deserializer).map(|one| vec![one])
+    }
+}
+
+impl<'de, T> Visitor<'de> for OneOrMany<T>
+where
+    T: Deserialize<'de>,
+{
+    type Value = Vec<T>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a single value or a sequence")
+    }
+
+    fn visit_bool<E: Error>(self, v: bool) -> Result<Self::Value, E> {
+        Self::one(BoolDeserializer::new(v))
+    }
+
+    fn visit_i64<E: Error>(self, v: i64) -> Result<Self::Value, E> {
+        Self::one(I64Deserializer::new(v))
+    }
+
+    fn visit_u64<E: Error>(self, v: u64) -> Result<Self::Value, E> {
+        Self::one(U64Deserializer::new(v))
+    }
+
+    fn visit_f64<E: Error>(self, v: f64) -> Result<Self::Value, E> {
+        Self::one(F64Deserializer::new(v))
+    }
+
+    fn visit_str<E: Error>(self, v: &str) -> Result<Self::Value, E> {
+        Self::one(StrDeserializer::new(v))
+    }
+
+    fn visit_string<E: Error>(self, v: String) -> Result<Self::Value, E> {
+        Self::one(StringDeserializer::new(v))
+    }
+
+    fn visit_unit<E: Error>(self) -> Result<Self::Value, E> {
+        Ok(Vec::new())
+    }
+
+    fn visit_none<E: Error>(self) -> Result<Self::Value, E> {
+        Ok(Vec::new())
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        one_or_many(deserializer)
+    }
+
+    fn visit_map<A>(self, map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        Self::one(MapAccessDeserializer::new(map))
+    }
+
+    fn visit_enum<A>(self, data: A) -> Result<Self::Value, A::Error>
+    where
+        A: EnumAccess<'de>,
+    {
+        Self::one(EnumAccessDeserializer::new(data))
+    }
+
+    fn visit_seq<A>(self, seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        Vec::deserialize(SeqAccessDeserializer::new(seq))
+    }
+}
+
+/// Deserialize an `Option<T>`, treating an empty string like a missing value.
+///
+/// YAML documents commonly write `key:` or `key: ""` to mean "unset"; both
+/// yield `None` instead of `Some("")`.
+pub fn empty_as_none<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: DeserializeOwned,
+{
+    match Option::<Value>::deserialize(deserializer)? {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(string)) if string.is_empty() => Ok(None),
+        Some(value) => T::deserialize(value).map(Some).map_err(D::Error::custom),
+    }
+}
+
+/// Deserializer of a document treating empty strings as missing values for
+/// every `Option` field.
+pub(crate) struct EmptyAsNone(pub(crate) Value);
+
+impl<'de> IntoDeserializer<'de, serde_yaml::Error> for EmptyAsNone {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+macro_rules! forward_to_value {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+            where
+                V: Visitor<'de>,
+            {
+                self.0.$method(visitor)
+            }
+        )*
+    };
+}
+
+impl<'de> Deserializer<'de> for EmptyAsNone {
+    type Error = serde_yaml::Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self.0 {
+            Value::Sequence(sequence) => {
+                let mut access = SeqDeserializer::new(sequence.into_iter().map(EmptyAsNone));
+                let value = visitor.visit_seq(&mut access)?;
+                access.end()?;
+                Ok(value)
+            }
+            Value::Mapping(mapping) => {
+                let mut access = MapDeserializer::new(
+                    mapping
+                        .into_iter()
+                        .map(|(key, value)| (EmptyAsNone(key), EmptyAsNone(value))),
+                );
+                let value = visitor.visit_map(&mut access)?;
+                access.end()?;
+                Ok(value)
+            }
+            value => value.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match &self.0 {
+            Value::Null => visitor.visit_none(),
+            Value::String(string) if string.is_empty() => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.0.deserialize_enum(name, variants, visitor)
+    }
+
+    forward_to_value! {
+        deserialize_bool deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64
+        deserialize_i128 deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64
+        deserialize_u128 deserialize_f32 deserialize_f64 deserialize_char deserialize_str
+        deserialize_string deserialize_bytes deserialize_byte_buf deserialize_unit
+        deserialize_identifier
+    }
+
+    fn deserialize_unit_struct<V>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.0.deserialize_unit_struct(name, visitor)
+    }
+
+    serde::forward_to_deserialize_any! {
+        seq tuple tuple_struct map struct ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Port {
+        port: u16,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Service {
+        #[serde(default, deserialize_with = "one_or_many")]
+        ports: Vec<Port>,
+        #[serde(default, deserialize_with = "one_or_many")]
+        hosts: Vec<String>,
+    }
+
+    #[test]
+    fn single_or_sequence() {
+        let service: Service = serde_yaml::from_str("ports: {port: 80}\nhosts: a\n").unwrap();
+        assert_eq!(service.ports, [Port { port: 80 }]);
+        assert_eq!(service.hosts, ["a"]);
+
+        let service: Service =
+            serde_yaml::from_str("ports: [{port: 80}, {port: 443}]\nhosts:\n").unwrap();
+        assert_eq!(service.ports, [Port { port: 80 }, Port { port: 443 }]);
+        assert!(service.hosts.is_empty());
+
+        let service: Service = serde_yaml::from_str("{}").unwrap();
+        assert!(service.ports.is_empty());
+
+        let err = serde_yaml::from_str::<Service>("ports: [{port: x}]").unwrap_err();
+        assert!(err.to_string().contains("invalid type"), "{err}");
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Profile {
+        name: String,
+        #[serde(default, deserialize_with = "empty_as_none")]
+        email: Option<String>,
+        nested: Option<Nested>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Nested {
+        note: Option<String>,
+    }
+
+    #[test]
+    fn empty_string_field() {
+        for source in ["name: ''\nemail: ''", "name: ''\nemail:", "name: ''"] {
+            let profile: Profile = serde_yaml::from_str(source).unwrap();
+            assert_eq!(profile.email, None, "{source:?}");
+        }
+        let profile: Profile = serde_yaml::from_str("name: a\nemail: a@b").unwrap();
+        assert_eq!(profile.email.as_deref(), Some("a@b"));
+    }
+
+    #[test]
+    fn empty_as_none_deserializer() {
+        let document = serde_yaml::from_str("name: ''\nnested:\n  note: ''\n").unwrap();
+        let profile = Profile::deserialize(EmptyAsNone(document)).unwrap();
+
+        assert_eq!(profile.name, "");
+        assert_eq!(profile.nested.unwrap().note, None);
+
+        let document = serde_yaml::from_str("name: a\nnested: ''\n").unwrap();
+        assert!(Profile::deserialize(EmptyAsNone(document))
+            .unwrap()
+            .nested
+            .is_none());
+    }
+}
@@ -0,0 +1,338 @@
+//! Debugging aids for YAML responses.
+//!
+//! [`RoundTripLayer`] checks every YAML response of a router in debug builds,
+//! [`RoundTripYaml`] a single response, down to its Rust type.
+//!
+//! Requires the `tracing` feature.
+
+use std::cell::Cell;
+use std::fmt::Debug;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use axum_core::response::{IntoResponse, Response};
+use http::Request;
+use pin_project_lite::pin_project;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_yaml::Value;
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::ser::{self, SerializeConfig};
+
+thread_local! {
+    /// Whether a [`RoundTripLayer`] is being polled.
+    static CHECKED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// YAML response checking that its value survives a round-trip.
+///
+/// In debug builds the body served for the value, as written with the
+/// [`SerializeConfig`] of the process, is parsed back into `T` and compared
+/// against the original value (`T -> yaml -> T == T`). Lossy round-trips, such
+/// as floating point precision loss, a too small
+/// [`FloatFormat`](crate::ser::FloatFormat) precision or surprising enum
+/// representations, are logged as `WARN` events under the
+/// `axum_yaml::round_trip` target. In release builds it behaves exactly like
+/// [`Yaml`](crate::Yaml).
+///
+/// ```no_run
+/// use axum_yaml::debug::RoundTripYaml;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Debug, PartialEq, Serialize, Deserialize)]
+/// struct Price {
+///     amount: f64,
+/// }
+///
+/// async fn get_price() -> RoundTripYaml<Price> {
+///     RoundTripYaml(Price { amount: 0.1 + 0.2 })
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RoundTripYaml<T>(pub T);
+
+impl<T> IntoResponse for RoundTripYaml<T>
+where
+    T: Serialize + DeserializeOwned + PartialEq + Debug,
+{
+    fn into_response(self) -> Response {
+        let config = SerializeConfig::current();
+        if !cfg!(debug_assertions) {
+            return ser::response(&self.0, &config);
+        }
+
+        ser::response_with(&self.0, &config, |body| {
+            if let Some(mismatch) = round_trip_mismatch(&self.0, body) {
+                tracing::warn!(
+                    target: "axum_yaml::round_trip",
+                    value_type = std::any::type_name::<T>(),
+                    "lossy YAML round-trip: {mismatch}",
+                );
+            }
+        })
+    }
+}
+
+/// Describe how `value` changes when parsed back from the served `body`, if
+/// it does.
+fn round_trip_mismatch<T>(value: &T, body: &[u8]) -> Option<String>
+where
+    T: DeserializeOwned + PartialEq + Debug,
+{
+    let body = body.strip_prefix("\u{FEFF}".as_bytes()).unwrap_or(body);
+    match serde_yaml::from_slice::<T>(body) {
+        Ok(parsed) if parsed == *value => None,
+        Ok(parsed) => Some(format!("{value:?} was read back as {parsed:?}")),
+        Err(err) => Some(format!("{value:?} cannot be read back: {err}")),
+    }
+}
+
+/// [`Layer`] checking that the YAML responses of the wrapped service read
+/// back as the values they were serialized from.
+///
+/// In debug builds, the body of each response built within the wrapped
+/// service by [`Yaml`](crate::Yaml), [`ConfiguredYaml`](crate::ser::ConfiguredYaml)
+/// and the other extractors serializing a single value is parsed back into a
+/// [`serde_yaml::Value`] and compared against the document written for the
+/// value, with the key case of its [`SerializeConfig`]. Mismatches, such as a
+/// too small [`FloatFormat`](crate::ser::FloatFormat) precision or a hook
+/// rewriting the body, are logged as `WARN` events under the
+/// `axum_yaml::round_trip` target. In release builds the layer does nothing.
+///
+/// Documents are compared rather than Rust values, since the layer does not
+/// know the types of the responses: a field skipped when serializing, or an
+/// enum read back as another variant, goes unnoticed. Wrap such responses in
+/// [`RoundTripYaml`] to check them down to their type.
+///
+/// Like the [`SerializeConfigLayer`](crate::ser::SerializeConfigLayer), the
+/// layer applies to the polls of the wrapped service, so responses built on
+/// other tasks are not checked.
+///
+/// ```no_run
+/// use axum::{routing::get, Router};
+/// use axum_yaml::debug::RoundTripLayer;
+/// use axum_yaml::Yaml;
+///
+/// async fn ratio() -> Yaml<f64> {
+///     Yaml(2.0 / 3.0)
+/// }
+///
+/// let app: Router = Router::new()
+///     .route("/ratio", get(ratio))
+///     .layer(RoundTripLayer);
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RoundTripLayer;
+
+impl<S> Layer<S> for RoundTripLayer {
+    type Service = RoundTripService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RoundTripService { inner }
+    }
+}
+
+/// Middleware created by [`RoundTripLayer`].
+#[derive(Debug, Clone)]
+pub struct RoundTripService<S> {
+    inner: S,
+}
+
+impl<S, ReqBody> Service<Request<ReqBody>> for RoundTripService<S>
+where
+    S: Service<Request<ReqBody>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
+        // Handlers returning a ready future may build their response here.
+        let inner = checked(|| self.inner.call(req));
+        ResponseFuture { inner }
+    }
+}
+
+pin_project! {
+    /// Response future for [`RoundTripService`].
+    pub struct ResponseFuture<F> {
+        #[pin]
+        inner: F,
+    }
+}
+
+impl<F: Future> Future for ResponseFuture<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let inner = self.project().inner;
+        checked(|| inner.poll(cx))
+    }
+}
+
+/// Run `f` with the responses it builds checked.
+fn checked<R>(f: impl FnOnce() -> R) -> R {
+    /// Restores the enclosing state, even if `f` panics.
+    struct Restore(bool);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            CHECKED.set(self.0);
+        }
+    }
+
+    let _restore = Restore(CHECKED.replace(true));
+    f()
+}
+
+/// Log how the document served in `body` differs from the one written for
+/// `value` with `config`, within a [`RoundTripLayer`] in debug builds.
+pub(crate) fn check_response<T>(value: &T, config: &SerializeConfig, body: &[u8])
+where
+    T: Serialize + ?Sized,
+{
+    if !cfg!(debug_assertions) || !CHECKED.get() {
+        return;
+    }
+    if let Some(mismatch) = document_mismatch(value, config, body) {
+        tracing::warn!(
+            target: "axum_yaml::round_trip",
+            value_type = std::any::type_name::<T>(),
+            "lossy YAML round-trip: {mismatch}",
+        );
+    }
+}
+
+/// Describe how the document written for `value` with `config` changes when
+/// parsed back from the served `body`, if it does.
+fn document_mismatch<T>(value: &T, config: &SerializeConfig, body: &[u8]) -> Option<String>
+where
+    T: Serialize + ?Sized,
+{
+    // A value that cannot be serialized is answered with a 500 instead.
+    let expected = config.to_value(value).ok()?;
+    let body = body.strip_prefix("\u{FEFF}".as_bytes()).unwrap_or(body);
+    match serde_yaml::from_slice::<Value>(body) {
+        Ok(parsed) if parsed == expected => None,
+        Ok(parsed) => Some(format!("{expected:?} was read back as {parsed:?}")),
+        Err(err) => Some(format!("{expected:?} cannot be read back: {err}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde::Deserialize;
+
+    use crate::ser::FloatFormat;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Price {
+        amount: f64,
+    }
+
+    /// The mismatch of `value` served with `config`.
+    fn mismatch<T>(value: &T, config: &SerializeConfig) -> Option<String>
+    where
+        T: Serialize + DeserializeOwned + PartialEq + Debug,
+    {
+        let mut mismatch = None;
+        ser::response_with(value, config, |body| {
+            mismatch = round_trip_mismatch(value, body);
+        });
+        mismatch
+    }
+
+    #[test]
+    fn lossless() {
+        let price = Price { amount: 0.1 + 0.2 };
+        assert_eq!(mismatch(&price, &SerializeConfig::new()), None);
+        assert_eq!(mismatch(&price, &SerializeConfig::new().bom(true)), None);
+    }
+
+    #[test]
+    fn lossy() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Flags {
+            #[serde(skip_serializing, default)]
+            enabled: bool,
+        }
+
+        assert_eq!(
+            mismatch(&Flags { enabled: true }, &SerializeConfig::new()),
+            Some("Flags { enabled: true } was read back as Flags { enabled: false }".to_owned())
+        );
+    }
+
+    #[test]
+    fn documents() {
+        let price = Price { amount: 0.25 };
+        let config = SerializeConfig::new().key_case(crate::case::KeyCase::Camel);
+        let mismatch = |config: &SerializeConfig| {
+            let mut mismatch = None;
+            ser::response_with(&price, config, |body| {
+                mismatch = document_mismatch(&price, config, body);
+            });
+            mismatch
+        };
+        assert_eq!(mismatch(&config), None);
+        assert_eq!(mismatch(&config.clone().sort_keys(true).bom(true)), None);
+
+        let config = config.float_format(FloatFormat::new().precision(1));
+        assert_eq!(
+            mismatch(&config),
+            Some(
+                "Mapping {\"amount\": Number(0.25)} was read back as \
+                 Mapping {\"amount\": Number(0.2)}"
+                    .to_owned()
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn layer() {
+        use axum::{routing::get, Router};
+        use tower::ServiceExt;
+
+        let app = Router::new()
+            .route("/", get(|| async { CHECKED.get().to_string() }))
+            .layer(RoundTripLayer);
+        let res = app
+            .oneshot(
+                http::Request::get("/")
+                    .body(axum::body::Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(body, "true");
+        assert!(!CHECKED.get());
+    }
+
+    #[test]
+    fn serialize_config() {
+        let price = Price { amount: 0.25 };
+        assert_eq!(mismatch(&price, &SerializeConfig::new()), None);
+
+        let config = SerializeConfig::new().float_format(FloatFormat::new().precision(1));
+        assert_eq!(
+            mismatch(&price, &config),
+            Some("Price { amount: 0.25 } was read back as Price { amount: 0.2 }".to_owned())
+        );
+
+        let config = SerializeConfig::new().hook(|body, _| body.clear());
+        assert!(mismatch(&price, &config)
+            .unwrap()
+            .starts_with("Price { amount: 0.25 } cannot be read back: "));
+    }
+}
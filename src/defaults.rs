@@ -0,0 +1,181 @@
+//! Default filling of documents from JSON Schemas.
+//!
+//! Requires the `schemars` feature. Filling missing fields with the `default`
+//! values of the published schema, instead of relying on separate
+//! `#[serde(default)]` functions, keeps the defaults applied by the server
+//! and the ones documented to clients from drifting apart.
+//!
+//! Install a schema with [`YamlConfig::fill_defaults`](crate::YamlConfig::fill_defaults)
+//! to fill every extracted document, or call [`fill_defaults`] directly.
+
+use schemars::Schema;
+use serde_json::Value as JsonValue;
+use serde_yaml::Value;
+
+// Bounds chains of `$ref`s pointing at each other without any nesting.
+const MAX_REF_CHAIN: usize = 32;
+
+/// Insert the `default` of every property of `schema` missing from
+/// `document`, recursively.
+///
+/// Defaults are only inserted into mappings present in the document.
+pub fn fill_defaults(schema: &Schema, document: &mut Value) {
+    Filler {
+        root: schema.as_value(),
+    }
+    .fill(schema.as_value(), document);
+}
+
+struct Filler<'a> {
+    root: &'a JsonValue,
+}
+
+impl<'a> Filler<'a> {
+    fn resolve(&self, schema: &'a JsonValue) -> &'a JsonValue {
+        let mut schema = schema;
+        for _ in 0..MAX_REF_CHAIN {
+            match schema
+                .get("$ref")
+                .and_then(JsonValue::as_str)
+                .and_then(|reference| reference.strip_prefix('#'))
+                .and_then(|pointer| self.root.pointer(pointer))
+            {
+                Some(target) => schema = target,
+                None => break,
+            }
+        }
+        schema
+    }
+
+    fn fill(&self, schema: &'a JsonValue, value: &mut Value) {
+        let schema = self.resolve(schema);
+        let JsonValue::Object(object) = schema else {
+            return;
+        };
+
+        for subschema in object
+            .get("allOf")
+            .and_then(JsonValue::as_array)
+            .into_iter()
+            .flatten()
+        {
+            self.fill(subschema, value);
+        }
+        // Only follow alternatives when a single one describes non-null
+        // values, as for `Option<T>`.
+        for alternatives in ["anyOf", "oneOf"] {
+            let mut non_null = object
+                .get(alternatives)
+                .and_then(JsonValue::as_array)
+                .into_iter()
+                .flatten()
+                .filter(|subschema| {
+                    self.resolve(subschema).get("type") != Some(&JsonValue::from("null"))
+                });
+            if let (Some(subschema), None) = (non_null.next(), non_null.next()) {
+                self.fill(subschema, value);
+            }
+        }
+
+        match value {
+            Value::Mapping(mapping) => {
+                let properties = object.get("properties").and_then(JsonValue::as_object);
+                for (name, property) in properties.into_iter().flatten() {
+                    let key = Value::String(name.clone());
+                    match mapping.get_mut(&key) {
+                        Some(child) => self.fill(property, child),
+                        None => {
+                            let default = self.resolve(property).get("default");
+                            if let Some(default) =
+                                default.and_then(|default| serde_yaml::to_value(default).ok())
+                            {
+                                mapping.insert(key, default);
+                            }
+                        }
+                    }
+                }
+
+                if let Some(additional) = object
+                    .get("additionalProperties")
+                    .filter(|schema| schema.is_object())
+                {
+                    for (key, child) in mapping.iter_mut() {
+                        let known = key
+                            .as_str()
+                            .is_some_and(|key| properties.is_some_and(|p| p.contains_key(key)));
+                        if !known {
+                            self.fill(additional, child);
+                        }
+                    }
+                }
+            }
+            Value::Sequence(sequence) => {
+                if let Some(items) = object.get("items") {
+                    for item in sequence {
+                        self.fill(items, item);
+                    }
+                }
+            }
+            Value::Tagged(tagged) => self.fill(schema, &mut tagged.value),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use schemars::JsonSchema;
+    use serde::Deserialize;
+
+    #[allow(dead_code)]
+    #[derive(Deserialize, JsonSchema)]
+    struct Deployment {
+        name: String,
+        #[serde(default = "default_replicas")]
+        replicas: u32,
+        containers: Vec<Container>,
+        strategy: Option<Strategy>,
+    }
+
+    #[allow(dead_code)]
+    #[derive(Deserialize, JsonSchema)]
+    struct Container {
+        image: String,
+        #[schemars(default = "default_pull_policy")]
+        pull_policy: Option<String>,
+    }
+
+    #[allow(dead_code)]
+    #[derive(Deserialize, JsonSchema)]
+    struct Strategy {
+        #[serde(default)]
+        max_surge: u32,
+    }
+
+    fn default_replicas() -> u32 {
+        1
+    }
+
+    fn default_pull_policy() -> Option<String> {
+        Some("IfNotPresent".to_owned())
+    }
+
+    #[test]
+    fn fill() {
+        let mut document: Value = serde_yaml::from_str(
+            "name: web\ncontainers:\n- image: a\n- image: b\n  pull_policy: Always\nstrategy: {}\n",
+        )
+        .unwrap();
+        fill_defaults(&schemars::schema_for!(Deployment), &mut document);
+
+        assert_eq!(
+            document,
+            serde_yaml::from_str::<Value>(
+                "name: web\ncontainers:\n- image: a\n  pull_policy: IfNotPresent\n- image: b\n  pull_policy: Always\nstrategy: {max_surge: 0}\nreplicas: 1\n"
+            )
+            .unwrap()
+        );
+    }
+}
@@ -0,0 +1,226 @@
+//! HTML pages of [`BodyFormat::Html`](crate::rejection::BodyFormat::Html).
+
+use std::fmt::Write;
+
+use crate::rejection::RejectionBody;
+
+const STYLE: &str = "\
+body { font-family: sans-serif; margin: 2em auto; max-width: 60em; color: #222; }
+h1 { color: #b00020; }
+.code, code, pre { font-family: monospace; }
+.code { color: #666; }
+dt { font-weight: bold; }
+pre { background: #f6f8fa; padding: 1em; overflow-x: auto; }
+.gutter { color: #999; }
+.failing { background: #fde8e8; }
+.caret { color: #b00020; font-weight: bold; }
+.key { color: #005cc5; }
+.comment { color: #6a737d; }";
+
+/// Render `rejection` as an HTML page.
+pub(crate) fn page(rejection: &RejectionBody) -> String {
+    let status = rejection.status();
+    let title = format!(
+        "{} {}",
+        status.as_u16(),
+        status.canonical_reason().unwrap_or_default()
+    );
+    let message = rejection
+        .snippet()
+        .and_then(|snippet| rejection.message().strip_suffix(snippet))
+        .map_or(rejection.message(), str::trim_end);
+    // The errors of `YamlErrors` are listed below, one per line.
+    let message = match rejection.errors() {
+        [] => message,
+        _ => message.lines().next().unwrap_or_default(),
+    };
+
+    let mut page = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n\
+         <style>\n{STYLE}\n</style>\n</head>\n<body>\n<h1>{title}</h1>\n\
+         <p class=\"code\">{}</p>\n<p>{}</p>\n",
+        escape(rejection.code()),
+        escape(message),
+    );
+
+    if rejection.path().is_some() || rejection.line().is_some() {
+        page.push_str("<dl>\n");
+        if let Some(path) = rejection.path() {
+            let _ = writeln!(page, "<dt>Path</dt><dd><code>{}</code></dd>", escape(path));
+        }
+        if let (Some(line), Some(column)) = (rejection.line(), rejection.column()) {
+            let _ = writeln!(
+                page,
+                "<dt>Location</dt><dd>line {line}, column {column}</dd>"
+            );
+        }
+        page.push_str("</dl>\n");
+    }
+
+    if !rejection.errors().is_empty() {
+        page.push_str("<ul>\n");
+        for err in rejection.errors() {
+            let _ = writeln!(
+                page,
+                "<li><code>{}</code>: {}</li>",
+                escape(err.path().unwrap_or_default()),
+                escape(err.message())
+            );
+        }
+        page.push_str("</ul>\n");
+    }
+
+    if let Some(snippet) = rejection.snippet() {
+        page.push_str("<pre>");
+        for line in snippet.lines() {
+            page.push_str(&highlight(line, rejection.line()));
+            page.push('\n');
+        }
+        page.push_str("</pre>\n");
+    }
+
+    page.push_str("</body>\n</html>\n");
+    page
+}
+
+/// Highlight a line of a [`YamlError::snippet`](crate::rejection::YamlError::snippet),
+/// `N | code` for the quoted lines and ` | ^` for the caret.
+fn highlight(line: &str, failing: Option<usize>) -> String {
+    let Some((number, code)) = line.split_once(" |") else {
+        return escape(line);
+    };
+    let gutter = format!("<span class=\"gutter\">{} |</span>", escape(number));
+
+    let number = number.trim();
+    if number.is_empty() {
+        return format!("{gutter}<span class=\"caret\">{}</span>", escape(code));
+    }
+    let code = highlight_yaml(code);
+    if failing.is_some() && number.parse().ok() == failing {
+        format!("<span class=\"failing\">{gutter}{code}</span>")
+    } else {
+        format!("{gutter}{code}")
+    }
+}
+
+/// Highlight the mapping key and the comment of a line of YAML.
+fn highlight_yaml(code: &str) -> String {
+    let (code, comment) = match code.find(" #") {
+        Some(start) => code.split_at(start),
+        None => (code, ""),
+    };
+
+    let indent = code.len() - code.trim_start_matches([' ', '-']).len();
+    let (indent, rest) = code.split_at(indent);
+    let mut highlighted = escape(indent);
+    match rest.split_once(':').filter(|(key, value)| {
+        !key.starts_with(['"', '\'', '{', '[']) && (value.is_empty() || value.starts_with(' '))
+    }) {
+        Some((key, value)) => {
+            let _ = write!(
+                highlighted,
+                "<span class=\"key\">{}</span>:{}",
+                escape(key),
+                escape(value)
+            );
+        }
+        None => highlighted.push_str(&escape(rest)),
+    }
+    if !comment.is_empty() {
+        let _ = write!(
+            highlighted,
+            "<span class=\"comment\">{}</span>",
+            escape(comment)
+        );
+    }
+    highlighted
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn highlight_lines() {
+        assert_eq!(
+            highlight("2 |   - name: <web> # front", Some(2)),
+            "<span class=\"failing\"><span class=\"gutter\">2 |</span>   - \
+             <span class=\"key\">name</span>: &lt;web&gt;\
+             <span class=\"comment\"> # front</span></span>"
+        );
+        assert_eq!(
+            highlight("1 | a: 1", Some(2)),
+            "<span class=\"gutter\">1 |</span> <span class=\"key\">a</span>: 1"
+        );
+        assert_eq!(
+            highlight("  |     ^", Some(2)),
+            "<span class=\"gutter\">  |</span><span class=\"caret\">     ^</span>"
+        );
+        assert_eq!(
+            highlight("1 | \"a: b\"", Some(2)),
+            "<span class=\"gutter\">1 |</span> &quot;a: b&quot;"
+        );
+    }
+
+    #[test]
+    fn yaml_error_page() {
+        use crate::rejection::{ToRejectionBody, YamlRejection};
+        use crate::YamlConfig;
+
+        let Err(YamlRejection::YamlError(err)) = YamlConfig::new()
+            .error_snippets(true)
+            .deserialize::<Vec<u8>>(b"- 1\n- <x>\n")
+        else {
+            panic!("`<x>` is not a number");
+        };
+        let page = page(&err.rejection_body());
+
+        assert!(page.contains("<title>400 Bad Request</title>"));
+        assert!(page.contains("<p class=\"code\">invalid_type</p>"));
+        assert!(page.contains(
+            "<p>Failed to deserialize the YAML body into the target type: [1]: .[1]: \
+             invalid type: string &quot;&lt;x&gt;&quot;, expected u8 at line 2 column 3</p>"
+        ));
+        assert!(page.contains("<dt>Path</dt><dd><code>[1]</code></dd>"));
+        assert!(page.contains("<dt>Location</dt><dd>line 2, column 3</dd>"));
+        assert!(page.contains(
+            "<span class=\"failing\"><span class=\"gutter\">2 |</span> - &lt;x&gt;</span>"
+        ));
+    }
+
+    #[test]
+    fn yaml_errors_page() {
+        use crate::rejection::{ToRejectionBody, YamlRejection};
+        use crate::YamlConfig;
+
+        let Err(YamlRejection::YamlErrors(errors)) = YamlConfig::new()
+            .collect_errors(10)
+            .deserialize::<Vec<u8>>(b"- x\n- <y>\n")
+        else {
+            panic!("`x` and `<y>` are not numbers");
+        };
+        let page = page(&errors.rejection_body());
+
+        assert!(page
+            .contains("<p>Failed to deserialize the YAML body into the target type: 2 errors</p>"));
+        assert!(page.contains(
+            "<ul>\n<li><code>[0]</code>: invalid type: string &quot;x&quot;, expected u8</li>\n\
+             <li><code>[1]</code>: invalid type: string &quot;&lt;y&gt;&quot;, expected u8</li>\n</ul>"
+        ));
+    }
+}
@@ -0,0 +1,88 @@
+//! Debug endpoint dumping the running configuration.
+//!
+//! Answers "what configuration is this instance actually running with" in
+//! YAML form, like Spring's `/configprops`. Secrets are removed with the
+//! paths hidden by a [`FieldFilter`], along with the paths hidden for the
+//! current viewer.
+//!
+//! ```no_run
+//! use std::sync::Arc;
+//!
+//! use axum::{routing::get, Router};
+//! use axum_yaml::{dump, filter::FieldFilter};
+//! use serde::Serialize;
+//!
+//! #[derive(Clone, Serialize)]
+//! struct AppConfig {
+//!     listen: String,
+//!     database_url: String,
+//! }
+//!
+//! let config = Arc::new(AppConfig {
+//!     listen: "0.0.0.0:8080".to_owned(),
+//!     database_url: "postgres://app:hunter2@db/app".to_owned(),
+//! });
+//!
+//! let app: Router = Router::new().route(
+//!     "/debug/config",
+//!     get(dump::config_handler(
+//!         move || (*config).clone(),
+//!         FieldFilter::new().hide("database_url"),
+//!     )),
+//! );
+//! ```
+
+use std::future::{ready, Ready};
+
+use serde::Serialize;
+
+use crate::filter::{FieldFilter, FilteredYaml};
+
+/// Create a handler serving the snapshot returned by `snapshot` as YAML,
+/// with the paths hidden by `redact` removed.
+///
+/// `snapshot` is called on every request, so it can read configuration that
+/// changes at runtime. The [`FieldFilter`] of the request is applied as well.
+pub fn config_handler<T, F>(
+    snapshot: F,
+    redact: FieldFilter,
+) -> impl Fn(FieldFilter) -> Ready<FilteredYaml<T>> + Clone + Send + Sync + 'static
+where
+    T: Serialize,
+    F: Fn() -> T + Clone + Send + Sync + 'static,
+{
+    move |viewer: FieldFilter| ready(viewer.merge(&redact).yaml(snapshot()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::BTreeMap;
+
+    use axum::routing::get;
+    use axum::{Extension, Router};
+
+    use crate::test_client::TestClient;
+
+    #[tokio::test]
+    async fn redacted() {
+        let config = BTreeMap::from([
+            ("listen", "0.0.0.0:8080"),
+            ("password", "hunter2"),
+            ("token", "abc"),
+        ]);
+        let app = Router::new()
+            .route(
+                "/",
+                get(config_handler(
+                    move || config.clone(),
+                    FieldFilter::new().hide("password"),
+                )),
+            )
+            .layer(Extension(FieldFilter::new().hide("token")));
+
+        let res = TestClient::new(app).get("/").await;
+        assert_eq!(res.text().await, "listen: 0.0.0.0:8080\n");
+    }
+}
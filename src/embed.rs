@@ -0,0 +1,120 @@
+//! Typed documents embedded in the binary.
+//!
+//! [`yaml_typed!`](crate::yaml_typed) declares a `static` holding a YAML
+//! file embedded with `include_str!` and deserialized into its Rust type:
+//!
+//! ```
+//! use axum_yaml::yaml_typed;
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize)]
+//! struct Limits {
+//!     max_users: u32,
+//!     regions: Vec<String>,
+//! }
+//!
+//! yaml_typed! {
+//!     /// Limits applied when the deployment does not override them.
+//!     static DEFAULT_LIMITS: Limits = "../testdata/limits.yaml";
+//! }
+//!
+//! assert_eq!(DEFAULT_LIMITS.max_users, 100);
+//! ```
+//!
+//! The file is embedded at compile time, but it is not checked against its
+//! type then: the build does not fail when the file does not match. Neither
+//! a declarative nor a procedural macro can run the `Deserialize` impl of
+//! the type it is given, so the document is parsed on first access and a
+//! mismatch panics with the file name, line and column. Force every
+//! embedded document in a test, or validate the files from `build.rs` with
+//! `build::Fixtures` of the `build` feature, to catch drift before
+//! deployment:
+//!
+//! ```ignore
+//! #[test]
+//! fn embedded_defaults() {
+//!     std::sync::LazyLock::force(&DEFAULT_LIMITS);
+//! }
+//! ```
+
+use serde::de::DeserializeOwned;
+
+/// Declare a `static` holding a YAML file deserialized into a Rust type.
+///
+/// The path is resolved like `include_str!`, relative to the file invoking
+/// the macro. See the [module documentation](crate::embed).
+#[macro_export]
+macro_rules! yaml_typed {
+    (
+        $(#[$m:meta])*
+        $vis:vis static $name:ident : $ty:ty = $path:literal $(;)?
+    ) => {
+        $(#[$m])*
+        $vis static $name: ::std::sync::LazyLock<$ty> = ::std::sync::LazyLock::new(|| {
+            $crate::embed::__deserialize(include_str!($path), $path)
+        });
+    };
+}
+
+#[doc(hidden)]
+#[track_caller]
+pub fn __deserialize<T>(source: &str, path: &str) -> T
+where
+    T: DeserializeOwned,
+{
+    let deserializer = serde_yaml::Deserializer::from_str(source);
+    match serde_path_to_error::deserialize(deserializer) {
+        Ok(value) => value,
+        Err(err) => panic!(
+            "embedded `{path}` does not match `{}`: {err}",
+            std::any::type_name::<T>()
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::BTreeMap;
+
+    use serde::Deserialize;
+
+    #[test]
+    fn deserialize() {
+        let value: BTreeMap<String, u32> = __deserialize("a: 1\nb: 2\n", "inline.yaml");
+        assert_eq!(value["b"], 2);
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Limits {
+        max_users: u32,
+        regions: Vec<String>,
+    }
+
+    crate::yaml_typed! {
+        static LIMITS: Limits = "../testdata/limits.yaml";
+    }
+
+    crate::yaml_typed! {
+        static MISMATCHED: BTreeMap<String, u32> = "../testdata/limits.yaml";
+    }
+
+    #[test]
+    fn embedded() {
+        assert_eq!(LIMITS.max_users, 100);
+        assert_eq!(LIMITS.regions, ["eu-west", "us-east"]);
+    }
+
+    #[test]
+    #[should_panic(expected = "embedded `../testdata/limits.yaml` does not match")]
+    fn embedded_mismatch() {
+        std::sync::LazyLock::force(&MISMATCHED);
+    }
+
+    #[test]
+    #[should_panic(expected = "embedded `inline.yaml` does not match")]
+    fn mismatch() {
+        __deserialize::<BTreeMap<String, u32>>("a: 1\nb: two\n", "inline.yaml");
+    }
+}
@@ -0,0 +1,500 @@
+//! Block-style YAML emitter for configured serialization.
+//!
+//! `serde_yaml` offers no control over indentation or the formatting of
+//! floats, so these two serializer settings are applied by emitting a
+//! [`Value`] here instead; the other settings post-process the [`Value`] and
+//! still emit through `serde_yaml`. Scalars are formatted by `serde_yaml`
+//! itself, and the output matches it byte for byte at the default width.
+
+use std::fmt::Write;
+
+use serde_yaml::{value::TaggedValue, Mapping, Number, Value};
+
+use crate::ser::FloatFormat;
+
+pub(crate) struct Emitter {
+    pub(crate) indent: usize,
+    pub(crate) float_format: Option<FloatFormat>,
+}
+
+impl Emitter {
+    pub(crate) fn emit(&self, value: &Value) -> Result<String, serde_yaml::Error> {
+        let mut out = String::new();
+        self.node(&mut out, value, 0, false)?;
+        if !out.ends_with('\n') {
+            out.push('\n');
+        }
+        Ok(out)
+    }
+
+    /// Write `value` at indentation `indent`, the cursor being already placed
+    /// after an indicator (`key:`, `-`, `?`) when `inline` is set.
+    fn node(
+        &self,
+        out: &mut String,
+        value: &Value,
+        indent: usize,
+        inline: bool,
+    ) -> Result<(), serde_yaml::Error> {
+        match value {
+            Value::Mapping(mapping) if !mapping.is_empty() => {
+                self.mapping(out, mapping, indent, inline)
+            }
+            Value::Sequence(sequence) if !sequence.is_empty() => {
+                for (index, item) in sequence.iter().enumerate() {
+                    if index > 0 || !inline {
+                        pad(out, indent);
+                    }
+                    self.entry(out, "-", item, indent)?;
+                }
+                Ok(())
+            }
+            Value::Tagged(tagged) => self.tagged(out, tagged, indent),
+            scalar => {
+                let scalar = self.scalar(scalar, indent)?;
+                out.push_str(&scalar);
+                out.push('\n');
+                Ok(())
+            }
+        }
+    }
+
+    fn mapping(
+        &self,
+        out: &mut String,
+        mapping: &Mapping,
+        indent: usize,
+        inline: bool,
+    ) -> Result<(), serde_yaml::Error> {
+        for (index, (key, value)) in mapping.iter().enumerate() {
+            if index > 0 || !inline {
+                pad(out, indent);
+            }
+
+            // libyaml only writes single-line scalars as implicit keys.
+            if is_block_collection(key) || is_multi_line(key) {
+                self.entry(out, "?", key, indent)?;
+                pad(out, indent);
+                self.entry(out, ":", value, indent)?;
+                continue;
+            }
+
+            out.push_str(&self.scalar(key, indent)?);
+            out.push(':');
+            match value {
+                Value::Mapping(mapping) if !mapping.is_empty() => {
+                    out.push('\n');
+                    self.mapping(out, mapping, indent + self.indent, false)?;
+                }
+                // Sequences are not indented under their key, like libyaml.
+                Value::Sequence(sequence) if !sequence.is_empty() => {
+                    out.push('\n');
+                    self.node(out, value, indent, false)?;
+                }
+                value => {
+                    out.push(' ');
+                    self.node(out, value, indent, true)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Write an indicator followed by `value`, whose nested content is
+    /// indented one level deeper than `indent`.
+    fn entry(
+        &self,
+        out: &mut String,
+        indicator: &str,
+        value: &Value,
+        indent: usize,
+    ) -> Result<(), serde_yaml::Error> {
+        out.push_str(indicator);
+        if is_block_collection(value) {
+            pad(out, self.indent.saturating_sub(1).max(1));
+            self.node(out, value, indent + self.indent, true)
+        } else {
+            out.push(' ');
+            self.node(out, value, indent, true)
+        }
+    }
+
+    fn tagged(
+        &self,
+        out: &mut String,
+        tagged: &TaggedValue,
+        indent: usize,
+    ) -> Result<(), serde_yaml::Error> {
+        out.push_str(&tagged.tag.to_string());
+        if is_block_collection(&tagged.value) {
+            out.push('\n');
+            self.node(out, &tagged.value, indent + self.indent, false)
+        } else {
+            out.push(' ');
+            self.node(out, &tagged.value, indent, true)
+        }
+    }
+
+    /// Format a scalar or an empty collection, with the content of block
+    /// scalars indented one level deeper than `indent`.
+    fn scalar(&self, value: &Value, indent: usize) -> Result<String, serde_yaml::Error> {
+        match value {
+            Value::Number(number) if number.is_f64() => Ok(self.float(number)),
+            Value::String(string) => Ok(self.string(string, indent)),
+            Value::Tagged(tagged) => Ok(format!(
+                "{} {}",
+                tagged.tag,
+                self.scalar(&tagged.value, indent)?
+            )),
+            Value::Mapping(_) | Value::Sequence(_) if is_block_collection(value) => Ok(flow(value)),
+            value => Ok(serde_yaml::to_string(value)?.trim_end().to_owned()),
+        }
+    }
+
+    fn float(&self, number: &Number) -> String {
+        let value = number.as_f64().unwrap_or_default();
+        match self.float_format {
+            Some(format) if value.is_finite() => format.format(value),
+            _ => serde_yaml::to_string(number)
+                .map(|number| number.trim_end().to_owned())
+                .unwrap_or_default(),
+        }
+    }
+
+    fn string(&self, string: &str, indent: usize) -> String {
+        let formatted = serde_yaml::to_string(string).unwrap_or_default();
+        let mut lines = formatted.lines();
+        let header = lines.next().unwrap_or_default();
+
+        match header.strip_prefix('|') {
+            Some(indicators) => {
+                // An explicit indentation indicator is relative to the
+                // enclosing node, which is indented by our width.
+                let mut block = match indicators.strip_prefix('2') {
+                    Some(chomping) => format!("|{}{chomping}", self.indent),
+                    None => header.to_owned(),
+                };
+                for line in lines {
+                    block.push('\n');
+                    if let Some(content) = line.strip_prefix("  ") {
+                        pad(&mut block, indent + self.indent);
+                        block.push_str(content);
+                    }
+                }
+                block
+            }
+            None => formatted.trim_end_matches('\n').to_owned(),
+        }
+    }
+}
+
+impl FloatFormat {
+    pub(crate) fn format(&self, value: f64) -> String {
+        let mut formatted = match self.precision {
+            Some(precision) => format!("{value:.precision$}"),
+            None => value.to_string(),
+        };
+        if self.precision == Some(0) {
+            // `2.` is still a float when read back.
+            formatted.push('.');
+            return formatted;
+        }
+
+        if self.trim_trailing_zeros && formatted.contains('.') {
+            let trimmed = formatted.trim_end_matches('0').len();
+            formatted.truncate(trimmed);
+        }
+        if formatted.ends_with('.') || !formatted.contains('.') {
+            // Keep the value a float when read back.
+            formatted.truncate(formatted.trim_end_matches('.').len());
+            formatted.push_str(".0");
+        }
+        formatted
+    }
+}
+
+fn is_block_collection(value: &Value) -> bool {
+    match value {
+        Value::Mapping(mapping) => !mapping.is_empty(),
+        Value::Sequence(sequence) => !sequence.is_empty(),
+        _ => false,
+    }
+}
+
+fn is_multi_line(value: &Value) -> bool {
+    match value {
+        Value::String(string) => string.contains(['\n', '\r', '\u{85}', '\u{2028}', '\u{2029}']),
+        Value::Tagged(tagged) => is_multi_line(&tagged.value),
+        _ => false,
+    }
+}
+
+fn pad(out: &mut String, indent: usize) {
+    out.extend(std::iter::repeat_n(' ', indent));
+}
+
+fn flow(value: &Value) -> String {
+    match value {
+        Value::Mapping(mapping) => {
+            let entries: Vec<String> = mapping
+                .iter()
+                .map(|(key, value)| format!("{}: {}", flow(key), flow(value)))
+                .collect();
+            format!("{{{}}}", entries.join(", "))
+        }
+        Value::Sequence(sequence) => {
+            let items: Vec<String> = sequence.iter().map(flow).collect();
+            format!("[{}]", items.join(", "))
+        }
+        Value::Tagged(tagged) => format!("{} {}", tagged.tag, flow(&tagged.value)),
+        Value::String(string) => double_quoted(string),
+        scalar => serde_yaml::to_string(scalar)
+            .map(|scalar| scalar.trim_end().to_owned())
+            .unwrap_or_default(),
+    }
+}
+
+fn double_quoted(string: &str) -> String {
+    let mut quoted = String::with_capacity(string.len() + 2);
+    quoted.push('"');
+    for c in string.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(quoted, "\\u{:04x}", c as u32);
+            }
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn emit(source: &str, indent: usize) -> String {
+        let value: Value = serde_yaml::from_str(source).unwrap();
+        Emitter {
+            indent,
+            float_format: None,
+        }
+        .emit(&value)
+        .unwrap()
+    }
+
+    #[test]
+    fn matches_serde_yaml() {
+        let sources = [
+            "a: {b: [1, [2, 3], {c: d}], e: {}}\nf: []\n",
+            "? [x, y]\n: z\n",
+            "- a: 1\n  b: !t\n    c: 1\n- !u [1]\n- !w x\n- s: \"l1\\nl2\"\n",
+            "text: \"  lead\\nx\"\nempty: ''\nquoted: '1.0'\nnull: ~\n",
+            "[1.5, 1e20, .nan, true]",
+            "\"multi\\nline\\n\"",
+        ];
+
+        for source in sources {
+            let value: Value = serde_yaml::from_str(source).unwrap();
+            let emitted = emit(source, 2);
+            assert_eq!(
+                serde_yaml::from_str::<Value>(&emitted).unwrap(),
+                value,
+                "{emitted}"
+            );
+            assert_eq!(emitted, serde_yaml::to_string(&value).unwrap());
+        }
+    }
+
+    #[test]
+    fn tricky_scalars() {
+        // Strings libyaml has to quote, escape or write as block scalars.
+        let scalars = [
+            "yes",
+            "no",
+            "on",
+            "y",
+            "1e3",
+            "0x1A",
+            "0o17",
+            "+1",
+            ".5",
+            "1_000",
+            "~",
+            "null",
+            "true",
+            "",
+            " ",
+            "lead ",
+            " trail",
+            "-",
+            "- a",
+            "-a",
+            "?",
+            "? a",
+            ":",
+            "a:",
+            "a: b",
+            ":a",
+            "#",
+            "a #b",
+            "a#b",
+            "@",
+            "`",
+            "%",
+            "!",
+            "&",
+            "*",
+            "|",
+            ">",
+            "'",
+            "\"",
+            "a'b",
+            "a\"b",
+            "[",
+            "]",
+            "{",
+            "}",
+            ",",
+            "a, b",
+            "\t",
+            "a\tb",
+            "\n",
+            "a\nb",
+            "a\nb\n",
+            "a\n\n",
+            "\na",
+            " a\nb",
+            "\r",
+            "a\rb",
+            "\u{7}",
+            "\u{1b}",
+            "\u{7f}",
+            "\u{85}",
+            "\u{a0}",
+            "\u{FEFF}",
+            "\u{2028}",
+            "\u{2029}",
+            "\u{1F600}",
+            "é",
+            "\\",
+        ];
+
+        for scalar in scalars {
+            let string = Value::String(scalar.to_owned());
+            let mut mapping = Mapping::new();
+            mapping.insert(string.clone(), string.clone());
+            mapping.insert("key".into(), string.clone());
+            let values = [
+                string.clone(),
+                Value::Mapping(mapping),
+                Value::Sequence(vec![string.clone(), string]),
+            ];
+
+            for value in values {
+                let emitted = Emitter {
+                    indent: 2,
+                    float_format: None,
+                }
+                .emit(&value)
+                .unwrap();
+                assert_eq!(
+                    emitted,
+                    serde_yaml::to_string(&value).unwrap(),
+                    "{scalar:?}"
+                );
+                for indent in [2, 4] {
+                    let emitted = Emitter {
+                        indent,
+                        float_format: None,
+                    }
+                    .emit(&value)
+                    .unwrap();
+                    assert_eq!(
+                        serde_yaml::from_str::<Value>(&emitted).unwrap(),
+                        value,
+                        "{emitted}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn float_format() {
+        let format = |format: FloatFormat, value: f64| format.format(value);
+
+        assert_eq!(format(FloatFormat::new(), 1e-5), "0.00001");
+        assert_eq!(format(FloatFormat::new(), 1e20), "100000000000000000000.0");
+        assert_eq!(format(FloatFormat::new().precision(3), 1.5), "1.500");
+        assert_eq!(
+            format(
+                FloatFormat::new().precision(3).trim_trailing_zeros(true),
+                1.5
+            ),
+            "1.5"
+        );
+        assert_eq!(
+            format(
+                FloatFormat::new().precision(2).trim_trailing_zeros(true),
+                2.001
+            ),
+            "2.0"
+        );
+        assert_eq!(format(FloatFormat::new().precision(0), 2.5), "2.");
+        assert_eq!(format(FloatFormat::new().precision(0), -3.7), "-4.");
+        assert_eq!(
+            format(
+                FloatFormat::new().precision(0).trim_trailing_zeros(true),
+                10.0
+            ),
+            "10."
+        );
+        assert_eq!(
+            serde_yaml::from_str::<f64>(&format(FloatFormat::new().precision(0), 1e20)).unwrap(),
+            1e20
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let sources = [
+            // Strings that would be read back as other scalars unquoted.
+            "- '1.0'\n- '42'\n- '0x1F'\n- '1e3'\n- '.inf'\n- '.nan'\n- '-'\n",
+            "- 'true'\n- 'false'\n- 'null'\n- '~'\n- ''\n- 'yes'\n- 'off'\n",
+            "'123': '2001-12-14'\n'true': 'null'\n'~': ' padded '\n",
+            // Multi-line strings, in keys, nested values and sequences.
+            "a:\n  b: \"l1\\nl2\\n\"\n  c: \"trailing\\n\\n\"\n  d: \"\\n lead\"\n",
+            "\"k1\\nk2\": \"v1\\nv2\"\nitems: [\"x\\ny\", {\"p\\nq\": 1}]\n",
+            // Empty collections at every position.
+            "a: {}\nb: []\nc: [{}, [], {d: {}}]\n? {}\n: []\n",
+            // Tagged values of every kind.
+            "a: !point {x: 1.5, y: -2.0}\nb: !list [!str '1', !bool true]\nc: !empty {}\nd: !text \"l1\\nl2\"\n",
+            "- !wrap\n  - !inner {k: v}\n- !!binary aGk=\n",
+        ];
+
+        for source in sources {
+            let value: Value = serde_yaml::from_str(source).unwrap();
+            for indent in [2, 4] {
+                for float_format in [None, Some(FloatFormat::new().precision(1))] {
+                    let emitted = Emitter {
+                        indent,
+                        float_format,
+                    }
+                    .emit(&value)
+                    .unwrap();
+                    assert_eq!(
+                        serde_yaml::from_str::<Value>(&emitted).unwrap(),
+                        value,
+                        "{emitted}"
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,428 @@
+//! Example document generation from JSON Schemas.
+//!
+//! Requires the `schemars` feature. [`example`] produces a YAML document that
+//! satisfies the [`JsonSchema`] of a type — honoring `default`, `examples`,
+//! `enum`, string formats and numeric ranges — so frontend and QA teams can
+//! get valid sample payloads for `Yaml<T>` endpoints.
+//!
+//! Strings and arrays are at most 256 characters or items long, so schemas
+//! requiring longer ones get an example that falls short of them.
+
+use schemars::{JsonSchema, Schema};
+use serde_json::{Map, Value as JsonValue};
+use serde_yaml::Value;
+
+use crate::Yaml;
+
+/// Generate an example document for `T`.
+///
+/// # Example
+///
+/// ```
+/// use axum_yaml::example;
+/// use schemars::JsonSchema;
+///
+/// #[derive(JsonSchema)]
+/// struct Deployment {
+///     name: String,
+///     #[schemars(range(min = 1, max = 10))]
+///     replicas: u8,
+/// }
+///
+/// let example = example::example::<Deployment>();
+/// assert_eq!(example["replicas"], 1);
+/// ```
+pub fn example<T>() -> Value
+where
+    T: JsonSchema,
+{
+    example_from_schema(&schemars::schema_for!(T))
+}
+
+/// Generate an example document satisfying `schema`.
+pub fn example_from_schema(schema: &Schema) -> Value {
+    let example = Generator {
+        root: schema.as_value(),
+    }
+    .generate(schema.as_value(), &mut vec!["#"]);
+    serde_yaml::to_value(example).unwrap_or(Value::Null)
+}
+
+/// Handler serving an example document for `T`.
+///
+/// ```no_run
+/// use axum::{routing::get, Router};
+/// use axum_yaml::example;
+/// # #[derive(schemars::JsonSchema)]
+/// # struct Deployment {}
+///
+/// let app = Router::new().route(
+///     "/deployments/example.yaml",
+///     get(example::example_handler::<Deployment>),
+/// );
+/// # let _: Router = app;
+/// ```
+pub async fn example_handler<T>() -> Yaml<Value>
+where
+    T: JsonSchema,
+{
+    Yaml(example::<T>())
+}
+
+/// Largest number of items, or characters of a string, generated for a
+/// `minItems` or `minLength`, keeping untrusted schemas from allocating
+/// without bound.
+const MAX_LEN: usize = 256;
+
+struct Generator<'a> {
+    root: &'a JsonValue,
+}
+
+impl<'a> Generator<'a> {
+    fn generate(&self, schema: &'a JsonValue, refs: &mut Vec<&'a str>) -> JsonValue {
+        let Some(schema) = schema.as_object() else {
+            return JsonValue::Null;
+        };
+
+        if let Some(example) = schema
+            .get("examples")
+            .and_then(JsonValue::as_array)
+            .and_then(|examples| examples.first())
+        {
+            return example.clone();
+        }
+        for keyword in ["const", "default"] {
+            if let Some(value) = schema.get(keyword) {
+                return value.clone();
+            }
+        }
+        if let Some(value) = schema
+            .get("enum")
+            .and_then(JsonValue::as_array)
+            .and_then(|values| values.first())
+        {
+            return value.clone();
+        }
+
+        // Recursive references resolve to `null` so that recursive types
+        // produce finite examples.
+        if let Some(reference) = schema.get("$ref").and_then(JsonValue::as_str) {
+            let target = reference
+                .strip_prefix('#')
+                .and_then(|pointer| self.root.pointer(pointer));
+            return match target {
+                Some(target) if !refs.contains(&reference) => {
+                    refs.push(reference);
+                    let example = self.generate(target, refs);
+                    refs.pop();
+                    example
+                }
+                _ => JsonValue::Null,
+            };
+        }
+
+        if let Some(all_of) = schema.get("allOf").and_then(JsonValue::as_array) {
+            let mut merged = Map::new();
+            for schema in all_of {
+                match self.generate(schema, refs) {
+                    JsonValue::Object(object) => merged.extend(object),
+                    other => return other,
+                }
+            }
+            return JsonValue::Object(merged);
+        }
+
+        for keyword in ["oneOf", "anyOf"] {
+            if let Some(options) = schema.get(keyword).and_then(JsonValue::as_array) {
+                let option = options
+                    .iter()
+                    .find(|option| option.get("type") != Some(&JsonValue::from("null")))
+                    .or(options.first());
+                return option.map_or(JsonValue::Null, |option| self.generate(option, refs));
+            }
+        }
+
+        let ty = match schema.get("type") {
+            Some(JsonValue::String(ty)) => Some(ty.as_str()),
+            Some(JsonValue::Array(types)) => types
+                .iter()
+                .filter_map(JsonValue::as_str)
+                .find(|ty| *ty != "null")
+                .or(Some("null")),
+            _ if schema.contains_key("properties") => Some("object"),
+            _ if schema.contains_key("items") || schema.contains_key("prefixItems") => {
+                Some("array")
+            }
+            _ => None,
+        };
+
+        match ty {
+            Some("object") => self.object(schema, refs),
+            Some("array") => self.array(schema, refs),
+            Some("string") => string(schema),
+            Some("integer") => integer(schema),
+            Some("number") => number(schema),
+            Some("boolean") => JsonValue::Bool(false),
+            _ => JsonValue::Null,
+        }
+    }
+
+    fn object(&self, schema: &'a Map<String, JsonValue>, refs: &mut Vec<&'a str>) -> JsonValue {
+        let properties = schema
+            .get("properties")
+            .and_then(JsonValue::as_object)
+            .into_iter()
+            .flatten()
+            .map(|(name, schema)| (name.clone(), self.generate(schema, refs)))
+            .collect();
+        JsonValue::Object(properties)
+    }
+
+    fn array(&self, schema: &'a Map<String, JsonValue>, refs: &mut Vec<&'a str>) -> JsonValue {
+        // Draft 2020-12 `prefixItems` and `items`, or the older tuple form of
+        // `items` with `additionalItems`.
+        let (prefix, items) = match schema.get("items") {
+            Some(JsonValue::Array(prefix)) => (prefix.as_slice(), schema.get("additionalItems")),
+            items => (
+                schema
+                    .get("prefixItems")
+                    .and_then(JsonValue::as_array)
+                    .map_or(&[][..], Vec::as_slice),
+                items,
+            ),
+        };
+        let closed = items == Some(&JsonValue::Bool(false));
+        let count = |keyword| {
+            schema
+                .get(keyword)
+                .and_then(JsonValue::as_u64)
+                .map(|count| usize::try_from(count).unwrap_or(usize::MAX))
+        };
+
+        // Show one item of the `items` schema unless fewer are allowed.
+        let shown = prefix.len().max(usize::from(items.is_some() && !closed));
+        let mut len = count("minItems").unwrap_or(0).max(shown);
+        if let Some(max) = count("maxItems") {
+            len = len.min(max);
+        }
+        if closed {
+            len = len.min(prefix.len());
+        }
+        len = len.min(MAX_LEN);
+
+        let mut array: Vec<_> = prefix
+            .iter()
+            .take(len)
+            .map(|schema| self.generate(schema, refs))
+            .collect();
+        if array.len() < len {
+            // Without an `items` schema, any item is allowed.
+            let item = items.map_or(JsonValue::Null, |items| self.generate(items, refs));
+            array.resize(len, item);
+        }
+        JsonValue::Array(array)
+    }
+}
+
+fn string(schema: &Map<String, JsonValue>) -> JsonValue {
+    let formatted = match schema.get("format").and_then(JsonValue::as_str) {
+        Some("date-time") => Some("1970-01-01T00:00:00Z"),
+        Some("date") => Some("1970-01-01"),
+        Some("time") => Some("00:00:00"),
+        Some("uuid") => Some("00000000-0000-0000-0000-000000000000"),
+        Some("email") => Some("user@example.com"),
+        Some("uri" | "url") => Some("https://example.com/"),
+        Some("hostname") => Some("example.com"),
+        Some("ipv4") => Some("127.0.0.1"),
+        Some("ipv6") => Some("::1"),
+        _ => None,
+    };
+    if let Some(formatted) = formatted {
+        return formatted.into();
+    }
+
+    let min = schema
+        .get("minLength")
+        .and_then(JsonValue::as_u64)
+        .unwrap_or(0) as usize;
+    let max = schema
+        .get("maxLength")
+        .and_then(JsonValue::as_u64)
+        .map_or(usize::MAX, |max| max as usize);
+
+    let mut value = String::from("string");
+    let min = min.min(MAX_LEN);
+    if value.len() < min {
+        value.extend(std::iter::repeat_n('x', min - value.len()));
+    }
+    value.truncate(max);
+    value.into()
+}
+
+fn integer(schema: &Map<String, JsonValue>) -> JsonValue {
+    let bound = |keyword| schema.get(keyword).and_then(JsonValue::as_f64);
+
+    let value = if let Some(min) = bound("minimum") {
+        min.ceil()
+    } else if let Some(min) = bound("exclusiveMinimum") {
+        min.floor() + 1.0
+    } else if let Some(max) = bound("maximum").filter(|max| *max < 0.0) {
+        max.floor()
+    } else if let Some(max) = bound("exclusiveMaximum").filter(|max| *max <= 0.0) {
+        max.ceil() - 1.0
+    } else {
+        0.0
+    };
+    (value as i64).into()
+}
+
+fn number(schema: &Map<String, JsonValue>) -> JsonValue {
+    let bound = |keyword| schema.get(keyword).and_then(JsonValue::as_f64);
+
+    let value = if let Some(min) = bound("minimum") {
+        min
+    } else if let Some(min) = bound("exclusiveMinimum") {
+        min + bound("exclusiveMaximum")
+            .or(bound("maximum"))
+            .map_or(1.0, |max| (max - min) / 2.0)
+    } else if let Some(max) = bound("maximum").filter(|max| *max < 0.0) {
+        max
+    } else if let Some(max) = bound("exclusiveMaximum").filter(|max| *max <= 0.0) {
+        max - 1.0
+    } else {
+        0.0
+    };
+    value.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde::Deserialize;
+
+    #[allow(dead_code)]
+    #[derive(Deserialize, JsonSchema)]
+    #[serde(rename_all = "lowercase")]
+    enum Kind {
+        Web,
+        Worker,
+    }
+
+    #[allow(dead_code)]
+    #[derive(Deserialize, JsonSchema)]
+    struct Container {
+        #[schemars(length(min = 8))]
+        image: String,
+        #[schemars(range(min = 1024))]
+        port: u16,
+        #[schemars(extend("exclusiveMinimum" = 0.0, "maximum" = 1.0))]
+        cpu: f64,
+    }
+
+    #[allow(dead_code)]
+    #[derive(Deserialize, JsonSchema)]
+    struct Deployment {
+        #[schemars(extend("format" = "uuid"))]
+        id: String,
+        kind: Kind,
+        #[serde(default = "default_replicas")]
+        replicas: u8,
+        containers: Vec<Container>,
+        parent: Option<Box<Deployment>>,
+    }
+
+    fn default_replicas() -> u8 {
+        3
+    }
+
+    #[test]
+    fn example_deserializes() {
+        let example = example::<Deployment>();
+        let expected: Value = serde_yaml::from_str(
+            r#"
+            id: 00000000-0000-0000-0000-000000000000
+            kind: web
+            replicas: 3
+            containers:
+            - image: stringxx
+              port: 1024
+              cpu: 0.5
+            parent: null
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(example, expected);
+        serde_yaml::from_value::<Deployment>(example).unwrap();
+    }
+
+    fn generate(schema: JsonValue) -> JsonValue {
+        let schema = Schema::try_from(schema).unwrap();
+        serde_json::to_value(example_from_schema(&schema)).unwrap()
+    }
+
+    #[test]
+    fn arrays() {
+        use serde_json::json;
+
+        let string = json!({"type": "string"});
+        assert_eq!(generate(json!({"items": string})), json!(["string"]));
+        assert_eq!(generate(json!({"items": string, "maxItems": 0})), json!([]));
+        assert_eq!(
+            generate(json!({"items": string, "minItems": 2, "maxItems": 3})),
+            json!(["string", "string"])
+        );
+        assert_eq!(
+            generate(json!({"type": "array", "minItems": 2})),
+            json!([null, null])
+        );
+        assert_eq!(
+            generate(json!({"items": string, "minItems": u64::MAX}))
+                .as_array()
+                .unwrap()
+                .len(),
+            MAX_LEN
+        );
+
+        let prefix = json!([{"type": "integer", "minimum": 1}, {"type": "boolean"}]);
+        assert_eq!(generate(json!({"prefixItems": prefix})), json!([1, false]));
+        assert_eq!(
+            generate(json!({"prefixItems": prefix, "items": string, "minItems": 3})),
+            json!([1, false, "string"])
+        );
+        assert_eq!(
+            generate(json!({"prefixItems": prefix, "items": false, "minItems": 3})),
+            json!([1, false])
+        );
+        assert_eq!(
+            generate(json!({"prefixItems": prefix, "maxItems": 1})),
+            json!([1])
+        );
+        assert_eq!(
+            generate(json!({"items": prefix, "additionalItems": false})),
+            json!([1, false])
+        );
+    }
+
+    #[test]
+    fn strings() {
+        use serde_json::json;
+
+        assert_eq!(
+            generate(json!({"type": "string", "minLength": 10})),
+            json!("stringxxxx")
+        );
+        assert_eq!(
+            generate(json!({"type": "string", "maxLength": 3})),
+            json!("str")
+        );
+        assert_eq!(
+            generate(json!({"type": "string", "minLength": u64::MAX}))
+                .as_str()
+                .unwrap()
+                .len(),
+            MAX_LEN
+        );
+    }
+}
@@ -0,0 +1,362 @@
+//! Extraction of documents along with the fields their type does not know.
+
+use std::cell::RefCell;
+use std::time::Instant;
+
+use axum_core::extract::{FromRequest, Request};
+use serde::de::{
+    DeserializeOwned, DeserializeSeed, Deserializer, IntoDeserializer, MapAccess, Visitor,
+};
+use serde_yaml::{Mapping, Value};
+
+use crate::classify::Classified;
+use crate::rejection::*;
+use crate::timing::ParseTiming;
+use crate::YamlConfig;
+
+/// Extractor deserializing the request body like [`Yaml`](crate::Yaml), along
+/// with the fields `T` ignored.
+///
+/// The second element is a mapping holding exactly the keys of the document
+/// that `T` did not consume, nested under their parent keys, so user-defined
+/// extension fields can be stored and echoed back without being typed. Keys
+/// ignored inside sequences are not collected.
+///
+/// # Example
+///
+/// ```no_run
+/// use axum::{routing::post, Router};
+/// use axum_yaml::extra::YamlExtra;
+/// use serde::Deserialize;
+/// use serde_yaml::Value;
+///
+/// #[derive(Deserialize)]
+/// struct Resource {
+///     name: String,
+/// }
+///
+/// async fn create(YamlExtra(resource, extensions): YamlExtra<Resource>) {
+///     // `extensions` holds e.g. `x-owner: alice` for `{name: a, x-owner: alice}`
+/// }
+///
+/// let app = Router::new().route("/resources", post(create));
+/// # let _: Router = app;
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct YamlExtra<T>(pub T, pub Value);
+
+impl<T, S> FromRequest<S> for YamlExtra<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = YamlRejection;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let config = YamlConfig::from_extensions(req.extensions());
+        config
+            .report(async {
+                if config.yaml_content_type(req.headers()) {
+                    let timing = req.extensions().get::<ParseTiming>().cloned();
+                    let bytes = config.read_body(req, state).await?;
+
+                    let start = Instant::now();
+                    let result = config.document_body(bytes).await.and_then(|document| {
+                        config
+                            .deserialize_extra(document)
+                            .map(|(value, extra)| YamlExtra(value, extra))
+                            .map_err(|err| config.error_status(YamlError::data(err).into()))
+                    });
+                    if let Some(timing) = timing {
+                        timing.record(start.elapsed());
+                    }
+                    result
+                } else {
+                    config.drain(req).await;
+                    Err(config.missing_content_type().into())
+                }
+            })
+            .await
+    }
+}
+
+/// Deserialize `document` into `T`, collecting the entries `T` ignored.
+pub(crate) fn deserialize_extra<T>(
+    document: Value,
+    empty_as_none: bool,
+) -> Result<(T, Value), Classified>
+where
+    T: DeserializeOwned,
+{
+    let ignored = RefCell::new(Vec::new());
+    let value = crate::classify::deserialize(Tracked {
+        value: document,
+        path: Vec::new(),
+        ignored: &ignored,
+        empty_as_none,
+    })?;
+
+    let mut extra = Mapping::new();
+    for (path, value) in ignored.into_inner() {
+        insert(&mut extra, &path, value);
+    }
+    Ok((value, Value::Mapping(extra)))
+}
+
+fn insert(mapping: &mut Mapping, path: &[Value], value: Value) {
+    let [key, rest @ ..] = path else {
+        return;
+    };
+    if rest.is_empty() {
+        mapping.insert(key.clone(), value);
+        return;
+    }
+
+    let entry = mapping
+        .entry(key.clone())
+        .or_insert_with(|| Value::Mapping(Mapping::new()));
+    if let Value::Mapping(child) = entry {
+        insert(child, rest, value);
+    }
+}
+
+type Ignored = RefCell<Vec<(Vec<Value>, Value)>>;
+
+/// Deserializer over a document recording the mapping values deserialized
+/// as `IgnoredAny`, which is how derived types skip unknown fields.
+struct Tracked<'a> {
+    value: Value,
+    path: Vec<Value>,
+    ignored: &'a Ignored,
+    empty_as_none: bool,
+}
+
+impl<'de> Deserializer<'de> for Tracked<'_> {
+    type Error = serde_yaml::Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self.value {
+            Value::Mapping(mapping) => {
+                let mut access = TrackedMap {
+                    entries: mapping.into_iter(),
+                    next: None,
+                    path: self.path,
+                    ignored: self.ignored,
+                    empty_as_none: self.empty_as_none,
+                };
+                visitor.visit_map(&mut access)
+            }
+            value => value.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        if !self.path.is_empty() {
+            self.ignored.borrow_mut().push((self.path, self.value));
+        }
+        visitor.visit_unit()
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match &self.value {
+            Value::Null => visitor.visit_none(),
+            Value::String(string) if self.empty_as_none && string.is_empty() => {
+                visitor.visit_none()
+            }
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_any(visitor)
+    }
+
+    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_any(visitor)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.value.deserialize_enum(name, variants, visitor)
+    }
+
+    fn deserialize_unit_struct<V>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.value.deserialize_unit_struct(name, visitor)
+    }
+
+    fn deserialize_tuple_struct<V>(
+        self,
+        name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.value.deserialize_tuple_struct(name, len, visitor)
+    }
+
+    fn deserialize_tuple<V>(self, len: usize, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.value.deserialize_tuple(len, visitor)
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.value.deserialize_seq(visitor)
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit identifier
+    }
+}
+
+struct TrackedMap<'a> {
+    entries: serde_yaml::mapping::IntoIter,
+    next: Option<(Value, Value)>,
+    path: Vec<Value>,
+    ignored: &'a Ignored,
+    empty_as_none: bool,
+}
+
+impl<'de> MapAccess<'de> for TrackedMap<'_> {
+    type Error = serde_yaml::Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        let Some((key, value)) = self.entries.next() else {
+            return Ok(None);
+        };
+        let result = seed.deserialize(key.clone().into_deserializer());
+        self.next = Some((key, value));
+        result.map(Some)
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let (key, value) = self.next.take().ok_or_else(|| {
+            <Self::Error as serde::de::Error>::custom("value requested before its key")
+        })?;
+
+        let mut path = self.path.clone();
+        path.push(key);
+        seed.deserialize(Tracked {
+            value,
+            path,
+            ignored: self.ignored,
+            empty_as_none: self.empty_as_none,
+        })
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.entries.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize)]
+    struct Resource {
+        name: String,
+        spec: Spec,
+        #[serde(default)]
+        tags: Vec<Tag>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Spec {
+        replicas: u32,
+        image: Option<String>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Tag {
+        #[allow(dead_code)]
+        key: String,
+    }
+
+    #[test]
+    fn unknown_fields() {
+        let document = serde_yaml::from_str(
+            "name: web\nx-owner: alice\nspec:\n  replicas: 2\n  image: ''\n  \
+             x-notes: {a: 1}\ntags:\n- key: a\n  x-ignored: b\n",
+        )
+        .unwrap();
+
+        let (resource, extra) = deserialize_extra::<Resource>(document, true).unwrap();
+        assert_eq!(resource.name, "web");
+        assert_eq!(resource.spec.replicas, 2);
+        assert_eq!(resource.spec.image, None);
+        assert_eq!(resource.tags.len(), 1);
+        assert_eq!(
+            extra,
+            serde_yaml::from_str::<Value>("x-owner: alice\nspec:\n  x-notes: {a: 1}\n").unwrap()
+        );
+    }
+
+    #[test]
+    fn errors_keep_their_path() {
+        let document = serde_yaml::from_str("name: web\nspec:\n  replicas: x\n").unwrap();
+        let err = deserialize_extra::<Resource>(document, false).unwrap_err();
+        assert_eq!(err.error.path().to_string(), "spec.replicas");
+        assert_eq!(err.kind, YamlErrorKind::InvalidType);
+    }
+}
@@ -0,0 +1,270 @@
+//! Field filtering of serialized responses.
+//!
+//! A [`FieldFilter`] hides paths of a response document depending on who is
+//! looking at it, so one set of types can serve every role instead of a
+//! parallel DTO per role. Authentication middleware inserts the filter for the
+//! current viewer into the request extensions; handlers extract it and wrap
+//! their responses with [`FieldFilter::yaml`].
+//!
+//! The filter fails closed: extracting it from a request without one, e.g.
+//! on a route the middleware does not cover, is rejected with
+//! `500 Internal Server Error` rather than serving every field.
+//!
+//! Paths can be hidden one by one with [`FieldFilter::hide`], or declared
+//! once per type by implementing [`FieldRules`], naming the role a viewer
+//! needs to see each path:
+//!
+//! ```no_run
+//! use axum::{extract::Request, middleware::Next, response::Response, routing::get, Router};
+//! use axum_yaml::filter::{FieldFilter, FieldRules, FilteredYaml};
+//! use serde::Serialize;
+//!
+//! #[derive(Serialize)]
+//! struct Service {
+//!     name: String,
+//!     secrets: Vec<String>,
+//! }
+//!
+//! impl FieldRules for Service {
+//!     const RULES: &'static [(&'static str, &'static str)] = &[("secrets", "admin")];
+//! }
+//!
+//! async fn viewer(mut req: Request, next: Next) -> Response {
+//!     let mut filter = FieldFilter::new();
+//!     if req.headers().contains_key("x-admin") {
+//!         filter = filter.role("admin");
+//!     }
+//!     req.extensions_mut().insert(filter);
+//!     next.run(req).await
+//! }
+//!
+//! async fn get_service(filter: FieldFilter) -> FilteredYaml<Service> {
+//!     filter.rules::<Service>().yaml(Service {
+//!         name: "web".to_owned(),
+//!         secrets: vec!["hunter2".to_owned()],
+//!     })
+//! }
+//!
+//! let app: Router = Router::new()
+//!     .route("/service", get(get_service))
+//!     .layer(axum::middleware::from_fn(viewer));
+//! ```
+
+use axum_core::{
+    extract::FromRequestParts,
+    response::{IntoResponse, Response},
+};
+use http::request::Parts;
+use serde::Serialize;
+
+use crate::macros::__define_rejection as define_rejection;
+use crate::path::YamlPath;
+use crate::{ser, Yaml, YamlConfig};
+
+/// Paths of a type that only viewers with a given role may see.
+///
+/// Applied to a [`FieldFilter`] with [`FieldFilter::rules`].
+pub trait FieldRules {
+    /// Pairs of a [`YamlPath`] in the serialized type and the role a viewer
+    /// needs to see the nodes it selects.
+    const RULES: &'static [(&'static str, &'static str)];
+}
+
+/// Paths hidden from the serialized response for the current viewer.
+///
+/// When used as an extractor, it is cloned from the request extensions and
+/// rejects the request with [`MissingFieldFilter`] if the extensions don't
+/// contain one.
+#[derive(Debug, Clone, Default)]
+pub struct FieldFilter {
+    hidden: Vec<YamlPath>,
+    roles: Vec<String>,
+}
+
+impl FieldFilter {
+    /// Create a filter hiding nothing, for a viewer without roles.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Grant the viewer `role`, showing the paths [`FieldRules`] reserve to
+    /// it.
+    pub fn role(mut self, role: impl Into<String>) -> Self {
+        self.roles.push(role.into());
+        self
+    }
+
+    /// Also hide the paths of `T` reserved to roles the viewer lacks.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a path of the rules is not a valid [`YamlPath`].
+    #[track_caller]
+    pub fn rules<T: FieldRules>(mut self) -> Self {
+        for (path, role) in T::RULES {
+            if !self.roles.iter().any(|granted| granted == role) {
+                self.hidden.push(YamlPath::new(path));
+            }
+        }
+        self
+    }
+
+    /// Hide every node selected by `path`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `path` is not a valid [`YamlPath`].
+    #[track_caller]
+    pub fn hide(mut self, path: &str) -> Self {
+        self.hidden.push(YamlPath::new(path));
+        self
+    }
+
+    /// Also hide the paths hidden by `other`, and grant its roles.
+    pub fn merge(mut self, other: &FieldFilter) -> Self {
+        self.hidden.extend(other.hidden.iter().cloned());
+        self.roles.extend(other.roles.iter().cloned());
+        self
+    }
+
+    /// Remove the hidden paths from `document`.
+    pub fn apply(&self, document: &mut serde_yaml::Value) {
+        for path in &self.hidden {
+            path.remove(document);
+        }
+    }
+
+    /// Create a YAML response for `value` with the hidden paths removed.
+    pub fn yaml<T>(self, value: T) -> FilteredYaml<T> {
+        FilteredYaml {
+            value,
+            filter: self,
+        }
+    }
+}
+
+impl<S> FromRequestParts<S> for FieldFilter
+where
+    S: Send + Sync,
+{
+    type Rejection = MissingFieldFilter;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        match parts.extensions.get::<Self>() {
+            Some(filter) => Ok(filter.clone()),
+            None => {
+                let config = YamlConfig::from_extensions(&parts.extensions);
+                Err(config.reject(MissingFieldFilter::default()))
+            }
+        }
+    }
+}
+
+define_rejection! {
+    #[status = INTERNAL_SERVER_ERROR]
+    #[body = "Missing field filter for the current viewer"]
+    /// Rejection type for [`FieldFilter`] used if the request extensions
+    /// contain no filter, instead of serving every field.
+    pub struct MissingFieldFilter;
+}
+
+/// YAML response with the paths hidden by a [`FieldFilter`] removed.
+///
+/// Created with [`FieldFilter::yaml`].
+#[derive(Debug, Clone)]
+pub struct FilteredYaml<T> {
+    value: T,
+    filter: FieldFilter,
+}
+
+impl<T> IntoResponse for FilteredYaml<T>
+where
+    T: Serialize,
+{
+    fn into_response(self) -> Response {
+        match serde_yaml::to_value(&self.value) {
+            Ok(mut document) => {
+                self.filter.apply(&mut document);
+                Yaml(document).into_response()
+            }
+            Err(err) => ser::error_response(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use axum::routing::get;
+    use axum::{Extension, Router};
+    use http::StatusCode;
+
+    use crate::test_client::TestClient;
+
+    #[derive(Serialize)]
+    struct Service {
+        name: String,
+        secrets: Vec<String>,
+    }
+
+    async fn get_service(filter: FieldFilter) -> FilteredYaml<Service> {
+        filter.yaml(Service {
+            name: "web".to_owned(),
+            secrets: vec!["hunter2".to_owned()],
+        })
+    }
+
+    impl FieldRules for Service {
+        const RULES: &'static [(&'static str, &'static str)] = &[("secrets", "admin")];
+    }
+
+    #[tokio::test]
+    async fn no_filter() {
+        let app = Router::new().route("/", get(get_service));
+
+        let res = TestClient::new(app).get("/").await;
+        assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(
+            res.text().await,
+            "Missing field filter for the current viewer"
+        );
+    }
+
+    #[tokio::test]
+    async fn no_hidden_path() {
+        let app = Router::new()
+            .route("/", get(get_service))
+            .layer(Extension(FieldFilter::new()));
+
+        let res = TestClient::new(app).get("/").await;
+        assert_eq!(res.text().await, "name: web\nsecrets:\n- hunter2\n");
+    }
+
+    #[tokio::test]
+    async fn rules() {
+        let get_service = |filter: FieldFilter| get_service(filter.rules::<Service>());
+
+        let app = Router::new()
+            .route("/", get(get_service))
+            .layer(Extension(FieldFilter::new().role("viewer")));
+        let res = TestClient::new(app).get("/").await;
+        assert_eq!(res.text().await, "name: web\n");
+
+        let app = Router::new()
+            .route("/", get(get_service))
+            .layer(Extension(FieldFilter::new().role("admin")));
+        let res = TestClient::new(app).get("/").await;
+        assert_eq!(res.text().await, "name: web\nsecrets:\n- hunter2\n");
+    }
+
+    #[tokio::test]
+    async fn hidden_path() {
+        let app = Router::new()
+            .route("/", get(get_service))
+            .layer(Extension(FieldFilter::new().hide("secrets")));
+
+        let res = TestClient::new(app).get("/").await;
+        assert_eq!(res.text().await, "name: web\n");
+    }
+}
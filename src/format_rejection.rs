@@ -0,0 +1,239 @@
+//! Application-defined rejection responses.
+//!
+//! [`FormatRejectionLayer`] hands the [`RejectionBody`] of every rejection of
+//! this crate to a formatter, whose response replaces the one the rejection
+//! rendered. The formatter also gets the request headers, so it can localize
+//! messages according to `Accept-Language` or wrap them in the error envelope
+//! the rest of an API uses, without reimplementing the extractors:
+//!
+//! ```no_run
+//! use axum::{
+//!     response::{IntoResponse, Response},
+//!     routing::post,
+//!     Router,
+//! };
+//! use axum_yaml::{format_rejection::FormatRejectionLayer, rejection::RejectionBody, Yaml};
+//! use http::HeaderMap;
+//! use serde_yaml::Value;
+//!
+//! async fn echo(Yaml(value): Yaml<Value>) -> Yaml<Value> {
+//!     Yaml(value)
+//! }
+//!
+//! fn envelope(rejection: &RejectionBody, _headers: &HeaderMap) -> Response {
+//!     let body = format!("error:\n  code: {}\n", rejection.code());
+//!     (rejection.status(), body).into_response()
+//! }
+//!
+//! let app: Router = Router::new()
+//!     .route("/echo", post(echo))
+//!     .layer(FormatRejectionLayer::new(envelope));
+//! ```
+
+use std::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use axum_core::response::Response;
+use http::{header, HeaderMap, Request};
+use pin_project_lite::pin_project;
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::rejection::RejectionBody;
+
+/// [`Layer`] replacing the responses of the rejections of this crate with the
+/// response of a formatter.
+///
+/// The headers of the rejection response, such as the `Accept-Post` header
+/// of `415 Unsupported Media Type` responses, are kept unless the formatter
+/// sets them, except for `Content-Type` and `Content-Length`. Other
+/// responses pass through unchanged.
+#[derive(Clone, Copy)]
+pub struct FormatRejectionLayer<F> {
+    formatter: F,
+}
+
+impl<F> FormatRejectionLayer<F>
+where
+    F: Fn(&RejectionBody, &HeaderMap) -> Response + Clone,
+{
+    /// Create a layer formatting rejections with `formatter`, which gets the
+    /// rejection and the headers of the rejected request.
+    pub fn new(formatter: F) -> Self {
+        Self { formatter }
+    }
+}
+
+impl<F> fmt::Debug for FormatRejectionLayer<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FormatRejectionLayer")
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S, F> Layer<S> for FormatRejectionLayer<F>
+where
+    F: Clone,
+{
+    type Service = FormatRejection<S, F>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        FormatRejection {
+            inner,
+            formatter: self.formatter.clone(),
+        }
+    }
+}
+
+/// Middleware created by [`FormatRejectionLayer`].
+#[derive(Clone)]
+pub struct FormatRejection<S, F> {
+    inner: S,
+    formatter: F,
+}
+
+impl<S, F> fmt::Debug for FormatRejection<S, F>
+where
+    S: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FormatRejection")
+            .field("inner", &self.inner)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S, F, ReqBody> Service<Request<ReqBody>> for FormatRejection<S, F>
+where
+    S: Service<Request<ReqBody>, Response = Response>,
+    F: Fn(&RejectionBody, &HeaderMap) -> Response + Clone,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future, F>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
+        ResponseFuture {
+            headers: req.headers().clone(),
+            formatter: self.formatter.clone(),
+            inner: self.inner.call(req),
+        }
+    }
+}
+
+pin_project! {
+    /// Response future for [`FormatRejection`].
+    pub struct ResponseFuture<Fut, F> {
+        #[pin]
+        inner: Fut,
+        headers: HeaderMap,
+        formatter: F,
+    }
+}
+
+impl<Fut, F, E> Future for ResponseFuture<Fut, F>
+where
+    Fut: Future<Output = Result<Response, E>>,
+    F: Fn(&RejectionBody, &HeaderMap) -> Response,
+{
+    type Output = Fut::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let res = std::task::ready!(this.inner.poll(cx))?;
+        let Some(rejection) = res.extensions().get::<RejectionBody>() else {
+            return Poll::Ready(Ok(res));
+        };
+
+        let mut formatted = (this.formatter)(rejection, this.headers);
+        let (parts, _) = res.into_parts();
+        for name in parts.headers.keys() {
+            if name == header::CONTENT_TYPE
+                || name == header::CONTENT_LENGTH
+                || formatted.headers().contains_key(name)
+            {
+                continue;
+            }
+            for value in parts.headers.get_all(name) {
+                formatted.headers_mut().append(name, value.clone());
+            }
+        }
+        Poll::Ready(Ok(formatted))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use axum::response::IntoResponse;
+    use axum::routing::post;
+    use axum::Router;
+    use http::StatusCode;
+    use serde_yaml::Value;
+
+    use crate::test_client::TestClient;
+    use crate::Yaml;
+
+    fn localized(rejection: &RejectionBody, headers: &HeaderMap) -> Response {
+        let french = headers
+            .get(header::ACCEPT_LANGUAGE)
+            .is_some_and(|language| language.as_bytes().starts_with(b"fr"));
+        let message = match rejection.code() {
+            "missing_yaml_content_type" if french => "Type de contenu YAML attendu",
+            _ => rejection.message(),
+        };
+        (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            format!("{}: {message}", rejection.code()),
+        )
+            .into_response()
+    }
+
+    #[tokio::test]
+    async fn format_rejections() {
+        let app = Router::new()
+            .route("/", post(|Yaml(value): Yaml<Value>| async { Yaml(value) }))
+            .layer(FormatRejectionLayer::new(localized));
+        let client = TestClient::new(app);
+
+        let res = client
+            .post("/")
+            .body("a: 1")
+            .header("accept-language", "fr-CH, fr;q=0.9")
+            .await;
+        assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(res.headers()["content-type"], "text/plain; charset=utf-8");
+        assert_eq!(
+            res.headers()["accept-post"],
+            "application/yaml, application/*+yaml"
+        );
+        assert_eq!(
+            res.text().await,
+            "missing_yaml_content_type: Type de contenu YAML attendu"
+        );
+
+        let res = client
+            .post("/")
+            .body("a: [")
+            .header("content-type", "application/yaml")
+            .await;
+        assert!(res.text().await.starts_with("syntax: Failed to"));
+
+        let res = client
+            .post("/")
+            .body("a: 1")
+            .header("content-type", "application/yaml")
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.text().await, "a: 1\n");
+    }
+}
@@ -0,0 +1,322 @@
+//! Documents with interned strings.
+//!
+//! Large documents such as Kubernetes manifests repeat the same keys and
+//! values thousands of times. [`InternedValue`] stores every distinct string
+//! of a document once, behind an `Arc<str>` shared by all its occurrences,
+//! which cuts the memory held by handlers keeping parsed documents around.
+//!
+//! Interning is opt-in: extract `Yaml<InternedValue>` instead of
+//! `Yaml<serde_yaml::Value>`.
+//!
+//! ```
+//! use axum_yaml::{intern::InternedValue, Yaml};
+//!
+//! async fn store(Yaml(manifest): Yaml<InternedValue>) {
+//!     let kind = manifest.get("kind").and_then(InternedValue::as_str);
+//!     // ...
+//! }
+//! ```
+
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::Arc;
+
+use serde::de::{
+    self, DeserializeSeed, Deserializer, EnumAccess, MapAccess, SeqAccess, VariantAccess, Visitor,
+};
+use serde::ser::{SerializeMap, SerializeSeq, Serializer};
+use serde::{Deserialize, Serialize};
+use serde_yaml::value::{Tag, TaggedValue};
+use serde_yaml::{Number, Value};
+
+/// YAML document whose strings are interned.
+///
+/// Every string of a document deserialized at once, keys and values alike,
+/// shares its allocation with the equal strings of the same document.
+#[derive(Debug, Clone, PartialEq)]
+pub enum InternedValue {
+    /// A null value.
+    Null,
+    /// A boolean.
+    Bool(bool),
+    /// An integer or floating point number.
+    Number(Number),
+    /// An interned string.
+    String(Arc<str>),
+    /// A sequence of values.
+    Sequence(Vec<InternedValue>),
+    /// A mapping, in document order.
+    Mapping(Vec<(InternedValue, InternedValue)>),
+    /// A value with a tag such as `!Ref`.
+    Tagged(Box<(Arc<str>, InternedValue)>),
+}
+
+impl InternedValue {
+    /// Get the value of the string key `key` if this is a mapping.
+    pub fn get(&self, key: &str) -> Option<&InternedValue> {
+        match self {
+            Self::Mapping(mapping) => mapping
+                .iter()
+                .find(|(k, _)| k.as_str() == Some(key))
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    /// Get the string if this is a string.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(string) => Some(string),
+            _ => None,
+        }
+    }
+
+    /// Get the shared string if this is a string.
+    pub fn as_arc_str(&self) -> Option<&Arc<str>> {
+        match self {
+            Self::String(string) => Some(string),
+            _ => None,
+        }
+    }
+}
+
+impl From<InternedValue> for Value {
+    fn from(value: InternedValue) -> Self {
+        match value {
+            InternedValue::Null => Value::Null,
+            InternedValue::Bool(b) => Value::Bool(b),
+            InternedValue::Number(n) => Value::Number(n),
+            InternedValue::String(s) => Value::String(s.to_string()),
+            InternedValue::Sequence(sequence) => {
+                Value::Sequence(sequence.into_iter().map(Value::from).collect())
+            }
+            InternedValue::Mapping(mapping) => Value::Mapping(
+                mapping
+                    .into_iter()
+                    .map(|(key, value)| (key.into(), value.into()))
+                    .collect(),
+            ),
+            InternedValue::Tagged(tagged) => {
+                let (tag, value) = *tagged;
+                Value::Tagged(Box::new(TaggedValue {
+                    tag: Tag::new(&*tag),
+                    value: value.into(),
+                }))
+            }
+        }
+    }
+}
+
+impl Serialize for InternedValue {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            Self::Null => serializer.serialize_unit(),
+            Self::Bool(b) => serializer.serialize_bool(*b),
+            Self::Number(n) => n.serialize(serializer),
+            Self::String(s) => serializer.serialize_str(s),
+            Self::Sequence(sequence) => {
+                let mut seq = serializer.serialize_seq(Some(sequence.len()))?;
+                for item in sequence {
+                    seq.serialize_element(item)?;
+                }
+                seq.end()
+            }
+            Self::Mapping(mapping) => {
+                let mut map = serializer.serialize_map(Some(mapping.len()))?;
+                for (key, value) in mapping {
+                    map.serialize_entry(key, value)?;
+                }
+                map.end()
+            }
+            Self::Tagged(tagged) => {
+                // `serde_yaml` recognizes tags written with `collect_str`.
+                struct SerializeTag<'a>(&'a str);
+
+                impl Serialize for SerializeTag<'_> {
+                    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+                    where
+                        S: Serializer,
+                    {
+                        serializer.collect_str(&Tag::new(self.0))
+                    }
+                }
+
+                let (tag, value) = &**tagged;
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry(&SerializeTag(tag), value)?;
+                map.end()
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for InternedValue {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Interner::default().deserialize(deserializer)
+    }
+}
+
+/// Pool of the strings of one document.
+#[derive(Default)]
+struct Interner {
+    strings: HashSet<Arc<str>>,
+}
+
+impl Interner {
+    fn intern(&mut self, string: &str) -> Arc<str> {
+        if let Some(interned) = self.strings.get(string) {
+            return interned.clone();
+        }
+        let interned: Arc<str> = Arc::from(string);
+        self.strings.insert(interned.clone());
+        interned
+    }
+
+    fn deserialize<'de, D>(&mut self, deserializer: D) -> Result<InternedValue, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        InternSeed(self).deserialize(deserializer)
+    }
+}
+
+struct InternSeed<'a>(&'a mut Interner);
+
+impl<'de> DeserializeSeed<'de> for InternSeed<'_> {
+    type Value = InternedValue;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for InternSeed<'_> {
+    type Value = InternedValue;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("any YAML value")
+    }
+
+    fn visit_bool<E>(self, b: bool) -> Result<Self::Value, E> {
+        Ok(InternedValue::Bool(b))
+    }
+
+    fn visit_i64<E>(self, n: i64) -> Result<Self::Value, E> {
+        Ok(InternedValue::Number(n.into()))
+    }
+
+    fn visit_u64<E>(self, n: u64) -> Result<Self::Value, E> {
+        Ok(InternedValue::Number(n.into()))
+    }
+
+    fn visit_f64<E>(self, n: f64) -> Result<Self::Value, E> {
+        Ok(InternedValue::Number(n.into()))
+    }
+
+    fn visit_str<E>(self, s: &str) -> Result<Self::Value, E> {
+        Ok(InternedValue::String(self.0.intern(s)))
+    }
+
+    fn visit_unit<E>(self) -> Result<Self::Value, E> {
+        Ok(InternedValue::Null)
+    }
+
+    fn visit_none<E>(self) -> Result<Self::Value, E> {
+        Ok(InternedValue::Null)
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        self.deserialize(deserializer)
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut sequence = Vec::with_capacity(seq.size_hint().unwrap_or_default());
+        while let Some(item) = seq.next_element_seed(InternSeed(self.0))? {
+            sequence.push(item);
+        }
+        Ok(InternedValue::Sequence(sequence))
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut mapping = Vec::with_capacity(map.size_hint().unwrap_or_default());
+        while let Some(key) = map.next_key_seed(InternSeed(self.0))? {
+            let value = map.next_value_seed(InternSeed(self.0))?;
+            mapping.push((key, value));
+        }
+        Ok(InternedValue::Mapping(mapping))
+    }
+
+    fn visit_enum<A>(self, data: A) -> Result<Self::Value, A::Error>
+    where
+        A: EnumAccess<'de>,
+    {
+        // `serde_yaml` hands tagged values over as enums named by the tag.
+        let (tag, variant) = data.variant::<String>()?;
+        let tag = self.0.intern(&tag);
+        let value = variant.newtype_variant_seed(InternSeed(self.0))?;
+        Ok(InternedValue::Tagged(Box::new((tag, value))))
+    }
+
+    fn visit_bytes<E>(self, _: &[u8]) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Err(E::custom("binary data is not supported"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shared_strings() {
+        let document: InternedValue = serde_yaml::from_str(
+            "- name: web\n  image: nginx\n- name: api\n  image: nginx\n  tag: !Ref web\n",
+        )
+        .unwrap();
+
+        let InternedValue::Sequence(items) = &document else {
+            panic!("expected a sequence");
+        };
+        let web = items[0].get("name").and_then(InternedValue::as_arc_str);
+        let image = items[0].get("image").and_then(InternedValue::as_arc_str);
+        let other_image = items[1].get("image").and_then(InternedValue::as_arc_str);
+        assert!(Arc::ptr_eq(image.unwrap(), other_image.unwrap()));
+
+        let InternedValue::Tagged(tagged) = items[1].get("tag").unwrap() else {
+            panic!("expected a tagged value");
+        };
+        assert!(Arc::ptr_eq(web.unwrap(), tagged.1.as_arc_str().unwrap()));
+    }
+
+    #[test]
+    fn round_trip() {
+        let source = "a: 1\nb:\n- x\n- 1.5\n- null\nc: !Ref d\n";
+        let document: InternedValue = serde_yaml::from_str(source).unwrap();
+
+        assert_eq!(
+            Value::from(document.clone()),
+            serde_yaml::from_str::<Value>(source).unwrap()
+        );
+        assert_eq!(serde_yaml::to_string(&document).unwrap(), source);
+    }
+}
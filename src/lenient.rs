@@ -0,0 +1,254 @@
+//! Reporting of requests accepted only thanks to a lenient mode.
+//!
+//! A body is parsed leniently when its `Content-Type` is a legacy media type
+//! accepted with [`YamlConfig::accept_media_types`] or rewritten by the
+//! [`NormalizeContentTypeLayer`](crate::normalize::NormalizeContentTypeLayer),
+//! or when [`LenientYaml`](crate::LenientYaml) parses it whatever its
+//! `Content-Type` says. Each time, a [`Leniency`] is reported:
+//!
+//! - as a `WARN` event under the `axum_yaml::lenient` target with the
+//!   `tracing` feature, and a `WARN` record with the `log` feature, naming
+//!   the leniency and the `Content-Type` received;
+//! - in an `X-Yaml-Lenient` response header, e.g.
+//!   `X-Yaml-Lenient: legacy-content-type`, when the router is wrapped with
+//!   a [`LenientWarningLayer`], so clients can see what to fix before strict
+//!   parsing is enforced.
+//!
+//! The layer must wrap the `NormalizeContentTypeLayer` to see its rewrites:
+//!
+//! ```no_run
+//! use axum::{routing::post, Router};
+//! use axum_yaml::lenient::LenientWarningLayer;
+//! use axum_yaml::normalize::NormalizeContentTypeLayer;
+//! use axum_yaml::Yaml;
+//! use serde_yaml::Value;
+//!
+//! async fn echo(Yaml(value): Yaml<Value>) -> Yaml<Value> {
+//!     Yaml(value)
+//! }
+//!
+//! let app: Router = Router::new()
+//!     .route("/echo", post(echo))
+//!     .layer(NormalizeContentTypeLayer)
+//!     .layer(LenientWarningLayer);
+//! ```
+
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+
+use http::{header, header::HeaderName, Extensions, HeaderMap, HeaderValue, Request, Response};
+use pin_project_lite::pin_project;
+use tower_layer::Layer;
+use tower_service::Service;
+
+#[cfg(doc)]
+use crate::YamlConfig;
+
+static YAML_LENIENT: HeaderName = HeaderName::from_static("x-yaml-lenient");
+
+/// Lenient mode a request body was accepted with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Leniency {
+    /// The `Content-Type` is a legacy YAML media type, such as `text/yaml`.
+    LegacyContentType,
+    /// The `Content-Type` is missing or does not name YAML, and was ignored.
+    IgnoredContentType,
+}
+
+impl Leniency {
+    /// The value of the `X-Yaml-Lenient` header for this leniency.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::LegacyContentType => "legacy-content-type",
+            Self::IgnoredContentType => "ignored-content-type",
+        }
+    }
+
+    /// Report that the request with `headers` and `extensions` was accepted
+    /// with this leniency.
+    pub(crate) fn report(self, headers: &HeaderMap, extensions: &Extensions) {
+        let content_type = headers
+            .get(header::CONTENT_TYPE)
+            .map(|content_type| String::from_utf8_lossy(content_type.as_bytes()));
+        #[cfg(feature = "tracing")]
+        tracing::warn!(
+            target: "axum_yaml::lenient",
+            leniency = self.as_str(),
+            content_type = content_type.as_deref(),
+            "accepting request leniently",
+        );
+        #[cfg(feature = "log")]
+        log::warn!(
+            target: "axum_yaml::lenient",
+            "accepting request leniently: leniency={} content_type={:?}",
+            self.as_str(),
+            content_type,
+        );
+        #[cfg(not(any(feature = "tracing", feature = "log")))]
+        let _ = content_type;
+
+        if let Some(reported) = extensions.get::<Reported>() {
+            reported.record(self);
+        }
+    }
+}
+
+/// Leniency of the current request, shared between the layer and extractors
+/// through request extensions.
+#[derive(Debug, Clone, Default)]
+struct Reported(Arc<Mutex<Option<Leniency>>>);
+
+impl Reported {
+    fn record(&self, leniency: Leniency) {
+        let mut reported = self.0.lock().unwrap_or_else(|err| err.into_inner());
+        reported.get_or_insert(leniency);
+    }
+
+    fn get(&self) -> Option<Leniency> {
+        *self.0.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+/// [`Layer`] adding an `X-Yaml-Lenient` header to the responses of requests
+/// accepted with a [`Leniency`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LenientWarningLayer;
+
+impl<S> Layer<S> for LenientWarningLayer {
+    type Service = LenientWarning<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        LenientWarning { inner }
+    }
+}
+
+/// Middleware created by [`LenientWarningLayer`].
+#[derive(Debug, Clone)]
+pub struct LenientWarning<S> {
+    inner: S,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for LenientWarning<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request<ReqBody>) -> Self::Future {
+        let reported = Reported::default();
+        req.extensions_mut().insert(reported.clone());
+
+        ResponseFuture {
+            inner: self.inner.call(req),
+            reported,
+        }
+    }
+}
+
+pin_project! {
+    /// Response future for [`LenientWarning`].
+    pub struct ResponseFuture<F> {
+        #[pin]
+        inner: F,
+        reported: Reported,
+    }
+}
+
+impl<F, ResBody, E> Future for ResponseFuture<F>
+where
+    F: Future<Output = Result<Response<ResBody>, E>>,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let mut res = std::task::ready!(this.inner.poll(cx))?;
+        if let Some(leniency) = this.reported.get() {
+            res.headers_mut().insert(
+                YAML_LENIENT.clone(),
+                HeaderValue::from_static(leniency.as_str()),
+            );
+        }
+        Poll::Ready(Ok(res))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use axum::routing::post;
+    use axum::Router;
+    use serde_yaml::Value;
+
+    use crate::normalize::NormalizeContentTypeLayer;
+    use crate::test_client::TestClient;
+    use crate::{LenientYaml, Yaml, YamlConfig};
+
+    async fn lenient(app: Router, content_type: Option<&str>) -> Option<String> {
+        let client = TestClient::new(app);
+        let mut req = client.post("/").body("a: 1");
+        if let Some(content_type) = content_type {
+            req = req.header("content-type", content_type);
+        }
+        let res = req.await;
+        assert_eq!(res.status(), http::StatusCode::OK);
+        res.headers()
+            .get("x-yaml-lenient")
+            .map(|value| value.to_str().unwrap().to_owned())
+    }
+
+    #[tokio::test]
+    async fn legacy_content_type() {
+        let app = Router::new()
+            .route("/", post(|Yaml(value): Yaml<Value>| async { Yaml(value) }))
+            .layer(axum::Extension(
+                YamlConfig::new().accept_media_types(["text/yaml"]),
+            ))
+            .layer(LenientWarningLayer);
+        assert_eq!(lenient(app.clone(), Some("application/yaml")).await, None);
+        assert_eq!(
+            lenient(app, Some("text/yaml")).await.as_deref(),
+            Some("legacy-content-type")
+        );
+
+        let app = Router::new()
+            .route("/", post(|Yaml(value): Yaml<Value>| async { Yaml(value) }))
+            .layer(NormalizeContentTypeLayer)
+            .layer(LenientWarningLayer);
+        assert_eq!(
+            lenient(app, Some("application/x-yaml")).await.as_deref(),
+            Some("legacy-content-type")
+        );
+    }
+
+    #[tokio::test]
+    async fn ignored_content_type() {
+        let app = Router::new()
+            .route(
+                "/",
+                post(|LenientYaml(value): LenientYaml<Value>| async { Yaml(value) }),
+            )
+            .layer(LenientWarningLayer);
+        assert_eq!(lenient(app.clone(), Some("application/yaml")).await, None);
+        assert_eq!(
+            lenient(app.clone(), Some("text/plain")).await.as_deref(),
+            Some("ignored-content-type")
+        );
+        assert_eq!(
+            lenient(app, None).await.as_deref(),
+            Some("ignored-content-type")
+        );
+    }
+}
@@ -1,15 +1,145 @@
-//! YAML extractor for axum
+//! YAML extractors and responses for axum
 //!
-//! This crate provides struct `Yaml` that can be used to extract typed information from request's body.
+//! [`Yaml`] extracts typed documents from request bodies and serializes
+//! responses, with [`serde_yaml`] under the hood. [`YamlConfig`] controls
+//! both directions: installed as a layer, it sets how request bodies are
+//! parsed and validated, how rejections are reported, and, with
+//! [`YamlConfig::serialize`], how responses are written.
 //!
-//! [`serde_yaml`] parser under the hood.
+//! # Modules
+//!
+//! Extractors and responses:
+//!
+//! - [`yaml`]: [`Yaml`] and its variants, such as [`StateYaml`],
+//!   [`LenientYaml`], [`OptionalYaml`], [`LazyYaml`] and [`BytesYaml`].
+//! - [`multi_doc`]: multi-document bodies, streamed or paginated.
+//! - [`bundle`]: a document together with its raw bytes and parse metadata.
+//! - [`any_config`]: YAML, JSON or TOML bodies into the same type.
+//! - [`any_of`]: trying several target types in turn.
+//! - [`extra`]: documents along with the fields their type does not know.
+//! - [`seed`]: stateful deserialization of request bodies.
+//! - [`intern`]: documents with interned strings.
+//! - [`value`]: YAML document types.
+//! - [`media_type`]: vendor media types declared by the exchanged types.
+//! - [`normalize`]: normalization of legacy YAML request content types.
+//! - [`negotiate`]: content negotiation against the `Accept` header, with
+//!   pluggable codecs for formats other than YAML.
+//! - [`prefer`]: the `Prefer: return=minimal` request header.
+//! - [`crud`]: YAML CRUD endpoints for a resource type.
+//! - [`embed`]: typed documents embedded in the binary.
+//!
+//! Validation and rejections:
+//!
+//! - [`policy`]: rejecting forbidden paths or values.
+//! - [`lint`]: scalars whose meaning depends on the YAML version.
+//! - [`path`]: paths selecting nodes of a document.
+//! - [`case`]: case transformation of mapping keys.
+//! - [`de`]: serde helpers for common YAML idioms.
+//! - [`rejection`]: the rejections of the extractors and their format.
+//! - [`format_rejection`]: application-defined rejection responses.
+//! - [`audit`]: auditing of rejected request bodies.
+//! - [`lenient`]: reporting of requests accepted by a lenient mode.
+//!
+//! Responses:
+//!
+//! - [`ser`]: serializer settings, scoped with a layer or per response.
+//! - [`filter`]: field filtering of serialized responses.
+//! - [`dump`]: a debug endpoint dumping the running configuration.
+//! - [`timing`]: `Server-Timing` of parse and serialize durations.
+//!
+//! Schemas and tooling:
+//!
+//! - [`schema`]: schema inference from example documents.
+//!
+//! # Feature flags
+//!
+//! | Feature | Enables |
+//! |---|---|
+//! | `json` | JSON bodies in [`AnyConfig`] and JSON rejection bodies |
+//! | `toml` | TOML bodies in [`AnyConfig`] |
+//! | `headers` | `typed_header`: typed YAML `Content-Type` and `Accept` headers |
+//! | `schemars` | `contract`, `defaults`, `example` and `registry`: JSON Schema support |
+//! | `rego` | `rego`: OPA/Rego policy evaluation |
+//! | `build` | `build`: validation of YAML fixtures from a build script |
+//! | `tracing` | rejection events and parse/serialize spans with `tracing`, and the `debug` module |
+//! | `log` | rejection and parse/serialize records with `log` |
+//! | `metrics` | `metrics`: counting rejected requests |
+//! | `dev-errors` | HTML rejection pages for development |
+//! | `timeout` | [`YamlConfig`] parse timeouts |
+//! | `digest` | [`YamlConfig`] verification of `Content-Digest` and `Repr-Digest` |
 
 mod macros;
 
 #[cfg(test)]
 mod test_client;
+#[cfg(all(test, feature = "log"))]
+mod test_log;
 
+pub mod any_config;
+pub mod any_of;
+pub mod audit;
+#[cfg(feature = "build")]
+pub mod build;
+pub mod bundle;
+pub mod case;
+mod charset;
+mod classify;
+mod collect;
+mod config;
+#[cfg(feature = "digest")]
+mod content_digest;
+#[cfg(feature = "schemars")]
+pub mod contract;
+pub mod crud;
+pub mod de;
+#[cfg(feature = "tracing")]
+pub mod debug;
+#[cfg(feature = "schemars")]
+pub mod defaults;
+#[cfg(feature = "dev-errors")]
+mod dev_errors;
+pub mod dump;
+pub mod embed;
+mod emit;
+#[cfg(feature = "schemars")]
+pub mod example;
+pub mod extra;
+pub mod filter;
+pub mod format_rejection;
+pub mod intern;
+pub mod lenient;
+mod limits;
+pub mod lint;
+pub mod media_type;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+pub mod multi_doc;
+pub mod negotiate;
+pub mod normalize;
+pub mod path;
+pub mod policy;
+pub mod prefer;
+#[cfg(feature = "schemars")]
+pub mod registry;
+#[cfg(feature = "rego")]
+pub mod rego;
 pub mod rejection;
+pub mod schema;
+pub mod seed;
+pub mod ser;
+pub mod timing;
+#[cfg(feature = "headers")]
+pub mod typed_header;
+pub mod value;
 pub mod yaml;
 
-pub use crate::yaml::Yaml;
+pub use crate::{
+    any_config::AnyConfig,
+    bundle::YamlBundle,
+    config::{YamlConfig, YamlConfigService},
+    multi_doc::{YamlDocumentStream, YamlMultiDoc, YamlPage},
+    yaml::{
+        is_yaml_content_type, is_yaml_media_type, BytesYaml, LazyYaml, LenientYaml, OptionalYaml,
+        StateYaml, Yaml, YamlWithRejection,
+    },
+};
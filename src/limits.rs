@@ -0,0 +1,713 @@
+//! Structural limits on extracted documents.
+//!
+//! Limits are checked by a streaming pass over the request body before it is
+//! parsed into a [`Value`], since an alias bomb expands to millions of nodes
+//! from a few hundred bytes, and `[0,0,0,...]` allocates a node every two.
+
+use std::cell::{Cell, RefCell};
+use std::collections::HashSet;
+use std::fmt;
+
+use serde::de::{self, DeserializeSeed, EnumAccess, MapAccess, SeqAccess, VariantAccess, Visitor};
+use serde_yaml::Value;
+
+use crate::path::key_to_string;
+use crate::rejection::{DisallowedYamlTag, YamlError, YamlLimitExceeded, YamlRejection};
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Limits {
+    pub(crate) max_mapping_keys: Option<usize>,
+    pub(crate) max_sequence_len: Option<usize>,
+    pub(crate) max_aliases: Option<usize>,
+    pub(crate) max_nodes: Option<usize>,
+    pub(crate) max_depth: Option<usize>,
+    pub(crate) unique_keys: bool,
+    pub(crate) core_tags_only: bool,
+}
+
+impl Limits {
+    /// Check the limits against the request body `source` before it is
+    /// parsed.
+    ///
+    /// Syntax errors are left for the parser to report.
+    pub(crate) fn check_source(&self, source: &[u8]) -> Result<(), YamlRejection> {
+        if let Some(max) = self.max_aliases {
+            let aliases = count_aliases(&String::from_utf8_lossy(source));
+            if aliases > max {
+                return Err(YamlLimitExceeded::from_err(format!(
+                    "body has {aliases} aliases, more than the limit of {max}"
+                ))
+                .into());
+            }
+        }
+
+        if self.core_tags_only {
+            if let Some((tag, line, column)) = non_core_tag(&String::from_utf8_lossy(source)) {
+                return Err(DisallowedYamlTag::from_err(format!(
+                    "`{tag}` at line {line} column {column}"
+                ))
+                .into());
+            }
+        }
+
+        if self.max_nodes.is_some()
+            || self.max_depth.is_some()
+            || self.max_mapping_keys.is_some()
+            || self.max_sequence_len.is_some()
+            || self.unique_keys
+        {
+            let scan = Scan {
+                limits: self,
+                nodes: Cell::new(0),
+                depth: Cell::new(0),
+                path: RefCell::default(),
+                exceeded: RefCell::new(None),
+                duplicate: Cell::new(false),
+            };
+            let mut scanned = Ok(());
+            for document in serde_yaml::Deserializer::from_slice(source) {
+                scanned = (&scan).deserialize(document);
+                if scanned.is_err() {
+                    break;
+                }
+            }
+            if let Some(message) = scan.exceeded.into_inner() {
+                return Err(YamlLimitExceeded::from_err(message).into());
+            }
+            match scanned {
+                Err(err) if scan.duplicate.get() => return Err(YamlError::syntax(err).into()),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Count the aliases, such as `*defaults`, of the YAML `source`.
+fn count_aliases(source: &str) -> usize {
+    let mut aliases = 0;
+    node_properties(source, |token, _| {
+        if token.len() > 1 && token.starts_with('*') {
+            aliases += 1;
+        }
+    });
+    aliases
+}
+
+/// YAML core schema tags, see
+/// <https://yaml.org/spec/1.2.2/#103-core-schema>.
+const CORE_TAGS: [&str; 7] = ["null", "bool", "int", "float", "str", "seq", "map"];
+
+/// Find the first tag of the YAML `source` outside the core schema, with its
+/// line and column.
+///
+/// Tags are resolved with the `%TAG` directives of the source, so `!e!int`
+/// is not mistaken for `!!int`. The non-specific tag `!` is allowed.
+fn non_core_tag(source: &str) -> Option<(String, usize, usize)> {
+    let mut handles = vec![("!!", "tag:yaml.org,2002:"), ("!", "!")];
+    for line in source.lines() {
+        let mut directive = line.split_whitespace();
+        if let (Some("%TAG"), Some(handle), Some(prefix)) =
+            (directive.next(), directive.next(), directive.next())
+        {
+            handles.insert(0, (handle, prefix));
+        }
+    }
+
+    let mut found = None;
+    node_properties(source, |token, (line, column)| {
+        if found.is_some() || !token.starts_with('!') || token == "!" {
+            return;
+        }
+        let resolved = match token.strip_prefix("!<") {
+            Some(verbatim) => verbatim.trim_end_matches('>').to_owned(),
+            None => {
+                let handle = match token[1..].find('!') {
+                    Some(end) => &token[..end + 2],
+                    None => "!",
+                };
+                let prefix = handles
+                    .iter()
+                    .find(|(h, _)| *h == handle)
+                    .map_or(handle, |(_, prefix)| prefix);
+                format!("{prefix}{}", &token[handle.len()..])
+            }
+        };
+        let core = resolved
+            .strip_prefix("tag:yaml.org,2002:")
+            .is_some_and(|name| CORE_TAGS.contains(&name));
+        if !core {
+            found = Some((token.to_owned(), line, column));
+        }
+    });
+    found
+}
+
+/// Call `f` with the aliases, such as `*defaults`, and tags, such as
+/// `!!str`, of the YAML `source` along with their line and column, skipping
+/// comments, directives, quoted scalars, block scalars and the continuation
+/// lines of plain scalars.
+fn node_properties<'s>(source: &'s str, mut f: impl FnMut(&'s str, (usize, usize))) {
+    let mut quote = None;
+    let mut block_indent = None;
+    let mut flow_depth = 0_usize;
+    // Plain scalar still open at the end of the previous line: the
+    // indentation of the line it started on, and whether it started the line.
+    let mut plain: Option<(usize, bool)> = None;
+
+    for (number, line) in source.lines().enumerate() {
+        let indent = line.len() - line.trim_start_matches(' ').len();
+        if let Some(parent) = block_indent {
+            if line.trim().is_empty() || indent > parent {
+                continue;
+            }
+            block_indent = None;
+        }
+        if quote.is_none() && line.starts_with('%') {
+            continue;
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let marker = quote.is_none()
+            && (line.starts_with("---") || line.starts_with("..."))
+            && line[3..].chars().next().is_none_or(char::is_whitespace);
+        if marker {
+            plain = None;
+            flow_depth = 0;
+        }
+        // A more indented line, or any line of a flow collection, continues
+        // the plain scalar rather than starting a node.
+        let mut plain_start = plain.take().filter(|&(start, starts_line)| {
+            flow_depth > 0 || indent > start || (starts_line && indent == start)
+        });
+        let mut in_plain = plain_start.is_some();
+
+        // Whether the next character starts a node, after an indicator.
+        let mut node_start = !in_plain;
+        let mut prev_blank = true;
+        let mut block_scalar = false;
+        let mut chars = line
+            .char_indices()
+            .skip(if marker { 3 } else { 0 })
+            .peekable();
+        while let Some((i, c)) = chars.next() {
+            match (quote, c) {
+                (Some('"'), '\\') => {
+                    chars.next();
+                }
+                (Some('\''), '\'') if chars.peek().map(|&(_, c)| c) == Some('\'') => {
+                    chars.next();
+                }
+                (Some(q), c) if c == q => quote = None,
+                (Some(_), _) | (None, ' ' | '\t') => {}
+                (None, '#') if prev_blank => {
+                    in_plain = false;
+                    break;
+                }
+                (None, '"' | '\'') if node_start => {
+                    quote = Some(c);
+                    node_start = false;
+                }
+                (None, '*' | '&' | '!') if node_start => {
+                    let rest = &line[i..];
+                    let len = if rest.starts_with("!<") {
+                        rest.find('>').map_or(rest.len(), |end| end + 1)
+                    } else {
+                        1 + rest[1..]
+                            .find(|c| !is_anchor_char(c))
+                            .unwrap_or(rest.len() - 1)
+                    };
+                    f(&rest[..len], (number + 1, i + 1));
+                    while chars.next_if(|&(j, _)| j < i + len).is_some() {}
+                    // Anchors and tags are followed by the node they apply to.
+                    node_start = c != '*';
+                }
+                (None, '|' | '>') if node_start => {
+                    block_scalar = true;
+                    node_start = false;
+                }
+                (None, '-' | '+' | '0'..='9') if block_scalar => {}
+                (None, c) => {
+                    let next_blank = chars.peek().is_none_or(|(_, next)| next.is_whitespace());
+                    // `-` and `?` are only indicators where a node starts,
+                    // as in `- - a`, and text within a plain scalar.
+                    let indicator = matches!(c, '[' | '{' | ',' | ']' | '}')
+                        || (c == ':' && next_blank)
+                        || (matches!(c, '-' | '?') && next_blank && node_start);
+                    match c {
+                        '[' | '{' if node_start => flow_depth += 1,
+                        ']' | '}' => flow_depth = flow_depth.saturating_sub(1),
+                        _ => {}
+                    }
+                    if !indicator && !in_plain {
+                        plain_start = Some((indent, i == indent));
+                    }
+                    in_plain = !indicator;
+                    node_start = indicator && !matches!(c, ']' | '}');
+                    block_scalar = false;
+                }
+            }
+            prev_blank = c.is_whitespace();
+        }
+        if block_scalar && quote.is_none() {
+            block_indent = Some(indent);
+        }
+        if in_plain && quote.is_none() {
+            plain = plain_start;
+        }
+    }
+}
+
+fn is_anchor_char(c: char) -> bool {
+    !c.is_whitespace() && !matches!(c, ',' | '[' | ']' | '{' | '}')
+}
+
+/// Streaming scan of a document, counting its nodes as the parser expands
+/// aliases and tracking their depth and path without building them.
+struct Scan<'a> {
+    limits: &'a Limits,
+    nodes: Cell<usize>,
+    depth: Cell<usize>,
+    /// Path of the current node, e.g. `b[0]`.
+    path: RefCell<String>,
+    exceeded: RefCell<Option<String>>,
+    /// Whether the scan failed on a duplicate key.
+    duplicate: Cell<bool>,
+}
+
+impl Scan<'_> {
+    fn node<E>(&self) -> Result<(), E>
+    where
+        E: de::Error,
+    {
+        let nodes = self.nodes.get() + 1;
+        self.nodes.set(nodes);
+        match self.limits.max_nodes {
+            Some(max) if nodes > max => Err(self.exceed(format!(
+                "body has more than {max} nodes once aliases are expanded"
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Scan the entries of a collection with `scan_entries`, one level deeper.
+    fn collection<E, F>(&self, scan_entries: F) -> Result<(), E>
+    where
+        E: de::Error,
+        F: FnOnce() -> Result<(), E>,
+    {
+        self.node()?;
+        let depth = self.depth.get() + 1;
+        if let Some(max) = self.limits.max_depth.filter(|&max| depth > max) {
+            return Err(self.exceed(format!("document is nested more than {max} levels deep")));
+        }
+        self.depth.set(depth);
+        scan_entries()?;
+        self.depth.set(depth - 1);
+        Ok(())
+    }
+
+    /// Scan the node at `segment` of the current path with `scan_node`.
+    fn child<T, F>(&self, segment: &str, scan_node: F) -> T
+    where
+        F: FnOnce() -> T,
+    {
+        let len = self.path.borrow().len();
+        self.path.borrow_mut().push_str(segment);
+        let scanned = scan_node();
+        self.path.borrow_mut().truncate(len);
+        scanned
+    }
+
+    fn exceed<E>(&self, message: String) -> E
+    where
+        E: de::Error,
+    {
+        *self.exceeded.borrow_mut() = Some(message);
+        E::custom("limit exceeded")
+    }
+
+    fn display_path(&self) -> String {
+        match self.path.borrow().as_str() {
+            "" => ".".to_owned(),
+            path => path.to_owned(),
+        }
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for &Scan<'_> {
+    type Value = ();
+
+    fn deserialize<D>(self, deserializer: D) -> Result<(), D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer.deserialize_any(self)
+    }
+}
+
+macro_rules! scan_scalar {
+    ($($method:ident: $ty:ty,)*) => {
+        $(
+            fn $method<E>(self, _: $ty) -> Result<(), E>
+            where
+                E: de::Error,
+            {
+                self.node()
+            }
+        )*
+    };
+}
+
+impl<'de> Visitor<'de> for &Scan<'_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("any YAML node")
+    }
+
+    scan_scalar! {
+        visit_bool: bool,
+        visit_i64: i64,
+        visit_u64: u64,
+        visit_f64: f64,
+        visit_str: &str,
+    }
+
+    fn visit_unit<E>(self) -> Result<(), E>
+    where
+        E: de::Error,
+    {
+        self.node()
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<(), A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        self.collection(|| {
+            let mut len = 0;
+            while self
+                .child(&format!("[{len}]"), || seq.next_element_seed(self))?
+                .is_some()
+            {
+                len += 1;
+                if let Some(max) = self.limits.max_sequence_len.filter(|&max| len > max) {
+                    while seq.next_element::<de::IgnoredAny>()?.is_some() {
+                        len += 1;
+                    }
+                    return Err(self.exceed(format!(
+                        "sequence at `{}` has {len} items, more than the limit of {max}",
+                        self.display_path()
+                    )));
+                }
+            }
+            Ok(())
+        })
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<(), A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        self.collection(|| {
+            let mut len = 0;
+            let mut seen = self.limits.unique_keys.then(HashSet::new);
+            while let Some(key) = map.next_key_seed(Key {
+                scan: self,
+                seen: seen.as_mut(),
+            })? {
+                len += 1;
+                if let Some(max) = self.limits.max_mapping_keys.filter(|&max| len > max) {
+                    map.next_value::<de::IgnoredAny>()?;
+                    while map
+                        .next_entry::<de::IgnoredAny, de::IgnoredAny>()?
+                        .is_some()
+                    {
+                        len += 1;
+                    }
+                    return Err(self.exceed(format!(
+                        "mapping at `{}` has {len} keys, more than the limit of {max}",
+                        self.display_path()
+                    )));
+                }
+
+                let segment = match self.path.borrow().is_empty() {
+                    true => key,
+                    false => format!(".{key}"),
+                };
+                self.child(&segment, || map.next_value_seed(self))?;
+            }
+            Ok(())
+        })
+    }
+
+    fn visit_enum<A>(self, data: A) -> Result<(), A::Error>
+    where
+        A: EnumAccess<'de>,
+    {
+        let (_, variant) = data.variant::<de::IgnoredAny>()?;
+        variant.newtype_variant_seed(self)
+    }
+}
+
+/// Mapping key scanned as a node of the document, rendered as a path
+/// segment.
+struct Key<'s, 'a> {
+    scan: &'s Scan<'a>,
+    /// Scalar keys of the mapping so far, when duplicates are rejected.
+    seen: Option<&'s mut HashSet<Value>>,
+}
+
+impl Key<'_, '_> {
+    fn scalar<E>(self, key: Value) -> Result<String, E>
+    where
+        E: de::Error,
+    {
+        self.scan.node()?;
+        let segment = key_to_string(&key);
+        if let Some(seen) = self.seen {
+            if !seen.insert(key) {
+                self.scan.duplicate.set(true);
+                return Err(E::custom(format_args!("duplicate key `{segment}`")));
+            }
+        }
+        Ok(segment)
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for Key<'_, '_> {
+    type Value = String;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<String, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer.deserialize_any(self)
+    }
+}
+
+macro_rules! key_scalar {
+    ($($method:ident: $ty:ty,)*) => {
+        $(
+            fn $method<E>(self, key: $ty) -> Result<String, E>
+            where
+                E: de::Error,
+            {
+                self.scalar(Value::from(key))
+            }
+        )*
+    };
+}
+
+impl<'de> Visitor<'de> for Key<'_, '_> {
+    type Value = String;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a mapping key")
+    }
+
+    key_scalar! {
+        visit_bool: bool,
+        visit_i64: i64,
+        visit_u64: u64,
+        visit_f64: f64,
+        visit_str: &str,
+    }
+
+    fn visit_unit<E>(self) -> Result<String, E>
+    where
+        E: de::Error,
+    {
+        self.scalar(Value::Null)
+    }
+
+    fn visit_seq<A>(self, seq: A) -> Result<String, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        self.scan.visit_seq(seq).map(|()| "?".to_owned())
+    }
+
+    fn visit_map<A>(self, map: A) -> Result<String, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        self.scan.visit_map(map).map(|()| "?".to_owned())
+    }
+
+    fn visit_enum<A>(self, data: A) -> Result<String, A::Error>
+    where
+        A: EnumAccess<'de>,
+    {
+        self.scan.visit_enum(data).map(|()| "?".to_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn max_mapping_keys() {
+        let limits = Limits {
+            max_mapping_keys: Some(2),
+            ..Limits::default()
+        };
+        let check = |source: &str| {
+            limits
+                .check_source(source.as_bytes())
+                .map_err(|err| err.body_text())
+        };
+
+        assert!(check("a: 1\nb: {c: 1, d: 2}\n").is_ok());
+        assert_eq!(
+            check("a: 1\nb:\n- {c: 1, d: 2, e: 3}\n").unwrap_err(),
+            "YAML document exceeds a limit: mapping at `b[0]` has 3 keys, more than the limit of 2"
+        );
+        assert!(check("{a: 1, b: 2, c: 3}").is_err());
+    }
+
+    #[test]
+    fn max_sequence_len() {
+        let limits = Limits {
+            max_sequence_len: Some(2),
+            ..Limits::default()
+        };
+        let check = |source: &str| {
+            limits
+                .check_source(source.as_bytes())
+                .map_err(|err| err.body_text())
+        };
+
+        assert!(check("a: [1, 2]\nb: [[1, 2], [3]]\n").is_ok());
+        assert_eq!(
+            check("a: 1\nb:\n  c: [[1], [1, 2, 3, 4]]\n").unwrap_err(),
+            "YAML document exceeds a limit: sequence at `b.c[1]` has 4 items, more than the limit of 2"
+        );
+        assert!(check("[1, 2, 3]").is_err());
+        assert!(check("{[1, 2, 3]: 1}").is_err());
+    }
+
+    #[test]
+    fn unique_keys() {
+        let limits = Limits {
+            unique_keys: true,
+            ..Limits::default()
+        };
+        let check = |source: &str| {
+            limits
+                .check_source(source.as_bytes())
+                .map_err(|err| err.body_text())
+        };
+
+        assert!(check("a: 1\nb: {a: 1}\n'1': x\n1: y\n").is_ok());
+        assert_eq!(
+            check("a: 1\nb:\n  c: 1\n  d: 2\n  c: 3\n").unwrap_err(),
+            "Failed to deserialize the YAML body into the target type: \
+             b: duplicate key `c` at line 5 column 3"
+        );
+        assert!(check("{a: 1, \"a\": 2}").is_err());
+        assert!(check("a: 1\n---\na: 2\n").is_ok());
+    }
+
+    #[test]
+    fn aliases() {
+        assert_eq!(
+            count_aliases("a: &a 1\nb: *a\nc: [*a, *a]\nd: {e: *a}\n"),
+            4
+        );
+        assert_eq!(count_aliases("- *a\n- - *b # *c\n? *d\n: *e\n"), 4);
+        assert_eq!(
+            count_aliases("a: '*a'\nb: \"*b \\\" *c\"\nc: x *c\nd: 2 * 3\n"),
+            0
+        );
+        assert_eq!(count_aliases("a: |\n  *a\n\n  *b\nb: >-\n  *c\nc: *d\n"), 1);
+        assert_eq!(
+            count_aliases("a: 'it''s *not'\nb: 'multi\n  *line'\nc: *a"),
+            1
+        );
+        // Continuation lines of plain scalars.
+        assert_eq!(count_aliases("a: foo\n  *bar\nb: *c\n"), 1);
+        assert_eq!(count_aliases("- a\n  - *b\n- *c\n"), 1);
+        assert_eq!(count_aliases("a:\n  foo\n  *bar\n\n  *baz\n"), 0);
+        assert_eq!(count_aliases("foo\n*bar\n---\n*a\n"), 1);
+        assert_eq!(count_aliases("[foo\n*bar, *c]\n"), 1);
+        assert_eq!(count_aliases("a: foo # *x\n  *b\n"), 1);
+        assert_eq!(count_aliases("a: x - *y\nb: x ? *z\n"), 0);
+    }
+
+    #[test]
+    fn core_tags() {
+        assert_eq!(non_core_tag("a: !!str 1\nb: !!int '2'\nc: ! x\n"), None);
+        assert_eq!(
+            non_core_tag("a: !<tag:yaml.org,2002:map> {}\nb: [!!seq []]\n"),
+            None
+        );
+        assert_eq!(
+            non_core_tag("a: '!x'\nb: |\n  !x\nc: x!y # !z\nd: &a !!binary aGk=\n"),
+            Some(("!!binary".to_owned(), 5, 7))
+        );
+        assert_eq!(
+            non_core_tag("- !Circle {r: 1}\n"),
+            Some(("!Circle".to_owned(), 1, 3))
+        );
+        assert_eq!(
+            non_core_tag("%TAG !e! tag:example.com,2000:\n---\na: !e!int 1\n"),
+            Some(("!e!int".to_owned(), 3, 4))
+        );
+        assert_eq!(
+            non_core_tag("%TAG ! tag:yaml.org,2002:\n---\na: !str 1\n"),
+            None
+        );
+        assert_eq!(
+            non_core_tag("a: !<tag:example.com,2000:x> 1\n"),
+            Some(("!<tag:example.com,2000:x>".to_owned(), 1, 4))
+        );
+        assert_eq!(non_core_tag("note: see\n  !important\n"), None);
+        assert_eq!(
+            non_core_tag("note: see\n  !important\nb: !x 1\n"),
+            Some(("!x".to_owned(), 3, 4))
+        );
+        assert_eq!(non_core_tag("{a: see\n!important}\n"), None);
+    }
+
+    #[test]
+    fn max_nodes() {
+        let limits = Limits {
+            max_nodes: Some(20),
+            ..Limits::default()
+        };
+        let bomb = "a: &a [x, x, x]\nb: &b [*a, *a, *a]\nc: [*b, *b, *b]\n";
+        assert_eq!(
+            limits
+                .check_source(bomb.as_bytes())
+                .unwrap_err()
+                .body_text(),
+            "YAML document exceeds a limit: body has more than 20 nodes once aliases are expanded"
+        );
+        assert!(limits
+            .check_source(b"a: [1, 2, 3]\n---\nb: {c: 1}\n")
+            .is_ok());
+        assert!(limits.check_source(b"a: [1, 2").is_ok());
+    }
+
+    #[test]
+    fn max_depth() {
+        let limits = Limits {
+            max_depth: Some(3),
+            ..Limits::default()
+        };
+        assert!(limits.check_source(b"a: [{b: 1}]\nc: [[1], [2]]\n").is_ok());
+        assert_eq!(
+            limits
+                .check_source(b"a: [{b: [1]}]\n")
+                .unwrap_err()
+                .body_text(),
+            "YAML document exceeds a limit: document is nested more than 3 levels deep"
+        );
+        assert!(limits.check_source(&b"[".repeat(1_000)).is_err());
+    }
+}
@@ -0,0 +1,365 @@
+//! Linting of scalars whose meaning depends on the YAML version.
+//!
+//! YAML 1.1 parsers read `NO` and `on` as booleans, `0755` as an octal number
+//! and `1:20` as a sexagesimal one, while this crate follows YAML 1.2 and
+//! reads them as strings. Unquoted version numbers such as `1.10` are floats
+//! in both, losing their trailing zeros. A document relying on either
+//! behaviour means something else to other tools, so [`lint`] flags these
+//! scalars, and the [`YamlLinted`] extractor hands the findings to the
+//! handler to return as `Warning` headers, while the [`StrictYaml`]
+//! extractor rejects such documents outright.
+//!
+//! ```no_run
+//! use axum::{routing::post, Router};
+//! use axum_yaml::{lint::{Lints, YamlLinted}, Yaml};
+//! use serde_yaml::Value;
+//!
+//! async fn apply(YamlLinted(config, lints): YamlLinted<Value>) -> (Lints, Yaml<Value>) {
+//!     (lints, Yaml(config))
+//! }
+//!
+//! let app = Router::new().route("/config", post(apply));
+//! # let _: Router = app;
+//! ```
+
+use std::convert::Infallible;
+use std::fmt;
+use std::time::Instant;
+
+use axum_core::{
+    extract::{FromRequest, Request},
+    response::{IntoResponseParts, ResponseParts},
+};
+use http::{header, HeaderValue};
+use serde::de::DeserializeOwned;
+use serde_yaml::Value;
+
+use crate::path::YamlPath;
+use crate::rejection::*;
+use crate::timing::ParseTiming;
+use crate::YamlConfig;
+
+/// Scalar whose meaning depends on the YAML version or on quoting.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lint {
+    /// Location of the scalar, such as `spec.containers[0].image`.
+    pub location: String,
+    /// Why the scalar is ambiguous.
+    pub message: String,
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let location = match self.location.as_str() {
+            "" => ".",
+            location => location,
+        };
+        write!(f, "`{location}`: {}", self.message)
+    }
+}
+
+/// Every ambiguous scalar found in a document.
+///
+/// As a response part, adds a `Warning: 299` header for each of them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Lints(pub Vec<Lint>);
+
+impl Lints {
+    /// Whether no scalar was flagged.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl IntoResponseParts for Lints {
+    type Error = Infallible;
+
+    fn into_response_parts(self, mut res: ResponseParts) -> Result<ResponseParts, Self::Error> {
+        for lint in self.0 {
+            let text = lint.to_string().replace(['\\', '"'], "'");
+            if let Ok(value) = HeaderValue::from_str(&format!("299 axum-yaml \"{text}\"")) {
+                res.headers_mut().append(header::WARNING, value);
+            }
+        }
+        Ok(res)
+    }
+}
+
+/// Flag every scalar of `document` whose meaning depends on the YAML version
+/// or on quoting.
+///
+/// A parsed document does not tell quoted strings apart, so `'NO'` is
+/// flagged like `NO`. The extractors of this module only flag strings
+/// written unquoted in the request body.
+pub fn lint(document: &Value) -> Lints {
+    lint_source(document, None)
+}
+
+/// Like [`lint`], skipping strings that never appear unquoted in `source`.
+fn lint_source(document: &Value, source: Option<&str>) -> Lints {
+    let mut lints = Vec::new();
+    for (location, value) in YamlPath::new("**").find(document) {
+        let message = match value {
+            Value::String(string) if source.is_none_or(|source| is_plain(source, string)) => {
+                lint_string(string)
+            }
+            Value::Number(number) if number.is_f64() && is_version_key(&location) => {
+                Some("version numbers are read as floats; quote the version".to_owned())
+            }
+            _ => None,
+        };
+        if let Some(message) = message {
+            lints.push(Lint { location, message });
+        }
+    }
+    Lints(lints)
+}
+
+fn lint_string(string: &str) -> Option<String> {
+    const BOOLEANS: &[&str] = &[
+        "y", "Y", "yes", "Yes", "YES", "n", "N", "no", "No", "NO", "on", "On", "ON", "off", "Off",
+        "OFF",
+    ];
+
+    let kind = if BOOLEANS.contains(&string) {
+        "a boolean"
+    } else if is_octal(string) {
+        "an octal number"
+    } else if is_sexagesimal(string) {
+        "a sexagesimal number"
+    } else if is_underscored(string) {
+        "a number"
+    } else {
+        return None;
+    };
+    Some(format!("`{string}` is {kind} in YAML 1.1; quote it"))
+}
+
+/// Whether `scalar` appears as an unquoted value in `source`.
+fn is_plain(source: &str, scalar: &str) -> bool {
+    source.match_indices(scalar).any(|(start, _)| {
+        let before = &source[..start];
+        let indicator = before.trim_end_matches([' ', '\t']);
+        let spaced = indicator.len() < before.len();
+        let preceded = match indicator.chars().next_back() {
+            None | Some('\n') => true,
+            Some(':' | '-') => spaced,
+            Some('[' | '{' | ',') => true,
+            _ => false,
+        };
+
+        let after = source[start + scalar.len()..].trim_start_matches([' ', '\t']);
+        preceded && (after.is_empty() || after.starts_with(['\r', '\n', '#', ',', ']', '}']))
+    })
+}
+
+fn is_octal(string: &str) -> bool {
+    let digits = string.strip_prefix(['-', '+']).unwrap_or(string);
+    digits.len() > 1 && digits.starts_with('0') && digits.bytes().all(|b| b.is_ascii_digit())
+}
+
+fn is_sexagesimal(string: &str) -> bool {
+    let digits = string.strip_prefix(['-', '+']).unwrap_or(string);
+    let mut parts = digits.split(':');
+    let first = parts.next().unwrap_or_default();
+    let mut rest = parts.peekable();
+    rest.peek().is_some()
+        && is_digits(first)
+        && !first.starts_with('0')
+        && rest.all(|part| is_digits(part) && part.len() <= 2 && part < "60")
+}
+
+fn is_underscored(string: &str) -> bool {
+    let digits = string.strip_prefix(['-', '+']).unwrap_or(string);
+    digits.contains('_')
+        && digits.starts_with(|c: char| c.is_ascii_digit())
+        && digits
+            .bytes()
+            .all(|b| b.is_ascii_digit() || b == b'_' || b == b'.')
+}
+
+fn is_digits(string: &str) -> bool {
+    !string.is_empty() && string.bytes().all(|b| b.is_ascii_digit())
+}
+
+fn is_version_key(location: &str) -> bool {
+    let key = location.rsplit('.').next().unwrap_or_default();
+    let key = key.split('[').next().unwrap_or_default();
+    key.to_ascii_lowercase().contains("version")
+}
+
+/// Extractor deserializing the request body like [`Yaml`](crate::Yaml), along
+/// with the [`Lints`] of the document.
+#[derive(Debug, Clone, Default)]
+pub struct YamlLinted<T>(pub T, pub Lints);
+
+impl<T, S> FromRequest<S> for YamlLinted<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = YamlRejection;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let config = YamlConfig::from_extensions(req.extensions());
+        config
+            .report(async {
+                if config.yaml_content_type(req.headers()) {
+                    let timing = req.extensions().get::<ParseTiming>().cloned();
+                    let bytes = config.read_body(req, state).await?;
+
+                    let start = Instant::now();
+                    let result = config
+                        .document_body(bytes.clone())
+                        .await
+                        .and_then(|document| {
+                            let lints = lint_source(&document, std::str::from_utf8(&bytes).ok());
+                            config
+                                .deserialize_value(document)
+                                .map(|value| YamlLinted(value, lints))
+                                .map_err(|err| config.error_status(YamlError::data(err).into()))
+                        });
+                    if let Some(timing) = timing {
+                        timing.record(start.elapsed());
+                    }
+                    result
+                } else {
+                    config.drain(req).await;
+                    Err(config.missing_content_type().into())
+                }
+            })
+            .await
+    }
+}
+
+/// Extractor deserializing the request body like [`Yaml`](crate::Yaml),
+/// rejecting documents holding any scalar flagged by [`lint`].
+///
+/// Protects configuration APIs from the "Norway problem": a client writing
+/// `country: NO` has to quote it, so the document means the same to every
+/// tool. The rejection is a [`YamlError`] listing the ambiguous scalars.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StrictYaml<T>(pub T);
+
+impl<T, S> FromRequest<S> for StrictYaml<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = YamlRejection;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let config = YamlConfig::from_extensions(req.extensions());
+        config
+            .report(async {
+                let YamlLinted(value, lints) = YamlLinted::from_request(req, state).await?;
+                if lints.is_empty() {
+                    return Ok(StrictYaml(value));
+                }
+
+                let lints = lints
+                    .0
+                    .iter()
+                    .map(Lint::to_string)
+                    .collect::<Vec<_>>()
+                    .join("; ");
+                Err(config.error_status(
+                    YamlError::new(
+                        YamlErrorKind::InvalidValue,
+                        format!("ambiguous scalars: {lints}"),
+                    )
+                    .into(),
+                ))
+            })
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use axum::routing::post;
+    use axum::Router;
+    use http::StatusCode;
+
+    use crate::test_client::TestClient;
+
+    #[test]
+    fn ambiguous_scalars() {
+        let document = serde_yaml::from_str(
+            "country: NO\nmode: 0755\ntime: 1:20\nsize: 1_000\nversion: 1.10\nratio: 1.5\n\
+             name: 'no-op'\ntags: [on, 'off', x]\n",
+        )
+        .unwrap();
+
+        let locations: Vec<_> = lint(&document)
+            .0
+            .into_iter()
+            .map(|lint| lint.location)
+            .collect();
+        assert_eq!(
+            locations,
+            ["country", "mode", "time", "size", "version", "tags[0]", "tags[1]"]
+        );
+    }
+
+    #[test]
+    fn quoted_scalars() {
+        let source = "country: 'NO'\nlanguage: no # plain\ntags: [\"on\", off]\nmode: \"0755\"\n";
+        let document = serde_yaml::from_str(source).unwrap();
+
+        let locations: Vec<_> = lint_source(&document, Some(source))
+            .0
+            .into_iter()
+            .map(|lint| lint.location)
+            .collect();
+        assert_eq!(locations, ["language", "tags[1]"]);
+    }
+
+    #[tokio::test]
+    async fn warning_headers() {
+        let app = Router::new().route(
+            "/",
+            post(|YamlLinted(_, lints): YamlLinted<Value>| async move { (lints, ()) }),
+        );
+
+        let res = TestClient::new(app)
+            .post("/")
+            .body("country: NO\n")
+            .header("content-type", "application/yaml")
+            .await;
+
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(
+            res.headers()["warning"],
+            "299 axum-yaml \"`country`: `NO` is a boolean in YAML 1.1; quote it\""
+        );
+    }
+
+    #[tokio::test]
+    async fn strict() {
+        let app = Router::new().route("/", post(|_: StrictYaml<Value>| async {}));
+        let client = TestClient::new(app);
+
+        let res = client
+            .post("/")
+            .body("country: 'NO'\nversion: '1.10'\n")
+            .header("content-type", "application/yaml")
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let res = client
+            .post("/")
+            .body("country: NO\nversion: 1.10\n")
+            .header("content-type", "application/yaml")
+            .await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            res.text().await,
+            "Failed to deserialize the YAML body into the target type: ambiguous scalars: \
+             `country`: `NO` is a boolean in YAML 1.1; quote it; \
+             `version`: version numbers are read as floats; quote the version"
+        );
+    }
+}
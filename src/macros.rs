@@ -6,16 +6,48 @@ macro_rules! __log_rejection {
         rejection_type = $ty:ident,
         body_text = $body_text:expr,
         status = $status:expr,
+        level = $level:expr,
     ) => {
         #[cfg(feature = "tracing")]
         {
-            tracing::event!(
-                target: "axum_yaml::rejection", // Renamed to "axum_yaml"
-                tracing::Level::TRACE,
-                status = $status.as_u16(),
-                body = $body_text,
-                rejection_type = std::any::type_name::<$ty>(),
-                "rejecting request",
+            // The level of a `tracing` event must be known at the callsite.
+            macro_rules! event {
+                ($event_level:ident) => {
+                    tracing::event!(
+                        target: "axum_yaml::rejection", // Renamed to "axum_yaml"
+                        tracing::Level::$event_level,
+                        status = $status.as_u16(),
+                        body = $body_text,
+                        rejection_type = std::any::type_name::<$ty>(),
+                        "rejecting request",
+                    )
+                };
+            }
+
+            match $level {
+                $crate::rejection::LogLevel::Trace => event!(TRACE),
+                $crate::rejection::LogLevel::Debug => event!(DEBUG),
+                $crate::rejection::LogLevel::Info => event!(INFO),
+                $crate::rejection::LogLevel::Warn => event!(WARN),
+                $crate::rejection::LogLevel::Error => event!(ERROR),
+            }
+        }
+        #[cfg(feature = "log")]
+        {
+            let level = match $level {
+                $crate::rejection::LogLevel::Trace => log::Level::Trace,
+                $crate::rejection::LogLevel::Debug => log::Level::Debug,
+                $crate::rejection::LogLevel::Info => log::Level::Info,
+                $crate::rejection::LogLevel::Warn => log::Level::Warn,
+                $crate::rejection::LogLevel::Error => log::Level::Error,
+            };
+            log::log!(
+                target: "axum_yaml::rejection",
+                level,
+                "rejecting request: status={} body={:?} rejection_type={}",
+                $status.as_u16(),
+                $body_text,
+                std::any::type_name::<$ty>(),
             );
         }
     };
@@ -30,25 +62,50 @@ macro_rules! __define_rejection {
         pub struct $name:ident;
     ) => {
         $(#[$m])*
-        #[derive(Debug)]
+        #[derive(Debug, Default)]
         #[non_exhaustive]
-        pub struct $name;
+        pub struct $name {
+            settings: $crate::rejection::RejectionSettings,
+        }
 
         impl axum_core::response::IntoResponse for $name {
             fn into_response(self) -> axum_core::response::Response {
                 super::macros::__log_rejection!(
                     rejection_type = $name,
-                    body_text = $body,
+                    body_text = self.body_text(),
                     status = http::StatusCode::$status,
+                    level = self.settings.log_level,
                 );
-                (self.status(), $body).into_response()
+                $crate::rejection::ToRejectionBody::rejection_body(&self).render(self.settings.format)
+            }
+        }
+
+        impl $crate::rejection::WithSettings for $name {
+            fn with_settings(mut self, settings: $crate::rejection::RejectionSettings) -> Self {
+                self.settings = settings;
+                self
+            }
+        }
+
+        impl $crate::rejection::ToRejectionBody for $name {
+            fn rejection_body(&self) -> $crate::rejection::RejectionBody {
+                $crate::rejection::RejectionBody::new(self.status(), stringify!($name), self.body_text())
+            }
+        }
+
+        impl serde::Serialize for $name {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: serde::Serializer,
+            {
+                $crate::rejection::ToRejectionBody::rejection_body(self).serialize_rejection(serializer)
             }
         }
 
         impl $name {
             /// Get the response body text used for this rejection.
             pub fn body_text(&self) -> String {
-                $body.into()
+                self.settings.limit($body.into())
             }
 
             /// Get the status code used for this rejection.
@@ -64,12 +121,6 @@ macro_rules! __define_rejection {
         }
 
         impl std::error::Error for $name {}
-
-        impl Default for $name {
-            fn default() -> Self {
-                Self
-            }
-        }
     };
 
     (
@@ -80,14 +131,15 @@ macro_rules! __define_rejection {
     ) => {
         $(#[$m])*
         #[derive(Debug)]
-        pub struct $name(pub(crate) axum_core::Error);
+        pub struct $name(pub(crate) axum_core::Error, $crate::rejection::RejectionSettings);
 
         impl $name {
+            #[allow(dead_code)]
             pub(crate) fn from_err<E>(err: E) -> Self
             where
                 E: Into<axum_core::BoxError>,
             {
-                Self(axum_core::Error::new(err))
+                Self(axum_core::Error::new(err), Default::default())
             }
         }
 
@@ -97,15 +149,38 @@ macro_rules! __define_rejection {
                     rejection_type = $name,
                     body_text = self.body_text(),
                     status = http::StatusCode::$status,
+                    level = self.1.log_level,
                 );
-                (self.status(), self.body_text()).into_response()
+                $crate::rejection::ToRejectionBody::rejection_body(&self).render(self.1.format)
+            }
+        }
+
+        impl $crate::rejection::WithSettings for $name {
+            fn with_settings(mut self, settings: $crate::rejection::RejectionSettings) -> Self {
+                self.1 = settings;
+                self
+            }
+        }
+
+        impl $crate::rejection::ToRejectionBody for $name {
+            fn rejection_body(&self) -> $crate::rejection::RejectionBody {
+                $crate::rejection::RejectionBody::new(self.status(), stringify!($name), self.body_text())
+            }
+        }
+
+        impl serde::Serialize for $name {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: serde::Serializer,
+            {
+                $crate::rejection::ToRejectionBody::rejection_body(self).serialize_rejection(serializer)
             }
         }
 
         impl $name {
             /// Get the response body text used for this rejection.
             pub fn body_text(&self) -> String {
-                format!(concat!($body, ": {}"), self.0).into()
+                self.1.limit(format!(concat!($body, ": {}"), self.0))
             }
 
             /// Get the status code used for this rejection.
@@ -157,6 +232,18 @@ macro_rules! __composite_rejection {
             }
         }
 
+        impl $crate::rejection::WithSettings for $name {
+            fn with_settings(self, settings: $crate::rejection::RejectionSettings) -> Self {
+                match self {
+                    $(
+                        Self::$variant(inner) => Self::$variant(
+                            $crate::rejection::WithSettings::with_settings(inner, settings),
+                        ),
+                    )+
+                }
+            }
+        }
+
         impl $name {
             /// Get the response body text used for this rejection.
             pub fn body_text(&self) -> String {
@@ -177,6 +264,25 @@ macro_rules! __composite_rejection {
             }
         }
 
+        impl $crate::rejection::ToRejectionBody for $name {
+            fn rejection_body(&self) -> $crate::rejection::RejectionBody {
+                match self {
+                    $(
+                        Self::$variant(inner) => $crate::rejection::ToRejectionBody::rejection_body(inner),
+                    )+
+                }
+            }
+        }
+
+        impl serde::Serialize for $name {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: serde::Serializer,
+            {
+                $crate::rejection::ToRejectionBody::rejection_body(self).serialize_rejection(serializer)
+            }
+        }
+
         $(
             impl From<$variant> for $name {
                 fn from(inner: $variant) -> Self {
@@ -0,0 +1,310 @@
+//! Vendor media types declared by the exchanged types.
+//!
+//! A type implementing [`YamlMediaType`] declares the media type its
+//! documents are exchanged with, e.g. `application/vnd.mycorp.deploy+yaml`.
+//! [`VendorYaml`] then only extracts request bodies sent with that
+//! `Content-Type`, and responds with it, so one service can expose several
+//! vendor media types without custom extractors.
+//!
+//! ```no_run
+//! use axum::{routing::post, Router};
+//! use axum_yaml::media_type::{VendorYaml, YamlMediaType};
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Deserialize, Serialize)]
+//! struct Deployment {
+//!     image: String,
+//! }
+//!
+//! impl YamlMediaType for Deployment {
+//!     const MEDIA_TYPE: &'static str = "application/vnd.mycorp.deploy+yaml";
+//! }
+//!
+//! async fn deploy(VendorYaml(deployment): VendorYaml<Deployment>) -> VendorYaml<Deployment> {
+//!     VendorYaml(deployment)
+//! }
+//!
+//! let app = Router::new().route("/deployments", post(deploy));
+//! # let _: Router = app;
+//! ```
+//!
+//! Parameters of the `Content-Type`, such as a payload version declared by the
+//! client, are available to handlers through [`YamlContentTypeInfo`].
+
+use std::time::Instant;
+
+use axum_core::{
+    extract::{FromRequest, FromRequestParts, Request},
+    response::{IntoResponse, Response},
+};
+use http::{header, request::Parts, HeaderMap};
+use mime::Mime;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::rejection::*;
+use crate::ser::{self, SerializeConfig};
+use crate::timing::ParseTiming;
+use crate::YamlConfig;
+
+/// Media type of the YAML documents of a type.
+pub trait YamlMediaType {
+    /// The media type, without parameters.
+    ///
+    /// It must be a YAML media type, i.e. `application/yaml` or an
+    /// `application` type with a `+yaml` suffix, for responses to be built.
+    const MEDIA_TYPE: &'static str = "application/yaml";
+}
+
+/// YAML extractor / response using the media type declared by `T`.
+///
+/// When used as an extractor, the request is rejected with
+/// [`UnexpectedYamlMediaType`] unless its `Content-Type` is
+/// [`T::MEDIA_TYPE`](YamlMediaType::MEDIA_TYPE), parameters aside. The body
+/// is then deserialized like [`Yaml`](crate::Yaml).
+///
+/// When used as a response, the value is serialized like
+/// [`Yaml`](crate::Yaml) with `T::MEDIA_TYPE` as the `Content-Type`.
+///
+/// See the [module documentation](self) for an example.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct VendorYaml<T>(pub T);
+
+impl<T, S> FromRequest<S> for VendorYaml<T>
+where
+    T: DeserializeOwned + YamlMediaType,
+    S: Send + Sync,
+{
+    type Rejection = YamlRejection;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let config = YamlConfig::from_extensions(req.extensions());
+        config
+            .report(async {
+                if let Err(rejection) = check_media_type(req.headers(), T::MEDIA_TYPE) {
+                    config.drain(req).await;
+                    return Err(rejection.into());
+                }
+
+                let timing = req.extensions().get::<ParseTiming>().cloned();
+                let bytes = config.read_body(req, state).await?;
+
+                let start = Instant::now();
+                let result = config.deserialize_body(bytes).await.map(VendorYaml);
+                if let Some(timing) = timing {
+                    timing.record(start.elapsed());
+                }
+                result
+            })
+            .await
+    }
+}
+
+fn check_media_type(headers: &HeaderMap, expected: &str) -> Result<(), UnexpectedYamlMediaType> {
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .unwrap_or_default();
+    let matches = content_type
+        .parse::<mime::Mime>()
+        .is_ok_and(|mime| mime.essence_str().eq_ignore_ascii_case(expected));
+    if matches {
+        return Ok(());
+    }
+
+    Err(UnexpectedYamlMediaType::from_err(match content_type {
+        "" => format!("expected `{expected}`"),
+        content_type => format!("expected `{expected}`, got `{content_type}`"),
+    }))
+}
+
+impl<T> IntoResponse for VendorYaml<T>
+where
+    T: Serialize + YamlMediaType,
+{
+    fn into_response(self) -> Response {
+        let config = (*SerializeConfig::current())
+            .clone()
+            .media_type(T::MEDIA_TYPE);
+        ser::response(&self.0, &config)
+    }
+}
+
+/// Media type and parameters of the YAML `Content-Type` of a request.
+///
+/// Lets handlers branch on parameters declared by the client, such as
+/// `application/yaml; version=1.2`. [`NormalizeContentTypeLayer`] inserts it
+/// into the request extensions for YAML requests.
+///
+/// When used as an extractor, it is cloned from the request extensions, or
+/// else parsed from the `Content-Type` header. The request is rejected with
+/// [`MissingYamlContentType`] if that content type is not accepted by the
+/// [`YamlConfig`].
+///
+/// ```no_run
+/// use axum::{routing::post, Router};
+/// use axum_yaml::{media_type::YamlContentTypeInfo, Yaml};
+/// use serde_yaml::Value;
+///
+/// async fn import(info: YamlContentTypeInfo, Yaml(document): Yaml<Value>) {
+///     match info.param("version") {
+///         Some("2") => { /* current format */ }
+///         _ => { /* legacy format */ }
+///     }
+/// }
+///
+/// let app: Router = Router::new().route("/import", post(import));
+/// ```
+///
+/// [`NormalizeContentTypeLayer`]: crate::normalize::NormalizeContentTypeLayer
+#[derive(Debug, Clone, PartialEq)]
+pub struct YamlContentTypeInfo {
+    mime: Mime,
+}
+
+impl YamlContentTypeInfo {
+    /// Parse the `Content-Type` of `headers`, `None` if it is missing or
+    /// malformed.
+    ///
+    /// The media type is not checked to be YAML.
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let mime = headers
+            .get(header::CONTENT_TYPE)?
+            .to_str()
+            .ok()?
+            .parse()
+            .ok()?;
+        Some(Self { mime })
+    }
+
+    /// The media type without parameters, in lowercase, e.g.
+    /// `application/yaml`.
+    pub fn media_type(&self) -> &str {
+        self.mime.essence_str()
+    }
+
+    /// The value of the parameter `name`, matched case-insensitively.
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params()
+            .find(|(param, _)| param.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value)
+    }
+
+    /// The parameters in the order they were sent, names in lowercase.
+    pub fn params(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.mime
+            .params()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+}
+
+impl<S> FromRequestParts<S> for YamlContentTypeInfo
+where
+    S: Send + Sync,
+{
+    type Rejection = MissingYamlContentType;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        if let Some(info) = parts.extensions.get::<Self>() {
+            return Ok(info.clone());
+        }
+
+        let config = YamlConfig::from_extensions(&parts.extensions);
+        if config.yaml_content_type(&parts.headers) {
+            if let Some(info) = Self::from_headers(&parts.headers) {
+                return Ok(info);
+            }
+        }
+        Err(config.missing_content_type())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use axum::routing::post;
+    use axum::Router;
+    use http::StatusCode;
+    use serde::Deserialize;
+
+    use crate::test_client::TestClient;
+
+    #[derive(Deserialize, Serialize)]
+    struct Deployment {
+        image: String,
+    }
+
+    impl YamlMediaType for Deployment {
+        const MEDIA_TYPE: &'static str = "application/vnd.mycorp.deploy+yaml";
+    }
+
+    #[tokio::test]
+    async fn vendor_media_type() {
+        let app = Router::new().route(
+            "/",
+            post(|deployment: VendorYaml<Deployment>| async { deployment }),
+        );
+        let client = TestClient::new(app);
+
+        let res = client
+            .post("/")
+            .body("image: web")
+            .header(
+                "content-type",
+                "application/vnd.mycorp.deploy+yaml; charset=utf-8",
+            )
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(
+            res.headers()["content-type"],
+            "application/vnd.mycorp.deploy+yaml"
+        );
+        assert_eq!(res.text().await, "image: web\n");
+
+        let res = client
+            .post("/")
+            .body("image: web")
+            .header("content-type", "application/yaml")
+            .await;
+        assert_eq!(res.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        assert_eq!(
+            res.text().await,
+            "Unexpected YAML media type: expected `application/vnd.mycorp.deploy+yaml`, \
+             got `application/yaml`"
+        );
+    }
+
+    #[tokio::test]
+    async fn content_type_params() {
+        let app = Router::new().route(
+            "/",
+            post(|info: YamlContentTypeInfo| async move {
+                let params: Vec<_> = info
+                    .params()
+                    .map(|(name, value)| format!("{name}={value}"))
+                    .collect();
+                format!("{} {}", info.media_type(), params.join(" "))
+            }),
+        );
+        let client = TestClient::new(app);
+
+        let res = client
+            .post("/")
+            .header(
+                "content-type",
+                "Application/YAML; Version=1.2; schema=\"urn:deploy\"",
+            )
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(
+            res.text().await,
+            "application/yaml version=1.2 schema=urn:deploy"
+        );
+
+        let res = client
+            .post("/")
+            .header("content-type", "text/plain; version=1.2")
+            .await;
+        assert_eq!(res.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+}
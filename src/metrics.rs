@@ -0,0 +1,221 @@
+//! Counting rejected requests with the `metrics` crate.
+//!
+//! Requires the `metrics` feature. [`RejectionMetricsLayer`] increments the
+//! `axum_yaml_rejections_total` counter of the installed recorder for every
+//! rejection of this crate, labelled with the `kind` of the rejection and the
+//! `route` the layer was created for, so operators can alert on spikes of
+//! malformed YAML traffic.
+//!
+//! `kind` is the `code` of the [`RejectionBody`], such as `syntax`,
+//! `invalid_type` or `missing_yaml_content_type`. Apply the layer to each
+//! route to label its rejections:
+//!
+//! ```no_run
+//! use axum::{routing::post, Router};
+//! use axum_yaml::{metrics::RejectionMetricsLayer, Yaml};
+//! use serde_yaml::Value;
+//!
+//! async fn create(Yaml(manifest): Yaml<Value>) {}
+//!
+//! let app: Router = Router::new().route(
+//!     "/manifests",
+//!     post(create).layer(RejectionMetricsLayer::new("/manifests")),
+//! );
+//! ```
+
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use axum_core::response::Response;
+use http::Request;
+use pin_project_lite::pin_project;
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::rejection::RejectionBody;
+
+/// Name of the counter of rejected requests.
+const REJECTIONS_TOTAL: &str = "axum_yaml_rejections_total";
+
+/// [`Layer`] counting the rejections of this crate in the
+/// `axum_yaml_rejections_total` counter.
+#[derive(Debug, Clone)]
+pub struct RejectionMetricsLayer {
+    route: String,
+}
+
+impl RejectionMetricsLayer {
+    /// Create a layer labelling the rejections it counts with `route`.
+    pub fn new(route: impl Into<String>) -> Self {
+        Self {
+            route: route.into(),
+        }
+    }
+}
+
+impl<S> Layer<S> for RejectionMetricsLayer {
+    type Service = RejectionMetrics<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RejectionMetrics {
+            inner,
+            route: self.route.clone(),
+        }
+    }
+}
+
+/// Middleware created by [`RejectionMetricsLayer`].
+#[derive(Debug, Clone)]
+pub struct RejectionMetrics<S> {
+    inner: S,
+    route: String,
+}
+
+impl<S, ReqBody> Service<Request<ReqBody>> for RejectionMetrics<S>
+where
+    S: Service<Request<ReqBody>, Response = Response>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
+        ResponseFuture {
+            inner: self.inner.call(req),
+            route: self.route.clone(),
+        }
+    }
+}
+
+pin_project! {
+    /// Response future for [`RejectionMetrics`].
+    pub struct ResponseFuture<F> {
+        #[pin]
+        inner: F,
+        route: String,
+    }
+}
+
+impl<F, E> Future for ResponseFuture<F>
+where
+    F: Future<Output = Result<Response, E>>,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let res = std::task::ready!(this.inner.poll(cx))?;
+        if let Some(rejection) = res.extensions().get::<RejectionBody>() {
+            ::metrics::counter!(
+                REJECTIONS_TOTAL,
+                "kind" => rejection.code().to_owned(),
+                "route" => this.route.clone(),
+            )
+            .increment(1);
+        }
+        Poll::Ready(Ok(res))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    };
+
+    use ::metrics::{
+        Counter, CounterFn, Gauge, Histogram, Key, KeyName, Metadata, Recorder, SharedString, Unit,
+    };
+    use axum_core::response::IntoResponse;
+    use futures_util::FutureExt;
+
+    use crate::rejection::{MissingYamlContentType, YamlRejection};
+
+    #[derive(Default)]
+    struct Counters(Mutex<Vec<(Key, Arc<AtomicU64>)>>);
+
+    impl Counters {
+        fn get(&self, kind: &str) -> u64 {
+            let counters = self.0.lock().unwrap();
+            counters
+                .iter()
+                .filter(|(key, _)| {
+                    key.name() == REJECTIONS_TOTAL
+                        && key
+                            .labels()
+                            .any(|label| label.key() == "kind" && label.value() == kind)
+                        && key
+                            .labels()
+                            .any(|label| label.key() == "route" && label.value() == "/manifests")
+                })
+                .map(|(_, count)| count.load(Ordering::Relaxed))
+                .sum()
+        }
+    }
+
+    struct Count(Arc<AtomicU64>);
+
+    impl CounterFn for Count {
+        fn increment(&self, value: u64) {
+            self.0.fetch_add(value, Ordering::Relaxed);
+        }
+
+        fn absolute(&self, value: u64) {
+            self.0.fetch_max(value, Ordering::Relaxed);
+        }
+    }
+
+    impl Recorder for Counters {
+        fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+        fn register_counter(&self, key: &Key, _: &Metadata<'_>) -> Counter {
+            let count = Arc::new(AtomicU64::new(0));
+            self.0.lock().unwrap().push((key.clone(), count.clone()));
+            Counter::from_arc(Arc::new(Count(count)))
+        }
+
+        fn register_gauge(&self, _: &Key, _: &Metadata<'_>) -> Gauge {
+            Gauge::noop()
+        }
+
+        fn register_histogram(&self, _: &Key, _: &Metadata<'_>) -> Histogram {
+            Histogram::noop()
+        }
+    }
+
+    #[test]
+    fn count_rejections() {
+        let mut service = RejectionMetricsLayer::new("/manifests").layer(tower::service_fn(
+            |req: Request<()>| async move {
+                let res = match req.uri().path() {
+                    "/rejected" => {
+                        YamlRejection::from(MissingYamlContentType::default()).into_response()
+                    }
+                    _ => ().into_response(),
+                };
+                Ok::<_, std::convert::Infallible>(res)
+            },
+        ));
+
+        let counters = Counters::default();
+        ::metrics::with_local_recorder(&counters, || {
+            for uri in ["/rejected", "/", "/rejected"] {
+                let req = Request::builder().uri(uri).body(()).unwrap();
+                service.call(req).now_or_never().unwrap().unwrap();
+            }
+        });
+        assert_eq!(counters.get("missing_yaml_content_type"), 2);
+    }
+}
@@ -0,0 +1,718 @@
+use std::convert::Infallible;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+use std::time::Instant;
+
+use axum_core::{
+    body::{Body, BodyDataStream},
+    extract::{rejection::BytesRejection, DefaultBodyLimit, FromRequest, Request},
+    response::{IntoResponse, Response},
+    BoxError, RequestExt,
+};
+use bytes::{BufMut, Bytes, BytesMut};
+use futures_core::Stream;
+use http::{header, HeaderValue};
+use serde::{de::DeserializeOwned, Serialize};
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::charset::Charset;
+use crate::rejection::*;
+use crate::ser::{self, SerializeConfig};
+use crate::timing::{ParseTiming, Step};
+use crate::YamlConfig;
+
+/// Multi-document YAML extractor / response.
+///
+/// When used as an extractor, it deserializes every `---` separated document
+/// of the request body into `T`. A body holding a single document, with or
+/// without a `---` marker, yields a single element, so bulk endpoints accept
+/// one resource or several on the same route.
+///
+/// When used as a response, every element is written as its own document,
+/// introduced by a `---` marker. The whole response is serialized at once,
+/// which suits bundles of moderate size.
+///
+/// # Extractor example
+///
+/// ```no_run
+/// use axum::{routing::post, Router};
+/// use axum_yaml::YamlMultiDoc;
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Manifest {
+///     kind: String,
+/// }
+///
+/// async fn apply(YamlMultiDoc(manifests): YamlMultiDoc<Manifest>) {
+///     for manifest in manifests {
+///         // ...
+///     }
+/// }
+///
+/// let app = Router::new().route("/apply", post(apply));
+/// # let _: Router = app;
+/// ```
+///
+/// # Response example
+///
+/// ```no_run
+/// use axum::{routing::get, Router};
+/// use axum_yaml::YamlMultiDoc;
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct Manifest {
+///     kind: String,
+/// }
+///
+/// async fn manifests() -> YamlMultiDoc<Manifest> {
+///     YamlMultiDoc(vec![
+///         Manifest { kind: "Deployment".to_owned() },
+///         Manifest { kind: "Service".to_owned() },
+///     ])
+/// }
+///
+/// let app = Router::new().route("/manifests", get(manifests));
+/// # let _: Router = app;
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct YamlMultiDoc<T>(pub Vec<T>);
+
+impl<T, S> FromRequest<S> for YamlMultiDoc<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = YamlRejection;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let config = YamlConfig::from_extensions(req.extensions());
+        config
+            .report(async {
+                if config.yaml_content_type(req.headers()) {
+                    let timing = req.extensions().get::<ParseTiming>().cloned();
+                    let bytes = config.read_body(req, state).await?;
+
+                    let start = Instant::now();
+                    let result = config
+                        .deserialize_documents_body(bytes)
+                        .await
+                        .map(YamlMultiDoc);
+                    if let Some(timing) = timing {
+                        timing.record(start.elapsed());
+                    }
+                    result
+                } else {
+                    config.drain(req).await;
+                    Err(config.missing_content_type().into())
+                }
+            })
+            .await
+    }
+}
+
+impl<T> IntoResponse for YamlMultiDoc<T>
+where
+    T: Serialize,
+{
+    fn into_response(self) -> Response {
+        let config = SerializeConfig::current();
+        let step = Step::serialize(self.0.len());
+        let mut buf = BytesMut::with_capacity(128).writer();
+        for document in &self.0 {
+            buf.get_mut().put_slice(b"---\n");
+
+            if let Err(err) = config.to_writer(&mut buf, document) {
+                return ser::error_response(err);
+            }
+        }
+
+        config.finish(buf.into_inner(), step)
+    }
+}
+
+/// Page of a multi-document listing, linking to its neighbours.
+///
+/// The documents are written like those of a [`YamlMultiDoc`] response, and
+/// the links are sent in an RFC 8288 `Link` header so clients can page
+/// through a large listing without parsing the body for a cursor. The
+/// handler builds the URIs, typically from a cursor of its storage.
+///
+/// # Example
+///
+/// ```no_run
+/// use axum::{extract::Query, routing::get, Router};
+/// use axum_yaml::YamlPage;
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Cursor {
+///     after: Option<u64>,
+/// }
+///
+/// async fn events(Query(cursor): Query<Cursor>) -> YamlPage<u64> {
+///     let start = cursor.after.map_or(0, |after| after + 1);
+///     let events: Vec<u64> = (start..start + 100).collect();
+///     let last = events[events.len() - 1];
+///     YamlPage::new(events).next(format!("/events?after={last}"))
+/// }
+///
+/// let app = Router::new().route("/events", get(events));
+/// # let _: Router = app;
+/// ```
+#[derive(Debug, Clone)]
+pub struct YamlPage<T> {
+    documents: Vec<T>,
+    links: Vec<(String, String)>,
+}
+
+impl<T> YamlPage<T> {
+    /// Create a page holding `documents`, without links.
+    pub fn new(documents: Vec<T>) -> Self {
+        Self {
+            documents,
+            links: Vec::new(),
+        }
+    }
+
+    /// Link to the next page.
+    pub fn next(self, uri: impl Into<String>) -> Self {
+        self.link("next", uri)
+    }
+
+    /// Link to the previous page.
+    pub fn prev(self, uri: impl Into<String>) -> Self {
+        self.link("prev", uri)
+    }
+
+    /// Link to the first page.
+    pub fn first(self, uri: impl Into<String>) -> Self {
+        self.link("first", uri)
+    }
+
+    /// Link to `uri` with the relation type `rel`.
+    pub fn link(mut self, rel: impl Into<String>, uri: impl Into<String>) -> Self {
+        self.links.push((rel.into(), uri.into()));
+        self
+    }
+
+    fn link_header(&self) -> Result<Option<HeaderValue>, http::header::InvalidHeaderValue> {
+        if self.links.is_empty() {
+            return Ok(None);
+        }
+
+        let links = self
+            .links
+            .iter()
+            .map(|(rel, uri)| format!("<{uri}>; rel=\"{rel}\""))
+            .collect::<Vec<_>>()
+            .join(", ");
+        HeaderValue::from_str(&links).map(Some)
+    }
+}
+
+impl<T> IntoResponse for YamlPage<T>
+where
+    T: Serialize,
+{
+    fn into_response(self) -> Response {
+        let link = match self.link_header() {
+            Ok(link) => link,
+            Err(err) => {
+                return ser::error_response(format!("invalid `Link` header: {err}"));
+            }
+        };
+
+        let mut response = YamlMultiDoc(self.documents).into_response();
+        if let Some(link) = link {
+            if response.status().is_success() {
+                response.headers_mut().insert(header::LINK, link);
+            }
+        }
+        response
+    }
+}
+
+/// Streaming multi-document YAML extractor.
+///
+/// Unlike [`YamlMultiDoc`], the request body is not buffered as a whole: each
+/// `---` separated document is deserialized into `T` and yielded as soon as
+/// the next document marker, or the end of the body, has been received, so
+/// memory stays bounded by the largest document of very large uploads. The
+/// body limit of axum still applies to the whole body; disable it with
+/// `DefaultBodyLimit::disable` for unbounded uploads.
+///
+/// A document failing to deserialize yields an error without ending the
+/// stream, while a failure to read the body ends it. The body must be UTF-8,
+/// even with [`YamlConfig::transcode_charsets`].
+///
+/// # Example
+///
+/// ```no_run
+/// use axum::{routing::post, Router};
+/// use axum_yaml::YamlDocumentStream;
+/// use futures_util::StreamExt;
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Event {
+///     kind: String,
+/// }
+///
+/// async fn ingest(mut events: YamlDocumentStream<Event>) {
+///     while let Some(event) = events.next().await {
+///         // ...
+///     }
+/// }
+///
+/// let app = Router::new().route("/events", post(ingest));
+/// # let _: Router = app;
+/// ```
+pub struct YamlDocumentStream<T> {
+    body: BodyDataStream,
+    documents: Documents,
+    config: YamlConfig,
+    timing: Option<ParseTiming>,
+    /// Number of documents split off the body so far.
+    count: usize,
+    finished: bool,
+    failed: Option<Pin<Box<dyn Future<Output = BytesRejection> + Send>>>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> std::fmt::Debug for YamlDocumentStream<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("YamlDocumentStream")
+            .field("buffered", &self.documents.buf.len())
+            .field("finished", &self.finished)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T, S> FromRequest<S> for YamlDocumentStream<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = YamlRejection;
+
+    async fn from_request(req: Request, _state: &S) -> Result<Self, Self::Rejection> {
+        let config = YamlConfig::from_extensions(req.extensions());
+        if config.yaml_content_type(req.headers()) {
+            // Documents are parsed as they arrive, so the body is not transcoded.
+            if let Err(rejection) = Charset::from_headers(req.headers(), false) {
+                config.drain(req).await;
+                return Err(config.reject(rejection.into()));
+            }
+
+            let timing = req.extensions().get::<ParseTiming>().cloned();
+            let body = config.track_progress(req).into_limited_body();
+            Ok(Self {
+                body: body.into_data_stream(),
+                documents: Documents::default(),
+                config,
+                timing,
+                count: 0,
+                finished: false,
+                failed: None,
+                _marker: PhantomData,
+            })
+        } else {
+            config.drain(req).await;
+            Err(config.missing_content_type().into())
+        }
+    }
+}
+
+impl<T> Stream for YamlDocumentStream<T>
+where
+    T: DeserializeOwned,
+{
+    type Item = Result<T, YamlRejection>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            if let Some(failed) = &mut this.failed {
+                let rejection = ready!(failed.as_mut().poll(cx));
+                this.failed = None;
+                return Poll::Ready(Some(Err(this.config.reject(rejection.into()))));
+            }
+
+            if let Some(document) = this.documents.next(this.finished) {
+                this.count += 1;
+                if let Err(rejection) = this.config.check_document_count(this.count) {
+                    this.finished = true;
+                    this.documents = Documents::default();
+                    return Poll::Ready(Some(Err(this.config.reject(rejection.into()))));
+                }
+                let start = Instant::now();
+                let result = this
+                    .config
+                    .deserialize(&document)
+                    .map_err(|rejection| this.config.reject(rejection));
+                if let Some(timing) = &this.timing {
+                    timing.record(start.elapsed());
+                }
+                return Poll::Ready(Some(result));
+            }
+            if this.finished {
+                return Poll::Ready(None);
+            }
+
+            match ready!(Pin::new(&mut this.body).poll_next(cx)) {
+                Some(Ok(data)) => this.documents.buf.extend_from_slice(&data),
+                Some(Err(err)) => {
+                    this.finished = true;
+                    this.documents = Documents::default();
+                    this.failed = Some(body_rejection(err));
+                }
+                None => this.finished = true,
+            }
+        }
+    }
+}
+
+/// Turn an error reading the body into the rejection `Bytes` returns for it,
+/// e.g. `413 Payload Too Large` for an exceeded body limit.
+fn body_rejection(err: axum_core::Error) -> Pin<Box<dyn Future<Output = BytesRejection> + Send>> {
+    /// Body stream failing with an error already received.
+    struct Failed(Option<BoxError>);
+
+    impl Stream for Failed {
+        type Item = Result<Bytes, BoxError>;
+
+        fn poll_next(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            Poll::Ready(self.0.take().map(Err))
+        }
+    }
+
+    /// Service extracting `Bytes` from a body that fails.
+    struct Extract;
+
+    impl Service<Request> for Extract {
+        type Response = BytesRejection;
+        type Error = Infallible;
+        type Future = Pin<Box<dyn Future<Output = Result<BytesRejection, Infallible>> + Send>>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, req: Request) -> Self::Future {
+            Box::pin(async move {
+                match Bytes::from_request(req, &()).await {
+                    Err(rejection) => Ok(rejection),
+                    Ok(_) => unreachable!("the body fails"),
+                }
+            })
+        }
+    }
+
+    // The limit was enforced already, and must not wrap the error again.
+    let req = Request::new(Body::from_stream(Failed(Some(err.into_inner()))));
+    let future = DefaultBodyLimit::disable().layer(Extract).call(req);
+    Box::pin(async move {
+        match future.await {
+            Ok(rejection) => rejection,
+            Err(never) => match never {},
+        }
+    })
+}
+
+/// Buffer splitting a multi-document body on its document markers.
+///
+/// A line starting with `---` begins a document, and one starting with `...`
+/// ends it, wherever they appear. Directives and comments before a document
+/// are kept with it.
+#[derive(Default)]
+struct Documents {
+    buf: BytesMut,
+    /// Length of the complete lines of `buf` already looked at.
+    scanned: usize,
+    /// Whether `buf` holds the start of a document.
+    started: bool,
+}
+
+impl Documents {
+    /// Split the next complete document off the buffer. Once `eof` is
+    /// reached, the remainder of the buffer is a complete document.
+    fn next(&mut self, eof: bool) -> Option<Bytes> {
+        if eof && !self.buf.is_empty() && !self.buf.ends_with(b"\n") {
+            self.buf.put_u8(b'\n');
+        }
+
+        while let Some(end) = self.buf[self.scanned..].iter().position(|&b| b == b'\n') {
+            let start = self.scanned;
+            let end = start + end + 1;
+            let line = self.buf[start..end].trim_ascii_end();
+
+            if is_marker(line, b"---") {
+                if self.started && start > 0 {
+                    self.scanned = end - start;
+                    return Some(self.buf.split_to(start).freeze());
+                }
+                self.started = true;
+            } else if is_marker(line, b"...") {
+                let document = self.buf.split_to(end).freeze();
+                self.scanned = 0;
+                if std::mem::take(&mut self.started) {
+                    return Some(document);
+                }
+                continue;
+            } else if !self.started {
+                let text = line.trim_ascii_start();
+                self.started =
+                    !(text.is_empty() || text.starts_with(b"#") || line.starts_with(b"%"));
+            }
+            self.scanned = end;
+        }
+
+        if !eof {
+            return None;
+        }
+        self.scanned = 0;
+        let document = self.buf.split().freeze();
+        std::mem::take(&mut self.started).then_some(document)
+    }
+}
+
+pub(crate) fn is_marker(line: &[u8], marker: &[u8]) -> bool {
+    line.strip_prefix(marker)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with(b" ") || rest.starts_with(b"\t"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use axum::routing::{get, post};
+    use axum::Router;
+    use http::StatusCode;
+    use serde::Deserialize;
+
+    use crate::test_client::TestClient;
+
+    #[derive(Serialize)]
+    struct Manifest {
+        kind: &'static str,
+    }
+
+    #[tokio::test]
+    async fn documents() {
+        let app = Router::new().route(
+            "/",
+            get(|| async {
+                YamlMultiDoc(vec![
+                    Manifest { kind: "Deployment" },
+                    Manifest { kind: "Service" },
+                ])
+            }),
+        );
+
+        let res = TestClient::new(app).get("/").await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(
+            res.text().await,
+            "---\nkind: Deployment\n---\nkind: Service\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn single_or_many() {
+        #[derive(Deserialize)]
+        struct Manifest {
+            kind: String,
+        }
+
+        let app = Router::new().route(
+            "/",
+            post(
+                |YamlMultiDoc(manifests): YamlMultiDoc<Manifest>| async move {
+                    manifests
+                        .into_iter()
+                        .map(|manifest| manifest.kind)
+                        .collect::<Vec<_>>()
+                        .join(",")
+                },
+            ),
+        );
+        let client = TestClient::new(app);
+
+        for (body, kinds) in [
+            (
+                "kind: Service
+",
+                "Service",
+            ),
+            (
+                "---
+kind: Service
+",
+                "Service",
+            ),
+            (
+                "kind: Deployment
+---
+kind: Service
+",
+                "Deployment,Service",
+            ),
+            ("", ""),
+        ] {
+            let res = client
+                .post("/")
+                .body(body)
+                .header("content-type", "application/yaml")
+                .await;
+            assert_eq!(res.status(), StatusCode::OK, "{body:?}");
+            assert_eq!(res.text().await, kinds, "{body:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn page_links() {
+        let app = Router::new().route(
+            "/",
+            get(|| async {
+                YamlPage::new(vec![1, 2])
+                    .first("/?after=0")
+                    .next("/?after=2")
+            }),
+        );
+
+        let res = TestClient::new(app).get("/").await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(
+            res.headers()["link"],
+            "</?after=0>; rel=\"first\", </?after=2>; rel=\"next\""
+        );
+        assert_eq!(res.text().await, "---\n1\n---\n2\n");
+    }
+
+    #[test]
+    fn split_documents() {
+        let body =
+            "# header\n%YAML 1.2\n---\na: 1\n--- |\n  text\n...\n# trailer\n---\n---\nb: [\n  2]\n";
+
+        // Feed the body byte by byte, as the worst chunking a client can do.
+        let mut documents = Documents::default();
+        let mut found = Vec::new();
+        for byte in body.bytes() {
+            documents.buf.put_u8(byte);
+            found.extend(documents.next(false));
+        }
+        found.extend(documents.next(true));
+        assert_eq!(documents.next(true), None);
+
+        assert_eq!(
+            found,
+            [
+                "# header\n%YAML 1.2\n---\na: 1\n",
+                "--- |\n  text\n...\n",
+                "# trailer\n---\n",
+                "---\nb: [\n  2]\n",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn document_stream() {
+        use axum::extract::DefaultBodyLimit;
+        use futures_util::StreamExt;
+
+        let app = Router::new()
+            .route(
+                "/",
+                post(|mut documents: YamlDocumentStream<u32>| async move {
+                    let mut results = Vec::new();
+                    while let Some(document) = documents.next().await {
+                        results.push(match document {
+                            Ok(number) => number.to_string(),
+                            Err(rejection) => rejection.status().as_u16().to_string(),
+                        });
+                    }
+                    results.join(",")
+                }),
+            )
+            .layer(DefaultBodyLimit::max(16));
+        let client = TestClient::new(app);
+
+        let res = client
+            .post("/")
+            .body("1\n---\nx\n---\n3")
+            .header("content-type", "application/yaml")
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.text().await, "1,400,3");
+
+        let res = client
+            .post("/")
+            .body("1\n---\n2\n---\n3\n---\n4\n---\n5\n")
+            .header("content-type", "application/yaml")
+            .await;
+        assert_eq!(res.text().await, "413");
+
+        let res = client.post("/").body("1").await;
+        assert_eq!(res.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+
+    #[tokio::test]
+    async fn max_documents() {
+        use axum::Extension;
+        use futures_util::StreamExt;
+
+        let app = Router::new()
+            .route(
+                "/",
+                post(|YamlMultiDoc(numbers): YamlMultiDoc<u32>| async move {
+                    numbers.len().to_string()
+                }),
+            )
+            .route(
+                "/stream",
+                post(|mut documents: YamlDocumentStream<u32>| async move {
+                    let mut results = Vec::new();
+                    while let Some(document) = documents.next().await {
+                        results.push(match document {
+                            Ok(number) => number.to_string(),
+                            Err(rejection) => rejection.status().as_u16().to_string(),
+                        });
+                    }
+                    results.join(",")
+                }),
+            )
+            .layer(Extension(YamlConfig::new().max_documents(2)));
+        let client = TestClient::new(app);
+
+        let res = client
+            .post("/")
+            .body("1\n---\n2\n")
+            .header("content-type", "application/yaml")
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.text().await, "2");
+
+        let res = client
+            .post("/")
+            .body("1\n---\n2\n---\n3\n---\n4\n")
+            .header("content-type", "application/yaml")
+            .await;
+        assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(
+            res.text().await,
+            "Too many YAML documents: body has more than 2 documents"
+        );
+
+        let res = client
+            .post("/stream")
+            .body("1\n---\n2\n---\n3\n---\n4\n")
+            .header("content-type", "application/yaml")
+            .await;
+        assert_eq!(res.text().await, "1,2,413");
+    }
+}
@@ -0,0 +1,675 @@
+//! Content negotiation against the `Accept` request header.
+//!
+//! [`negotiate`] picks the media type a response should use among those a
+//! handler or middleware can produce, following the quality values and
+//! wildcards of RFC 9110: the most specific media range matching a media
+//! type decides its quality, `application/*+yaml` matches every `+yaml`
+//! type, and ties go to the earliest available media type.
+//!
+//! ```
+//! use axum_yaml::negotiate::negotiate;
+//! use http::{header, HeaderMap, HeaderValue};
+//! use mime::Mime;
+//!
+//! let mut headers = HeaderMap::new();
+//! headers.insert(
+//!     header::ACCEPT,
+//!     HeaderValue::from_static("application/json;q=0.5, application/*+yaml"),
+//! );
+//!
+//! let available: [Mime; 2] = [
+//!     mime::APPLICATION_JSON,
+//!     "application/vnd.mycorp.deploy+yaml".parse().unwrap(),
+//! ];
+//! let chosen = negotiate(&headers, &available).unwrap();
+//! assert_eq!(chosen.essence_str(), "application/vnd.mycorp.deploy+yaml");
+//! ```
+//!
+//! [`NegotiateRejectionLayer`] applies it to the rejections of this crate, so
+//! JSON-consuming clients of a mixed API get JSON error bodies:
+//!
+//! ```no_run
+//! use axum::{routing::post, Router};
+//! use axum_yaml::{negotiate::NegotiateRejectionLayer, Yaml};
+//! use serde_yaml::Value;
+//!
+//! async fn echo(Yaml(value): Yaml<Value>) -> Yaml<Value> {
+//!     Yaml(value)
+//! }
+//!
+//! let app: Router = Router::new()
+//!     .route("/echo", post(echo))
+//!     .layer(NegotiateRejectionLayer);
+//! ```
+//!
+//! [`NegotiateLayer`] applies it to the [`Negotiated`] responses of handlers,
+//! written as YAML by default or with one of the [`Codecs`] registered for
+//! other media types, such as JSON, TOML or MessagePack:
+//!
+//! ```no_run
+//! use axum::{routing::get, Router};
+//! use axum_yaml::negotiate::{Codecs, NegotiateLayer, Negotiated};
+//! use std::collections::BTreeMap;
+//!
+//! async fn counts() -> Negotiated<BTreeMap<String, u32>> {
+//!     Negotiated(BTreeMap::from([("a".to_owned(), 1)]))
+//! }
+//!
+//! let codecs = Codecs::new().codec(
+//!     "text/plain".parse().unwrap(),
+//!     |value: &serde_yaml::Value| Ok::<_, std::fmt::Error>(format!("{value:?}").into_bytes()),
+//! );
+//! let app: Router = Router::new()
+//!     .route("/counts", get(counts))
+//!     .layer(NegotiateLayer::new(codecs));
+//! ```
+
+use std::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use axum_core::body::Body;
+use axum_core::response::{IntoResponse, Response};
+use axum_core::BoxError;
+use http::{header, HeaderMap, HeaderValue, Request, StatusCode};
+use mime::Mime;
+use pin_project_lite::pin_project;
+use serde::Serialize;
+use serde_yaml::Value;
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::rejection::{BodyFormat, RejectionBody};
+use crate::ser::{self, SerializeConfig};
+
+/// Choose the media type of `available` the client prefers, according to the
+/// `Accept` headers of the request.
+///
+/// Without an `Accept` header every media type is acceptable and the first
+/// one is returned. `None` means the client accepts none of them, which
+/// handlers usually answer with `406 Not Acceptable`. Malformed media ranges
+/// are ignored.
+pub fn negotiate(headers: &HeaderMap, available: &[Mime]) -> Option<Mime> {
+    let ranges: Vec<_> = headers
+        .get_all(header::ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| media_ranges(value).filter_map(Result::ok))
+        .collect();
+    if ranges.is_empty() {
+        return available.first().cloned();
+    }
+
+    let mut chosen: Option<(&Mime, f32)> = None;
+    for mime in available {
+        let quality = ranges
+            .iter()
+            .filter_map(|(range, quality)| Some((specificity(range, mime)?, *quality)))
+            .max_by_key(|(specificity, _)| *specificity)
+            .map_or(0.0, |(_, quality)| quality);
+        if quality > 0.0 && chosen.is_none_or(|(_, best)| quality > best) {
+            chosen = Some((mime, quality));
+        }
+    }
+    chosen.map(|(mime, _)| mime.clone())
+}
+
+/// [`Layer`] rendering the body of the rejections of this crate in the
+/// [`BodyFormat`] the client prefers according to its `Accept` header.
+///
+/// Requests without an `Accept` header, or accepting none of the formats, get
+/// the [`YamlConfig::rejection_format`](crate::YamlConfig::rejection_format)
+/// the rejection was rendered in, which also wins ties.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NegotiateRejectionLayer;
+
+impl<S> Layer<S> for NegotiateRejectionLayer {
+    type Service = NegotiateRejection<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        NegotiateRejection { inner }
+    }
+}
+
+/// Middleware created by [`NegotiateRejectionLayer`].
+#[derive(Debug, Clone)]
+pub struct NegotiateRejection<S> {
+    inner: S,
+}
+
+impl<S, ReqBody> Service<Request<ReqBody>> for NegotiateRejection<S>
+where
+    S: Service<Request<ReqBody>, Response = Response>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
+        let mut accept = HeaderMap::new();
+        for value in req.headers().get_all(header::ACCEPT) {
+            accept.append(header::ACCEPT, value.clone());
+        }
+        ResponseFuture {
+            accept,
+            inner: self.inner.call(req),
+        }
+    }
+}
+
+pin_project! {
+    /// Response future for [`NegotiateRejection`].
+    pub struct ResponseFuture<F> {
+        #[pin]
+        inner: F,
+        accept: HeaderMap,
+    }
+}
+
+impl<F, E> Future for ResponseFuture<F>
+where
+    F: Future<Output = Result<Response, E>>,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let res = std::task::ready!(this.inner.poll(cx))?;
+        let format = res
+            .extensions()
+            .get::<RejectionBody>()
+            .and_then(|body| rejection_format(this.accept, body.format()));
+        Poll::Ready(Ok(match format {
+            Some(format) => RejectionBody::render_again(res, format),
+            None => res,
+        }))
+    }
+}
+
+/// The rejection body format preferred by the `Accept` headers of a
+/// request, `None` to keep the `default` the rejection was rendered in.
+fn rejection_format(headers: &HeaderMap, default: BodyFormat) -> Option<BodyFormat> {
+    if !headers.contains_key(header::ACCEPT) {
+        return None;
+    }
+
+    let formats: Vec<BodyFormat> = std::iter::once(default)
+        .chain(
+            BodyFormat::ALL
+                .iter()
+                .copied()
+                .filter(|&format| format != default),
+        )
+        .collect();
+    let available: Vec<Mime> = formats
+        .iter()
+        .filter_map(|format| format.media_type().parse().ok())
+        .collect();
+
+    let chosen = negotiate(headers, &available)?;
+    formats
+        .into_iter()
+        .zip(&available)
+        .find(|(_, mime)| **mime == chosen)
+        .map(|(format, _)| format)
+        .filter(|&format| format != default)
+}
+
+type Encode = dyn Fn(&Value) -> Result<Vec<u8>, BoxError> + Send + Sync;
+
+/// Codecs writing [`Negotiated`] responses in media types other than YAML,
+/// for a [`NegotiateLayer`].
+///
+/// A codec encodes the document of the response, a [`serde_yaml::Value`]
+/// with the key case and key order of the [`SerializeConfig`] applied, so
+/// any `serde` format can be plugged in. YAML is always available, and
+/// preferred when the client accepts several media types equally.
+#[derive(Clone, Default)]
+pub struct Codecs {
+    codecs: Vec<(Mime, Arc<Encode>)>,
+}
+
+impl Codecs {
+    /// Codecs for YAML only.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Write responses accepting `media_type` with `encode`.
+    ///
+    /// Codecs registered first win ties between equally accepted media
+    /// types.
+    pub fn codec<F, E>(mut self, media_type: Mime, encode: F) -> Self
+    where
+        F: Fn(&Value) -> Result<Vec<u8>, E> + Send + Sync + 'static,
+        E: Into<BoxError>,
+    {
+        let encode = move |value: &Value| encode(value).map_err(Into::into);
+        self.codecs.push((media_type, Arc::new(encode)));
+        self
+    }
+
+    /// Write responses accepting `application/json` as JSON.
+    ///
+    /// Requires the `json` feature.
+    #[cfg(feature = "json")]
+    pub fn json(self) -> Self {
+        self.codec(mime::APPLICATION_JSON, |value: &Value| {
+            serde_json::to_vec(value)
+        })
+    }
+
+    /// Write responses accepting `application/toml` as TOML.
+    ///
+    /// Requires the `toml` feature.
+    #[cfg(feature = "toml")]
+    pub fn toml(self) -> Self {
+        let media_type = "application/toml".parse().expect("valid media type");
+        self.codec(media_type, |value: &Value| {
+            toml::to_string(value).map(String::into_bytes)
+        })
+    }
+}
+
+impl fmt::Debug for Codecs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.codecs.iter().map(|(media_type, _)| media_type))
+            .finish()
+    }
+}
+
+/// Response written in the media type the client prefers among YAML and the
+/// [`Codecs`] of the enclosing [`NegotiateLayer`].
+///
+/// The response is written as YAML, like [`Yaml`](crate::Yaml), and keeps
+/// its document for the layer to write it again with another codec. Outside
+/// of a `NegotiateLayer` it is always YAML.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Negotiated<T>(pub T);
+
+/// Document of a [`Negotiated`] response, carried in its extensions.
+#[derive(Clone)]
+struct Document(Arc<Value>);
+
+impl<T> IntoResponse for Negotiated<T>
+where
+    T: Serialize,
+{
+    fn into_response(self) -> Response {
+        let config = SerializeConfig::current();
+        let document = match config.to_value(&self.0) {
+            Ok(document) => document,
+            Err(err) => return ser::error_response(err),
+        };
+        let mut res = ser::response(&document, &config);
+        if res.status().is_success() {
+            res.extensions_mut().insert(Document(Arc::new(document)));
+        }
+        res
+    }
+}
+
+/// [`Layer`] writing the [`Negotiated`] responses of the wrapped service in
+/// the media type the `Accept` header of the request prefers.
+///
+/// Negotiated responses vary on `Accept`. Requests accepting none of the
+/// media types are answered with `406 Not Acceptable`, listing them.
+#[derive(Debug, Clone, Default)]
+pub struct NegotiateLayer {
+    codecs: Arc<Codecs>,
+}
+
+impl NegotiateLayer {
+    /// Negotiate between YAML and `codecs`.
+    pub fn new(codecs: Codecs) -> Self {
+        Self {
+            codecs: Arc::new(codecs),
+        }
+    }
+}
+
+impl<S> Layer<S> for NegotiateLayer {
+    type Service = Negotiate<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Negotiate {
+            inner,
+            codecs: self.codecs.clone(),
+        }
+    }
+}
+
+/// Middleware created by [`NegotiateLayer`].
+#[derive(Debug, Clone)]
+pub struct Negotiate<S> {
+    inner: S,
+    codecs: Arc<Codecs>,
+}
+
+impl<S, ReqBody> Service<Request<ReqBody>> for Negotiate<S>
+where
+    S: Service<Request<ReqBody>, Response = Response>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = NegotiateFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
+        let mut accept = HeaderMap::new();
+        for value in req.headers().get_all(header::ACCEPT) {
+            accept.append(header::ACCEPT, value.clone());
+        }
+        NegotiateFuture {
+            accept,
+            codecs: self.codecs.clone(),
+            inner: self.inner.call(req),
+        }
+    }
+}
+
+pin_project! {
+    /// Response future for [`Negotiate`].
+    pub struct NegotiateFuture<F> {
+        #[pin]
+        inner: F,
+        accept: HeaderMap,
+        codecs: Arc<Codecs>,
+    }
+}
+
+impl<F, E> Future for NegotiateFuture<F>
+where
+    F: Future<Output = Result<Response, E>>,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let res = std::task::ready!(this.inner.poll(cx))?;
+        Poll::Ready(Ok(encode(res, this.accept, this.codecs)))
+    }
+}
+
+/// Write the document of the [`Negotiated`] response `res`, if it is one,
+/// with the codec `accept` prefers.
+fn encode(mut res: Response, accept: &HeaderMap, codecs: &Codecs) -> Response {
+    let Some(Document(document)) = res.extensions_mut().remove::<Document>() else {
+        return res;
+    };
+    res.headers_mut()
+        .append(header::VARY, HeaderValue::from_static("accept"));
+
+    let yaml = res
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok()?.parse::<Mime>().ok())
+        .unwrap_or_else(|| "application/yaml".parse().expect("valid media type"));
+    let available: Vec<Mime> = std::iter::once(yaml.clone())
+        .chain(
+            codecs
+                .codecs
+                .iter()
+                .map(|(media_type, _)| media_type.clone()),
+        )
+        .collect();
+
+    let Some(chosen) = negotiate(accept, &available) else {
+        let available: Vec<_> = available.iter().map(Mime::essence_str).collect();
+        let mut not_acceptable = (
+            StatusCode::NOT_ACCEPTABLE,
+            format!("Not acceptable, expected one of {}", available.join(", ")),
+        )
+            .into_response();
+        not_acceptable
+            .headers_mut()
+            .append(header::VARY, HeaderValue::from_static("accept"));
+        return not_acceptable;
+    };
+    if chosen == yaml {
+        return res;
+    }
+
+    let Some((media_type, encode)) = codecs
+        .codecs
+        .iter()
+        .find(|(media_type, _)| *media_type == chosen)
+    else {
+        return res;
+    };
+    let body = match encode(&document) {
+        Ok(body) => body,
+        Err(err) => return ser::error_response(err),
+    };
+    let (mut parts, _) = res.into_parts();
+    parts.headers.remove(header::CONTENT_LENGTH);
+    if let Ok(content_type) = HeaderValue::from_str(media_type.as_ref()) {
+        parts.headers.insert(header::CONTENT_TYPE, content_type);
+    }
+    Response::from_parts(parts, Body::from(body))
+}
+
+/// Parse the comma-separated media ranges of an `Accept` header value along
+/// with their quality values.
+pub(crate) fn media_ranges(value: &str) -> impl Iterator<Item = Result<(Mime, f32), ()>> + '_ {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|range| !range.is_empty())
+        .map(|range| {
+            let range: Mime = range.parse().map_err(drop)?;
+            let quality = match range.get_param("q") {
+                Some(quality) => quality
+                    .as_str()
+                    .parse::<f32>()
+                    .ok()
+                    .filter(|quality| (0.0..=1.0).contains(quality))
+                    .ok_or(())?,
+                None => 1.0,
+            };
+            Ok((range, quality))
+        })
+}
+
+/// How specifically `range` matches `mime`, `None` if it does not.
+fn specificity(range: &Mime, mime: &Mime) -> Option<u8> {
+    if range.type_() == mime::STAR && range.subtype() == mime::STAR {
+        return Some(0);
+    }
+    if range.type_() != mime.type_() {
+        return None;
+    }
+    if range.subtype() == mime::STAR {
+        return match range.suffix() {
+            Some(suffix) if mime.suffix() == Some(suffix) => Some(2),
+            Some(_) => None,
+            None => Some(1),
+        };
+    }
+    (range.essence_str() == mime.essence_str()).then_some(3)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use http::{HeaderValue, StatusCode};
+
+    fn negotiate(accept: Option<&'static str>, available: &[&str]) -> Option<String> {
+        let mut headers = HeaderMap::new();
+        if let Some(accept) = accept {
+            headers.insert(header::ACCEPT, HeaderValue::from_static(accept));
+        }
+        let available: Vec<Mime> = available.iter().map(|mime| mime.parse().unwrap()).collect();
+        super::negotiate(&headers, &available).map(|mime| mime.to_string())
+    }
+
+    #[test]
+    fn quality_and_specificity() {
+        let available = ["application/yaml", "application/json"];
+
+        assert_eq!(
+            negotiate(None, &available).as_deref(),
+            Some("application/yaml")
+        );
+        assert_eq!(
+            negotiate(Some("application/json"), &available).as_deref(),
+            Some("application/json")
+        );
+        assert_eq!(
+            negotiate(Some("application/*;q=0.5, application/json"), &available).as_deref(),
+            Some("application/json")
+        );
+        assert_eq!(
+            negotiate(Some("*/*, application/yaml;q=0"), &available).as_deref(),
+            Some("application/json")
+        );
+        assert_eq!(negotiate(Some("text/html"), &available), None);
+        assert_eq!(
+            negotiate(Some("bogus, application/yaml;q=7, */*"), &available).as_deref(),
+            Some("application/yaml")
+        );
+    }
+
+    #[test]
+    fn rejection_formats() {
+        let format = |accept: &'static str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(header::ACCEPT, HeaderValue::from_static(accept));
+            rejection_format(&headers, BodyFormat::Text)
+        };
+
+        assert_eq!(rejection_format(&HeaderMap::new(), BodyFormat::Text), None);
+        assert_eq!(format("*/*"), None);
+        assert_eq!(format("text/plain"), None);
+        assert_eq!(format("application/yaml"), Some(BodyFormat::Yaml));
+        assert_eq!(
+            format("application/yaml;q=0.5, application/problem+yaml"),
+            Some(BodyFormat::ProblemYaml)
+        );
+        #[cfg(feature = "json")]
+        assert_eq!(format("application/json"), Some(BodyFormat::Json));
+        #[cfg(feature = "dev-errors")]
+        assert_eq!(
+            format("text/html,application/xhtml+xml,*/*;q=0.8"),
+            Some(BodyFormat::Html)
+        );
+    }
+
+    #[tokio::test]
+    async fn negotiate_rejections() {
+        use axum::routing::post;
+        use axum::Router;
+        use serde_yaml::Value;
+
+        use crate::test_client::TestClient;
+        use crate::Yaml;
+
+        let app = Router::new()
+            .route("/", post(|Yaml(value): Yaml<Value>| async { Yaml(value) }))
+            .layer(NegotiateRejectionLayer);
+        let client = TestClient::new(app);
+
+        let res = client
+            .post("/")
+            .body("a: 1")
+            .header("accept", "application/yaml")
+            .await;
+        assert_eq!(res.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        assert_eq!(res.headers()["content-type"], "application/yaml");
+        assert_eq!(
+            res.headers()["accept-post"],
+            "application/yaml, application/*+yaml"
+        );
+        assert_eq!(
+            res.text().await,
+            "code: missing_yaml_content_type\n\
+             message: 'Expected request with `Content-Type: application/yaml`'\n"
+        );
+
+        let res = client.post("/").body("a: 1").await;
+        assert_eq!(res.headers()["content-type"], "text/plain; charset=utf-8");
+    }
+
+    #[tokio::test]
+    async fn negotiated() {
+        use std::collections::BTreeMap;
+
+        use axum::routing::get;
+        use axum::Router;
+
+        use crate::test_client::TestClient;
+
+        let codecs = Codecs::new().codec("text/plain".parse().unwrap(), |value: &Value| {
+            Ok::<_, fmt::Error>(format!("{value:?}").into_bytes())
+        });
+        let app = Router::new()
+            .route(
+                "/",
+                get(|| async { Negotiated(BTreeMap::from([("a", 1)])) }),
+            )
+            .layer(NegotiateLayer::new(codecs));
+        let client = TestClient::new(app);
+
+        let res = client.get("/").await;
+        assert_eq!(res.headers()["content-type"], "application/yaml");
+        assert_eq!(res.headers()["vary"], "accept");
+        assert_eq!(res.text().await, "a: 1\n");
+
+        let res = client
+            .get("/")
+            .header("accept", "application/yaml;q=0.5, text/plain")
+            .await;
+        assert_eq!(res.headers()["content-type"], "text/plain");
+        assert_eq!(res.text().await, "Mapping {\"a\": Number(1)}");
+
+        let res = client.get("/").header("accept", "application/json").await;
+        assert_eq!(res.status(), StatusCode::NOT_ACCEPTABLE);
+        assert_eq!(res.headers()["vary"], "accept");
+        assert_eq!(
+            res.text().await,
+            "Not acceptable, expected one of application/yaml, text/plain"
+        );
+    }
+
+    #[cfg(feature = "json")]
+    #[tokio::test]
+    async fn json() {
+        use axum::routing::get;
+        use axum::Router;
+
+        use crate::test_client::TestClient;
+
+        let app = Router::new()
+            .route("/", get(|| async { Negotiated(vec![1, 2]) }))
+            .layer(NegotiateLayer::new(Codecs::new().json()));
+        let client = TestClient::new(app);
+
+        let res = client.get("/").header("accept", "application/json").await;
+        assert_eq!(res.headers()["content-type"], "application/json");
+        assert_eq!(res.text().await, "[1,2]");
+    }
+
+    #[test]
+    fn suffix() {
+        let available = ["application/json", "application/vnd.mycorp.deploy+yaml"];
+
+        assert_eq!(
+            negotiate(Some("application/*+yaml"), &available).as_deref(),
+            Some("application/vnd.mycorp.deploy+yaml")
+        );
+        assert_eq!(negotiate(Some("application/*+xml"), &available), None);
+    }
+}
@@ -0,0 +1,172 @@
+//! Normalization of legacy YAML request content types.
+//!
+//! Older clients send YAML as `text/yaml`, `text/x-yaml` or
+//! `application/x-yaml`, which the extractors of this crate reject. Wrap a
+//! router with [`NormalizeContentTypeLayer`] to rewrite these content types to
+//! `application/yaml` before they reach the extractors, keeping their
+//! parameters, so existing handlers accept them unchanged.
+//!
+//! The layer also inserts the [`YamlContentTypeInfo`] of YAML requests into
+//! their extensions, for the handlers and middleware it wraps. Each rewrite
+//! is reported as a [`Leniency::LegacyContentType`], see the
+//! [`lenient`](crate::lenient) module.
+//!
+//! ```no_run
+//! use axum::{routing::post, Router};
+//! use axum_yaml::{normalize::NormalizeContentTypeLayer, Yaml};
+//! use serde_yaml::Value;
+//!
+//! async fn echo(Yaml(value): Yaml<Value>) -> Yaml<Value> {
+//!     Yaml(value)
+//! }
+//!
+//! let app: Router = Router::new()
+//!     .route("/echo", post(echo))
+//!     .layer(NormalizeContentTypeLayer);
+//! ```
+
+use std::task::{Context, Poll};
+
+use http::{header, HeaderValue, Request};
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::lenient::Leniency;
+use crate::media_type::YamlContentTypeInfo;
+use crate::yaml::is_yaml_content_type;
+
+/// Content types rewritten to `application/yaml`.
+const LEGACY_MEDIA_TYPES: [&str; 3] = ["text/yaml", "text/x-yaml", "application/x-yaml"];
+
+/// [`Layer`] rewriting legacy YAML request content types to
+/// `application/yaml` and inserting the [`YamlContentTypeInfo`] of YAML
+/// requests into their extensions.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NormalizeContentTypeLayer;
+
+impl<S> Layer<S> for NormalizeContentTypeLayer {
+    type Service = NormalizeContentType<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        NormalizeContentType { inner }
+    }
+}
+
+/// Middleware created by [`NormalizeContentTypeLayer`].
+#[derive(Debug, Clone)]
+pub struct NormalizeContentType<S> {
+    inner: S,
+}
+
+impl<S, ReqBody> Service<Request<ReqBody>> for NormalizeContentType<S>
+where
+    S: Service<Request<ReqBody>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request<ReqBody>) -> Self::Future {
+        let normalized = req
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|content_type| normalize(content_type.to_str().ok()?));
+        if let Some(content_type) = normalized {
+            Leniency::LegacyContentType.report(req.headers(), req.extensions());
+            req.headers_mut().insert(header::CONTENT_TYPE, content_type);
+        }
+        if is_yaml_content_type(req.headers()) {
+            if let Some(info) = YamlContentTypeInfo::from_headers(req.headers()) {
+                req.extensions_mut().insert(info);
+            }
+        }
+
+        self.inner.call(req)
+    }
+}
+
+/// `content_type` with a legacy essence replaced by `application/yaml`, `None`
+/// if it does not need rewriting.
+fn normalize(content_type: &str) -> Option<HeaderValue> {
+    let mime = content_type.parse::<mime::Mime>().ok()?;
+    if !LEGACY_MEDIA_TYPES.contains(&mime.essence_str()) {
+        return None;
+    }
+
+    let params = content_type
+        .find(';')
+        .map_or("", |start| &content_type[start..]);
+    HeaderValue::from_str(&format!("application/yaml{params}")).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use axum::routing::post;
+    use axum::Router;
+    use http::StatusCode;
+    use serde_yaml::Value;
+
+    use crate::test_client::TestClient;
+    use crate::Yaml;
+
+    #[test]
+    fn rewrite() {
+        assert_eq!(normalize("text/yaml").unwrap(), "application/yaml");
+        assert_eq!(
+            normalize("Application/X-YAML; charset=utf-8").unwrap(),
+            "application/yaml; charset=utf-8"
+        );
+        assert_eq!(normalize("application/yaml"), None);
+        assert_eq!(normalize("text/plain"), None);
+    }
+
+    #[tokio::test]
+    async fn legacy_content_types() {
+        let app = Router::new()
+            .route("/", post(|Yaml(value): Yaml<Value>| async { Yaml(value) }))
+            .layer(NormalizeContentTypeLayer);
+        let client = TestClient::new(app);
+
+        for content_type in ["text/yaml", "application/x-yaml; charset=utf-8"] {
+            let res = client
+                .post("/")
+                .body("foo: bar")
+                .header("content-type", content_type)
+                .await;
+            assert_eq!(res.status(), StatusCode::OK);
+            assert_eq!(res.text().await, "foo: bar\n");
+        }
+
+        let res = client
+            .post("/")
+            .body("foo: bar")
+            .header("content-type", "text/plain")
+            .await;
+        assert_eq!(res.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+
+    #[tokio::test]
+    async fn content_type_info() {
+        let app = Router::new()
+            .route(
+                "/",
+                post(|req: Request<axum::body::Body>| async move {
+                    let info = req.extensions().get::<YamlContentTypeInfo>().unwrap();
+                    format!("{} {:?}", info.media_type(), info.param("version"))
+                }),
+            )
+            .layer(NormalizeContentTypeLayer);
+
+        let res = TestClient::new(app)
+            .post("/")
+            .header("content-type", "text/yaml; version=2")
+            .await;
+        assert_eq!(res.text().await, "application/yaml Some(\"2\")");
+    }
+}
@@ -0,0 +1,315 @@
+//! Paths selecting nodes of a YAML document.
+//!
+//! A [`YamlPath`] is written as `.` separated mapping keys with optional
+//! `[n]` sequence indexes, e.g. `spec.containers[0].image`. Wildcards match
+//! several nodes at once:
+//!
+//! - `*` matches any mapping key and `[*]` any sequence item,
+//! - `**` matches any number of nested levels, so `**.privileged` selects
+//!   every `privileged` key of the document.
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde_yaml::Value;
+
+/// Path selecting nodes of a YAML document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct YamlPath {
+    source: String,
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Key(String),
+    Index(usize),
+    AnyKey,
+    AnyIndex,
+    Descendants,
+}
+
+/// Error returned when parsing an invalid [`YamlPath`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidYamlPath(String);
+
+impl fmt::Display for InvalidYamlPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid YAML path `{}`", self.0)
+    }
+}
+
+impl std::error::Error for InvalidYamlPath {}
+
+impl FromStr for YamlPath {
+    type Err = InvalidYamlPath;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        let invalid = || InvalidYamlPath(source.to_owned());
+
+        let mut segments = Vec::new();
+        for part in source.split('.') {
+            let (key, mut indexes) = match part.find('[') {
+                Some(bracket) => part.split_at(bracket),
+                None => (part, ""),
+            };
+
+            match key {
+                "" if indexes.is_empty() => return Err(invalid()),
+                "" => {}
+                "*" => segments.push(Segment::AnyKey),
+                "**" => segments.push(Segment::Descendants),
+                key => segments.push(Segment::Key(key.to_owned())),
+            }
+
+            while !indexes.is_empty() {
+                let end = indexes.find(']').ok_or_else(invalid)?;
+                let index = &indexes[1..end];
+                segments.push(match index {
+                    "*" => Segment::AnyIndex,
+                    index => Segment::Index(index.parse().map_err(|_| invalid())?),
+                });
+                indexes = &indexes[end + 1..];
+                if !indexes.is_empty() && !indexes.starts_with('[') {
+                    return Err(invalid());
+                }
+            }
+        }
+
+        Ok(Self {
+            source: source.to_owned(),
+            segments,
+        })
+    }
+}
+
+impl fmt::Display for YamlPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+impl YamlPath {
+    /// Parse a path, panicking if it is invalid.
+    ///
+    /// Meant for paths written in source code, where an invalid path is a bug.
+    #[track_caller]
+    pub fn new(source: &str) -> Self {
+        match source.parse() {
+            Ok(path) => path,
+            Err(err) => panic!("{err}"),
+        }
+    }
+
+    /// Find every node selected by this path, along with its concrete
+    /// location such as `spec.containers[0].image`.
+    pub fn find<'a>(&self, value: &'a Value) -> Vec<(String, &'a Value)> {
+        let mut found = Vec::new();
+        find(&self.segments, value, String::new(), &mut found);
+        found
+    }
+
+    /// Remove every node selected by this path from `value`.
+    pub fn remove(&self, value: &mut Value) {
+        remove(&self.segments, value);
+    }
+}
+
+fn find<'a>(
+    segments: &[Segment],
+    value: &'a Value,
+    location: String,
+    found: &mut Vec<(String, &'a Value)>,
+) {
+    let value = untag(value);
+    let Some((segment, rest)) = segments.split_first() else {
+        found.push((location, value));
+        return;
+    };
+
+    if let Segment::Descendants = segment {
+        find(rest, value, location.clone(), found);
+    }
+    for (step, child) in children(value) {
+        let matched = match (&step, segment) {
+            (_, Segment::Descendants) => segments,
+            (Step::Key(key), segment) if matches_key(segment, key) => rest,
+            (Step::Index(index), segment) if matches_index(segment, *index) => rest,
+            _ => continue,
+        };
+        let child_location = match step {
+            Step::Key(key) if location.is_empty() => key,
+            Step::Key(key) => format!("{location}.{key}"),
+            Step::Index(index) => format!("{location}[{index}]"),
+        };
+        find(matched, child, child_location, found);
+    }
+}
+
+fn remove(segments: &[Segment], value: &mut Value) {
+    let value = untag_mut(value);
+    let [segment, rest @ ..] = segments else {
+        return;
+    };
+
+    if let Segment::Descendants = segment {
+        remove(rest, value);
+        match value {
+            Value::Mapping(mapping) => mapping
+                .values_mut()
+                .for_each(|child| remove(segments, child)),
+            Value::Sequence(sequence) => sequence
+                .iter_mut()
+                .for_each(|child| remove(segments, child)),
+            _ => {}
+        }
+        return;
+    }
+
+    match value {
+        Value::Mapping(mapping) => {
+            if rest.is_empty() {
+                mapping.retain(|key, _| !matches_key(segment, &key_to_string(key)));
+            } else {
+                for (key, child) in mapping.iter_mut() {
+                    if matches_key(segment, &key_to_string(key)) {
+                        remove(rest, child);
+                    }
+                }
+            }
+        }
+        Value::Sequence(sequence) => {
+            if rest.is_empty() {
+                let mut index = 0;
+                sequence.retain(|_| {
+                    let keep = !matches_index(segment, index);
+                    index += 1;
+                    keep
+                });
+            } else {
+                for (index, child) in sequence.iter_mut().enumerate() {
+                    if matches_index(segment, index) {
+                        remove(rest, child);
+                    }
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+enum Step {
+    Key(String),
+    Index(usize),
+}
+
+fn children(value: &Value) -> Vec<(Step, &Value)> {
+    match value {
+        Value::Mapping(mapping) => mapping
+            .iter()
+            .map(|(key, child)| (Step::Key(key_to_string(key)), child))
+            .collect(),
+        Value::Sequence(sequence) => sequence
+            .iter()
+            .enumerate()
+            .map(|(index, child)| (Step::Index(index), child))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn matches_key(segment: &Segment, key: &str) -> bool {
+    match segment {
+        Segment::Key(expected) => expected == key,
+        Segment::AnyKey => true,
+        _ => false,
+    }
+}
+
+fn matches_index(segment: &Segment, index: usize) -> bool {
+    match segment {
+        Segment::Index(expected) => *expected == index,
+        Segment::AnyIndex => true,
+        _ => false,
+    }
+}
+
+pub(crate) fn key_to_string(key: &Value) -> String {
+    match untag(key) {
+        Value::String(key) => key.clone(),
+        key => serde_yaml::to_string(key)
+            .map(|key| key.trim_end().to_owned())
+            .unwrap_or_default(),
+    }
+}
+
+fn untag(value: &Value) -> &Value {
+    match value {
+        Value::Tagged(tagged) => untag(&tagged.value),
+        value => value,
+    }
+}
+
+fn untag_mut(value: &mut Value) -> &mut Value {
+    match value {
+        Value::Tagged(tagged) => untag_mut(&mut tagged.value),
+        value => value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn locations(path: &str, document: &str) -> Vec<String> {
+        let document: Value = serde_yaml::from_str(document).unwrap();
+        YamlPath::new(path)
+            .find(&document)
+            .into_iter()
+            .map(|(location, _)| location)
+            .collect()
+    }
+
+    #[test]
+    fn find() {
+        let document = "spec:\n  containers:\n  - image: a\n  - image: b\n    privileged: true\n";
+
+        assert_eq!(
+            locations("spec.containers[1].image", document),
+            ["spec.containers[1].image"]
+        );
+        assert_eq!(
+            locations("spec.containers[*].image", document),
+            ["spec.containers[0].image", "spec.containers[1].image"]
+        );
+        assert_eq!(
+            locations("*.containers[0]", document),
+            ["spec.containers[0]"]
+        );
+        assert_eq!(
+            locations("**.privileged", document),
+            ["spec.containers[1].privileged"]
+        );
+        assert!(locations("spec.volumes", document).is_empty());
+    }
+
+    #[test]
+    fn remove() {
+        let mut document: Value =
+            serde_yaml::from_str("a: 1\nitems:\n- secret: x\n  name: y\n- secret: z\n").unwrap();
+        YamlPath::new("**.secret").remove(&mut document);
+        YamlPath::new("a").remove(&mut document);
+
+        assert_eq!(
+            document,
+            serde_yaml::from_str::<Value>("items:\n- name: y\n- {}\n").unwrap()
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        for path in ["", "a..b", "a[", "a[x]", "a[0]b"] {
+            assert!(path.parse::<YamlPath>().is_err(), "{path:?}");
+        }
+    }
+}
@@ -0,0 +1,218 @@
+//! Policies rejecting documents that contain forbidden paths or values.
+
+use serde_yaml::Value;
+
+use crate::path::YamlPath;
+use crate::rejection::PolicyViolation;
+
+/// Set of rules denying paths or values in incoming documents.
+///
+/// Policies are evaluated on the parsed document before it is deserialized,
+/// so handler code never runs for a document violating them. Configure one
+/// with [`YamlConfig::policy`](crate::YamlConfig::policy); violating requests
+/// are rejected with `403 Forbidden` and the location of the first violation.
+///
+/// # Example
+///
+/// ```
+/// use axum_yaml::policy::Policy;
+///
+/// let policy = Policy::new()
+///     .deny_value("spec.hostNetwork", true)
+///     .deny_value("**.privileged", true)
+///     .deny_path("spec.volumes[*].hostPath");
+///
+/// let document = serde_yaml::from_str("spec:\n  containers:\n  - privileged: true\n").unwrap();
+/// let err = policy.check(&document).unwrap_err();
+/// assert_eq!(
+///     err.body_text(),
+///     "Document violates policy: `spec.containers[0].privileged: true` is not allowed"
+/// );
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Policy {
+    rules: Vec<Rule>,
+}
+
+#[derive(Debug, Clone)]
+struct Rule {
+    path: YamlPath,
+    value: Option<Value>,
+}
+
+impl Policy {
+    /// Create a policy allowing every document.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Deny documents containing any node selected by `path`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `path` is not a valid [`YamlPath`].
+    #[track_caller]
+    pub fn deny_path(mut self, path: &str) -> Self {
+        self.rules.push(Rule {
+            path: YamlPath::new(path),
+            value: None,
+        });
+        self
+    }
+
+    /// Deny documents in which any node selected by `path` equals `value`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `path` is not a valid [`YamlPath`].
+    #[track_caller]
+    pub fn deny_value(mut self, path: &str, value: impl Into<Value>) -> Self {
+        self.rules.push(Rule {
+            path: YamlPath::new(path),
+            value: Some(value.into()),
+        });
+        self
+    }
+
+    /// Check `document` against every rule, returning the first violation.
+    pub fn check(&self, document: &Value) -> Result<(), PolicyViolation> {
+        for rule in &self.rules {
+            for (location, found) in rule.path.find(document) {
+                match &rule.value {
+                    None => {
+                        return Err(PolicyViolation::from_err(format!(
+                            "`{location}` is not allowed"
+                        )))
+                    }
+                    Some(value) if value == found => {
+                        let value = serde_yaml::to_string(value).unwrap_or_default();
+                        return Err(PolicyViolation::from_err(format!(
+                            "`{location}: {}` is not allowed",
+                            value.trim_end()
+                        )));
+                    }
+                    Some(_) => {}
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(policy: &Policy, source: &str) -> Result<(), String> {
+        let document = serde_yaml::from_str(source).unwrap();
+        policy.check(&document).map_err(|err| err.body_text())
+    }
+
+    #[test]
+    fn deny_path() {
+        let policy = Policy::new().deny_path("spec.hostNetwork");
+
+        assert_eq!(check(&policy, "spec: {replicas: 1}\n"), Ok(()));
+        // Any value is denied, even `false` or `null`.
+        for value in ["true", "false", "null", "{}"] {
+            assert_eq!(
+                check(&policy, &format!("spec:\n  hostNetwork: {value}\n")),
+                Err("Document violates policy: `spec.hostNetwork` is not allowed".to_owned())
+            );
+        }
+    }
+
+    #[test]
+    fn any_index() {
+        let policy = Policy::new()
+            .deny_path("spec.volumes[*].hostPath")
+            .deny_value("spec.containers[*].image", "latest");
+
+        assert_eq!(
+            check(
+                &policy,
+                "spec:\n  volumes:\n  - name: data\n  - hostPath: /var\n"
+            ),
+            Err("Document violates policy: `spec.volumes[1].hostPath` is not allowed".to_owned())
+        );
+        assert_eq!(
+            check(
+                &policy,
+                "spec:\n  containers:\n  - image: nginx\n  - image: latest\n"
+            ),
+            Err(
+                "Document violates policy: `spec.containers[1].image: latest` is not allowed"
+                    .to_owned()
+            )
+        );
+        // `[*]` only selects sequence items.
+        assert_eq!(
+            check(&policy, "spec:\n  volumes:\n    a: {hostPath: /var}\n"),
+            Ok(())
+        );
+        assert_eq!(check(&policy, "spec:\n  volumes: []\n"), Ok(()));
+    }
+
+    #[test]
+    fn tagged_nodes() {
+        let policy = Policy::new()
+            .deny_value("spec.hostNetwork", true)
+            .deny_path("**.privileged");
+
+        // Tags do not hide nodes from the rules, at any depth.
+        assert_eq!(
+            check(&policy, "spec: !Spec\n  hostNetwork: !Flag true\n"),
+            Err("Document violates policy: `spec.hostNetwork: true` is not allowed".to_owned())
+        );
+        assert_eq!(
+            check(
+                &policy,
+                "!Pod\nspec:\n  containers: !List\n  - !Container {privileged: false}\n"
+            ),
+            Err(
+                "Document violates policy: `spec.containers[0].privileged` is not allowed"
+                    .to_owned()
+            )
+        );
+        assert_eq!(check(&policy, "spec: !Spec {hostNetwork: false}\n"), Ok(()));
+    }
+
+    #[tokio::test]
+    async fn config_rejection() {
+        use axum_core::extract::FromRequest;
+        use axum_core::response::IntoResponse;
+        use http::{header, Request, StatusCode};
+
+        use crate::{Yaml, YamlConfig};
+
+        let config = YamlConfig::new().policy(Policy::new().deny_value("spec.hostNetwork", true));
+        let request = |body: &'static str| {
+            let mut req = Request::post("/")
+                .header(header::CONTENT_TYPE, "application/yaml")
+                .body(axum_core::body::Body::from(body))
+                .unwrap();
+            req.extensions_mut().insert(config.clone());
+            req
+        };
+
+        let Yaml(document) =
+            Yaml::<Value>::from_request(request("spec: {hostNetwork: false}"), &())
+                .await
+                .unwrap();
+        assert_eq!(document["spec"]["hostNetwork"], false);
+
+        let res = Yaml::<Value>::from_request(request("spec: {hostNetwork: true}"), &())
+            .await
+            .unwrap_err()
+            .into_response();
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(
+            body,
+            "Document violates policy: `spec.hostNetwork: true` is not allowed"
+        );
+    }
+}
@@ -0,0 +1,185 @@
+//! Support for the `Prefer: return=minimal` request header of RFC 7240.
+//!
+//! Clients of write endpoints often discard the resource echoed back in the
+//! response. A handler extracting [`ReturnPreference`] and wrapping its
+//! response with [`ReturnPreference::yaml`] skips serializing the body for
+//! clients sending `Prefer: return=minimal`, answering `204 No Content` with
+//! `Preference-Applied: return=minimal` instead.
+//!
+//! ```no_run
+//! use axum::{routing::put, Router};
+//! use axum_yaml::prefer::{PreferredYaml, ReturnPreference};
+//! use axum_yaml::Yaml;
+//! use serde_yaml::Value;
+//!
+//! async fn update(prefer: ReturnPreference, Yaml(resource): Yaml<Value>) -> PreferredYaml<Value> {
+//!     // store `resource` ...
+//!     prefer.yaml(resource)
+//! }
+//!
+//! let app = Router::new().route("/resource", put(update));
+//! # let _: Router = app;
+//! ```
+
+use std::convert::Infallible;
+
+use axum_core::{
+    extract::FromRequestParts,
+    response::{IntoResponse, Response},
+};
+use http::{header, header::HeaderName, request::Parts, HeaderValue, StatusCode};
+use serde::Serialize;
+
+use crate::Yaml;
+
+const PREFER: HeaderName = HeaderName::from_static("prefer");
+const PREFERENCE_APPLIED: HeaderName = HeaderName::from_static("preference-applied");
+
+/// The `return` preference of a request.
+///
+/// When used as an extractor, it reads the `Prefer` headers of the request
+/// and never rejects.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReturnPreference {
+    /// The request did not express a `return` preference.
+    #[default]
+    Unspecified,
+    /// `Prefer: return=minimal`: the client does not need the body.
+    Minimal,
+    /// `Prefer: return=representation`: the client wants the body.
+    Representation,
+}
+
+impl ReturnPreference {
+    /// Read the `return` preference from `Prefer` header values.
+    pub fn from_headers<'a>(values: impl IntoIterator<Item = &'a HeaderValue>) -> Self {
+        for value in values {
+            let Ok(value) = value.to_str() else {
+                continue;
+            };
+            for preference in value.split(',') {
+                // Parameters of a preference follow a `;`.
+                let token = preference.split(';').next().unwrap_or_default();
+                let Some((name, value)) = token.split_once('=') else {
+                    continue;
+                };
+                if !name.trim().eq_ignore_ascii_case("return") {
+                    continue;
+                }
+                match value.trim().trim_matches('"') {
+                    "minimal" => return Self::Minimal,
+                    "representation" => return Self::Representation,
+                    _ => {}
+                }
+            }
+        }
+        Self::Unspecified
+    }
+
+    /// Create a YAML response for `value` honoring this preference.
+    pub fn yaml<T>(self, value: T) -> PreferredYaml<T> {
+        PreferredYaml {
+            value,
+            preference: self,
+        }
+    }
+}
+
+impl<S> FromRequestParts<S> for ReturnPreference
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(Self::from_headers(parts.headers.get_all(PREFER)))
+    }
+}
+
+/// YAML response honoring the `return` preference of the request.
+///
+/// Created with [`ReturnPreference::yaml`]. The value is only serialized if
+/// the client did not ask for `return=minimal`. A status code set alongside
+/// it, as in `(StatusCode::CREATED, prefer.yaml(value))`, replaces the
+/// `204 No Content` of minimal responses.
+#[derive(Debug, Clone)]
+pub struct PreferredYaml<T> {
+    value: T,
+    preference: ReturnPreference,
+}
+
+impl<T> IntoResponse for PreferredYaml<T>
+where
+    T: Serialize,
+{
+    fn into_response(self) -> Response {
+        let mut response = match self.preference {
+            ReturnPreference::Minimal => (
+                StatusCode::NO_CONTENT,
+                [(
+                    PREFERENCE_APPLIED,
+                    HeaderValue::from_static("return=minimal"),
+                )],
+            )
+                .into_response(),
+            ReturnPreference::Representation => (
+                [(
+                    PREFERENCE_APPLIED,
+                    HeaderValue::from_static("return=representation"),
+                )],
+                Yaml(self.value),
+            )
+                .into_response(),
+            ReturnPreference::Unspecified => Yaml(self.value).into_response(),
+        };
+        // The response depends on the preference, which caches must know.
+        response
+            .headers_mut()
+            .append(header::VARY, HeaderValue::from_static("prefer"));
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use axum::routing::put;
+    use axum::Router;
+
+    use crate::test_client::TestClient;
+
+    #[test]
+    fn parse() {
+        let parse = |value: &'static str| {
+            ReturnPreference::from_headers([&HeaderValue::from_static(value)])
+        };
+
+        assert_eq!(parse("return=minimal"), ReturnPreference::Minimal);
+        assert_eq!(
+            parse("respond-async, wait=10, Return=\"representation\""),
+            ReturnPreference::Representation
+        );
+        assert_eq!(parse("handling=lenient"), ReturnPreference::Unspecified);
+    }
+
+    #[tokio::test]
+    async fn minimal() {
+        let app = Router::new().route(
+            "/",
+            put(|prefer: ReturnPreference| async move { prefer.yaml(vec![1, 2]) }),
+        );
+        let client = TestClient::new(app);
+
+        let res = client.put("/").header("prefer", "return=minimal").await;
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+        assert_eq!(res.headers()["preference-applied"], "return=minimal");
+        assert_eq!(res.headers()["vary"], "prefer");
+        assert_eq!(res.text().await, "");
+
+        let res = client.put("/").await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(!res.headers().contains_key("preference-applied"));
+        assert_eq!(res.text().await, "- 1\n- 2\n");
+    }
+}
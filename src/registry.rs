@@ -0,0 +1,181 @@
+//! Publishing of the JSON Schemas of YAML payloads.
+//!
+//! Requires the `schemars` feature. A [`SchemaRegistry`] collects the schemas
+//! of the types a service accepts and serves them, as YAML, at
+//! `{name}.yaml` along with an `index.yaml` document listing them all. Mount
+//! it with `Router::nest_service`:
+//!
+//! ```no_run
+//! use axum::Router;
+//! use axum_yaml::registry::SchemaRegistry;
+//! use schemars::JsonSchema;
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize, JsonSchema)]
+//! struct User {
+//!     name: String,
+//! }
+//!
+//! #[derive(Deserialize, JsonSchema)]
+//! struct Group {
+//!     members: Vec<String>,
+//! }
+//!
+//! let schemas = SchemaRegistry::new()
+//!     .register::<User>("user")
+//!     .register::<Group>("group");
+//!
+//! // Serves `/schemas/index.yaml`, `/schemas/user.yaml` and `/schemas/group.yaml`.
+//! let app: Router = Router::new().nest_service("/schemas", schemas);
+//! ```
+
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::future::{ready, Ready};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use axum_core::response::{IntoResponse, Response};
+use http::{Method, Request, StatusCode};
+use schemars::JsonSchema;
+use serde::Serialize;
+use serde_yaml::Value;
+use tower_service::Service;
+
+use crate::Yaml;
+
+/// Registry of named JSON Schemas, served as YAML documents.
+///
+/// See the [module documentation](self).
+#[derive(Debug, Clone, Default)]
+pub struct SchemaRegistry {
+    schemas: Arc<BTreeMap<String, Value>>,
+}
+
+impl SchemaRegistry {
+    /// Create an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register the schema of `T`, served at `{name}.yaml`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `name` is already registered, is `index` or contains a `/`.
+    #[track_caller]
+    pub fn register<T>(mut self, name: &str) -> Self
+    where
+        T: JsonSchema,
+    {
+        assert!(
+            name != "index" && !name.is_empty() && !name.contains('/'),
+            "invalid schema name `{name}`"
+        );
+
+        let schema = serde_yaml::to_value(schemars::schema_for!(T))
+            .expect("JSON Schemas are representable in YAML");
+        let previous = Arc::make_mut(&mut self.schemas).insert(name.to_owned(), schema);
+        assert!(previous.is_none(), "schema `{name}` is already registered");
+        self
+    }
+
+    /// Get the registered schema named `name`.
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.schemas.get(name)
+    }
+
+    fn index(&self) -> Index<'_> {
+        Index {
+            schemas: self
+                .schemas
+                .iter()
+                .map(|(name, schema)| IndexEntry {
+                    name,
+                    title: schema.get("title").and_then(Value::as_str),
+                    url: format!("{name}.yaml"),
+                })
+                .collect(),
+        }
+    }
+
+    fn respond(&self, path: &str) -> Response {
+        let path = path.trim_start_matches('/');
+        if path.is_empty() || path == "index.yaml" {
+            return Yaml(self.index()).into_response();
+        }
+
+        match path
+            .strip_suffix(".yaml")
+            .and_then(|name| self.schemas.get(name))
+        {
+            Some(schema) => Yaml(schema).into_response(),
+            None => StatusCode::NOT_FOUND.into_response(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Index<'a> {
+    schemas: Vec<IndexEntry<'a>>,
+}
+
+#[derive(Serialize)]
+struct IndexEntry<'a> {
+    name: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    title: Option<&'a str>,
+    url: String,
+}
+
+impl<B> Service<Request<B>> for SchemaRegistry {
+    type Response = Response;
+    type Error = Infallible;
+    type Future = Ready<Result<Response, Infallible>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: Request<B>) -> Self::Future {
+        let response = match *req.method() {
+            Method::GET | Method::HEAD => self.respond(req.uri().path()),
+            _ => StatusCode::METHOD_NOT_ALLOWED.into_response(),
+        };
+        ready(Ok(response))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use axum::Router;
+
+    use crate::test_client::TestClient;
+
+    #[allow(dead_code)]
+    #[derive(JsonSchema)]
+    struct User {
+        name: String,
+    }
+
+    #[tokio::test]
+    async fn serve() {
+        let registry = SchemaRegistry::new().register::<User>("user");
+        let client = TestClient::new(Router::new().nest_service("/schemas", registry));
+
+        let res = client.get("/schemas/index.yaml").await;
+        assert_eq!(
+            res.text().await,
+            "schemas:\n- name: user\n  title: User\n  url: user.yaml\n"
+        );
+
+        let res = client.get("/schemas/user.yaml").await;
+        let schema: Value = serde_yaml::from_str(&res.text().await).unwrap();
+        assert_eq!(schema["properties"]["name"]["type"], "string");
+
+        let res = client.get("/schemas/group.yaml").await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,343 @@
+//! OPA/Rego policy evaluation for extracted documents.
+//!
+//! Requires the `rego` feature. [`RegoYaml`] evaluates the request body
+//! against Rego policies held in application state before deserializing it,
+//! which covers admission-control style services without an OPA sidecar.
+
+use std::fmt;
+
+use axum_core::{
+    extract::{FromRef, FromRequest, Request},
+    response::{IntoResponse, Response},
+    BoxError,
+};
+use http::{header, HeaderValue, StatusCode};
+use serde::de::DeserializeOwned;
+
+use crate::macros::{
+    __composite_rejection as composite_rejection, __define_rejection as define_rejection,
+};
+use crate::rejection::{
+    RejectionBody, RejectionSettings, ToRejectionBody, WithSettings, YamlRejection,
+};
+use crate::YamlConfig;
+
+/// Rego policies evaluated by [`RegoYaml`].
+///
+/// The rule named on construction must evaluate to a set or an array of deny
+/// reasons; a document is allowed when it is empty or undefined.
+///
+/// # Example
+///
+/// ```no_run
+/// use axum::{routing::post, Router};
+/// use axum_yaml::rego::{RegoPolicy, RegoYaml};
+/// use serde_yaml::Value;
+///
+/// const POLICY: &str = r#"
+/// package admission
+///
+/// deny contains msg if {
+///     input.spec.hostNetwork
+///     msg := "host network is not allowed"
+/// }
+/// "#;
+///
+/// async fn admit(RegoYaml(manifest): RegoYaml<Value>) {
+///     // `manifest` passed the policy
+/// }
+///
+/// let policy = RegoPolicy::new("data.admission.deny")
+///     .add_policy("admission.rego", POLICY)
+///     .unwrap();
+///
+/// let app: Router = Router::new()
+///     .route("/admit", post(admit))
+///     .with_state(policy);
+/// ```
+#[derive(Debug, Clone)]
+pub struct RegoPolicy {
+    engine: regorus::Engine,
+    rule: String,
+}
+
+impl RegoPolicy {
+    /// Create a policy set whose deny reasons are produced by `rule`, e.g.
+    /// `data.admission.deny`.
+    pub fn new(rule: impl Into<String>) -> Self {
+        Self {
+            engine: regorus::Engine::new(),
+            rule: rule.into(),
+        }
+    }
+
+    /// Add a Rego policy module from its source.
+    pub fn add_policy(mut self, path: &str, rego: &str) -> Result<Self, BoxError> {
+        self.engine
+            .add_policy(path.to_owned(), rego.to_owned())
+            .map_err(BoxError::from)?;
+        Ok(self)
+    }
+
+    /// Add data documents available to the policies under `data`.
+    pub fn add_data(mut self, data: serde_yaml::Value) -> Result<Self, BoxError> {
+        let data = serde_yaml::from_value(data)?;
+        self.engine.add_data(data).map_err(BoxError::from)?;
+        Ok(self)
+    }
+
+    /// Evaluate the policies against `document`, returning the deny reasons.
+    pub fn evaluate(&self, document: &serde_yaml::Value) -> Result<Vec<String>, BoxError> {
+        let mut engine = self.engine.clone();
+        engine.set_input(serde_yaml::from_value(document.clone())?);
+
+        let reasons = match engine
+            .eval_rule(self.rule.clone())
+            .map_err(BoxError::from)?
+        {
+            regorus::Value::Undefined => return Ok(Vec::new()),
+            regorus::Value::Set(reasons) => reasons.iter().cloned().collect(),
+            regorus::Value::Array(reasons) => reasons.to_vec(),
+            other => return Err(format!("`{}` evaluated to {other}", self.rule).into()),
+        };
+
+        Ok(reasons
+            .into_iter()
+            .map(|reason| match reason.as_string() {
+                Ok(reason) => reason.to_string(),
+                Err(_) => reason.to_string(),
+            })
+            .collect())
+    }
+}
+
+/// YAML extractor checking the document against the [`RegoPolicy`] in state.
+///
+/// The body is parsed according to the [`YamlConfig`] in the request
+/// extensions, and the policies see the document once its key case, defaults
+/// and [`YamlConfig::policy`] are applied. Documents with deny reasons are
+/// rejected with `403 Forbidden` and a YAML body listing the reasons.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RegoYaml<T>(pub T);
+
+impl<T, S> FromRequest<S> for RegoYaml<T>
+where
+    T: DeserializeOwned,
+    RegoPolicy: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = RegoYamlRejection;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let config = YamlConfig::from_extensions(req.extensions());
+        config
+            .report(async {
+                if !config.yaml_content_type(req.headers()) {
+                    config.drain(req).await;
+                    return Err(YamlRejection::from(config.missing_content_type()).into());
+                }
+
+                let bytes = config.read_body(req, state).await?;
+                let document = config.document_body(bytes.clone()).await?;
+
+                let reasons = RegoPolicy::from_ref(state)
+                    .evaluate(&document)
+                    .map_err(RegoEvaluationFailed::from_err)?;
+                if !reasons.is_empty() {
+                    return Err(RegoPolicyDenied {
+                        reasons,
+                        settings: RejectionSettings::default(),
+                    }
+                    .into());
+                }
+
+                config
+                    .deserialize_prepared(document)
+                    .map(RegoYaml)
+                    .map_err(|rejection| config.annotate(rejection, &bytes).into())
+            })
+            .await
+    }
+}
+
+/// Rejection type for [`RegoYaml`] used if the policies deny the document.
+#[derive(Debug)]
+pub struct RegoPolicyDenied {
+    reasons: Vec<String>,
+    settings: RejectionSettings,
+}
+
+impl RegoPolicyDenied {
+    /// Get the deny reasons produced by the policies.
+    pub fn reasons(&self) -> &[String] {
+        &self.reasons
+    }
+
+    /// Get the response body text used for this rejection.
+    pub fn body_text(&self) -> String {
+        #[derive(serde::Serialize)]
+        struct Body<'a> {
+            status: u16,
+            denied: &'a [String],
+        }
+
+        serde_yaml::to_string(&Body {
+            status: self.status().as_u16(),
+            denied: &self.reasons,
+        })
+        .unwrap_or_default()
+    }
+
+    /// Get the status code used for this rejection.
+    pub fn status(&self) -> StatusCode {
+        StatusCode::FORBIDDEN
+    }
+}
+
+impl IntoResponse for RegoPolicyDenied {
+    fn into_response(self) -> Response {
+        crate::macros::__log_rejection!(
+            rejection_type = RegoPolicyDenied,
+            body_text = self.body_text(),
+            status = self.status(),
+            level = self.settings.log_level,
+        );
+        (
+            self.status(),
+            [(
+                header::CONTENT_TYPE,
+                HeaderValue::from_static("application/yaml"),
+            )],
+            self.body_text(),
+        )
+            .into_response()
+    }
+}
+
+impl WithSettings for RegoPolicyDenied {
+    fn with_settings(mut self, settings: RejectionSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+}
+
+impl ToRejectionBody for RegoPolicyDenied {
+    fn rejection_body(&self) -> RejectionBody {
+        RejectionBody::new(self.status(), "RegoPolicyDenied", self.to_string())
+    }
+}
+
+impl serde::Serialize for RegoPolicyDenied {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        self.rejection_body().serialize_rejection(serializer)
+    }
+}
+
+impl fmt::Display for RegoPolicyDenied {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Document denied by policy: {}", self.reasons.join(", "))
+    }
+}
+
+impl std::error::Error for RegoPolicyDenied {}
+
+define_rejection! {
+    #[status = INTERNAL_SERVER_ERROR]
+    #[body = "Failed to evaluate the Rego policy"]
+    /// Rejection type for [`RegoYaml`] used if the policies cannot be
+    /// evaluated.
+    pub struct RegoEvaluationFailed(Error);
+}
+
+composite_rejection! {
+    /// Rejection used for [`RegoYaml`].
+    pub enum RegoYamlRejection {
+        YamlRejection,
+        RegoPolicyDenied,
+        RegoEvaluationFailed,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use axum::routing::post;
+    use axum::Router;
+
+    use crate::test_client::TestClient;
+
+    const POLICY: &str = r#"
+        package admission
+
+        deny contains msg if {
+            some container in input.containers
+            container.privileged
+            msg := sprintf("container %s is privileged", [container.name])
+        }
+    "#;
+
+    #[tokio::test]
+    async fn deny() {
+        let policy = RegoPolicy::new("data.admission.deny")
+            .add_policy("admission.rego", POLICY)
+            .unwrap();
+        let app = Router::new()
+            .route(
+                "/",
+                post(|RegoYaml(_): RegoYaml<serde_yaml::Value>| async {}),
+            )
+            .with_state(policy);
+        let client = TestClient::new(app);
+
+        let res = client
+            .post("/")
+            .body("containers:\n- name: web\n")
+            .header("content-type", "application/yaml")
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let res = client
+            .post("/")
+            .body("containers:\n- name: web\n  privileged: true\n")
+            .header("content-type", "application/yaml")
+            .await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+        assert_eq!(
+            res.text().await,
+            "status: 403\ndenied:\n- container web is privileged\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn config() {
+        use axum::Extension;
+
+        let policy = RegoPolicy::new("data.admission.deny")
+            .add_policy("admission.rego", POLICY)
+            .unwrap();
+        let app = Router::new()
+            .route(
+                "/",
+                post(|RegoYaml(_): RegoYaml<serde_yaml::Value>| async {}),
+            )
+            .layer(Extension(YamlConfig::new().max_sequence_len(1)))
+            .with_state(policy);
+        let client = TestClient::new(app);
+
+        let res = client
+            .post("/")
+            .body("containers:\n- name: web\n- name: db\n")
+            .header("content-type", "application/yaml")
+            .await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            res.text().await,
+            "YAML document exceeds a limit: sequence at `containers` has 2 items, \
+             more than the limit of 1"
+        );
+    }
+}
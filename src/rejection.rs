@@ -1,32 +1,1458 @@
+use axum_core::response::{IntoResponse, Response};
+use http::{header, HeaderValue, StatusCode};
+
 // We only use the pre-existing `BytesRejection` from `axum_core` because it does not qualify as a private API
 use axum_core::extract::rejection::BytesRejection;
+use serde::Serialize;
 
+use crate::case::KeyCase;
+use crate::classify::Classified;
 use crate::macros::{
     __composite_rejection as composite_rejection, __define_rejection as define_rejection,
 };
 
+/// Rejection type for `Yaml` that takes the [`serde_yaml::Error`] type.
+///
+/// This rejection is used when the request body cannot be deserialized
+/// into the target type or contains syntactically invalid YAML. Its
+/// [`kind`](YamlError::kind) tells these failures apart.
+#[derive(Debug)]
+pub struct YamlError {
+    kind: YamlErrorKind,
+    status: http::StatusCode,
+    error: axum_core::Error,
+    snippet: Option<String>,
+    suggestion: Option<(String, String)>,
+    path_headers: bool,
+    settings: RejectionSettings,
+}
+
+/// Kind of a [`YamlError`].
+///
+/// The kind is taken where the error is raised: by the YAML parser, or by
+/// the target type while it is handed the parsed values. A body is parsed as
+/// it is deserialized, so the first of its failures decides: in
+/// `a: [1` deserialized into a struct whose `a` is an integer, the sequence
+/// is an [`InvalidType`](Self::InvalidType) before it is found unclosed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum YamlErrorKind {
+    /// The request body is not valid YAML.
+    Syntax,
+    /// The request body holds several documents where one is expected.
+    MultipleDocuments,
+    /// A field required by the target type is missing.
+    MissingField,
+    /// A field is not known to the target type.
+    UnknownField,
+    /// A value does not have the type expected by the target type.
+    InvalidType,
+    /// A value is not accepted by the target type, e.g. an unknown enum
+    /// variant or a failed validation.
+    InvalidValue,
+}
+
+impl YamlErrorKind {
+    /// Whether the request body could not be parsed, as opposed to parsed
+    /// into a document that does not fit the target type.
+    pub fn is_syntax(&self) -> bool {
+        matches!(self, Self::Syntax | Self::MultipleDocuments)
+    }
+}
+
+/// Quote `line` of `source`, and the line before it, with a caret under
+/// `column`, both counted from 1.
+fn snippet(source: &str, line: usize, column: usize) -> Option<String> {
+    let lines: Vec<&str> = source.lines().collect();
+    lines.get(line.checked_sub(1)?)?;
+
+    let width = line.to_string().len();
+    let mut snippet = format!("{:width$} |\n", "");
+    for number in line.saturating_sub(1).max(1)..=line {
+        snippet.push_str(&format!("{number:>width$} | {}\n", lines[number - 1]));
+    }
+    snippet.push_str(&format!(
+        "{:width$} | {}^",
+        "",
+        " ".repeat(column.saturating_sub(1))
+    ));
+    Some(snippet)
+}
+
+/// Find the unknown field of a `serde` message, such as
+/// ``unknown field `replcas`, expected one of `name`, `replicas` ``, along
+/// with the expected field closest to it.
+fn suggest_field(message: &str) -> Option<(String, String)> {
+    let (_, rest) = message.split_once("unknown field `")?;
+    let (field, rest) = rest.split_once('`')?;
+    let (_, expected) = rest.split_once("expected ")?;
+
+    let max_distance = (field.chars().count() / 3).max(1);
+    expected
+        .split('`')
+        .skip(1)
+        .step_by(2)
+        .map(|candidate| (edit_distance(field, candidate), candidate))
+        .filter(|&(distance, _)| distance <= max_distance)
+        .min_by_key(|&(distance, _)| distance)
+        .map(|(_, candidate)| (field.to_owned(), candidate.to_owned()))
+}
+
+/// Edit distance between `a` and `b`, counting the transposition of two
+/// adjacent characters as one edit like the insertions, deletions and
+/// substitutions.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, distance) in distances[0].iter_mut().enumerate() {
+        *distance = j;
+    }
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let substitution = distances[i - 1][j - 1] + usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = substitution
+                .min(distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(distances[i - 2][j - 2] + 1);
+            }
+            distances[i][j] = distance;
+        }
+    }
+    distances[a.len()][b.len()]
+}
+
+impl YamlError {
+    pub(crate) fn new<E>(kind: YamlErrorKind, err: E) -> Self
+    where
+        E: Into<axum_core::BoxError>,
+    {
+        let error = axum_core::Error::new(err);
+        let suggestion = match kind {
+            YamlErrorKind::UnknownField => suggest_field(&error.to_string()),
+            _ => None,
+        };
+        Self {
+            kind,
+            status: http::StatusCode::BAD_REQUEST,
+            error,
+            snippet: None,
+            suggestion,
+            path_headers: false,
+            settings: RejectionSettings::default(),
+        }
+    }
+
+    /// Reply with `status` instead of `400 Bad Request`.
+    pub(crate) fn with_status(mut self, status: http::StatusCode) -> Self {
+        self.status = status;
+        self
+    }
+
+    /// Quote the lines of `source`, the request body, leading to the
+    /// location of this error.
+    pub(crate) fn with_snippet(mut self, source: &[u8]) -> Self {
+        if let Some(location) = self.location() {
+            let source = String::from_utf8_lossy(source);
+            self.snippet = snippet(&source, location.line(), location.column());
+        }
+        self
+    }
+
+    /// Report the path and location of this error in the `X-Error-Path` and
+    /// `X-Error-Location` response headers.
+    pub(crate) fn with_path_headers(mut self) -> Self {
+        self.path_headers = true;
+        self
+    }
+
+    /// Prefix the message with the `name` of the bundle document that failed.
+    pub(crate) fn in_document(self, name: &str) -> Self {
+        let message = format!("document `{name}`: {}", self.error);
+        Self {
+            settings: self.settings,
+            ..Self::new(self.kind, message).with_status(self.status)
+        }
+    }
+
+    /// Error parsing the request body as YAML.
+    pub(crate) fn syntax(err: serde_yaml::Error) -> Self {
+        Self::new(YamlErrorKind::Syntax, err)
+    }
+
+    /// Error parsing the request body into a [`serde_yaml::Value`], which
+    /// only fails on syntax, or on a body of several documents.
+    pub(crate) fn parse(err: Classified) -> Self {
+        match err.kind {
+            YamlErrorKind::MultipleDocuments => Self::new(err.kind, err.error.into_inner()),
+            _ => Self::syntax(err.error.into_inner()),
+        }
+    }
+
+    /// Error deserializing the request body into the target type.
+    pub(crate) fn data(err: Classified) -> Self {
+        Self::new(err.kind, err.error)
+    }
+
+    /// Get the kind of this error.
+    pub fn kind(&self) -> YamlErrorKind {
+        self.kind
+    }
+
+    /// Get the response body text used for this rejection.
+    pub fn body_text(&self) -> String {
+        let mut message = self.error.to_string();
+        if let Some((field, suggestion)) = &self.suggestion {
+            let unknown = format!("unknown field `{field}`");
+            message = message.replacen(
+                &unknown,
+                &format!("{unknown} (did you mean `{suggestion}`?)"),
+                1,
+            );
+        }
+        if self.settings.redact_values {
+            message = redact(&message);
+        } else if let Some(snippet) = &self.snippet {
+            message = format!("{message}\n\n{snippet}");
+        }
+        self.settings.limit(format!(
+            "Failed to deserialize the YAML body into the target type: {message}"
+        ))
+    }
+
+    /// Get the status code used for this rejection.
+    pub fn status(&self) -> http::StatusCode {
+        self.status
+    }
+
+    /// Position in the request body where deserialization failed.
+    ///
+    /// `None` if the failure is not tied to a position, e.g. when the
+    /// [`YamlConfig`](crate::YamlConfig) rewrote the document before it was
+    /// deserialized into the target type.
+    pub fn location(&self) -> Option<serde_yaml::Location> {
+        self.yaml_error()?.location()
+    }
+
+    /// Known field closest to the unknown field of an
+    /// [`YamlErrorKind::UnknownField`] error, if one is close enough to be a
+    /// typo of it.
+    pub fn suggestion(&self) -> Option<&str> {
+        Some(&self.suggestion.as_ref()?.1)
+    }
+
+    /// Lines of the request body leading to the failure, enabled with
+    /// [`YamlConfig::error_snippets`](crate::YamlConfig::error_snippets).
+    pub fn snippet(&self) -> Option<&str> {
+        self.snippet.as_deref()
+    }
+
+    /// Path to the value of the request body that failed to deserialize,
+    /// e.g. `b[0].y`.
+    ///
+    /// `None` if the failure is not tied to a value, e.g. a syntax error.
+    pub fn path(&self) -> Option<&serde_path_to_error::Path> {
+        Some(self.path_error()?.path())
+    }
+
+    fn path_error(&self) -> Option<&serde_path_to_error::Error<serde_yaml::Error>> {
+        std::error::Error::source(&self.error)?.downcast_ref()
+    }
+
+    fn yaml_error(&self) -> Option<&serde_yaml::Error> {
+        match self.path_error() {
+            Some(err) => Some(err.inner()),
+            None => std::error::Error::source(&self.error)?.downcast_ref(),
+        }
+    }
+}
+
+impl axum_core::response::IntoResponse for YamlError {
+    fn into_response(self) -> axum_core::response::Response {
+        crate::macros::__log_rejection!(
+            rejection_type = YamlError,
+            body_text = self.body_text(),
+            status = self.status(),
+            level = self.settings.log_level,
+        );
+        let mut headers = http::HeaderMap::new();
+        if self.path_headers {
+            let path = self.path().map(ToString::to_string);
+            if let Some(path) = path.and_then(|path| HeaderValue::from_str(&path).ok()) {
+                headers.insert(&X_ERROR_PATH, path);
+            }
+            if let Some(location) = self.location() {
+                let location = format!("{}:{}", location.line(), location.column());
+                if let Ok(location) = HeaderValue::from_str(&location) {
+                    headers.insert(&X_ERROR_LOCATION, location);
+                }
+            }
+        }
+        (headers, self.rejection_body().render(self.settings.format)).into_response()
+    }
+}
+
+impl WithSettings for YamlError {
+    fn with_settings(mut self, settings: RejectionSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+}
+
+static X_ERROR_PATH: http::HeaderName = http::HeaderName::from_static("x-error-path");
+static X_ERROR_LOCATION: http::HeaderName = http::HeaderName::from_static("x-error-location");
+
+impl ToRejectionBody for YamlError {
+    fn rejection_body(&self) -> RejectionBody {
+        RejectionBody::new(self.status(), "YamlError", self.body_text())
+            .with_code(&format!("{:?}", self.kind))
+            .with_error(self)
+    }
+}
+
+impl Serialize for YamlError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        self.rejection_body().serialize_rejection(serializer)
+    }
+}
+
+impl std::fmt::Display for YamlError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Failed to deserialize the YAML body into the target type")
+    }
+}
+
+impl std::error::Error for YamlError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// Rejection type for `Yaml` listing every value of the request body that
+/// failed to deserialize, enabled with
+/// [`YamlConfig::collect_errors`](crate::YamlConfig::collect_errors).
+#[derive(Debug)]
+pub struct YamlErrors {
+    status: http::StatusCode,
+    errors: Vec<YamlFieldError>,
+    settings: RejectionSettings,
+}
+
+/// Value of the request body that failed to deserialize, reported by
+/// [`YamlErrors`].
+#[derive(Debug, Clone)]
+pub struct YamlFieldError {
+    kind: YamlErrorKind,
+    path: String,
+    message: String,
+}
+
+impl YamlFieldError {
+    pub(crate) fn new(kind: YamlErrorKind, path: String, message: String) -> Self {
+        Self {
+            kind,
+            path,
+            message,
+        }
+    }
+
+    /// Get the kind of this error.
+    pub fn kind(&self) -> YamlErrorKind {
+        self.kind
+    }
+
+    /// Path to the value that failed to deserialize, e.g. `b[0].y`, or `.`
+    /// for the whole document.
+    pub fn path(&self) -> &str {
+        if self.path.is_empty() {
+            "."
+        } else {
+            &self.path
+        }
+    }
+
+    /// Message of the `serde` error, e.g. ``missing field `name` ``.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    fn rejection_body(&self, status: http::StatusCode, redact_values: bool) -> RejectionBody {
+        let mut message = self.message.clone();
+        if redact_values {
+            message = redact(&message);
+        }
+        let mut body = RejectionBody::new(status, &format!("{:?}", self.kind), message);
+        body.path = Some(self.path().to_owned());
+        body
+    }
+}
+
+impl YamlErrors {
+    pub(crate) fn new(errors: Vec<YamlFieldError>) -> Self {
+        Self {
+            status: http::StatusCode::BAD_REQUEST,
+            errors,
+            settings: RejectionSettings::default(),
+        }
+    }
+
+    /// Reply with `status` instead of `400 Bad Request`.
+    pub(crate) fn with_status(mut self, status: http::StatusCode) -> Self {
+        self.status = status;
+        self
+    }
+
+    /// Get the errors, in the order of the request body.
+    pub fn errors(&self) -> &[YamlFieldError] {
+        &self.errors
+    }
+
+    /// Get the response body text used for this rejection, with one line per
+    /// error.
+    pub fn body_text(&self) -> String {
+        let mut text = format!(
+            "Failed to deserialize the YAML body into the target type: {} errors",
+            self.errors.len()
+        );
+        for err in &self.errors {
+            let message = if self.settings.redact_values {
+                redact(&err.message)
+            } else {
+                err.message.clone()
+            };
+            text.push_str(&format!("\n- {}: {message}", err.path()));
+        }
+        self.settings.limit(text)
+    }
+
+    /// Get the status code used for this rejection.
+    pub fn status(&self) -> http::StatusCode {
+        self.status
+    }
+}
+
+impl axum_core::response::IntoResponse for YamlErrors {
+    fn into_response(self) -> axum_core::response::Response {
+        crate::macros::__log_rejection!(
+            rejection_type = YamlErrors,
+            body_text = self.body_text(),
+            status = self.status(),
+            level = self.settings.log_level,
+        );
+        self.rejection_body().render(self.settings.format)
+    }
+}
+
+impl WithSettings for YamlErrors {
+    fn with_settings(mut self, settings: RejectionSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+}
+
+impl ToRejectionBody for YamlErrors {
+    fn rejection_body(&self) -> RejectionBody {
+        let mut body = RejectionBody::new(self.status(), "YamlErrors", self.body_text());
+        body.errors = self
+            .errors
+            .iter()
+            .map(|err| err.rejection_body(self.status, self.settings.redact_values))
+            .collect();
+        body
+    }
+}
+
+impl Serialize for YamlErrors {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        self.rejection_body().serialize_rejection(serializer)
+    }
+}
+
+impl std::fmt::Display for YamlErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Failed to deserialize the YAML body into the target type")
+    }
+}
+
+impl std::error::Error for YamlErrors {}
+
+/// Rejection type for `Yaml` used if the `Content-Type`
+/// header is missing.
+///
+/// The response lists the media types the extractor accepts in
+/// `Accept-Post` and `Accept-Patch` headers, so clients can correct their
+/// request.
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct MissingYamlContentType {
+    vendor_types: Option<Vec<String>>,
+    media_types: Vec<String>,
+    settings: RejectionSettings,
+}
+
+impl MissingYamlContentType {
+    /// Rejection for an extractor accepting the `+yaml` types matching
+    /// `vendor_types`, or all of them, and also `media_types`.
+    pub(crate) fn accepting(vendor_types: Option<&[String]>, media_types: &[String]) -> Self {
+        Self {
+            vendor_types: vendor_types.map(<[String]>::to_vec),
+            media_types: media_types.to_vec(),
+            settings: RejectionSettings::default(),
+        }
+    }
+
+    /// The media types the extractor accepts.
+    pub fn accepted_media_types(&self) -> impl Iterator<Item = &str> {
+        let any_vendor_type = self.vendor_types.is_none().then_some("application/*+yaml");
+        std::iter::once("application/yaml")
+            .chain(any_vendor_type)
+            .chain(self.vendor_types.iter().flatten().map(String::as_str))
+            .chain(self.media_types.iter().map(String::as_str))
+    }
+
+    /// Get the response body text used for this rejection.
+    pub fn body_text(&self) -> String {
+        let body = "Expected request with `Content-Type: application/yaml`";
+        self.settings.limit(match &self.vendor_types {
+            Some(vendor_types) => format!("{body}, or one of {}", vendor_types.join(", ")),
+            None => body.into(),
+        })
+    }
+
+    /// Get the status code used for this rejection.
+    pub fn status(&self) -> http::StatusCode {
+        http::StatusCode::UNSUPPORTED_MEDIA_TYPE
+    }
+}
+
+impl axum_core::response::IntoResponse for MissingYamlContentType {
+    fn into_response(self) -> axum_core::response::Response {
+        crate::macros::__log_rejection!(
+            rejection_type = MissingYamlContentType,
+            body_text = self.body_text(),
+            status = self.status(),
+            level = self.settings.log_level,
+        );
+
+        let accepted = self.accepted_media_types().collect::<Vec<_>>().join(", ");
+        let headers = http::HeaderValue::from_str(&accepted).ok().map(|accepted| {
+            [
+                (
+                    http::HeaderName::from_static("accept-post"),
+                    accepted.clone(),
+                ),
+                (http::HeaderName::from_static("accept-patch"), accepted),
+            ]
+        });
+        (headers, self.rejection_body().render(self.settings.format)).into_response()
+    }
+}
+
+impl WithSettings for MissingYamlContentType {
+    fn with_settings(mut self, settings: RejectionSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+}
+
+impl ToRejectionBody for MissingYamlContentType {
+    fn rejection_body(&self) -> RejectionBody {
+        RejectionBody::new(self.status(), "MissingYamlContentType", self.body_text())
+    }
+}
+
+impl Serialize for MissingYamlContentType {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        self.rejection_body().serialize_rejection(serializer)
+    }
+}
+
+impl std::fmt::Display for MissingYamlContentType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.body_text())
+    }
+}
+
+impl std::error::Error for MissingYamlContentType {}
+
 define_rejection! {
     #[status = BAD_REQUEST]
-    #[body = "Failed to deserialize the YAML body into the target type"]
-    /// Rejection type for `Yaml` that takes the [`serde_yaml::Error`] type.
-    ///
-    /// This rejection is used when the request body cannot be deserialized
-    /// into the target type or contains syntactically invalid YAML.
-    pub struct YamlError(Error);
+    #[body = "Invalid YAML bundle"]
+    /// Rejection type for `YamlBundle` used if the request body cannot be
+    /// split into named documents.
+    pub struct InvalidYamlBundle(Error);
+}
+
+define_rejection! {
+    #[status = BAD_REQUEST]
+    #[body = "Failed to deserialize the JSON body into the target type"]
+    /// Rejection type for `AnyConfig` used if a JSON request body cannot be
+    /// deserialized into the target type.
+    pub struct JsonError(Error);
+}
+
+define_rejection! {
+    #[status = BAD_REQUEST]
+    #[body = "Failed to deserialize the TOML body into the target type"]
+    /// Rejection type for `AnyConfig` used if a TOML request body cannot be
+    /// deserialized into the target type.
+    pub struct TomlError(Error);
+}
+
+define_rejection! {
+    #[status = UNSUPPORTED_MEDIA_TYPE]
+    #[body = "Expected request with a YAML, JSON or TOML `Content-Type`"]
+    /// Rejection type for `AnyConfig` used if the `Content-Type`
+    /// header is missing or names an unsupported format.
+    pub struct UnsupportedConfigContentType;
+}
+
+define_rejection! {
+    #[status = FORBIDDEN]
+    #[body = "Document violates policy"]
+    /// Rejection type for `Yaml` used if the request body contains a path or
+    /// value denied by the configured [`Policy`](crate::policy::Policy).
+    pub struct PolicyViolation(Error);
+}
+
+define_rejection! {
+    #[status = BAD_REQUEST]
+    #[body = "YAML document exceeds a limit"]
+    /// Rejection type for `Yaml` used if the request body exceeds a limit
+    /// configured with [`YamlConfig`](crate::YamlConfig).
+    pub struct YamlLimitExceeded(Error);
+}
+
+define_rejection! {
+    #[status = PAYLOAD_TOO_LARGE]
+    #[body = "Too many YAML documents"]
+    /// Rejection type for `YamlMultiDoc` and `YamlDocumentStream` used if the
+    /// request body holds more documents than the configured
+    /// [`YamlConfig::max_documents`](crate::YamlConfig::max_documents).
+    pub struct TooManyYamlDocuments(Error);
+}
+
+define_rejection! {
+    #[status = REQUEST_TIMEOUT]
+    #[body = "YAML body took too long to parse"]
+    /// Rejection type for `Yaml` used if parsing the request body takes
+    /// longer than the configured `YamlConfig::parse_timeout`.
+    pub struct YamlParseTimeout(Error);
+}
+
+define_rejection! {
+    #[status = BAD_REQUEST]
+    #[body = "Unsupported YAML version"]
+    /// Rejection type for `Yaml` used if the request body declares a `%YAML`
+    /// version not accepted by the configured
+    /// [`YamlConfig::yaml_versions`](crate::YamlConfig::yaml_versions).
+    pub struct UnsupportedYamlVersion(Error);
+}
+
+define_rejection! {
+    #[status = BAD_REQUEST]
+    #[body = "YAML tag outside the core schema"]
+    /// Rejection type for `Yaml` used if the request body contains a tag such
+    /// as `!!binary` or `!Circle` while
+    /// [`YamlConfig::core_tags_only`](crate::YamlConfig::core_tags_only) is
+    /// enabled.
+    pub struct DisallowedYamlTag(Error);
+}
+
+define_rejection! {
+    #[status = BAD_REQUEST]
+    #[body = "Invalid request body digest"]
+    /// Rejection type for `Yaml` used if the request body does not match its
+    /// `Content-Digest` or `Repr-Digest` header, see
+    /// `YamlConfig::verify_digests`.
+    pub struct InvalidBodyDigest(Error);
+}
+
+define_rejection! {
+    #[status = UNSUPPORTED_MEDIA_TYPE]
+    #[body = "Unsupported charset"]
+    /// Rejection type for `Yaml` used if the `charset` of the `Content-Type`
+    /// header is not supported, see
+    /// [`YamlConfig::transcode_charsets`](crate::YamlConfig::transcode_charsets).
+    pub struct UnsupportedCharset(Error);
 }
 
 define_rejection! {
     #[status = UNSUPPORTED_MEDIA_TYPE]
-    #[body = "Expected request with `Content-Type: application/yaml`"]
-    /// Rejection type for `Yaml` used if the `Content-Type`
-    /// header is missing.
-    pub struct MissingYamlContentType;
+    #[body = "Unexpected YAML media type"]
+    /// Rejection type for [`VendorYaml`](crate::media_type::VendorYaml) used
+    /// if the `Content-Type` header is not the media type of the target type.
+    pub struct UnexpectedYamlMediaType(Error);
+}
+
+define_rejection! {
+    #[status = BAD_REQUEST]
+    #[body = "Request body not allowed"]
+    /// Rejection type for `Yaml` used if a `GET`, `HEAD` or `DELETE` request
+    /// carries a body, see
+    /// [`YamlConfig::reject_unexpected_bodies`](crate::YamlConfig::reject_unexpected_bodies).
+    pub struct UnexpectedRequestBody(Error);
 }
 
 composite_rejection! {
+    /// Rejection used for `Yaml`.
+    ///
+    /// Like its variants, it serializes to the document of
+    /// [`BodyFormat::Yaml`] with the numeric `status` of the rejection, for
+    /// structured logs and custom error envelopes:
+    ///
+    /// ```yaml
+    /// status: 400
+    /// code: invalid_type
+    /// message: 'Failed to deserialize the YAML body into the target type: ...'
+    /// path: b[0].y
+    /// line: 2
+    /// column: 15
+    /// ```
     pub enum YamlRejection {
         YamlError,
+        YamlErrors,
         MissingYamlContentType,
+        InvalidYamlBundle,
+        PolicyViolation,
+        YamlLimitExceeded,
+        TooManyYamlDocuments,
+        YamlParseTimeout,
+        UnsupportedYamlVersion,
+        DisallowedYamlTag,
+        InvalidBodyDigest,
+        UnsupportedCharset,
+        UnexpectedYamlMediaType,
+        UnexpectedRequestBody,
         BytesRejection,
     }
 }
+
+impl YamlRejection {
+    /// Position in the request body where deserialization failed, see
+    /// [`YamlError::location`].
+    pub fn location(&self) -> Option<serde_yaml::Location> {
+        match self {
+            Self::YamlError(err) => err.location(),
+            _ => None,
+        }
+    }
+
+    /// Path to the value of the request body that failed to deserialize, see
+    /// [`YamlError::path`].
+    pub fn path(&self) -> Option<&serde_path_to_error::Path> {
+        match self {
+            Self::YamlError(err) => err.path(),
+            _ => None,
+        }
+    }
+
+    /// Kind of the deserialization failure, see [`YamlError::kind`].
+    pub fn kind(&self) -> Option<YamlErrorKind> {
+        match self {
+            Self::YamlError(err) => Some(err.kind()),
+            _ => None,
+        }
+    }
+}
+
+composite_rejection! {
+    /// Rejection used for `AnyConfig`.
+    ///
+    /// Contains one variant for each way the `AnyConfig` extractor can fail.
+    pub enum AnyConfigRejection {
+        YamlRejection,
+        JsonError,
+        TomlError,
+        UnsupportedConfigContentType,
+    }
+}
+
+/// Level of the events emitted when a request is rejected.
+///
+/// Rejections are logged under the `axum_yaml::rejection` target when the
+/// `tracing` or `log` feature is enabled, at the level set with
+/// [`YamlConfig::rejection_log_level`](crate::YamlConfig::rejection_log_level).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LogLevel {
+    /// The `TRACE` level, used by default.
+    Trace,
+    /// The `DEBUG` level.
+    Debug,
+    /// The `INFO` level.
+    Info,
+    /// The `WARN` level.
+    Warn,
+    /// The `ERROR` level.
+    Error,
+}
+
+/// How rejections are reported, set with the
+/// [`YamlConfig`](crate::YamlConfig) of the rejected request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct RejectionSettings {
+    pub(crate) format: BodyFormat,
+    pub(crate) max_message_len: usize,
+    pub(crate) redact_values: bool,
+    pub(crate) log_level: LogLevel,
+}
+
+impl Default for RejectionSettings {
+    fn default() -> Self {
+        Self {
+            format: BodyFormat::Text,
+            max_message_len: usize::MAX,
+            redact_values: false,
+            log_level: LogLevel::Trace,
+        }
+    }
+}
+
+impl RejectionSettings {
+    /// Truncate `message` to the configured maximum length.
+    pub(crate) fn limit(&self, message: String) -> String {
+        truncate(message, self.max_message_len)
+    }
+}
+
+/// Rejection reported according to the [`RejectionSettings`] of the request.
+pub(crate) trait WithSettings {
+    fn with_settings(self, settings: RejectionSettings) -> Self;
+}
+
+/// `axum_core` renders and logs its rejections itself.
+impl WithSettings for BytesRejection {
+    fn with_settings(self, _settings: RejectionSettings) -> Self {
+        self
+    }
+}
+
+fn truncate(message: String, max: usize) -> String {
+    if message.chars().nth(max).is_none() {
+        return message;
+    }
+    let mut truncated: String = message.chars().take(max.saturating_sub(1)).collect();
+    truncated.push('…');
+    truncated
+}
+
+/// Replace the values quoted by the `serde` messages of `message` with `***`.
+fn redact(message: &str) -> String {
+    const QUOTED: [(&str, char); 6] = [
+        ("string \"", '"'),
+        ("integer `", '`'),
+        ("floating point `", '`'),
+        ("boolean `", '`'),
+        ("character `", '`'),
+        ("unknown variant `", '`'),
+    ];
+
+    let mut redacted = String::with_capacity(message.len());
+    let mut rest = message;
+    while let Some((start, prefix, close)) = QUOTED
+        .iter()
+        .filter_map(|&(prefix, close)| Some((rest.find(prefix)?, prefix, close)))
+        .min_by_key(|&(start, ..)| start)
+    {
+        let value_start = start + prefix.len();
+        redacted.push_str(&rest[..value_start]);
+        rest = &rest[value_start..];
+
+        // Strings are quoted with escapes, so `\"` does not close them.
+        let mut escaped = false;
+        let end = rest.char_indices().find_map(|(i, c)| {
+            let closes = c == close && !escaped;
+            escaped = c == '\\' && !escaped;
+            closes.then_some(i)
+        });
+        let Some(end) = end else {
+            redacted.push_str(rest);
+            return redacted;
+        };
+        redacted.push_str("***");
+        rest = &rest[end..];
+    }
+    redacted.push_str(rest);
+    redacted
+}
+
+/// Format of the body of rejection responses.
+///
+/// The format is set with
+/// [`YamlConfig::rejection_format`](crate::YamlConfig::rejection_format), or
+/// chosen per request from its `Accept` header by
+/// [`NegotiateRejectionLayer`](crate::negotiate::NegotiateRejectionLayer).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum BodyFormat {
+    /// A plain text message, used by default.
+    Text = 0,
+    /// A YAML document holding the `code`, `message` and, for
+    /// [`YamlError`], the `path`, `line` and `column` of the rejection:
+    ///
+    /// ```yaml
+    /// code: invalid_type
+    /// message: 'Failed to deserialize the YAML body into the target type: ...'
+    /// path: b[0].y
+    /// line: 2
+    /// column: 15
+    /// ```
+    ///
+    /// `code` is the [`YamlErrorKind`] of a [`YamlError`] and the type of
+    /// other rejections, in `snake_case`.
+    Yaml = 1,
+    /// The document of [`BodyFormat::Yaml`] in JSON.
+    ///
+    /// Requires the `json` feature.
+    #[cfg(feature = "json")]
+    Json = 2,
+    /// An RFC 9457 problem details document in YAML, sent as
+    /// `application/problem+yaml`, with the serde path of a [`YamlError`] in
+    /// a `path` extension member:
+    ///
+    /// ```yaml
+    /// type: about:blank
+    /// title: Bad Request
+    /// status: 400
+    /// detail: 'Failed to deserialize the YAML body into the target type: ...'
+    /// path: b[0].y
+    /// ```
+    ProblemYaml = 3,
+    /// The problem details document of [`BodyFormat::ProblemYaml`] in JSON,
+    /// sent as `application/problem+json`.
+    ///
+    /// Requires the `json` feature.
+    #[cfg(feature = "json")]
+    ProblemJson = 4,
+    /// An HTML page showing the message, path and location of the
+    /// rejection, with the [`YamlError::snippet`] highlighted, for testing
+    /// endpoints from a browser. Negotiated for `Accept: text/html` by
+    /// [`NegotiateRejectionLayer`](crate::negotiate::NegotiateRejectionLayer).
+    ///
+    /// Requires the `dev-errors` feature. Not meant for production, since the
+    /// page echoes the request body.
+    #[cfg(feature = "dev-errors")]
+    Html = 5,
+}
+
+impl BodyFormat {
+    /// Every format, in order of preference when negotiating.
+    pub(crate) const ALL: &[Self] = &[
+        Self::Text,
+        Self::Yaml,
+        #[cfg(feature = "json")]
+        Self::Json,
+        Self::ProblemYaml,
+        #[cfg(feature = "json")]
+        Self::ProblemJson,
+        #[cfg(feature = "dev-errors")]
+        Self::Html,
+    ];
+
+    /// The media type of the bodies rendered in this format.
+    pub(crate) fn media_type(self) -> &'static str {
+        match self {
+            Self::Text => "text/plain; charset=utf-8",
+            Self::Yaml => "application/yaml",
+            #[cfg(feature = "json")]
+            Self::Json => "application/json",
+            Self::ProblemYaml => "application/problem+yaml",
+            #[cfg(feature = "json")]
+            Self::ProblemJson => "application/problem+json",
+            #[cfg(feature = "dev-errors")]
+            Self::Html => "text/html; charset=utf-8",
+        }
+    }
+}
+
+/// Body of a rejection response, rendered in the
+/// [`YamlConfig::rejection_format`](crate::YamlConfig::rejection_format).
+///
+/// Rejection responses carry it in their extensions, to be rendered again in
+/// another format by [`NegotiateRejectionLayer`](crate::negotiate::NegotiateRejectionLayer)
+/// or by the formatter of a
+/// [`FormatRejectionLayer`](crate::format_rejection::FormatRejectionLayer).
+#[derive(Debug, Clone, Serialize)]
+pub struct RejectionBody {
+    #[serde(skip)]
+    status: StatusCode,
+    code: String,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    line: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    column: Option<usize>,
+    #[serde(skip)]
+    snippet: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    errors: Vec<RejectionBody>,
+    #[serde(skip)]
+    format: BodyFormat,
+}
+
+impl RejectionBody {
+    pub(crate) fn new(status: StatusCode, rejection_type: &str, message: String) -> Self {
+        Self {
+            status,
+            code: KeyCase::Snake.convert(rejection_type),
+            message,
+            path: None,
+            line: None,
+            column: None,
+            snippet: None,
+            errors: Vec::new(),
+            format: BodyFormat::Text,
+        }
+    }
+
+    /// Get the status code of the rejection.
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// Get the code of the rejection: the [`YamlErrorKind`] of a
+    /// [`YamlError`] and the type of other rejections, in `snake_case`.
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
+    /// Get the message of the rejection, as sent in plain text bodies.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Get the serde path of the value that failed to deserialize, for
+    /// [`YamlError`].
+    pub fn path(&self) -> Option<&str> {
+        self.path.as_deref()
+    }
+
+    /// Get the line of the request body where deserialization failed, for
+    /// [`YamlError`].
+    pub fn line(&self) -> Option<usize> {
+        self.line
+    }
+
+    /// Get the column of the request body where deserialization failed, for
+    /// [`YamlError`].
+    pub fn column(&self) -> Option<usize> {
+        self.column
+    }
+
+    /// Get the lines of the request body quoted by [`YamlError::snippet`],
+    /// which the message ends with.
+    pub fn snippet(&self) -> Option<&str> {
+        self.snippet.as_deref()
+    }
+
+    /// Get the errors of a [`YamlErrors`] rejection, each with its code,
+    /// message and path.
+    pub fn errors(&self) -> &[RejectionBody] {
+        &self.errors
+    }
+
+    /// Get the format the rejection was rendered in.
+    pub fn format(&self) -> BodyFormat {
+        self.format
+    }
+
+    /// Replace the code derived from the rejection type with `code`.
+    fn with_code(mut self, code: &str) -> Self {
+        self.code = KeyCase::Snake.convert(code);
+        self
+    }
+
+    /// Add the path and location of `err`.
+    fn with_error(mut self, err: &YamlError) -> Self {
+        self.path = err.path().map(ToString::to_string);
+        if let Some(location) = err.location() {
+            self.line = Some(location.line());
+            self.column = Some(location.column());
+        }
+        if !err.settings.redact_values {
+            self.snippet = err.snippet().map(ToOwned::to_owned);
+        }
+        self
+    }
+
+    /// Render the body in `format`, carrying it in the extensions of the
+    /// response.
+    pub(crate) fn render(&self, format: BodyFormat) -> Response {
+        let body = match format {
+            BodyFormat::Text => Ok(self.message.clone()),
+            BodyFormat::Yaml => serde_yaml::to_string(self).map_err(drop),
+            #[cfg(feature = "json")]
+            BodyFormat::Json => serde_json::to_string(self).map_err(drop),
+            BodyFormat::ProblemYaml => serde_yaml::to_string(&self.problem()).map_err(drop),
+            #[cfg(feature = "json")]
+            BodyFormat::ProblemJson => serde_json::to_string(&self.problem()).map_err(drop),
+            #[cfg(feature = "dev-errors")]
+            BodyFormat::Html => Ok(crate::dev_errors::page(self)),
+        };
+
+        let mut res = match body {
+            Ok(body) => (
+                self.status,
+                [(
+                    header::CONTENT_TYPE,
+                    HeaderValue::from_static(format.media_type()),
+                )],
+                body,
+            )
+                .into_response(),
+            Err(()) => (self.status, self.message.clone()).into_response(),
+        };
+        res.extensions_mut().insert(Self {
+            format,
+            ..self.clone()
+        });
+        res
+    }
+
+    /// Render the rejection body carried by `res`, if any, in `format`,
+    /// keeping the other headers of `res`.
+    pub(crate) fn render_again(res: Response, format: BodyFormat) -> Response {
+        let Some(body) = res.extensions().get::<Self>() else {
+            return res;
+        };
+        let (rendered, body) = body.render(format).into_parts();
+
+        let (mut parts, _) = res.into_parts();
+        parts.headers.remove(header::CONTENT_LENGTH);
+        if let Some(content_type) = rendered.headers.get(header::CONTENT_TYPE) {
+            parts
+                .headers
+                .insert(header::CONTENT_TYPE, content_type.clone());
+        }
+        Response::from_parts(parts, body)
+    }
+
+    /// Serialize the body with the status of the rejection, as the
+    /// `Serialize` implementation of rejections.
+    pub(crate) fn serialize_rejection<S>(self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        #[derive(Serialize)]
+        struct Rejection {
+            status: u16,
+            #[serde(flatten)]
+            body: RejectionBody,
+        }
+
+        Rejection {
+            status: self.status.as_u16(),
+            body: self,
+        }
+        .serialize(serializer)
+    }
+
+    fn problem(&self) -> Problem<'_> {
+        Problem {
+            type_: "about:blank",
+            title: self.status.canonical_reason().unwrap_or_default(),
+            status: self.status.as_u16(),
+            detail: &self.message,
+            path: self.path.as_deref(),
+            errors: &self.errors,
+        }
+    }
+}
+
+/// Conversion of a rejection to the [`RejectionBody`] it renders.
+pub(crate) trait ToRejectionBody {
+    fn rejection_body(&self) -> RejectionBody;
+}
+
+/// `axum_core` renders its rejections itself, this body is only serialized.
+impl ToRejectionBody for BytesRejection {
+    fn rejection_body(&self) -> RejectionBody {
+        RejectionBody::new(self.status(), "BytesRejection", self.body_text())
+    }
+}
+
+/// RFC 9457 problem details of a rejection.
+#[derive(Debug, Serialize)]
+struct Problem<'a> {
+    #[serde(rename = "type")]
+    type_: &'static str,
+    title: &'static str,
+    status: u16,
+    detail: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    path: Option<&'a str>,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    errors: &'a [RejectionBody],
+}
+
+impl IntoResponse for RejectionBody {
+    fn into_response(self) -> Response {
+        self.render(self.format)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::Yaml;
+
+    async fn body(res: Response) -> String {
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        String::from_utf8(body.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn yaml_body() {
+        let Err(YamlRejection::YamlError(err)) = Yaml::<Vec<u8>>::from_bytes(b"- 1\n- x\n") else {
+            panic!("`x` is not a number");
+        };
+        let res = RejectionBody::new(err.status(), "YamlError", err.body_text())
+            .with_code(&format!("{:?}", err.kind()))
+            .with_error(&err)
+            .render(BodyFormat::Yaml);
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(res.headers()[header::CONTENT_TYPE], "application/yaml");
+        assert_eq!(
+            body(res).await,
+            "code: invalid_type\n\
+             message: 'Failed to deserialize the YAML body into the target type: [1]: .[1]: \
+             invalid type: string \"x\", expected u8 at line 2 column 3'\n\
+             path: '[1]'\n\
+             line: 2\n\
+             column: 3\n"
+        );
+
+        let rejection = MissingYamlContentType::default();
+        let res = RejectionBody::new(
+            rejection.status(),
+            "MissingYamlContentType",
+            rejection.body_text(),
+        )
+        .render(BodyFormat::Yaml);
+        assert_eq!(
+            body(res).await,
+            "code: missing_yaml_content_type\n\
+             message: 'Expected request with `Content-Type: application/yaml`'\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn config_body_format() {
+        use axum_core::extract::{FromRequest, Request};
+
+        async fn reject(format: BodyFormat) -> Response {
+            let mut req = Request::new(axum_core::body::Body::from("[1, x]"));
+            req.headers_mut()
+                .insert(header::CONTENT_TYPE, "application/yaml".parse().unwrap());
+            req.extensions_mut()
+                .insert(crate::YamlConfig::new().rejection_format(format));
+            Yaml::<Vec<u8>>::from_request(req, &())
+                .await
+                .unwrap_err()
+                .into_response()
+        }
+
+        let message = "Failed to deserialize the YAML body into the target type: [1]: .[1]: \
+                       invalid type: string \"x\", expected u8 at line 1 column 5";
+        for &format in BodyFormat::ALL {
+            let res = reject(format).await;
+            assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+            assert_eq!(res.headers()[header::CONTENT_TYPE], format.media_type());
+            assert_eq!(
+                res.extensions().get::<RejectionBody>().unwrap().format(),
+                format
+            );
+
+            let body = body(res).await;
+            let expected = match format {
+                BodyFormat::Text => message.to_owned(),
+                BodyFormat::Yaml => format!(
+                    "code: invalid_type\nmessage: '{message}'\npath: '[1]'\nline: 1\ncolumn: 5\n"
+                ),
+                #[cfg(feature = "json")]
+                BodyFormat::Json => format!(
+                    r#"{{"code":"invalid_type","message":{message:?},"path":"[1]","line":1,"column":5}}"#
+                ),
+                BodyFormat::ProblemYaml => format!(
+                    "type: about:blank\ntitle: Bad Request\nstatus: 400\n\
+                     detail: '{message}'\npath: '[1]'\n"
+                ),
+                #[cfg(feature = "json")]
+                BodyFormat::ProblemJson => format!(
+                    r#"{{"type":"about:blank","title":"Bad Request","status":400,"detail":{message:?},"path":"[1]"}}"#
+                ),
+                #[cfg(feature = "dev-errors")]
+                BodyFormat::Html => {
+                    assert!(body.starts_with("<!DOCTYPE html>"), "{body}");
+                    continue;
+                }
+            };
+            assert_eq!(body, expected, "{format:?}");
+        }
+    }
+
+    #[test]
+    fn serialize() {
+        let Err(rejection) = Yaml::<Vec<u8>>::from_bytes(b"- 1\n- x\n") else {
+            panic!("`x` is not a number");
+        };
+        assert_eq!(
+            serde_yaml::to_string(&rejection).unwrap(),
+            "status: 400\n\
+             code: invalid_type\n\
+             message: 'Failed to deserialize the YAML body into the target type: [1]: .[1]: \
+             invalid type: string \"x\", expected u8 at line 2 column 3'\n\
+             path: '[1]'\n\
+             line: 2\n\
+             column: 3\n"
+        );
+
+        let rejection = YamlRejection::from(UnsupportedYamlVersion::from_err("1.3"));
+        assert_eq!(
+            serde_yaml::to_string(&rejection).unwrap(),
+            "status: 400\n\
+             code: unsupported_yaml_version\n\
+             message: 'Unsupported YAML version: 1.3'\n"
+        );
+    }
+
+    #[test]
+    fn redact_echoed_values() {
+        assert_eq!(
+            redact(r#"a: invalid type: string "hunter\"2", expected u8 at line 1 column 4"#),
+            r#"a: invalid type: string "***", expected u8 at line 1 column 4"#
+        );
+        assert_eq!(
+            redact("invalid value: integer `300`, expected u8; unknown variant `root`, expected `user`"),
+            "invalid value: integer `***`, expected u8; unknown variant `***`, expected `user`"
+        );
+        assert_eq!(
+            redact("missing field `password`"),
+            "missing field `password`"
+        );
+    }
+
+    #[test]
+    fn source_snippet() {
+        let source = "a: 1\nb: [{x: 1, y: z}]\n";
+        assert_eq!(
+            snippet(source, 2, 15).unwrap(),
+            "  |\n\
+             1 | a: 1\n\
+             2 | b: [{x: 1, y: z}]\n  \
+             |               ^"
+        );
+        assert_eq!(snippet(source, 1, 1).unwrap(), "  |\n1 | a: 1\n  | ^");
+        assert_eq!(snippet(source, 3, 1), None);
+    }
+
+    #[test]
+    fn suggest_fields() {
+        assert_eq!(edit_distance("replcas", "replicas"), 1);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("nmae", "name"), 1);
+
+        assert_eq!(
+            suggest_field("unknown field `replcas`, expected one of `name`, `replicas`"),
+            Some(("replcas".to_owned(), "replicas".to_owned()))
+        );
+        assert_eq!(
+            suggest_field("spec: unknown field `nmae`, expected `name` at line 2 column 3"),
+            Some(("nmae".to_owned(), "name".to_owned()))
+        );
+        assert_eq!(
+            suggest_field("unknown field `color`, expected one of `name`, `replicas`"),
+            None
+        );
+        assert_eq!(
+            suggest_field("unknown field `a`, there are no fields"),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn did_you_mean() {
+        #[derive(Debug, serde::Deserialize)]
+        #[serde(deny_unknown_fields)]
+        #[allow(dead_code)]
+        struct Deployment {
+            name: String,
+            replicas: u32,
+        }
+
+        let Err(YamlRejection::YamlError(err)) =
+            Yaml::<Deployment>::from_bytes(b"name: web\nreplcas: 2\n")
+        else {
+            panic!("`replcas` is unknown");
+        };
+        assert_eq!(err.suggestion(), Some("replicas"));
+        assert_eq!(
+            err.body_text(),
+            "Failed to deserialize the YAML body into the target type: replcas: unknown field \
+             `replcas` (did you mean `replicas`?), expected `name` or `replicas` at line 2 column 1"
+        );
+    }
+
+    #[test]
+    fn truncate_message() {
+        assert_eq!(truncate("short".to_owned(), usize::MAX), "short");
+        assert_eq!(truncate("short".to_owned(), 5), "short");
+        assert_eq!(truncate("énorme".to_owned(), 5), "énor…");
+    }
+
+    #[tokio::test]
+    async fn problem_details() {
+        let Err(YamlRejection::YamlError(err)) = Yaml::<Vec<u8>>::from_bytes(b"[1, x]") else {
+            panic!("`x` is not a number");
+        };
+        let problem =
+            || RejectionBody::new(err.status(), "YamlError", "invalid".to_owned()).with_error(&err);
+
+        let res = problem().render(BodyFormat::ProblemYaml);
+        assert_eq!(
+            res.headers()[header::CONTENT_TYPE],
+            "application/problem+yaml"
+        );
+        assert_eq!(
+            body(res).await,
+            "type: about:blank\n\
+             title: Bad Request\n\
+             status: 400\n\
+             detail: invalid\n\
+             path: '[1]'\n"
+        );
+
+        #[cfg(feature = "json")]
+        {
+            let res = problem().render(BodyFormat::ProblemJson);
+            assert_eq!(
+                res.headers()[header::CONTENT_TYPE],
+                "application/problem+json"
+            );
+            assert_eq!(
+                body(res).await,
+                r#"{"type":"about:blank","title":"Bad Request","status":400,"detail":"invalid","path":"[1]"}"#
+            );
+        }
+    }
+}
@@ -0,0 +1,194 @@
+//! Schema inference from example documents.
+//!
+//! [`infer`] derives a JSON-Schema-like description of one or more parsed
+//! examples: the type of every node, the properties of mappings and the keys
+//! present in all of them, the items of sequences, and an `enum` for string
+//! fields that only ever take a handful of values. It is meant as a starting
+//! point when onboarding payloads that predate any schema, not as a validator.
+
+use serde_yaml::{Mapping, Value};
+
+use crate::Yaml;
+
+/// Maximum number of distinct strings reported as an `enum`.
+const ENUM_LIMIT: usize = 5;
+
+/// Infer a schema describing all of the `examples`.
+///
+/// # Example
+///
+/// ```
+/// use axum_yaml::schema;
+///
+/// let examples = [
+///     serde_yaml::from_str("{ name: web, replicas: 2 }").unwrap(),
+///     serde_yaml::from_str("{ name: db }").unwrap(),
+/// ];
+/// let schema = schema::infer(&examples);
+///
+/// assert_eq!(schema["type"], "object");
+/// assert_eq!(schema["required"][0], "name");
+/// assert_eq!(schema["properties"]["replicas"]["type"], "integer");
+/// ```
+pub fn infer(examples: &[Value]) -> Value {
+    infer_node(&examples.iter().collect::<Vec<_>>())
+}
+
+/// Handler inferring the schema of a YAML sequence of examples.
+///
+/// Mount it on a debug route to get a schema for legacy payloads:
+///
+/// ```no_run
+/// use axum::{routing::post, Router};
+/// use axum_yaml::schema;
+///
+/// let app = Router::new().route("/debug/schema", post(schema::infer_handler));
+/// # let _: Router = app;
+/// ```
+pub async fn infer_handler(Yaml(examples): Yaml<Vec<Value>>) -> Yaml<Value> {
+    Yaml(infer(&examples))
+}
+
+fn infer_node(values: &[&Value]) -> Value {
+    let values: Vec<&Value> = values.iter().map(|value| untag(value)).collect();
+
+    let mut types = Vec::new();
+    let mut schema = Mapping::new();
+
+    if values.iter().any(|value| value.is_null()) {
+        types.push("null");
+    }
+    if values.iter().any(|value| value.is_bool()) {
+        types.push("boolean");
+    }
+    let numbers: Vec<_> = values.iter().filter_map(|value| value.as_f64()).collect();
+    if !numbers.is_empty() {
+        let integers = values
+            .iter()
+            .filter(|value| value.is_i64() || value.is_u64())
+            .count();
+        types.push(if integers == numbers.len() {
+            "integer"
+        } else {
+            "number"
+        });
+    }
+
+    let strings: Vec<&str> = values.iter().filter_map(|value| value.as_str()).collect();
+    if !strings.is_empty() {
+        types.push("string");
+        let mut distinct = strings.clone();
+        distinct.sort_unstable();
+        distinct.dedup();
+        if distinct.len() <= ENUM_LIMIT && distinct.len() < strings.len() {
+            schema.insert("enum".into(), distinct.into_iter().collect());
+        }
+    }
+
+    let sequences: Vec<_> = values
+        .iter()
+        .filter_map(|value| value.as_sequence())
+        .collect();
+    if !sequences.is_empty() {
+        types.push("array");
+        let items: Vec<&Value> = sequences.into_iter().flatten().collect();
+        if !items.is_empty() {
+            schema.insert("items".into(), infer_node(&items));
+        }
+    }
+
+    let mappings: Vec<_> = values
+        .iter()
+        .filter_map(|value| value.as_mapping())
+        .collect();
+    if !mappings.is_empty() {
+        types.push("object");
+
+        let mut keys: Vec<&Value> = Vec::new();
+        for mapping in &mappings {
+            for key in mapping.keys() {
+                if !keys.contains(&key) {
+                    keys.push(key);
+                }
+            }
+        }
+
+        let mut properties = Mapping::new();
+        let mut required = Vec::new();
+        for key in keys {
+            let present: Vec<&Value> = mappings
+                .iter()
+                .filter_map(|mapping| mapping.get(key))
+                .collect();
+            if present.len() == mappings.len() {
+                required.push(key.clone());
+            }
+            properties.insert(key.clone(), infer_node(&present));
+        }
+
+        schema.insert("properties".into(), properties.into());
+        if !required.is_empty() {
+            schema.insert("required".into(), required.into());
+        }
+    }
+
+    let ty = match types.as_slice() {
+        [ty] => Value::from(*ty),
+        types => types.iter().copied().collect(),
+    };
+    let mut node = Mapping::new();
+    node.insert("type".into(), ty);
+    node.extend(schema);
+    node.into()
+}
+
+fn untag(value: &Value) -> &Value {
+    match value {
+        Value::Tagged(tagged) => untag(&tagged.value),
+        value => value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn examples(sources: &[&str]) -> Vec<Value> {
+        sources
+            .iter()
+            .map(|source| serde_yaml::from_str(source).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn infer_object() {
+        let schema = infer(&examples(&[
+            "{ kind: a, size: 1, tags: [x] }",
+            "{ kind: a, size: 1.5 }",
+            "{ kind: b, size: 2, owner: ~ }",
+        ]));
+
+        let expected: Value = serde_yaml::from_str(
+            r#"
+            type: object
+            properties:
+              kind: { type: string, enum: [a, b] }
+              size: { type: number }
+              tags: { type: array, items: { type: string } }
+              owner: { type: "null" }
+            required: [kind, size]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(schema, expected);
+    }
+
+    #[test]
+    fn infer_mixed_types() {
+        let schema = infer(&examples(&["1", "one"]));
+        assert_eq!(
+            schema,
+            serde_yaml::from_str::<Value>("type: [integer, string]").unwrap()
+        );
+    }
+}
@@ -0,0 +1,192 @@
+//! Stateful deserialization of request bodies.
+//!
+//! [`YamlSeed`] deserializes the request body through a
+//! [`DeserializeSeed`] taken from the application state, so deserialization
+//! can use an interner, an arena or a schema only known at runtime, instead
+//! of being limited to [`DeserializeOwned`](serde::de::DeserializeOwned)
+//! types.
+//!
+//! ```no_run
+//! use std::collections::HashSet;
+//! use std::sync::{Arc, Mutex};
+//!
+//! use axum::{extract::FromRef, routing::post, Router};
+//! use axum_yaml::seed::YamlSeed;
+//! use serde::de::{Deserialize, DeserializeSeed, Deserializer};
+//!
+//! /// Interns the names of the request body.
+//! #[derive(Clone, Default)]
+//! struct Interner(Arc<Mutex<HashSet<Arc<str>>>>);
+//!
+//! impl<'de> DeserializeSeed<'de> for Interner {
+//!     type Value = Vec<Arc<str>>;
+//!
+//!     fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+//!         let names = Vec::<String>::deserialize(deserializer)?;
+//!         let mut interned = self.0.lock().unwrap();
+//!         Ok(names
+//!             .into_iter()
+//!             .map(|name| match interned.get(name.as_str()) {
+//!                 Some(name) => name.clone(),
+//!                 None => {
+//!                     let name: Arc<str> = name.into();
+//!                     interned.insert(name.clone());
+//!                     name
+//!                 }
+//!             })
+//!             .collect())
+//!     }
+//! }
+//!
+//! #[derive(Clone, Default)]
+//! struct AppState {
+//!     interner: Interner,
+//! }
+//!
+//! impl FromRef<AppState> for Interner {
+//!     fn from_ref(state: &AppState) -> Self {
+//!         state.interner.clone()
+//!     }
+//! }
+//!
+//! async fn register(YamlSeed(names): YamlSeed<Interner>) {
+//!     // `names` share their allocations with previous requests
+//! }
+//!
+//! let app = Router::new()
+//!     .route("/names", post(register))
+//!     .with_state(AppState::default());
+//! # let _: Router = app;
+//! ```
+
+use std::fmt;
+use std::time::Instant;
+
+use axum_core::extract::{FromRef, FromRequest, Request};
+use serde::de::DeserializeSeed;
+
+use crate::rejection::*;
+use crate::timing::ParseTiming;
+use crate::YamlConfig;
+
+/// Extractor deserializing the request body with the seed `D`, taken from
+/// the application state with [`FromRef`].
+///
+/// The document goes through the limits, key case, defaults and policy of
+/// the [`YamlConfig`] before reaching the seed, while
+/// [`YamlConfig::empty_as_none`] is left to the seed.
+///
+/// See the [module documentation](self) for an example.
+pub struct YamlSeed<D>(pub D::Value)
+where
+    D: DeserializeSeed<'static>;
+
+impl<D, S> FromRequest<S> for YamlSeed<D>
+where
+    D: DeserializeSeed<'static> + FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = YamlRejection;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let config = YamlConfig::from_extensions(req.extensions());
+        config
+            .report(async {
+                if config.yaml_content_type(req.headers()) {
+                    let timing = req.extensions().get::<ParseTiming>().cloned();
+                    let bytes = config.read_body(req, state).await?;
+
+                    let start = Instant::now();
+                    let document = config.document_body(bytes).await;
+                    let seed = D::from_ref(state);
+                    let result = document.and_then(|document| {
+                        crate::classify::deserialize_seed(seed, document)
+                            .map(YamlSeed)
+                            .map_err(|err| config.error_status(YamlError::data(err).into()))
+                    });
+                    if let Some(timing) = timing {
+                        timing.record(start.elapsed());
+                    }
+                    result
+                } else {
+                    config.drain(req).await;
+                    Err(config.missing_content_type().into())
+                }
+            })
+            .await
+    }
+}
+
+impl<D> fmt::Debug for YamlSeed<D>
+where
+    D: DeserializeSeed<'static>,
+    D::Value: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("YamlSeed").field(&self.0).finish()
+    }
+}
+
+impl<D> Clone for YamlSeed<D>
+where
+    D: DeserializeSeed<'static>,
+    D::Value: Clone,
+{
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use axum::routing::post;
+    use axum::Router;
+    use http::StatusCode;
+    use serde::de::{Deserialize, Deserializer};
+
+    use crate::test_client::TestClient;
+
+    /// Seed scaling the numbers of the document by a factor set at runtime.
+    #[derive(Clone)]
+    struct Scale(u64);
+
+    impl<'de> DeserializeSeed<'de> for Scale {
+        type Value = Vec<u64>;
+
+        fn deserialize<D: Deserializer<'de>>(
+            self,
+            deserializer: D,
+        ) -> Result<Self::Value, D::Error> {
+            let numbers = Vec::<u64>::deserialize(deserializer)?;
+            Ok(numbers.into_iter().map(|number| number * self.0).collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn seed_from_state() {
+        let app = Router::new()
+            .route(
+                "/",
+                post(|YamlSeed(numbers): YamlSeed<Scale>| async move { format!("{numbers:?}") }),
+            )
+            .with_state(Scale(10));
+        let client = TestClient::new(app);
+
+        let res = client
+            .post("/")
+            .body("[1, 2]")
+            .header("content-type", "application/yaml")
+            .await;
+        assert_eq!(res.text().await, "[10, 20]");
+
+        let res = client
+            .post("/")
+            .body("[1, x]")
+            .header("content-type", "application/yaml")
+            .await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        assert!(res.text().await.contains("[1]: invalid type"));
+    }
+}
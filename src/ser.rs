@@ -0,0 +1,709 @@
+//! Serializer settings for YAML responses.
+//!
+//! By default responses are written by `serde_yaml` as-is. Wrapping a router,
+//! or some of its routes, with a [`SerializeConfigLayer`] changes how the
+//! `Yaml<T>` responses built by its handlers are formatted, and
+//! [`Yaml::with_config`] overrides the settings of a single response.
+//!
+//! The layer scopes its configuration to the polls of the inner service
+//! future, so responses built on other tasks, e.g. with `tokio::spawn`, are
+//! written with the default configuration.
+
+use std::{
+    cell::RefCell,
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, LazyLock},
+    task::{Context, Poll},
+};
+
+use axum_core::response::{IntoResponse, Response};
+use bytes::{BufMut, BytesMut};
+use http::{header, HeaderMap, HeaderValue, Request, StatusCode};
+use pin_project_lite::pin_project;
+use serde::Serialize;
+use serde_yaml::Value;
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::case::KeyCase;
+use crate::emit::Emitter;
+use crate::path::key_to_string;
+use crate::timing::{SerializeTiming, Step};
+use crate::yaml::is_yaml_media_type;
+#[cfg(doc)]
+use crate::Yaml;
+
+static DEFAULT: LazyLock<Arc<SerializeConfig>> = LazyLock::new(Arc::default);
+
+thread_local! {
+    /// Configuration of the [`SerializeConfigLayer`] being polled.
+    static SCOPED: RefCell<Option<Arc<SerializeConfig>>> = const { RefCell::new(None) };
+}
+
+/// Settings controlling how YAML responses are serialized.
+///
+/// # Example
+///
+/// ```
+/// use axum::{routing::get, Router};
+/// use axum_yaml::ser::{FloatFormat, SerializeConfig, SerializeConfigLayer};
+/// use axum_yaml::Yaml;
+///
+/// async fn ratio() -> Yaml<f64> {
+///     Yaml(2.0 / 3.0)
+/// }
+///
+/// let config = SerializeConfig::new().float_format(FloatFormat::new().precision(2));
+/// let app: Router = Router::new()
+///     .route("/ratio", get(ratio))
+///     .layer(SerializeConfigLayer::new(config));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SerializeConfig {
+    float_format: Option<FloatFormat>,
+    key_case: Option<KeyCase>,
+    charset: bool,
+    bom: bool,
+    media_type: Option<HeaderValue>,
+    sort_keys: bool,
+    indent: Option<usize>,
+    hooks: Vec<ResponseHook>,
+}
+
+impl SerializeConfig {
+    /// Create the default configuration, matching `serde_yaml` output.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Format floating point numbers with `format`.
+    pub fn float_format(mut self, format: FloatFormat) -> Self {
+        self.float_format = Some(format);
+        self
+    }
+
+    /// Rename the mapping keys of responses to `case`.
+    pub fn key_case(mut self, case: KeyCase) -> Self {
+        self.key_case = Some(case);
+        self
+    }
+
+    /// Sort the keys of every mapping, for canonical output that does not
+    /// depend on the iteration order of maps.
+    pub fn sort_keys(mut self, sort: bool) -> Self {
+        self.sort_keys = sort;
+        self
+    }
+
+    /// Indent nested mappings by `width` spaces instead of 2.
+    ///
+    /// Sequences under a mapping key are not indented, like the default
+    /// output, and the items of sequences are aligned to `width`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `width` is not between 2 and 9, the widths `libyaml`
+    /// emits.
+    #[track_caller]
+    pub fn indent(mut self, width: usize) -> Self {
+        if !(2..=9).contains(&width) {
+            panic!("indentation width must be between 2 and 9, got {width}");
+        }
+        self.indent = Some(width);
+        self
+    }
+
+    /// Append `; charset=utf-8` to the `Content-Type` of responses, for
+    /// clients requiring the parameter.
+    pub fn charset(mut self, charset: bool) -> Self {
+        self.charset = charset;
+        self
+    }
+
+    /// Start the body of responses with a UTF-8 byte order mark, for clients
+    /// that only detect UTF-8 by it.
+    ///
+    /// The mark is written before the hooks run, so they see the body as it
+    /// is sent.
+    pub fn bom(mut self, bom: bool) -> Self {
+        self.bom = bom;
+        self
+    }
+
+    /// Use `media_type` instead of `application/yaml` as the `Content-Type` of
+    /// responses, e.g. a vendor type such as
+    /// `application/vnd.mycorp.deploy+yaml` that API gateways route on.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `media_type` is not a YAML media type, i.e. neither
+    /// `application/yaml` nor an `application` type with a `+yaml` suffix, as
+    /// the YAML extractors would not accept it back.
+    #[track_caller]
+    pub fn media_type(mut self, media_type: &str) -> Self {
+        if !is_yaml_media_type(media_type) || media_type.contains(';') {
+            panic!("`{media_type}` is not a YAML media type");
+        }
+        match HeaderValue::from_str(media_type) {
+            Ok(value) => self.media_type = Some(value),
+            Err(_) => panic!("invalid media type `{media_type}`"),
+        }
+        self
+    }
+
+    /// Run `hook` over the serialized body and the headers of every response
+    /// before it is sent, e.g. to attach a `Content-Digest`, sign the payload
+    /// or append an integrity comment.
+    ///
+    /// Hooks run in the order they were added.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use axum_yaml::ser::SerializeConfig;
+    /// use http::HeaderValue;
+    ///
+    /// let config = SerializeConfig::new().hook(|body, headers| {
+    ///     let length = HeaderValue::from(body.len());
+    ///     headers.insert("x-yaml-length", length);
+    /// });
+    /// ```
+    pub fn hook<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&mut BytesMut, &mut HeaderMap) + Send + Sync + 'static,
+    {
+        self.hooks.push(ResponseHook(Arc::new(hook)));
+        self
+    }
+
+    /// The configuration of the enclosing [`SerializeConfigLayer`], or the
+    /// default one outside of any.
+    pub(crate) fn current() -> Arc<Self> {
+        SCOPED
+            .with_borrow(Option::clone)
+            .unwrap_or_else(|| DEFAULT.clone())
+    }
+
+    /// Run `f` with `config` as the [`current`](Self::current) configuration.
+    pub(crate) fn scope<R>(config: &Arc<Self>, f: impl FnOnce() -> R) -> R {
+        /// Restores the enclosing configuration, even if `f` panics.
+        struct Restore(Option<Arc<SerializeConfig>>);
+
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                SCOPED.set(self.0.take());
+            }
+        }
+
+        let _restore = Restore(SCOPED.replace(Some(config.clone())));
+        f()
+    }
+
+    /// The `Content-Type` of responses serialized with this configuration.
+    pub(crate) fn content_type(&self) -> HeaderValue {
+        let media_type = self
+            .media_type
+            .clone()
+            .unwrap_or_else(|| HeaderValue::from_static("application/yaml"));
+        if !self.charset {
+            return media_type;
+        }
+
+        let mut content_type = media_type.as_bytes().to_vec();
+        content_type.extend_from_slice(b"; charset=utf-8");
+        HeaderValue::from_bytes(&content_type).unwrap_or(media_type)
+    }
+
+    /// Build the response for a serialized `body`, running the hooks.
+    pub(crate) fn finish(&self, body: BytesMut, step: Step) -> Response {
+        self.finish_with(body, step, |_| {})
+    }
+
+    /// Like [`finish`](Self::finish), passing the body to `inspect` once the
+    /// hooks have run, exactly as it is served.
+    fn finish_with<F>(&self, mut body: BytesMut, step: Step, inspect: F) -> Response
+    where
+        F: FnOnce(&[u8]),
+    {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::CONTENT_TYPE, self.content_type());
+        if self.bom {
+            let mut marked = BytesMut::with_capacity(body.len() + 3);
+            marked.put_slice("\u{FEFF}".as_bytes());
+            marked.put_slice(&body);
+            body = marked;
+        }
+        for hook in &self.hooks {
+            (hook.0)(&mut body, &mut headers);
+        }
+
+        inspect(&body);
+        step.body_size(body.len());
+        (headers, SerializeTiming(step.end()), body.freeze()).into_response()
+    }
+
+    /// The document written for `value`, with its keys renamed and sorted
+    /// according to this configuration.
+    pub(crate) fn to_value<T>(&self, value: &T) -> Result<Value, serde_yaml::Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        let mut value = serde_yaml::to_value(value)?;
+        if let Some(case) = self.key_case {
+            case.apply(&mut value);
+        }
+        if self.sort_keys {
+            sort_keys(&mut value);
+        }
+        Ok(value)
+    }
+
+    /// Serialize `value` into `buf` according to this configuration.
+    pub(crate) fn to_writer<W, T>(&self, buf: &mut W, value: &T) -> Result<(), serde_yaml::Error>
+    where
+        W: std::io::Write,
+        T: Serialize + ?Sized,
+    {
+        if self.float_format.is_none()
+            && self.key_case.is_none()
+            && !self.sort_keys
+            && self.indent.is_none()
+        {
+            return serde_yaml::to_writer(buf, value);
+        }
+
+        let value = self.to_value(value)?;
+        // Only the layout libyaml cannot produce goes through our emitter.
+        if self.float_format.is_none() && self.indent.is_none() {
+            return serde_yaml::to_writer(buf, &value);
+        }
+        let emitted = Emitter {
+            indent: self.indent.unwrap_or(2),
+            float_format: self.float_format,
+        }
+        .emit(&value)?;
+        buf.write_all(emitted.as_bytes())
+            .map_err(serde::ser::Error::custom)
+    }
+}
+
+type Hook = dyn Fn(&mut BytesMut, &mut HeaderMap) + Send + Sync;
+
+#[derive(Clone)]
+struct ResponseHook(Arc<Hook>);
+
+impl std::fmt::Debug for ResponseHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ResponseHook")
+    }
+}
+
+/// YAML response serialized with its own [`SerializeConfig`], overriding the
+/// one of the enclosing [`SerializeConfigLayer`].
+///
+/// Created with [`Yaml::with_config`].
+///
+/// # Example
+///
+/// ```
+/// use axum_yaml::ser::{ConfiguredYaml, SerializeConfig};
+/// use axum_yaml::Yaml;
+/// use std::collections::HashMap;
+///
+/// async fn canonical() -> ConfiguredYaml<HashMap<String, u32>> {
+///     let counts = HashMap::from([("b".to_owned(), 2), ("a".to_owned(), 1)]);
+///     Yaml(counts).with_config(SerializeConfig::new().sort_keys(true))
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ConfiguredYaml<T> {
+    pub(crate) value: T,
+    pub(crate) config: SerializeConfig,
+}
+
+impl<T> IntoResponse for ConfiguredYaml<T>
+where
+    T: Serialize,
+{
+    fn into_response(self) -> Response {
+        response(&self.value, &self.config)
+    }
+}
+
+/// Serialize `value` into a YAML response according to `config`.
+pub(crate) fn response<T>(value: &T, config: &SerializeConfig) -> Response
+where
+    T: Serialize + ?Sized,
+{
+    response_with(value, config, |_| {})
+}
+
+/// Like [`response`], passing the served body to `inspect`.
+pub(crate) fn response_with<T, F>(value: &T, config: &SerializeConfig, inspect: F) -> Response
+where
+    T: Serialize + ?Sized,
+    F: FnOnce(&[u8]),
+{
+    // Use a small initial capacity of 128 bytes like serde_json::to_vec
+    // https://docs.rs/serde_json/1.0.82/src/serde_json/ser.rs.html#2189
+    let step = Step::serialize(1);
+    let mut buf = BytesMut::with_capacity(128).writer();
+    match config.to_writer(&mut buf, value) {
+        Ok(()) => config.finish_with(buf.into_inner(), step, |body| {
+            #[cfg(feature = "tracing")]
+            crate::debug::check_response(value, config, body);
+            inspect(body)
+        }),
+        Err(err) => error_response(err),
+    }
+}
+
+/// Plain-text `500 Internal Server Error` response describing `err`, served
+/// instead of a YAML response that could not be written.
+pub(crate) fn error_response(err: impl fmt::Display) -> Response {
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        [(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static(mime::TEXT_PLAIN_UTF_8.as_ref()),
+        )],
+        err.to_string(),
+    )
+        .into_response()
+}
+
+/// [`Layer`] serializing the YAML responses of the wrapped service with a
+/// [`SerializeConfig`].
+///
+/// Layers can be nested, e.g. a router-wide layer and another one on a few
+/// routes; the innermost configuration applies.
+#[derive(Debug, Clone, Default)]
+pub struct SerializeConfigLayer {
+    config: Arc<SerializeConfig>,
+}
+
+impl SerializeConfigLayer {
+    /// Serialize responses with `config`.
+    pub fn new(config: SerializeConfig) -> Self {
+        Self {
+            config: Arc::new(config),
+        }
+    }
+}
+
+impl<S> Layer<S> for SerializeConfigLayer {
+    type Service = SerializeConfigService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        SerializeConfigService {
+            inner,
+            config: self.config.clone(),
+        }
+    }
+}
+
+/// Middleware created by [`SerializeConfigLayer`].
+#[derive(Debug, Clone)]
+pub struct SerializeConfigService<S> {
+    inner: S,
+    config: Arc<SerializeConfig>,
+}
+
+impl<S, ReqBody> Service<Request<ReqBody>> for SerializeConfigService<S>
+where
+    S: Service<Request<ReqBody>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
+        ResponseFuture::scoped(self.config.clone(), || self.inner.call(req))
+    }
+}
+
+pin_project! {
+    /// Response future for [`SerializeConfigService`].
+    pub struct ResponseFuture<F> {
+        #[pin]
+        inner: F,
+        config: Arc<SerializeConfig>,
+    }
+}
+
+impl<F> ResponseFuture<F> {
+    /// Create the future returned by `call`, running both with `config` as
+    /// the current configuration.
+    pub(crate) fn scoped(config: Arc<SerializeConfig>, call: impl FnOnce() -> F) -> Self {
+        // Handlers returning a ready future may build their response here.
+        let inner = SerializeConfig::scope(&config, call);
+        Self { inner, config }
+    }
+}
+
+impl<F: Future> Future for ResponseFuture<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        SerializeConfig::scope(this.config, || this.inner.poll(cx))
+    }
+}
+
+/// Formatting of floating point numbers in responses.
+///
+/// Numbers are written in positional notation, never in scientific notation
+/// such as `1e-5`, and always keep a fractional part so they are read back as
+/// floats. Infinite and NaN values keep their YAML spelling.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FloatFormat {
+    pub(crate) precision: Option<usize>,
+    pub(crate) trim_trailing_zeros: bool,
+}
+
+impl FloatFormat {
+    /// Write the shortest representation that reads back as the same number.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Write exactly `precision` digits after the decimal point.
+    ///
+    /// With a precision of `0`, numbers end with the decimal point, such as
+    /// `2.`, so they are still read back as floats.
+    pub fn precision(mut self, precision: usize) -> Self {
+        self.precision = Some(precision);
+        self
+    }
+
+    /// Remove trailing zeros of the fractional part, keeping at least one
+    /// digit after the decimal point.
+    pub fn trim_trailing_zeros(mut self, trim: bool) -> Self {
+        self.trim_trailing_zeros = trim;
+        self
+    }
+}
+
+fn sort_keys(value: &mut Value) {
+    match value {
+        Value::Mapping(mapping) => {
+            let mut entries: Vec<_> = std::mem::take(mapping).into_iter().collect();
+            entries.sort_by_cached_key(|(key, _)| key_to_string(key));
+            for (key, mut child) in entries {
+                sort_keys(&mut child);
+                mapping.insert(key, child);
+            }
+        }
+        Value::Sequence(sequence) => sequence.iter_mut().for_each(sort_keys),
+        Value::Tagged(tagged) => sort_keys(&mut tagged.value),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn float_format() {
+        #[derive(Serialize)]
+        struct Reading {
+            value: f64,
+            count: u32,
+        }
+
+        let config = SerializeConfig::new().float_format(FloatFormat::new().precision(3));
+        let mut buf = Vec::new();
+        config
+            .to_writer(
+                &mut buf,
+                &Reading {
+                    value: 1e-5,
+                    count: 2,
+                },
+            )
+            .unwrap();
+
+        assert_eq!(String::from_utf8(buf).unwrap(), "value: 0.000\ncount: 2\n");
+    }
+
+    #[test]
+    fn key_case() {
+        #[derive(Serialize)]
+        struct User {
+            user_id: u32,
+            display_name: &'static str,
+        }
+
+        let config = SerializeConfig::new().key_case(KeyCase::Camel);
+        let mut buf = Vec::new();
+        config
+            .to_writer(
+                &mut buf,
+                &User {
+                    user_id: 1,
+                    display_name: "Ann",
+                },
+            )
+            .unwrap();
+
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            "userId: 1\ndisplayName: Ann\n"
+        );
+    }
+
+    #[test]
+    fn indent() {
+        let value: Value =
+            serde_yaml::from_str("a:\n  b: 1\n  c:\n  - d: 2\n    e: [3, 4]\n  f: |\n    x\n")
+                .unwrap();
+        let mut buf = Vec::new();
+        SerializeConfig::new()
+            .indent(4)
+            .to_writer(&mut buf, &value)
+            .unwrap();
+
+        let emitted = String::from_utf8(buf).unwrap();
+        assert_eq!(
+            emitted,
+            "a:\n    b: 1\n    c:\n    -   d: 2\n        e:\n        - 3\n        - 4\n    f: |\n        x\n"
+        );
+        assert_eq!(serde_yaml::from_str::<Value>(&emitted).unwrap(), value);
+    }
+
+    #[test]
+    #[should_panic = "indentation width must be between 2 and 9, got 1"]
+    fn narrow_indent() {
+        SerializeConfig::new().indent(1);
+    }
+
+    #[test]
+    fn charset() {
+        assert_eq!(SerializeConfig::new().content_type(), "application/yaml");
+        assert_eq!(
+            SerializeConfig::new().charset(true).content_type(),
+            "application/yaml; charset=utf-8"
+        );
+    }
+
+    #[tokio::test]
+    async fn bom() {
+        let res = response(
+            &Value::Null,
+            &SerializeConfig::new().charset(true).bom(true),
+        );
+        assert_eq!(
+            res.headers()[header::CONTENT_TYPE],
+            "application/yaml; charset=utf-8"
+        );
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], "\u{FEFF}null\n".as_bytes());
+    }
+
+    #[test]
+    fn media_type() {
+        let config = SerializeConfig::new().media_type("application/vnd.mycorp.deploy+yaml");
+        assert_eq!(config.content_type(), "application/vnd.mycorp.deploy+yaml");
+        assert_eq!(
+            config.charset(true).content_type(),
+            "application/vnd.mycorp.deploy+yaml; charset=utf-8"
+        );
+    }
+
+    #[test]
+    #[should_panic = "`application/json` is not a YAML media type"]
+    fn non_yaml_media_type() {
+        let _ = SerializeConfig::new().media_type("application/json");
+    }
+
+    #[tokio::test]
+    async fn with_config() {
+        use axum::routing::get;
+        use axum::Router;
+
+        use crate::test_client::TestClient;
+        use crate::Yaml;
+
+        let app = Router::new().route(
+            "/",
+            get(|| async {
+                let value: Value = serde_yaml::from_str("b: {d: 1, c: 2}\na: 3\n").unwrap();
+                Yaml(value).with_config(SerializeConfig::new().sort_keys(true))
+            }),
+        );
+
+        let res = TestClient::new(app).get("/").await;
+        assert_eq!(res.text().await, "a: 3\nb:\n  c: 2\n  d: 1\n");
+    }
+
+    #[tokio::test]
+    async fn layer() {
+        use axum::routing::get;
+        use axum::Router;
+
+        use crate::test_client::TestClient;
+        use crate::Yaml;
+
+        let value = || serde_yaml::from_str::<Value>("b: 1.5\na: 2\n").unwrap();
+        let app = Router::new()
+            .route("/", get(move || async move { Yaml(value()) }))
+            .route(
+                "/nested",
+                get(move || async move { Yaml(value()) })
+                    .layer(SerializeConfigLayer::new(SerializeConfig::new())),
+            )
+            .route(
+                "/with-config",
+                get(move || async move { Yaml(value()).with_config(SerializeConfig::new()) }),
+            )
+            .route(
+                "/spawned",
+                get(move || async move {
+                    tokio::spawn(async move { Yaml(value()).into_response() })
+                        .await
+                        .unwrap()
+                }),
+            )
+            .layer(SerializeConfigLayer::new(
+                SerializeConfig::new()
+                    .sort_keys(true)
+                    .float_format(FloatFormat::new().precision(2)),
+            ));
+
+        let client = TestClient::new(app);
+        assert_eq!(client.get("/").await.text().await, "a: 2\nb: 1.50\n");
+        for path in ["/nested", "/with-config", "/spawned"] {
+            assert_eq!(client.get(path).await.text().await, "b: 1.5\na: 2\n");
+        }
+        let body = response(&value(), &SerializeConfig::current()).into_body();
+        let body = axum::body::to_bytes(body, usize::MAX).await.unwrap();
+        assert_eq!(&body[..], b"b: 1.5\na: 2\n");
+    }
+
+    #[tokio::test]
+    async fn hooks() {
+        let config = SerializeConfig::new()
+            .hook(|body, headers| {
+                headers.insert("x-length", HeaderValue::from(body.len()));
+            })
+            .hook(|body, _| body.put_slice(b"# signed\n"));
+
+        let response = response(&[1, 2], &config);
+        assert_eq!(response.headers()["x-length"], "8");
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/yaml");
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(body, "- 1\n- 2\n# signed\n");
+    }
+}
@@ -0,0 +1,48 @@
+//! A `log` logger recording every record, for tests of the `log` backend.
+//!
+//! `log` allows a single logger per process, so tests share this one and
+//! pick out their own records by a marker in the message.
+
+use std::sync::{Mutex, Once};
+
+use log::{Level, Log, Metadata, Record};
+
+static RECORDS: Mutex<Vec<(String, Level, String)>> = Mutex::new(Vec::new());
+
+struct Capture;
+
+impl Log for Capture {
+    fn enabled(&self, _: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record<'_>) {
+        RECORDS.lock().unwrap().push((
+            record.target().to_owned(),
+            record.level(),
+            record.args().to_string(),
+        ));
+    }
+
+    fn flush(&self) {}
+}
+
+/// Install the capturing logger, once per process.
+pub(crate) fn install() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        log::set_logger(&Capture).unwrap();
+        log::set_max_level(log::LevelFilter::Trace);
+    });
+}
+
+/// Target, level and message of the records whose message contains `marker`.
+pub(crate) fn records(marker: &str) -> Vec<(String, Level, String)> {
+    RECORDS
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|(_, _, message)| message.contains(marker))
+        .cloned()
+        .collect()
+}
@@ -0,0 +1,444 @@
+//! `Server-Timing` reporting of YAML parse and serialize durations.
+//!
+//! Wrap a router with [`ServerTimingLayer`] to have every response carry a
+//! `Server-Timing: yaml-parse;dur=..., yaml-serialize;dur=...` header, so that
+//! browser devtools and APM agents can see how much of the request time was
+//! spent in this crate. Durations are reported in milliseconds and only for the
+//! steps that actually happened during the request.
+//!
+//! ```no_run
+//! use axum::{routing::post, Router};
+//! use axum_yaml::{timing::ServerTimingLayer, Yaml};
+//! use serde_yaml::Value;
+//!
+//! async fn echo(Yaml(value): Yaml<Value>) -> Yaml<Value> {
+//!     Yaml(value)
+//! }
+//!
+//! let app: Router = Router::new()
+//!     .route("/echo", post(echo))
+//!     .layer(ServerTimingLayer);
+//! ```
+//!
+//! With the `tracing` feature, parsing and serializing also run in
+//! `yaml_parse` and `yaml_serialize` spans at the `INFO` level, recording the
+//! `body_size` in bytes, the number of `documents` and the `duration_ms` of
+//! the step, so slow parses stand out in distributed traces. With the `log`
+//! feature, the same fields are logged at the `DEBUG` level under the
+//! `axum_yaml` target once each step ends, e.g.
+//! `yaml_parse: body_size=8 documents=1 duration_ms=0.042`.
+
+use std::{
+    convert::Infallible,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use axum_core::response::{IntoResponseParts, ResponseParts};
+use http::{header::HeaderName, HeaderValue, Request, Response};
+use pin_project_lite::pin_project;
+use tower_layer::Layer;
+use tower_service::Service;
+
+static SERVER_TIMING: HeaderName = HeaderName::from_static("server-timing");
+
+/// Time spent parsing request bodies, shared between the layer and extractors
+/// through request extensions.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ParseTiming(Arc<Mutex<Option<Duration>>>);
+
+impl ParseTiming {
+    pub(crate) fn record(&self, elapsed: Duration) {
+        let mut total = self.0.lock().unwrap_or_else(|err| err.into_inner());
+        *total = Some(total.unwrap_or_default() + elapsed);
+    }
+
+    fn get(&self) -> Option<Duration> {
+        *self.0.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+/// Parse or serialize step, timed and traced in a span with the `tracing`
+/// feature, and logged when it ends with the `log` feature.
+///
+/// Spans are entered for the lifetime of the step, which must not be held
+/// across `.await` points.
+pub(crate) struct Step {
+    start: Instant,
+    #[cfg(feature = "tracing")]
+    span: tracing::span::EnteredSpan,
+    #[cfg(feature = "log")]
+    record: Record,
+}
+
+/// Fields of a step logged with the `log` feature, mirroring those of its
+/// span.
+#[cfg(feature = "log")]
+struct Record {
+    name: &'static str,
+    body_size: std::cell::Cell<Option<usize>>,
+    documents: std::cell::Cell<Option<usize>>,
+}
+
+#[cfg(feature = "log")]
+impl Record {
+    fn new(name: &'static str, body_size: Option<usize>, documents: Option<usize>) -> Self {
+        Self {
+            name,
+            body_size: body_size.into(),
+            documents: documents.into(),
+        }
+    }
+
+    fn log(&self, elapsed: Duration) {
+        let mut fields = Vec::new();
+        if let Some(body_size) = self.body_size.get() {
+            fields.push(format!("body_size={body_size}"));
+        }
+        if let Some(documents) = self.documents.get() {
+            fields.push(format!("documents={documents}"));
+        }
+        fields.push(format!("duration_ms={:.3}", elapsed.as_secs_f64() * 1000.0));
+        log::debug!(target: "axum_yaml", "{}: {}", self.name, fields.join(" "));
+    }
+}
+
+impl Step {
+    /// Start parsing a request body of `body_size` bytes.
+    #[cfg_attr(
+        not(any(feature = "tracing", feature = "log")),
+        allow(unused_variables)
+    )]
+    pub(crate) fn parse(body_size: usize) -> Self {
+        Self {
+            start: Instant::now(),
+            #[cfg(feature = "tracing")]
+            span: tracing::info_span!(
+                target: "axum_yaml",
+                "yaml_parse",
+                body_size,
+                documents = tracing::field::Empty,
+                duration_ms = tracing::field::Empty,
+            )
+            .entered(),
+            #[cfg(feature = "log")]
+            record: Record::new("yaml_parse", Some(body_size), None),
+        }
+    }
+
+    /// Start serializing `documents` into a response body.
+    #[cfg_attr(
+        not(any(feature = "tracing", feature = "log")),
+        allow(unused_variables)
+    )]
+    pub(crate) fn serialize(documents: usize) -> Self {
+        Self {
+            start: Instant::now(),
+            #[cfg(feature = "tracing")]
+            span: tracing::info_span!(
+                target: "axum_yaml",
+                "yaml_serialize",
+                body_size = tracing::field::Empty,
+                documents,
+                duration_ms = tracing::field::Empty,
+            )
+            .entered(),
+            #[cfg(feature = "log")]
+            record: Record::new("yaml_serialize", None, Some(documents)),
+        }
+    }
+
+    /// Record the number of documents parsed.
+    #[cfg_attr(
+        not(any(feature = "tracing", feature = "log")),
+        allow(unused_variables)
+    )]
+    pub(crate) fn documents(&self, documents: usize) {
+        #[cfg(feature = "tracing")]
+        self.span.record("documents", documents);
+        #[cfg(feature = "log")]
+        self.record.documents.set(Some(documents));
+    }
+
+    /// Record the size of the serialized body.
+    #[cfg_attr(
+        not(any(feature = "tracing", feature = "log")),
+        allow(unused_variables)
+    )]
+    pub(crate) fn body_size(&self, body_size: usize) {
+        #[cfg(feature = "tracing")]
+        self.span.record("body_size", body_size);
+        #[cfg(feature = "log")]
+        self.record.body_size.set(Some(body_size));
+    }
+
+    /// End the step, returning its duration.
+    pub(crate) fn end(self) -> Duration {
+        let elapsed = self.start.elapsed();
+        #[cfg(feature = "tracing")]
+        self.span
+            .record("duration_ms", elapsed.as_secs_f64() * 1000.0);
+        #[cfg(feature = "log")]
+        self.record.log(elapsed);
+        elapsed
+    }
+}
+
+/// Time spent serializing a response body, carried in response extensions.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct SerializeTiming(pub(crate) Duration);
+
+impl IntoResponseParts for SerializeTiming {
+    type Error = Infallible;
+
+    fn into_response_parts(self, mut res: ResponseParts) -> Result<ResponseParts, Self::Error> {
+        res.extensions_mut().insert(self);
+        Ok(res)
+    }
+}
+
+/// [`Layer`] adding a `Server-Timing` header with YAML parse and serialize
+/// durations to responses.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ServerTimingLayer;
+
+impl<S> Layer<S> for ServerTimingLayer {
+    type Service = ServerTiming<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ServerTiming { inner }
+    }
+}
+
+/// Middleware created by [`ServerTimingLayer`].
+#[derive(Debug, Clone)]
+pub struct ServerTiming<S> {
+    inner: S,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for ServerTiming<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request<ReqBody>) -> Self::Future {
+        let parse = ParseTiming::default();
+        req.extensions_mut().insert(parse.clone());
+
+        ResponseFuture {
+            inner: self.inner.call(req),
+            parse,
+        }
+    }
+}
+
+pin_project! {
+    /// Response future for [`ServerTiming`].
+    pub struct ResponseFuture<F> {
+        #[pin]
+        inner: F,
+        parse: ParseTiming,
+    }
+}
+
+impl<F, ResBody, E> Future for ResponseFuture<F>
+where
+    F: Future<Output = Result<Response<ResBody>, E>>,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let mut res = std::task::ready!(this.inner.poll(cx))?;
+
+        let metrics: Vec<String> = [
+            ("yaml-parse", this.parse.get()),
+            (
+                "yaml-serialize",
+                res.extensions()
+                    .get::<SerializeTiming>()
+                    .map(|timing| timing.0),
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(name, duration)| {
+            let duration = duration?;
+            Some(format!("{name};dur={:.3}", duration.as_secs_f64() * 1000.0))
+        })
+        .collect();
+
+        if !metrics.is_empty() {
+            if let Ok(value) = HeaderValue::from_str(&metrics.join(", ")) {
+                res.headers_mut().append(SERVER_TIMING.clone(), value);
+            }
+        }
+
+        Poll::Ready(Ok(res))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use axum::routing::{get, post};
+    use axum::Router;
+    use serde_yaml::Value;
+
+    use crate::test_client::TestClient;
+    use crate::Yaml;
+
+    #[tokio::test]
+    async fn parse_and_serialize() {
+        let app = Router::new()
+            .route("/", post(|Yaml(value): Yaml<Value>| async { Yaml(value) }))
+            .layer(ServerTimingLayer);
+
+        let res = TestClient::new(app)
+            .post("/")
+            .body("foo: bar")
+            .header("content-type", "application/yaml")
+            .await;
+
+        let timing = res.headers()[&SERVER_TIMING].to_str().unwrap();
+        let metrics: Vec<_> = timing
+            .split(", ")
+            .map(|metric| metric.split_once(";dur=").unwrap().0)
+            .collect();
+        assert_eq!(metrics, ["yaml-parse", "yaml-serialize"]);
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn spans() {
+        use std::fmt::Debug;
+
+        use tracing::field::{Field, Visit};
+        use tracing::span::{Attributes, Id, Record};
+        use tracing::{Event, Metadata, Subscriber};
+
+        /// Fields recorded on the `yaml_parse` and `yaml_serialize` spans.
+        #[derive(Default)]
+        struct Spans(Mutex<Vec<(u64, &'static str, Vec<String>)>>);
+
+        struct Fields<'a>(&'a mut Vec<String>);
+
+        impl Visit for Fields<'_> {
+            fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+                if field.name() != "duration_ms" {
+                    self.0.push(format!("{}={value:?}", field.name()));
+                } else {
+                    self.0.push("duration_ms".to_owned());
+                }
+            }
+        }
+
+        impl Subscriber for &'static Spans {
+            fn enabled(&self, _: &Metadata<'_>) -> bool {
+                true
+            }
+
+            fn new_span(&self, span: &Attributes<'_>) -> Id {
+                let mut spans = self.0.lock().unwrap();
+                let id = spans.len() as u64 + 1;
+                let mut fields = Vec::new();
+                span.record(&mut Fields(&mut fields));
+                spans.push((id, span.metadata().name(), fields));
+                Id::from_u64(id)
+            }
+
+            fn record(&self, span: &Id, values: &Record<'_>) {
+                let mut spans = self.0.lock().unwrap();
+                let (_, _, fields) = &mut spans[span.into_u64() as usize - 1];
+                values.record(&mut Fields(fields));
+            }
+
+            fn record_follows_from(&self, _: &Id, _: &Id) {}
+            fn event(&self, _: &Event<'_>) {}
+            fn enter(&self, _: &Id) {}
+            fn exit(&self, _: &Id) {}
+        }
+
+        let spans: &'static Spans = Box::leak(Box::default());
+        let _guard = tracing::subscriber::set_default(spans);
+
+        let Yaml(value) = Yaml::<Value>::from_bytes(b"foo: bar").unwrap();
+        let _ = axum_core::response::IntoResponse::into_response(Yaml(value));
+
+        let spans = spans.0.lock().unwrap();
+        let spans: Vec<_> = spans
+            .iter()
+            .map(|(_, name, fields)| (*name, fields.join(" ")))
+            .collect();
+        assert_eq!(
+            spans,
+            [
+                (
+                    "yaml_parse",
+                    "body_size=8 documents=1 duration_ms".to_owned()
+                ),
+                (
+                    "yaml_serialize",
+                    "documents=1 body_size=9 duration_ms".to_owned()
+                ),
+            ]
+        );
+    }
+
+    #[cfg(feature = "log")]
+    #[test]
+    fn log_records() {
+        crate::test_log::install();
+
+        // A body size no other test uses, to tell our records apart.
+        let body = format!("log_records: {}", "x".repeat(7906));
+        assert_eq!(body.len(), 7919);
+        let Yaml(value) = Yaml::<Value>::from_bytes(body.as_bytes()).unwrap();
+        let _ = axum_core::response::IntoResponse::into_response(Yaml(value));
+
+        let records: Vec<_> = crate::test_log::records("body_size=79")
+            .into_iter()
+            // `tracing` spans are forwarded to `log` too when its `log`
+            // feature is enabled, as it is by `tower`.
+            .filter(|(target, _, message)| target == "axum_yaml" && message.contains(": "))
+            .map(|(_, level, message)| {
+                let (fields, _) = message.split_once(" duration_ms=").unwrap();
+                (level, fields.to_owned())
+            })
+            .collect();
+        assert_eq!(
+            records,
+            [
+                (
+                    log::Level::Debug,
+                    "yaml_parse: body_size=7919 documents=1".to_owned()
+                ),
+                (
+                    log::Level::Debug,
+                    "yaml_serialize: body_size=7920 documents=1".to_owned()
+                ),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn no_yaml() {
+        let app = Router::new()
+            .route("/", get(|| async { "plain" }))
+            .layer(ServerTimingLayer);
+
+        let res = TestClient::new(app).get("/").await;
+
+        assert!(res.headers().get(&SERVER_TIMING).is_none());
+    }
+}
@@ -0,0 +1,212 @@
+//! Typed YAML `Content-Type` and `Accept` headers.
+//!
+//! Requires the `headers` feature. [`YamlContentType`] and [`YamlAccept`]
+//! implement [`headers::Header`], so they can be used with `TypedHeader`,
+//! and follow the same rules as the extractors of this crate: a YAML media
+//! type is `application/yaml` or any `application` type with a `+yaml`
+//! suffix, such as `application/vnd.mycorp.deploy+yaml`.
+//!
+//! ```
+//! use axum_yaml::typed_header::YamlAccept;
+//! use headers::{Header, HeaderValue};
+//!
+//! let value = HeaderValue::from_static("application/json, application/*+yaml;q=0.5");
+//! let accept = YamlAccept::decode(&mut [&value].into_iter()).unwrap();
+//! assert!(accept.accepts_yaml());
+//! assert_eq!(accept.yaml_quality(), 0.5);
+//! ```
+
+use std::fmt;
+
+use headers::{Error, Header};
+use http::{header, HeaderName, HeaderValue};
+use mime::Mime;
+
+use crate::negotiate::media_ranges;
+use crate::yaml::is_yaml_media_type;
+
+/// `Content-Type` header holding a YAML media type.
+///
+/// Decoding fails for any other media type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct YamlContentType(Mime);
+
+impl YamlContentType {
+    /// `application/yaml`.
+    pub fn yaml() -> Self {
+        Self("application/yaml".parse().expect("valid media type"))
+    }
+
+    /// Parse `content_type`, returning `None` if it is not a YAML media type.
+    pub fn new(content_type: &str) -> Option<Self> {
+        if !is_yaml_media_type(content_type) {
+            return None;
+        }
+        content_type.parse().ok().map(Self)
+    }
+
+    /// The media type.
+    pub fn mime(&self) -> &Mime {
+        &self.0
+    }
+
+    /// The vendor subtype of a vendor media type, such as
+    /// `vnd.mycorp.deploy` for `application/vnd.mycorp.deploy+yaml`.
+    pub fn vendor(&self) -> Option<&str> {
+        let subtype = self.0.subtype().as_str();
+        self.0.suffix()?;
+        subtype.starts_with("vnd.").then_some(subtype)
+    }
+}
+
+impl Default for YamlContentType {
+    fn default() -> Self {
+        Self::yaml()
+    }
+}
+
+impl fmt::Display for YamlContentType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl Header for YamlContentType {
+    fn name() -> &'static HeaderName {
+        &header::CONTENT_TYPE
+    }
+
+    fn decode<'i, I>(values: &mut I) -> Result<Self, Error>
+    where
+        I: Iterator<Item = &'i HeaderValue>,
+    {
+        values
+            .next()
+            .and_then(|value| value.to_str().ok())
+            .and_then(Self::new)
+            .ok_or_else(Error::invalid)
+    }
+
+    fn encode<E: Extend<HeaderValue>>(&self, values: &mut E) {
+        let value = HeaderValue::from_str(self.0.as_ref()).expect("media types are valid headers");
+        values.extend(std::iter::once(value));
+    }
+}
+
+/// `Accept` header, as far as YAML responses are concerned.
+///
+/// Every media range of the header is kept; [`YamlAccept::accepts_yaml`]
+/// and [`YamlAccept::yaml_quality`] tell whether and how much the client
+/// wants YAML. An empty header accepts everything.
+#[derive(Debug, Clone, PartialEq)]
+pub struct YamlAccept {
+    ranges: Vec<(Mime, f32)>,
+}
+
+impl YamlAccept {
+    /// The media ranges of the header along with their quality values.
+    pub fn ranges(&self) -> &[(Mime, f32)] {
+        &self.ranges
+    }
+
+    /// Whether the client accepts a YAML response.
+    pub fn accepts_yaml(&self) -> bool {
+        self.yaml_quality() > 0.0
+    }
+
+    /// The quality value of the most specific media range matching YAML,
+    /// `0` if none does.
+    pub fn yaml_quality(&self) -> f32 {
+        if self.ranges.is_empty() {
+            return 1.0;
+        }
+
+        self.ranges
+            .iter()
+            .filter_map(|(range, quality)| Some((yaml_specificity(range)?, *quality)))
+            .max_by_key(|(specificity, _)| *specificity)
+            .map_or(0.0, |(_, quality)| quality)
+    }
+}
+
+/// How specifically `range` matches YAML, `None` if it does not.
+fn yaml_specificity(range: &Mime) -> Option<u8> {
+    if range.type_() == mime::STAR && range.subtype() == mime::STAR {
+        return Some(0);
+    }
+    if range.type_() != mime::APPLICATION {
+        return None;
+    }
+    if range.subtype() == mime::STAR {
+        return match range.suffix() {
+            Some(suffix) if suffix == "yaml" => Some(2),
+            Some(_) => None,
+            None => Some(1),
+        };
+    }
+    is_yaml_media_type(range.essence_str()).then_some(3)
+}
+
+impl Header for YamlAccept {
+    fn name() -> &'static HeaderName {
+        &header::ACCEPT
+    }
+
+    fn decode<'i, I>(values: &mut I) -> Result<Self, Error>
+    where
+        I: Iterator<Item = &'i HeaderValue>,
+    {
+        let mut ranges = Vec::new();
+        for value in values {
+            let value = value.to_str().map_err(|_| Error::invalid())?;
+            for range in media_ranges(value) {
+                ranges.push(range.map_err(|()| Error::invalid())?);
+            }
+        }
+        Ok(Self { ranges })
+    }
+
+    fn encode<E: Extend<HeaderValue>>(&self, values: &mut E) {
+        let ranges = self
+            .ranges
+            .iter()
+            .map(|(range, _)| range.as_ref())
+            .collect::<Vec<_>>()
+            .join(", ");
+        values.extend(HeaderValue::from_str(&ranges).ok());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode<H: Header>(value: &'static str) -> Result<H, Error> {
+        H::decode(&mut [&HeaderValue::from_static(value)].into_iter())
+    }
+
+    #[test]
+    fn content_type() {
+        let content_type: YamlContentType =
+            decode("application/vnd.mycorp.deploy+yaml; charset=utf-8").unwrap();
+        assert_eq!(content_type.vendor(), Some("vnd.mycorp.deploy"));
+        assert_eq!(YamlContentType::yaml().vendor(), None);
+        assert!(decode::<YamlContentType>("application/json").is_err());
+
+        let mut values = Vec::new();
+        YamlContentType::yaml().encode(&mut values);
+        assert_eq!(values, ["application/yaml"]);
+    }
+
+    #[test]
+    fn accept() {
+        let quality = |value| decode::<YamlAccept>(value).unwrap().yaml_quality();
+
+        assert_eq!(quality("application/yaml"), 1.0);
+        assert_eq!(quality("application/vnd.mycorp.deploy+yaml;q=0.8"), 0.8);
+        assert_eq!(quality("*/*;q=0.1, application/*+yaml;q=0.7"), 0.7);
+        assert_eq!(quality("application/yaml;q=0, */*"), 0.0);
+        assert_eq!(quality("application/json"), 0.0);
+        assert!(decode::<YamlAccept>("application/yaml;q=2").is_err());
+    }
+}
@@ -0,0 +1,177 @@
+//! YAML document types.
+//!
+//! The types used by this crate to represent untyped documents, re-exported
+//! so handlers can name them without depending on the YAML backend
+//! themselves. They are the backend's own types, so values convert to and
+//! from the backend freely, and code written against this module keeps
+//! compiling if the backend changes.
+//!
+//! ```
+//! use axum_yaml::{value::Value, Yaml};
+//!
+//! async fn kind(Yaml(manifest): Yaml<Value>) -> String {
+//!     manifest["kind"].as_str().unwrap_or_default().to_owned()
+//! }
+//! ```
+//!
+//! The [`RawYaml`] extractor hands handlers such a document directly, for
+//! gateways and webhook receivers inspecting arbitrary YAML before routing
+//! it.
+
+use std::ops::Deref;
+use std::time::Instant;
+
+use axum_core::extract::{FromRequest, Request};
+
+pub use serde_yaml::value::{Tag, TaggedValue};
+pub use serde_yaml::{Error, Mapping, Number, Sequence, Value};
+
+use crate::path::YamlPath;
+use crate::rejection::*;
+use crate::timing::ParseTiming;
+use crate::YamlConfig;
+
+/// Convert `value` into a document.
+pub fn to_value<T>(value: T) -> Result<Value, Error>
+where
+    T: serde::Serialize,
+{
+    serde_yaml::to_value(value)
+}
+
+/// Deserialize a `T` from a document.
+pub fn from_value<T>(value: Value) -> Result<T, Error>
+where
+    T: serde::de::DeserializeOwned,
+{
+    serde_yaml::from_value(value)
+}
+
+/// Extractor parsing the request body into an untyped document.
+///
+/// The document goes through the limits, key case, defaults and policy of
+/// the [`YamlConfig`] like for [`Yaml`](crate::Yaml), without a target type.
+///
+/// # Example
+///
+/// ```no_run
+/// use axum::{routing::post, Router};
+/// use axum_yaml::value::RawYaml;
+///
+/// async fn webhook(event: RawYaml) -> &'static str {
+///     match event.str("action") {
+///         Some("opened") => "handled",
+///         _ => "ignored",
+///     }
+/// }
+///
+/// let app = Router::new().route("/webhook", post(webhook));
+/// # let _: Router = app;
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RawYaml(pub Value);
+
+impl RawYaml {
+    /// The first node selected by the [`YamlPath`] `path`, such as
+    /// `spec.containers[0].image`, if any.
+    ///
+    /// An invalid path selects nothing.
+    pub fn get(&self, path: &str) -> Option<&Value> {
+        let path: YamlPath = path.parse().ok()?;
+        path.find(&self.0)
+            .into_iter()
+            .next()
+            .map(|(_, value)| value)
+    }
+
+    /// The string selected by `path`, if any.
+    pub fn str(&self, path: &str) -> Option<&str> {
+        self.get(path)?.as_str()
+    }
+
+    /// Deserialize the document into `T`, e.g. once the handler knows which
+    /// type it holds.
+    pub fn deserialize<T>(self) -> Result<T, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        from_value(self.0)
+    }
+
+    /// The document.
+    pub fn into_inner(self) -> Value {
+        self.0
+    }
+}
+
+impl Deref for RawYaml {
+    type Target = Value;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<S> FromRequest<S> for RawYaml
+where
+    S: Send + Sync,
+{
+    type Rejection = YamlRejection;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let config = YamlConfig::from_extensions(req.extensions());
+        config
+            .report(async {
+                if config.yaml_content_type(req.headers()) {
+                    let timing = req.extensions().get::<ParseTiming>().cloned();
+                    let bytes = config.read_body(req, state).await?;
+
+                    let start = Instant::now();
+                    let result = config.document_body(bytes).await.map(RawYaml);
+                    if let Some(timing) = timing {
+                        timing.record(start.elapsed());
+                    }
+                    result
+                } else {
+                    config.drain(req).await;
+                    Err(config.missing_content_type().into())
+                }
+            })
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use axum::routing::post;
+    use axum::Router;
+    use http::StatusCode;
+
+    use crate::test_client::TestClient;
+
+    #[tokio::test]
+    async fn raw_document() {
+        let app = Router::new().route(
+            "/",
+            post(|raw: RawYaml| async move {
+                let image = raw
+                    .str("spec.containers[0].image")
+                    .unwrap_or("-")
+                    .to_owned();
+                let replicas = raw.get("spec.replicas").and_then(Value::as_u64);
+                format!("{image} {replicas:?} {:?}", raw.get("[not a path"))
+            }),
+        );
+
+        let res = TestClient::new(app)
+            .post("/")
+            .body("spec:\n  replicas: 2\n  containers:\n  - image: web\n")
+            .header("content-type", "application/yaml")
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.text().await, "web Some(2) None");
+    }
+}
@@ -1,14 +1,24 @@
-use std::ops::{Deref, DerefMut};
+use std::{
+    borrow::Cow,
+    fmt,
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+    time::Instant,
+};
 
 use axum_core::{
-    extract::{FromRequest, Request},
+    extract::{FromRef, FromRequest, Request},
     response::{IntoResponse, Response},
 };
-use bytes::{BufMut, Bytes, BytesMut};
-use http::{header, HeaderMap, HeaderValue, StatusCode};
+use bytes::Bytes;
+use http::{header, HeaderMap};
 use serde::{de::DeserializeOwned, Serialize};
 
+use crate::config::YamlConfig;
+use crate::lenient::Leniency;
 use crate::rejection::*;
+use crate::ser::{self, ConfiguredYaml, SerializeConfig};
+use crate::timing::{ParseTiming, Step};
 
 /// YAML Extractor / Response.
 ///
@@ -101,16 +111,106 @@ where
     type Rejection = YamlRejection;
 
     async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
-        if yaml_content_type(req.headers()) {
-            let bytes = Bytes::from_request(req, state).await?;
-            Self::from_bytes(&bytes)
-        } else {
-            Err(MissingYamlContentType.into())
-        }
+        let config = YamlConfig::from_extensions(req.extensions());
+        extract(&config, req, state).await.map(Yaml)
+    }
+}
+
+/// Deserialize the body of `req` according to `config`.
+async fn extract<T, S>(config: &YamlConfig, req: Request, state: &S) -> Result<T, YamlRejection>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    config
+        .report(async {
+            if config.yaml_content_type(req.headers()) {
+                let timing = req.extensions().get::<ParseTiming>().cloned();
+                let bytes = config.read_body(req, state).await?;
+
+                let start = Instant::now();
+                let result = config.deserialize_body(bytes).await;
+                if let Some(timing) = timing {
+                    timing.record(start.elapsed());
+                }
+                result
+            } else {
+                config.drain(req).await;
+                Err(config.missing_content_type().into())
+            }
+        })
+        .await
+}
+
+/// YAML extractor using the [`YamlConfig`] in state rather than the one in
+/// the request extensions.
+///
+/// Applications keeping their settings in state implement [`FromRef`] for
+/// [`YamlConfig`] once, instead of installing it with an `Extension` layer:
+///
+/// ```no_run
+/// use axum::{extract::FromRef, routing::post, Router};
+/// use axum_yaml::{StateYaml, YamlConfig};
+/// use http::StatusCode;
+/// use serde_yaml::Value;
+///
+/// #[derive(Clone)]
+/// struct AppState {
+///     yaml: YamlConfig,
+/// }
+///
+/// impl FromRef<AppState> for YamlConfig {
+///     fn from_ref(state: &AppState) -> Self {
+///         state.yaml.clone()
+///     }
+/// }
+///
+/// async fn create(StateYaml(manifest): StateYaml<Value>) {}
+///
+/// let state = AppState {
+///     yaml: YamlConfig::new().data_error_status(StatusCode::UNPROCESSABLE_ENTITY),
+/// };
+/// let app: Router = Router::new()
+///     .route("/manifests", post(create))
+///     .with_state(state);
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StateYaml<T>(pub T);
+
+impl<T, S> FromRequest<S> for StateYaml<T>
+where
+    T: DeserializeOwned,
+    YamlConfig: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = YamlRejection;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let config = YamlConfig::from_ref(state);
+        extract(&config, req, state).await.map(StateYaml)
     }
 }
 
-fn yaml_content_type(headers: &HeaderMap) -> bool {
+/// Whether the `Content-Type` of `headers` names YAML, as `application/yaml`
+/// or with a `+yaml` suffix.
+///
+/// This is the check the extractors of this crate apply under the default
+/// [`YamlConfig`], so middleware and custom extractors can agree with them.
+///
+/// ```
+/// use axum_yaml::is_yaml_content_type;
+/// use http::{header, HeaderMap, HeaderValue};
+///
+/// let mut headers = HeaderMap::new();
+/// assert!(!is_yaml_content_type(&headers));
+///
+/// headers.insert(
+///     header::CONTENT_TYPE,
+///     HeaderValue::from_static("application/yaml; charset=utf-8"),
+/// );
+/// assert!(is_yaml_content_type(&headers));
+/// ```
+pub fn is_yaml_content_type(headers: &HeaderMap) -> bool {
     let Some(content_type) = headers.get(header::CONTENT_TYPE) else {
         return false;
     };
@@ -119,14 +219,28 @@ fn yaml_content_type(headers: &HeaderMap) -> bool {
         return false;
     };
 
+    is_yaml_media_type(content_type)
+}
+
+/// Whether `content_type` names YAML, as `application/yaml` or with a `+yaml`
+/// suffix.
+///
+/// Parameters are allowed and ignored.
+///
+/// ```
+/// use axum_yaml::is_yaml_media_type;
+///
+/// assert!(is_yaml_media_type("application/yaml"));
+/// assert!(is_yaml_media_type("application/vnd.mycorp.deploy+yaml; charset=utf-8"));
+/// assert!(!is_yaml_media_type("text/yaml"));
+/// ```
+pub fn is_yaml_media_type(content_type: &str) -> bool {
     let Ok(mime) = content_type.parse::<mime::Mime>() else {
         return false;
     };
 
-    let is_yaml_content_type = mime.type_() == "application"
-        && (mime.subtype() == "yaml" || mime.suffix().map_or(false, |name| name == "yaml"));
-
-    is_yaml_content_type
+    mime.type_() == "application"
+        && (mime.subtype() == "yaml" || mime.suffix().is_some_and(|name| name == "yaml"))
 }
 
 impl<T> Deref for Yaml<T> {
@@ -159,11 +273,27 @@ where
     /// but special cases may require first extracting a `Request` into `Bytes` then optionally
     /// constructing a `Yaml<T>`.
     pub fn from_bytes(bytes: &[u8]) -> Result<Self, YamlRejection> {
+        let step = Step::parse(bytes.len());
         let deserializer = serde_yaml::Deserializer::from_slice(bytes);
 
-        match serde_path_to_error::deserialize(deserializer) {
-            Ok(value) => Ok(Yaml(value)),
-            Err(err) => Err(YamlError::from_err(err).into()),
+        match crate::classify::deserialize_stream(deserializer) {
+            Ok(value) => {
+                step.documents(1);
+                step.end();
+                Ok(Yaml(value))
+            }
+            Err(err) => Err(YamlError::data(err).into()),
+        }
+    }
+}
+
+impl<T> Yaml<T> {
+    /// Serialize this response with `config` instead of the one of the
+    /// enclosing [`SerializeConfigLayer`](crate::ser::SerializeConfigLayer).
+    pub fn with_config(self, config: SerializeConfig) -> ConfiguredYaml<T> {
+        ConfiguredYaml {
+            value: self.0,
+            config,
         }
     }
 }
@@ -173,27 +303,428 @@ where
     T: Serialize,
 {
     fn into_response(self) -> Response {
-        // Use a small initial capacity of 128 bytes like serde_json::to_vec
-        // https://docs.rs/serde_json/1.0.82/src/serde_json/ser.rs.html#2189
-        let mut buf = BytesMut::with_capacity(128).writer();
-        match serde_yaml::to_writer(&mut buf, &self.0) {
-            Ok(()) => (
-                [(
-                    header::CONTENT_TYPE,
-                    HeaderValue::from_static("application/yaml"),
-                )],
-                buf.into_inner().freeze(),
-            )
-                .into_response(),
-            Err(err) => (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                [(
-                    header::CONTENT_TYPE,
-                    HeaderValue::from_static(mime::TEXT_PLAIN_UTF_8.as_ref()),
-                )],
-                err.to_string(),
-            )
-                .into_response(),
+        ser::response(&self.0, &SerializeConfig::current())
+    }
+}
+
+/// YAML extractor rejecting with `R` instead of [`YamlRejection`].
+///
+/// Deserializes the request body like [`Yaml`], converting its rejection
+/// into `R`, so an application can reply with its own error format without
+/// wrapping every handler argument in `axum_extra::extract::WithRejection`.
+///
+/// The marker for `R` is private, so the value is reached through `Deref`,
+/// [`into_inner`](Self::into_inner) or `.0`, and a `YamlWithRejection` is
+/// built with [`new`](Self::new) or `From`.
+///
+/// # Example
+///
+/// ```no_run
+/// use axum::{
+///     response::{IntoResponse, Response},
+///     routing::post,
+///     Router,
+/// };
+/// use axum_yaml::{rejection::YamlRejection, YamlWithRejection};
+/// use serde::Deserialize;
+///
+/// struct ApiError {
+///     status: http::StatusCode,
+///     message: String,
+/// }
+///
+/// impl From<YamlRejection> for ApiError {
+///     fn from(rejection: YamlRejection) -> Self {
+///         Self {
+///             status: rejection.status(),
+///             message: rejection.body_text(),
+///         }
+///     }
+/// }
+///
+/// impl IntoResponse for ApiError {
+///     fn into_response(self) -> Response {
+///         (self.status, format!("error: {}", self.message)).into_response()
+///     }
+/// }
+///
+/// #[derive(Deserialize)]
+/// struct CreateUser {
+///     email: String,
+/// }
+///
+/// async fn create_user(payload: YamlWithRejection<CreateUser, ApiError>) {
+///     let user: CreateUser = payload.into_inner();
+/// }
+///
+/// let app = Router::new().route("/users", post(create_user));
+/// # let _: Router = app;
+/// ```
+pub struct YamlWithRejection<T, R = YamlRejection>(pub T, PhantomData<R>);
+
+impl<T, R> YamlWithRejection<T, R> {
+    /// Wrap `value`.
+    pub fn new(value: T) -> Self {
+        Self(value, PhantomData)
+    }
+
+    /// Unwrap the deserialized value.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T, R> From<T> for YamlWithRejection<T, R> {
+    fn from(inner: T) -> Self {
+        Self::new(inner)
+    }
+}
+
+impl<T, R, S> FromRequest<S> for YamlWithRejection<T, R>
+where
+    T: DeserializeOwned,
+    R: From<YamlRejection> + IntoResponse,
+    S: Send + Sync,
+{
+    type Rejection = R;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let Yaml(value) = Yaml::from_request(req, state).await?;
+        Ok(YamlWithRejection::new(value))
+    }
+}
+
+impl<T, R> Deref for YamlWithRejection<T, R> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T, R> DerefMut for YamlWithRejection<T, R> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<T, R> fmt::Debug for YamlWithRejection<T, R>
+where
+    T: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("YamlWithRejection").field(&self.0).finish()
+    }
+}
+
+impl<T, R> Clone for YamlWithRejection<T, R>
+where
+    T: Clone,
+{
+    fn clone(&self) -> Self {
+        Self::new(self.0.clone())
+    }
+}
+
+/// YAML extractor accepting empty request bodies.
+///
+/// Deserializes the request body like [`Yaml`], but yields `None` instead of
+/// rejecting when the body is empty, so clients can send no body to ask for
+/// defaults. A request with `Content-Length: 0` is accepted without a
+/// `Content-Type`, while a YAML body holding only whitespace is empty too.
+///
+/// # Example
+///
+/// ```no_run
+/// use axum::{routing::post, Router};
+/// use axum_yaml::OptionalYaml;
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize, Default)]
+/// struct Options {
+///     dry_run: bool,
+/// }
+///
+/// async fn deploy(OptionalYaml(options): OptionalYaml<Options>) {
+///     let options = options.unwrap_or_default();
+///     // ...
+/// }
+///
+/// let app = Router::new().route("/deploy", post(deploy));
+/// # let _: Router = app;
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OptionalYaml<T>(pub Option<T>);
+
+impl<T, S> FromRequest<S> for OptionalYaml<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = YamlRejection;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let empty = req
+            .headers()
+            .get(header::CONTENT_LENGTH)
+            .is_some_and(|length| length == "0");
+        if empty {
+            return Ok(OptionalYaml(None));
+        }
+
+        let config = YamlConfig::from_extensions(req.extensions());
+        config
+            .report(async {
+                if config.yaml_content_type(req.headers()) {
+                    let timing = req.extensions().get::<ParseTiming>().cloned();
+                    let bytes = config.read_body(req, state).await?;
+                    if bytes.trim_ascii().is_empty() {
+                        return Ok(OptionalYaml(None));
+                    }
+
+                    let start = Instant::now();
+                    let result = config
+                        .deserialize_body(bytes)
+                        .await
+                        .map(|value| OptionalYaml(Some(value)));
+                    if let Some(timing) = timing {
+                        timing.record(start.elapsed());
+                    }
+                    result
+                } else {
+                    config.drain(req).await;
+                    Err(config.missing_content_type().into())
+                }
+            })
+            .await
+    }
+}
+
+/// YAML extractor keeping the original request body.
+///
+/// Deserializes the request body like [`Yaml`], and also returns the exact
+/// bytes it was parsed from, so handlers can store or forward the original
+/// payload, e.g. for audit trails, signature verification or proxying,
+/// without serializing the value again. The bytes are shared, not copied.
+///
+/// # Example
+///
+/// ```no_run
+/// use axum::{routing::post, Router};
+/// use axum_yaml::BytesYaml;
+/// use serde_yaml::Value;
+///
+/// async fn audit(BytesYaml(event, original): BytesYaml<Value>) {
+///     // store `original` as received, act on `event`
+/// }
+///
+/// let app = Router::new().route("/events", post(audit));
+/// # let _: Router = app;
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct BytesYaml<T>(pub T, pub Bytes);
+
+impl<T, S> FromRequest<S> for BytesYaml<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = YamlRejection;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let config = YamlConfig::from_extensions(req.extensions());
+        config
+            .report(async {
+                if config.yaml_content_type(req.headers()) {
+                    let timing = req.extensions().get::<ParseTiming>().cloned();
+                    let (bytes, charset) = config.read_raw_body(req, state).await?;
+
+                    let start = Instant::now();
+                    let decoded = match charset.decode(&bytes)? {
+                        Cow::Borrowed(_) => bytes.clone(),
+                        Cow::Owned(decoded) => Bytes::from(decoded),
+                    };
+                    let result = config
+                        .deserialize_body(decoded)
+                        .await
+                        .map(|value| BytesYaml(value, bytes));
+                    if let Some(timing) = timing {
+                        timing.record(start.elapsed());
+                    }
+                    result
+                } else {
+                    config.drain(req).await;
+                    Err(config.missing_content_type().into())
+                }
+            })
+            .await
+    }
+}
+
+/// YAML extractor ignoring the `Content-Type` header.
+///
+/// Deserializes the request body like [`Yaml`], whatever its `Content-Type`
+/// says, for legacy clients sending `text/plain` or no `Content-Type` at
+/// all. Prefer [`Yaml`] for new APIs, since this extractor also parses
+/// bodies never meant as YAML, such as forms.
+///
+/// Bodies without a YAML `Content-Type` are reported as a
+/// [`Leniency::IgnoredContentType`], see the [`lenient`](crate::lenient)
+/// module.
+///
+/// # Example
+///
+/// ```no_run
+/// use axum::{routing::post, Router};
+/// use axum_yaml::LenientYaml;
+/// use serde_yaml::Value;
+///
+/// async fn legacy(LenientYaml(config): LenientYaml<Value>) {
+///     // ...
+/// }
+///
+/// let app = Router::new().route("/legacy", post(legacy));
+/// # let _: Router = app;
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LenientYaml<T>(pub T);
+
+impl<T, S> FromRequest<S> for LenientYaml<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = YamlRejection;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let config = YamlConfig::from_extensions(req.extensions());
+        config
+            .report(async {
+                if !config.yaml_content_type(req.headers()) {
+                    Leniency::IgnoredContentType.report(req.headers(), req.extensions());
+                }
+                let timing = req.extensions().get::<ParseTiming>().cloned();
+                let bytes = config.read_body(req, state).await?;
+
+                let start = Instant::now();
+                let result = config.deserialize_body(bytes).await.map(LenientYaml);
+                if let Some(timing) = timing {
+                    timing.record(start.elapsed());
+                }
+                result
+            })
+            .await
+    }
+}
+
+/// YAML extractor deferring deserialization to the handler.
+///
+/// Checks the `Content-Type` and reads the body like [`Yaml`], but only
+/// deserializes it when [`LazyYaml::parse`] is called, so handlers can run
+/// cheap checks such as authorization or quotas first and skip parsing
+/// entirely when returning early.
+///
+/// # Example
+///
+/// ```no_run
+/// use axum::{http::StatusCode, routing::post, Router};
+/// use axum_yaml::{rejection::YamlRejection, LazyYaml};
+/// use serde_yaml::Value;
+///
+/// async fn import(body: LazyYaml<Value>) -> Result<StatusCode, YamlRejection> {
+///     if !quota_left() {
+///         return Ok(StatusCode::TOO_MANY_REQUESTS);
+///     }
+///     let document = body.parse()?;
+///     // ...
+///     Ok(StatusCode::CREATED)
+/// }
+///
+/// fn quota_left() -> bool {
+///     // ...
+///     # true
+/// }
+///
+/// let app = Router::new().route("/import", post(import));
+/// # let _: Router = app;
+/// ```
+pub struct LazyYaml<T> {
+    bytes: Bytes,
+    config: YamlConfig,
+    timing: Option<ParseTiming>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> LazyYaml<T>
+where
+    T: DeserializeOwned,
+{
+    /// Deserialize the body into `T`, with the [`YamlConfig`] of the request.
+    pub fn parse(&self) -> Result<T, YamlRejection> {
+        let start = Instant::now();
+        let result = self
+            .config
+            .deserialize(&self.bytes)
+            .map_err(|rejection| self.config.reject(rejection));
+        if let Some(timing) = &self.timing {
+            timing.record(start.elapsed());
+        }
+        result
+    }
+}
+
+impl<T> LazyYaml<T> {
+    /// The body, transcoded to UTF-8 if
+    /// [`YamlConfig::transcode_charsets`] is set.
+    pub fn bytes(&self) -> &Bytes {
+        &self.bytes
+    }
+}
+
+impl<T> fmt::Debug for LazyYaml<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LazyYaml")
+            .field("bytes", &self.bytes)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T> Clone for LazyYaml<T> {
+    fn clone(&self) -> Self {
+        Self {
+            bytes: self.bytes.clone(),
+            config: self.config.clone(),
+            timing: self.timing.clone(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T, S> FromRequest<S> for LazyYaml<T>
+where
+    S: Send + Sync,
+{
+    type Rejection = YamlRejection;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let config = YamlConfig::from_extensions(req.extensions());
+        if config.yaml_content_type(req.headers()) {
+            let timing = req.extensions().get::<ParseTiming>().cloned();
+            let bytes = match config.read_body(req, state).await {
+                Ok(bytes) => bytes,
+                Err(rejection) => return Err(config.reject(rejection)),
+            };
+            Ok(LazyYaml {
+                bytes,
+                config,
+                timing,
+                _marker: PhantomData,
+            })
+        } else {
+            config.drain(req).await;
+            Err(config.missing_content_type().into())
         }
     }
 }
@@ -203,11 +734,13 @@ mod tests {
     use super::*;
 
     use axum::routing::post;
-    use axum::Router;
+    use axum::{Extension, Router};
     use http::StatusCode;
     use serde::Deserialize;
     use serde_yaml::Value;
 
+    use crate::case::KeyCase;
+    use crate::policy::Policy;
     use crate::test_client::TestClient;
 
     #[tokio::test]
@@ -268,6 +801,44 @@ mod tests {
         assert!(!valid_yaml_content_type("text/yaml").await);
     }
 
+    #[tokio::test]
+    async fn custom_rejection() {
+        struct Teapot(String);
+
+        impl From<YamlRejection> for Teapot {
+            fn from(rejection: YamlRejection) -> Self {
+                Self(rejection.body_text())
+            }
+        }
+
+        impl IntoResponse for Teapot {
+            fn into_response(self) -> Response {
+                (StatusCode::IM_A_TEAPOT, self.0).into_response()
+            }
+        }
+
+        let app = Router::new().route(
+            "/",
+            post(|value: YamlWithRejection<Value, Teapot>| async { Yaml(value.into_inner()) }),
+        );
+        let client = TestClient::new(app);
+
+        let res = client
+            .post("/")
+            .body("a: 1")
+            .header("content-type", "application/yaml")
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.text().await, "a: 1\n");
+
+        let res = client.post("/").body("a: 1").await;
+        assert_eq!(res.status(), StatusCode::IM_A_TEAPOT);
+        assert_eq!(
+            res.text().await,
+            "Expected request with `Content-Type: application/yaml`"
+        );
+    }
+
     #[tokio::test]
     async fn invalid_yaml_syntax() {
         let app = Router::new().route("/", post(|_: Yaml<Value>| async {}));
@@ -282,11 +853,97 @@ mod tests {
         assert_eq!(res.status(), StatusCode::BAD_REQUEST);
     }
 
-    #[derive(Deserialize)]
-    struct Foo {
-        #[allow(dead_code)]
-        a: i32,
-        #[allow(dead_code)]
+    #[test]
+    fn error_location() {
+        let err = Yaml::<Value>::from_bytes(b"a: 1\nb: [\n").unwrap_err();
+        let location = err.location().unwrap();
+        assert_eq!((location.line(), location.column()), (3, 1));
+
+        let Err(err) = Yaml::<Foo>::from_bytes(b"a: 1\nb: [{x: 1, y: z}]\n") else {
+            panic!("`y` is not a number");
+        };
+        let location = err.location().unwrap();
+        assert_eq!((location.line(), location.column()), (2, 15));
+
+        let err = YamlRejection::from(MissingYamlContentType::default());
+        assert!(err.location().is_none());
+    }
+
+    #[test]
+    fn error_path() {
+        let Err(err) = Yaml::<Foo>::from_bytes(b"a: 1\nb: [{x: 1, y: z}]\n") else {
+            panic!("`y` is not a number");
+        };
+        assert_eq!(err.path().unwrap().to_string(), "b[0].y");
+    }
+
+    #[test]
+    fn error_kind() {
+        #[derive(Debug, Deserialize)]
+        #[serde(deny_unknown_fields)]
+        #[allow(dead_code)]
+        struct Strict {
+            a: u8,
+        }
+
+        fn kind(config: &YamlConfig, body: &str) -> YamlErrorKind {
+            let err = config.deserialize::<Strict>(body.as_bytes()).unwrap_err();
+            err.kind().unwrap()
+        }
+
+        for config in [YamlConfig::new(), YamlConfig::new().empty_as_none(true)] {
+            assert_eq!(kind(&config, "a: '1"), YamlErrorKind::Syntax);
+            assert_eq!(
+                kind(&config, "a: 1\n---\na: 2\n"),
+                YamlErrorKind::MultipleDocuments
+            );
+            assert_eq!(kind(&config, "{}"), YamlErrorKind::MissingField);
+            assert_eq!(kind(&config, "a: 1\nb: 2\n"), YamlErrorKind::UnknownField);
+            assert_eq!(kind(&config, "a: x"), YamlErrorKind::InvalidType);
+            assert_eq!(kind(&config, "a: 300"), YamlErrorKind::InvalidValue);
+        }
+
+        // Deserialized as it is parsed, the body fails on the first problem
+        // met.
+        let kind = |body: &str| match Yaml::<Strict>::from_bytes(body.as_bytes()) {
+            Err(YamlRejection::YamlError(err)) => err.kind(),
+            _ => panic!("expected a YAML error for {body:?}"),
+        };
+        assert_eq!(kind("a: [1"), YamlErrorKind::InvalidType);
+        assert_eq!(
+            kind(
+                "a: 1
+b: [1"
+            ),
+            YamlErrorKind::UnknownField
+        );
+        assert_eq!(
+            kind(
+                "a: 1
+---
+"
+            ),
+            YamlErrorKind::MultipleDocuments
+        );
+
+        let kind = |body: &str| match Yaml::<(u8, Vec<u8>)>::from_bytes(body.as_bytes()) {
+            Err(YamlRejection::YamlError(err)) => err.kind(),
+            _ => panic!("expected a YAML error for {body:?}"),
+        };
+        assert_eq!(kind("[1, [2, 3"), YamlErrorKind::Syntax);
+        assert_eq!(kind("[1, [2], 3]"), YamlErrorKind::InvalidValue);
+        assert_eq!(kind("[1, [x]]"), YamlErrorKind::InvalidType);
+        assert_eq!(
+            kind("[1, [2]]\n---\n[3, []]"),
+            YamlErrorKind::MultipleDocuments
+        );
+    }
+
+    #[derive(Deserialize)]
+    struct Foo {
+        #[allow(dead_code)]
+        a: i32,
+        #[allow(dead_code)]
         b: Vec<Bar>,
     }
 
@@ -316,4 +973,555 @@ mod tests {
             "Failed to deserialize the YAML body into the target type: b[0]: b[0]: missing field `y` at line 3 column 7"
         );
     }
+
+    #[tokio::test]
+    async fn policy_violation() {
+        let config = YamlConfig::new().policy(Policy::new().deny_value("**.privileged", true));
+        let app = Router::new()
+            .route("/", post(|_: Yaml<Value>| async {}))
+            .layer(Extension(config));
+
+        let client = TestClient::new(app);
+        let res = client
+            .post("/")
+            .body("containers:\n- privileged: false\n- privileged: true")
+            .header("content-type", "application/yaml")
+            .await;
+
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+        assert_eq!(
+            res.text().await,
+            "Document violates policy: `containers[1].privileged: true` is not allowed"
+        );
+    }
+
+    #[tokio::test]
+    async fn key_case() {
+        #[derive(Deserialize)]
+        struct User {
+            user_id: u32,
+        }
+
+        let config = YamlConfig::new().key_case(KeyCase::Snake);
+        let app = Router::new()
+            .route(
+                "/",
+                post(|Yaml(user): Yaml<User>| async move { user.user_id.to_string() }),
+            )
+            .layer(Extension(config));
+
+        let client = TestClient::new(app);
+        let res = client
+            .post("/")
+            .body("userId: 7")
+            .header("content-type", "application/yaml")
+            .await;
+
+        assert_eq!(res.text().await, "7");
+    }
+
+    #[tokio::test]
+    async fn state_config() {
+        #[derive(Deserialize)]
+        struct User {
+            user_id: u32,
+        }
+
+        let app = Router::new()
+            .route(
+                "/",
+                post(|StateYaml(user): StateYaml<User>| async move { user.user_id.to_string() }),
+            )
+            .layer(Extension(YamlConfig::new()))
+            .with_state(YamlConfig::new().key_case(KeyCase::Snake));
+
+        let client = TestClient::new(app);
+        let res = client
+            .post("/")
+            .body("userId: 7")
+            .header("content-type", "application/yaml")
+            .await;
+        assert_eq!(res.text().await, "7");
+
+        let res = client.post("/").body("userId: 7").await;
+        assert_eq!(res.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+
+    #[tokio::test]
+    async fn rejection_settings() {
+        let config = YamlConfig::new()
+            .rejection_format(BodyFormat::Yaml)
+            .max_message_len(40);
+        let app = Router::new()
+            .route("/extension", post(|_: Yaml<Value>| async {}))
+            .route("/state", post(|_: StateYaml<Value>| async {}))
+            .layer(Extension(config.clone()))
+            .with_state(config.max_message_len(30));
+
+        let client = TestClient::new(app);
+        let res = client.post("/extension").body("a: 1").await;
+        assert_eq!(res.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        assert_eq!(res.headers()["content-type"], "application/yaml");
+        assert_eq!(
+            res.text().await,
+            "code: missing_yaml_content_type\n\
+             message: 'Expected request with `Content-Type: ap…'\n"
+        );
+
+        let res = client
+            .post("/state")
+            .body("a: [")
+            .header("content-type", "application/yaml")
+            .await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(res.headers()["content-type"], "application/yaml");
+        assert_eq!(
+            res.text().await,
+            "code: syntax\n\
+             message: Failed to deserialize the YAM…\n\
+             path: a\n\
+             line: 2\n\
+             column: 1\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn mapping_key_limit() {
+        let config = YamlConfig::new().max_mapping_keys(2);
+        let app = Router::new()
+            .route("/", post(|_: Yaml<Value>| async {}))
+            .layer(Extension(config));
+
+        let client = TestClient::new(app);
+        let res = client
+            .post("/")
+            .body("{a: 1, b: 2, c: 3}")
+            .header("content-type", "application/yaml")
+            .await;
+
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn sequence_len_limit() {
+        let config = YamlConfig::new().max_sequence_len(3);
+        let app = Router::new()
+            .route("/", post(|_: Yaml<Value>| async {}))
+            .layer(Extension(config));
+
+        let client = TestClient::new(app);
+        let res = client
+            .post("/")
+            .body("ports: [80, 443, 8080, 8443]")
+            .header("content-type", "application/yaml")
+            .await;
+
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            res.text().await,
+            "YAML document exceeds a limit: sequence at `ports` has 4 items, more than the limit of 3"
+        );
+    }
+
+    #[tokio::test]
+    async fn alias_limits() {
+        let bomb = "a: &a [x, x, x, x]\nb: &b [*a, *a, *a, *a]\nc: &c [*b, *b, *b, *b]\n";
+        let app = |config: YamlConfig| {
+            let app = Router::new()
+                .route("/", post(|_: Yaml<Value>| async {}))
+                .layer(Extension(config));
+            TestClient::new(app)
+        };
+
+        let res = app(YamlConfig::new().max_aliases(8))
+            .post("/")
+            .body(bomb)
+            .header("content-type", "application/yaml")
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let res = app(YamlConfig::new().max_aliases(7))
+            .post("/")
+            .body(bomb)
+            .header("content-type", "application/yaml")
+            .await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            res.text().await,
+            "YAML document exceeds a limit: body has 8 aliases, more than the limit of 7"
+        );
+
+        let res = app(YamlConfig::new().max_expanded_nodes(50))
+            .post("/")
+            .body(bomb)
+            .header("content-type", "application/yaml")
+            .await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn depth_limit() {
+        let config = YamlConfig::new().max_depth(2);
+        let app = Router::new()
+            .route("/", post(|_: Yaml<Value>| async {}))
+            .layer(Extension(config));
+
+        let client = TestClient::new(app);
+        let res = client
+            .post("/")
+            .body("a: [1, 2]")
+            .header("content-type", "application/yaml")
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let res = client
+            .post("/")
+            .body("a: [1, [2]]")
+            .header("content-type", "application/yaml")
+            .await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn duplicate_keys() {
+        let app = |config: YamlConfig| {
+            Router::new()
+                .route(
+                    "/",
+                    post(
+                        |Yaml(map): Yaml<std::collections::HashMap<String, u32>>| async move {
+                            map["a"].to_string()
+                        },
+                    ),
+                )
+                .layer(Extension(config))
+        };
+
+        let client = TestClient::new(app(YamlConfig::new()));
+        let res = client
+            .post("/")
+            .body("a: 1\nb: 2\na: 3\n")
+            .header("content-type", "application/yaml")
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.text().await, "3");
+
+        let client = TestClient::new(app(YamlConfig::new().reject_duplicate_keys(true)));
+        let res = client
+            .post("/")
+            .body("a: 1\nb: 2\na: 3\n")
+            .header("content-type", "application/yaml")
+            .await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            res.text().await,
+            "Failed to deserialize the YAML body into the target type: \
+             duplicate key `a` at line 3 column 1"
+        );
+    }
+
+    #[tokio::test]
+    async fn core_tags_only() {
+        let config = YamlConfig::new().core_tags_only(true);
+        let app = Router::new()
+            .route("/", post(|_: Yaml<Value>| async {}))
+            .layer(Extension(config));
+
+        let client = TestClient::new(app);
+        let res = client
+            .post("/")
+            .body("a: !!str 1\nb: !!int 2\n")
+            .header("content-type", "application/yaml")
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let res = client
+            .post("/")
+            .body("a: 1\nb: !Circle {r: 1}\n")
+            .header("content-type", "application/yaml")
+            .await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            res.text().await,
+            "YAML tag outside the core schema: `!Circle` at line 2 column 4"
+        );
+    }
+
+    #[tokio::test]
+    async fn empty_as_none() {
+        #[derive(Deserialize)]
+        struct Profile {
+            email: Option<String>,
+        }
+
+        let config = YamlConfig::new().empty_as_none(true);
+        let app = Router::new()
+            .route(
+                "/",
+                post(|Yaml(profile): Yaml<Profile>| async move { format!("{:?}", profile.email) }),
+            )
+            .layer(Extension(config));
+
+        let client = TestClient::new(app);
+        let res = client
+            .post("/")
+            .body("email: ''")
+            .header("content-type", "application/yaml")
+            .await;
+
+        assert_eq!(res.text().await, "None");
+    }
+
+    #[tokio::test]
+    async fn optional_body() {
+        #[derive(Debug, Deserialize)]
+        struct Input {
+            foo: String,
+        }
+
+        let app = Router::new().route(
+            "/",
+            post(|OptionalYaml(input): OptionalYaml<Input>| async move {
+                input.map_or_else(|| "default".to_owned(), |input| input.foo)
+            }),
+        );
+        let client = TestClient::new(app);
+
+        let res = client.post("/").header("content-length", "0").await;
+        assert_eq!(res.text().await, "default");
+
+        let res = client
+            .post("/")
+            .body(" \n")
+            .header("content-type", "application/yaml")
+            .await;
+        assert_eq!(res.text().await, "default");
+
+        let res = client
+            .post("/")
+            .body("foo: bar")
+            .header("content-type", "application/yaml")
+            .await;
+        assert_eq!(res.text().await, "bar");
+
+        let res = client.post("/").body("foo: bar").await;
+        assert_eq!(res.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+
+    #[tokio::test]
+    async fn original_bytes() {
+        let app = Router::new().route(
+            "/",
+            post(|BytesYaml(value, bytes): BytesYaml<Value>| async move {
+                format!("{} {:?}", value["foo"].as_str().unwrap(), bytes)
+            }),
+        );
+
+        let res = TestClient::new(app)
+            .post("/")
+            .body("foo:   bar # kept\n")
+            .header("content-type", "application/yaml")
+            .await;
+        assert_eq!(res.text().await, "bar b\"foo:   bar # kept\\n\"");
+    }
+
+    #[tokio::test]
+    async fn lenient_content_type() {
+        let app = Router::new().route(
+            "/",
+            post(|LenientYaml(value): LenientYaml<Value>| async move {
+                value["foo"].as_str().unwrap_or_default().to_owned()
+            }),
+        );
+        let client = TestClient::new(app);
+
+        let res = client.post("/").body("foo: bar").await;
+        assert_eq!(res.text().await, "bar");
+
+        let res = client
+            .post("/")
+            .body("foo: baz")
+            .header("content-type", "text/plain")
+            .await;
+        assert_eq!(res.text().await, "baz");
+
+        let res = client.post("/").body("foo: [").await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn lazy_parse() {
+        let app = Router::new().route(
+            "/",
+            post(|body: LazyYaml<Value>| async move {
+                if body.bytes().starts_with(b"#skip") {
+                    return "skipped".to_owned();
+                }
+                match body.parse() {
+                    Ok(value) => value["foo"].as_str().unwrap_or_default().to_owned(),
+                    Err(rejection) => rejection.body_text(),
+                }
+            }),
+        );
+        let client = TestClient::new(app);
+
+        let res = client
+            .post("/")
+            .body("#skip\nfoo: [")
+            .header("content-type", "application/yaml")
+            .await;
+        assert_eq!(res.text().await, "skipped");
+
+        let res = client
+            .post("/")
+            .body("foo: bar")
+            .header("content-type", "application/yaml")
+            .await;
+        assert_eq!(res.text().await, "bar");
+
+        let res = client
+            .post("/")
+            .body("foo: [")
+            .header("content-type", "application/yaml")
+            .await;
+        assert!(res
+            .text()
+            .await
+            .starts_with("Failed to deserialize the YAML body"));
+    }
+
+    #[tokio::test]
+    async fn charset() {
+        let app = Router::new()
+            .route(
+                "/",
+                post(|Yaml(value): Yaml<Value>| async move {
+                    value["name"].as_str().unwrap_or_default().to_owned()
+                }),
+            )
+            .layer(Extension(YamlConfig::new().transcode_charsets(true)));
+        let client = TestClient::new(app);
+
+        let res = client
+            .post("/")
+            .body(b"name: Jos\xE9".as_slice())
+            .header("content-type", "application/yaml; charset=iso-8859-1")
+            .await;
+        assert_eq!(res.text().await, "José");
+
+        let res = client
+            .post("/")
+            .body("name: x")
+            .header("content-type", "application/yaml; charset=shift_jis")
+            .await;
+        assert_eq!(res.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        assert_eq!(
+            res.text().await,
+            "Unsupported charset: `shift_jis` is not supported"
+        );
+    }
+
+    #[tokio::test]
+    async fn unexpected_bodies() {
+        let handler = |Yaml(value): Yaml<Value>| async move {
+            value["foo"].as_str().unwrap_or_default().to_owned()
+        };
+        let app = Router::new().route("/", axum::routing::get(handler)).route(
+            "/strict",
+            axum::routing::get(handler)
+                .layer(Extension(YamlConfig::new().reject_unexpected_bodies(true))),
+        );
+        let client = TestClient::new(app);
+
+        let res = client
+            .get("/")
+            .body("foo: bar")
+            .header("content-type", "application/yaml")
+            .await;
+        assert_eq!(res.text().await, "bar");
+
+        let res = client
+            .get("/strict")
+            .body("foo: bar")
+            .header("content-type", "application/yaml")
+            .await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            res.text().await,
+            "Request body not allowed: `GET` requests must not have a body"
+        );
+    }
+
+    #[tokio::test]
+    async fn bodiless_requests() {
+        let app = Router::new()
+            .route(
+                "/optional",
+                axum::routing::delete(|OptionalYaml(value): OptionalYaml<Value>| async move {
+                    format!("{value:?}")
+                }),
+            )
+            .route(
+                "/option",
+                axum::routing::delete(|Yaml(value): Yaml<Option<Value>>| async move {
+                    format!("{value:?}")
+                }),
+            )
+            .layer(Extension(YamlConfig::new().reject_unexpected_bodies(true)));
+        let client = TestClient::new(app);
+
+        for uri in ["/optional", "/option"] {
+            let res = client
+                .delete(uri)
+                .header("content-type", "application/yaml")
+                .await;
+            assert_eq!(res.status(), StatusCode::OK, "{uri}");
+            assert_eq!(res.text().await, "None", "{uri}");
+
+            let res = client
+                .delete(uri)
+                .body("foo: bar")
+                .header("content-type", "application/yaml")
+                .await;
+            assert_eq!(res.status(), StatusCode::BAD_REQUEST, "{uri}");
+            assert_eq!(
+                res.text().await,
+                "Request body not allowed: `DELETE` requests must not have a body"
+            );
+        }
+
+        // Bodies are found once read when no header announces them.
+        let mut req = Request::delete("/")
+            .header("content-type", "application/yaml")
+            .body(axum::body::Body::from("foo: bar"))
+            .unwrap();
+        req.extensions_mut()
+            .insert(YamlConfig::new().reject_unexpected_bodies(true));
+        let rejection = Yaml::<Option<Value>>::from_request(req, &()).await;
+        assert!(matches!(
+            rejection,
+            Err(YamlRejection::UnexpectedRequestBody(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn advertise_accepted_media_types() {
+        let app = Router::new()
+            .route("/", post(|_: Yaml<Value>| async {}))
+            .layer(Extension(
+                YamlConfig::new().accept_media_types(["text/yaml"]),
+            ));
+
+        let res = TestClient::new(app)
+            .post("/")
+            .body("foo: bar")
+            .header("content-type", "application/json")
+            .await;
+        assert_eq!(res.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        assert_eq!(
+            res.headers()["accept-post"],
+            "application/yaml, application/*+yaml, text/yaml"
+        );
+        assert_eq!(res.headers()["accept-patch"], res.headers()["accept-post"]);
+    }
 }